@@ -132,6 +132,8 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 			import_queue,
 			on_demand: None,
 			block_announce_validator_builder: None,
+			warp_sync: None,
+			sync_checkpoint: None,
 		})?;
 
 	if config.offchain_worker.enabled {
@@ -146,6 +148,12 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let slashing_protection = Arc::new(match config.slashing_protection_path() {
+		Some(path) => sc_consensus::SlashingProtection::open(path).map_err(|e| ServiceError::Other(
+			format!("Error opening slashing protection store: {}", e)
+		))?,
+		None => sc_consensus::SlashingProtection::in_memory(),
+	});
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
@@ -224,6 +232,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		name: Some(name),
 		observer_enabled: false,
 		keystore,
+		slashing_protection: Some(slashing_protection),
 		is_authority: role.is_network_authority(),
 	};
 
@@ -304,6 +313,8 @@ pub fn new_light(mut config: Configuration) -> Result<TaskManager, ServiceError>
 			import_queue,
 			on_demand: Some(on_demand.clone()),
 			block_announce_validator_builder: None,
+			warp_sync: None,
+			sync_checkpoint: None,
 		})?;
 
 	if config.offchain_worker.enabled {
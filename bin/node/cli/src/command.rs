@@ -21,6 +21,7 @@ use node_executor::Executor;
 use node_runtime::{Block, RuntimeApi};
 use sc_cli::{Result, SubstrateCli, RuntimeVersion, Role, ChainSpec};
 use sc_service::PartialComponents;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use crate::service::new_partial;
 
 impl SubstrateCli for Cli {
@@ -124,9 +125,20 @@ pub fn run() -> Result<()> {
 		Some(Subcommand::ExportState(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, ..}
+				let PartialComponents { client, task_manager, backend, ..}
 					= new_partial(&config)?;
-				Ok((cmd.run(client, config.chain_spec), task_manager))
+				let aux_data = move |header: &<Block as BlockT>::Header| {
+					let mut aux = sc_consensus_babe::aux_schema::snapshot_aux_data(
+						&*backend,
+						header.hash(),
+					)?;
+					aux.extend(grandpa::snapshot_aux_data::<Block, _>(
+						&*backend,
+						(header.hash(), *header.number()),
+					)?);
+					Ok(aux)
+				};
+				Ok((cmd.run_with_aux_data(client, config.chain_spec, aux_data), task_manager))
 			})
 		},
 		Some(Subcommand::ImportBlocks(cmd)) => {
@@ -149,5 +161,6 @@ pub fn run() -> Result<()> {
 				Ok((cmd.run(client, backend), task_manager))
 			})
 		},
+		Some(Subcommand::Db(cmd)) => cmd.run::<Block, _>(&cli),
 	}
 }
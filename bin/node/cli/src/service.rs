@@ -43,6 +43,24 @@ type FullGrandpaBlockImport =
 	grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>;
 type LightClient = sc_service::TLightClient<Block, RuntimeApi, Executor>;
 
+/// The sync state embedded in the chain spec, if full nodes can use it as a checkpoint.
+fn sync_state(
+	config: &Configuration,
+) -> Result<Option<sc_chain_spec::LightSyncState<Block>>, ServiceError> {
+	// The checkpoint is imported by the warp sync provider, along with the consensus data needed
+	// to continue from it.
+	if !cfg!(feature = "cli") {
+		return Ok(None)
+	}
+
+	let sync_state = match config.chain_spec.light_sync_state() {
+		Some(sync_state) => sc_chain_spec::LightSyncState::from_serializable(sync_state)
+			.map_err(|e| ServiceError::Other(format!("Invalid sync state in chain spec: {}", e)))?,
+		None => return Ok(None),
+	};
+	Ok(Some(sync_state).filter(|s| s.finalized_block_justification.is_some()))
+}
+
 pub fn new_partial(config: &Configuration) -> Result<sc_service::PartialComponents<
 	FullClient, FullBackend, FullSelectChain,
 	sp_consensus::DefaultImportQueue<Block, FullClient>,
@@ -197,9 +215,23 @@ pub fn new_full_base(
 	config.network.extra_sets.push(grandpa::grandpa_peers_set_config());
 
 	#[cfg(feature = "cli")]
-	config.network.request_response_protocols.push(sc_finality_grandpa_warp_sync::request_response_config_for_chain(
-		&config, task_manager.spawn_handle(), backend.clone(),
-	));
+	let warp_sync = {
+		let babe_link = import_setup.2.clone();
+		Some(sc_finality_grandpa_warp_sync::warp_sync_for_chain(
+			&config, task_manager.spawn_handle(), backend.clone(),
+			import_setup.1.shared_authority_set().clone(),
+			Some(sc_finality_grandpa_warp_sync::ConsensusDataImport {
+				prefix: sc_consensus_babe::epochs_state_prefix(),
+				import: Box::new(move |header: &_, state: &_| {
+					sc_consensus_babe::import_epochs_from_state(&babe_link, header, state)
+						.map_err(Into::into)
+				}),
+			}),
+		))
+	};
+	#[cfg(not(feature = "cli"))]
+	let warp_sync = None;
+	let sync_checkpoint = sync_state(&config)?.and_then(|sync_state| sync_state.to_checkpoint());
 
 	let (network, network_status_sinks, system_rpc_tx, network_starter) =
 		sc_service::build_network(sc_service::BuildNetworkParams {
@@ -210,6 +242,8 @@ pub fn new_full_base(
 			import_queue,
 			on_demand: None,
 			block_announce_validator_builder: None,
+			warp_sync,
+			sync_checkpoint,
 		})?;
 
 	if config.offchain_worker.enabled {
@@ -225,6 +259,12 @@ pub fn new_full_base(
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let slashing_protection = Arc::new(match config.slashing_protection_path() {
+		Some(path) => sc_consensus::SlashingProtection::open(path).map_err(|e| ServiceError::Other(
+			format!("Error opening slashing protection store: {}", e)
+		))?,
+		None => sc_consensus::SlashingProtection::in_memory(),
+	});
 
 	let (_rpc_handlers, telemetry_connection_notifier) = sc_service::spawn_tasks(
 		sc_service::SpawnTasksParams {
@@ -260,6 +300,7 @@ pub fn new_full_base(
 
 		let babe_config = sc_consensus_babe::BabeParams {
 			keystore: keystore_container.sync_keystore(),
+			slashing_protection: Some(slashing_protection.clone()),
 			client: client.clone(),
 			select_chain,
 			env: proposer,
@@ -312,6 +353,7 @@ pub fn new_full_base(
 		name: Some(name),
 		observer_enabled: false,
 		keystore,
+		slashing_protection: Some(slashing_protection),
 		is_authority: role.is_network_authority(),
 	};
 
@@ -415,6 +457,8 @@ pub fn new_light_base(mut config: Configuration) -> Result<(
 			import_queue,
 			on_demand: Some(on_demand.clone()),
 			block_announce_validator_builder: None,
+			warp_sync: None,
+			sync_checkpoint: None,
 		})?;
 	network_starter.start_network();
 
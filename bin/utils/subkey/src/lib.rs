@@ -19,7 +19,7 @@
 use structopt::StructOpt;
 use sc_cli::{
 	Error, VanityCmd, SignCmd, VerifyCmd, GenerateNodeKeyCmd, GenerateCmd, InspectKeyCmd,
-	InspectNodeKeyCmd
+	InspectNodeKeyCmd, InsertKeyCmd, EncryptKeystoreCmd,
 };
 
 #[derive(Debug, StructOpt)]
@@ -50,6 +50,12 @@ pub enum Subkey {
 
 	/// Verify a signature for a message, provided on STDIN, with a given (public or secret) key.
 	Verify(VerifyCmd),
+
+	/// Insert a key to the keystore given by `--keystore-path`, encrypted if a password is given.
+	Insert(InsertKeyCmd),
+
+	/// Encrypt the plaintext key files of the keystore given by `--keystore-path`.
+	EncryptKeystore(EncryptKeystoreCmd),
 }
 
 /// Run the subkey command, given the apropriate runtime.
@@ -62,5 +68,7 @@ pub fn run() -> Result<(), Error> {
 		Subkey::Vanity(cmd) => cmd.run(),
 		Subkey::Verify(cmd) => cmd.run(),
 		Subkey::Sign(cmd) => cmd.run(),
+		Subkey::Insert(cmd) => cmd.run_standalone(),
+		Subkey::EncryptKeystore(cmd) => cmd.run_standalone(),
 	}
 }
@@ -164,6 +164,30 @@ pub trait BlockImportOperation<Block: BlockT> {
 	/// Inject storage data into the database replacing any existing data.
 	fn reset_storage(&mut self, storage: Storage) -> sp_blockchain::Result<Block::Hash>;
 
+	/// Inject a chunk of the key-value pairs of a state, e.g. one downloaded in pieces. Once the
+	/// operation is committed, the state made of all the chunks replaces any existing data.
+	///
+	/// The pairs of the given child trie are inserted instead of the top trie if `child_info` is
+	/// set. The top trie chunks must not contain the roots of child tries, which are computed as
+	/// their chunks are inserted. Returns the root of the state made of all the chunks so far.
+	///
+	/// Backends that can not build a state this way return an error.
+	fn insert_storage_chunk(
+		&mut self,
+		_child_info: Option<&ChildInfo>,
+		_chunk: Vec<(Vec<u8>, Vec<u8>)>,
+	) -> sp_blockchain::Result<Block::Hash> {
+		Err(sp_blockchain::Error::Backend("Importing a state in chunks is not supported".into()))
+	}
+
+	/// Import the pending block as the new base of the chain, without any of its ancestors, e.g.
+	/// the target of a warp sync. The block must be final.
+	///
+	/// Backends that can not start a chain at an arbitrary block return an error.
+	fn set_new_base(&mut self) -> sp_blockchain::Result<()> {
+		Err(sp_blockchain::Error::Backend("Importing a new chain base is not supported".into()))
+	}
+
 	/// Set storage changes.
 	fn update_storage(
 		&mut self,
@@ -452,6 +476,20 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, block: BlockId<Block>) -> sp_blockchain::Result<Self::State>;
 
+	/// Returns the encoded trie node with the given hash, from the state of any block that is
+	/// still available.
+	///
+	/// Returns `None` if the node is unknown, or if the backend can't look up nodes by their
+	/// hash alone, as is the case for databases that don't count references.
+	fn trie_node(&self, _hash: &Block::Hash) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		Ok(None)
+	}
+
+	/// Returns the index of the events of the finalized chain, if the backend keeps one.
+	fn event_index(&self) -> Option<&dyn crate::EventIndex<Block>> {
+		None
+	}
+
 	/// Attempts to revert the chain by `n` blocks. If `revert_finalized` is set it will attempt to
 	/// revert past any finalized block, this is unsafe and can potentially leave the node in an
 	/// inconsistent state.
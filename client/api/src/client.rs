@@ -106,6 +106,16 @@ pub trait BlockBackend<Block: BlockT> {
 	fn have_extrinsic(&self, hash: &Block::Hash) -> sp_blockchain::Result<bool> {
 		Ok(self.extrinsic(hash)?.is_some())
 	}
+
+	/// Get block body by the hash of its SCALE encoding.
+	///
+	/// The default implementation doesn't look up any body.
+	fn block_body_by_content_hash(
+		&self,
+		_hash: &Block::Hash,
+	) -> sp_blockchain::Result<Option<Vec<<Block as BlockT>::Extrinsic>>> {
+		Ok(None)
+	}
 }
 
 /// Provide a list of potential uncle headers for a given block.
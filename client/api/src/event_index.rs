@@ -0,0 +1,94 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Index of the events of the finalized chain by account and topic.
+
+use codec::{Encode, Decode};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// Location of an event in the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct EventLocation<Hash, Number> {
+	/// Hash of the block that emitted the event.
+	pub block_hash: Hash,
+	/// Number of the block that emitted the event.
+	pub block_number: Number,
+	/// Index of the extrinsic that emitted the event, `None` for events emitted while
+	/// initializing or finalizing the block.
+	pub extrinsic_index: Option<u32>,
+	/// Index of the event among the events of the block.
+	pub event_index: u32,
+}
+
+/// An event of a block, as far as the index is concerned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexedEvent {
+	/// Index of the extrinsic that emitted the event, if any.
+	pub extrinsic_index: Option<u32>,
+	/// Encoded accounts the event refers to.
+	pub accounts: Vec<Vec<u8>>,
+	/// Topics of the event.
+	pub topics: Vec<Vec<u8>>,
+}
+
+/// What events are looked up by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventIndexKey<'a> {
+	/// Events referring to the given encoded account.
+	Account(&'a [u8]),
+	/// Events with the given topic.
+	Topic(&'a [u8]),
+}
+
+/// Index of the events of the finalized chain.
+pub trait EventIndex<Block: BlockT>: Send + Sync {
+	/// Number of the first indexed block, `None` if no block was indexed yet.
+	///
+	/// The events of earlier blocks are not indexed, e.g. because their state was pruned.
+	fn first_indexed(&self) -> sp_blockchain::Result<Option<NumberFor<Block>>>;
+
+	/// Number of the last indexed block, `None` if no block was indexed yet.
+	fn last_indexed(&self) -> sp_blockchain::Result<Option<NumberFor<Block>>>;
+
+	/// Index the events of a finalized block, in the order they were emitted.
+	///
+	/// The first indexed block may be any block, the following ones must be its descendants in
+	/// ascending order, without gaps.
+	fn index_block(
+		&self,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		events: &[IndexedEvent],
+	) -> sp_blockchain::Result<()>;
+
+	/// Locations of the events with the given key emitted by blocks `from..=to`, newest first.
+	/// At most `limit` locations are returned.
+	fn events(
+		&self,
+		key: EventIndexKey,
+		from: NumberFor<Block>,
+		to: NumberFor<Block>,
+		limit: usize,
+	) -> sp_blockchain::Result<Vec<EventLocation<Block::Hash, NumberFor<Block>>>>;
+}
+
+/// Provides access to the event index.
+pub trait EventIndexProvider<Block: BlockT> {
+	/// Returns the event index, if the backend supports it.
+	fn event_index(&self) -> Option<&dyn EventIndex<Block>>;
+}
@@ -23,6 +23,7 @@ pub mod backend;
 pub mod call_executor;
 pub mod client;
 pub mod cht;
+pub mod event_index;
 pub mod execution_extensions;
 pub mod in_mem;
 pub mod light;
@@ -35,6 +36,7 @@ pub use backend::*;
 pub use notifications::*;
 pub use call_executor::*;
 pub use client::*;
+pub use event_index::*;
 pub use light::*;
 pub use notifications::*;
 pub use proof_provider::*;
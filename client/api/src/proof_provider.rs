@@ -42,6 +42,23 @@ pub trait ProofProvider<Block: BlockT> {
 		keys: &mut dyn Iterator<Item=&[u8]>,
 	) -> sp_blockchain::Result<StorageProof>;
 
+	/// Reads the key-value pairs of the state at a given block that follow `start_key`, in key
+	/// order, until at least `size_limit` bytes are collected. Returns them together with their
+	/// read proof and whether the end of the state was reached.
+	///
+	/// The pairs of the given child trie are read instead of the top trie if `child_info` is set.
+	///
+	/// Providers that can not serve the state of a block this way return an error.
+	fn read_proof_collection(
+		&self,
+		_id: &BlockId<Block>,
+		_child_info: Option<&ChildInfo>,
+		_start_key: &[u8],
+		_size_limit: usize,
+	) -> sp_blockchain::Result<(Vec<(Vec<u8>, Vec<u8>)>, StorageProof, bool)> {
+		Err(sp_blockchain::Error::Backend("Reading the state in ranges is not supported".into()))
+	}
+
 	/// Execute a call to a contract on top of state in a block of given hash
 	/// AND returning execution proof.
 	///
@@ -70,4 +87,11 @@ pub trait ProofProvider<Block: BlockT> {
 		storage_key: Option<&PrefixedStorageKey>,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<ChangesProof<Block::Header>>;
+
+	/// Returns the encoded trie node with the given hash, if the backend can look it up.
+	///
+	/// The default implementation doesn't look up any node.
+	fn trie_node(&self, _hash: &Block::Hash) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		Ok(None)
+	}
 }
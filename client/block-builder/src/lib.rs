@@ -26,14 +26,17 @@
 
 #![warn(missing_docs)]
 
+use std::collections::HashMap;
+
 use codec::Encode;
 
 use sp_runtime::{
+	ApplyExtrinsicResult,
 	generic::BlockId,
 	traits::{Header as HeaderT, Hash, Block as BlockT, HashFor, DigestFor, NumberFor, One},
 };
 use sp_blockchain::{ApplyExtrinsicFailed, Error};
-use sp_core::ExecutionContext;
+use sp_core::{ExecutionContext, storage::ChildInfo};
 use sp_api::{
 	Core, ApiExt, ApiErrorFor, ApiRef, ProvideRuntimeApi, StorageChanges, StorageProof,
 	TransactionOutcome,
@@ -42,7 +45,7 @@ use sp_consensus::RecordProof;
 
 pub use sp_block_builder::BlockBuilder as BlockBuilderApi;
 
-use sc_client_api::backend;
+use sc_client_api::backend::{self, StateBackend as _};
 
 /// A block that was build by [`BlockBuilder`] plus some additional data.
 ///
@@ -66,6 +69,54 @@ impl<Block: BlockT, StateBackend: backend::StateBackend<HashFor<Block>>> BuiltBl
 	}
 }
 
+/// Storage entry changed by an extrinsic, as the key, the value before applying the extrinsic
+/// and the value after. A value of `None` means that the entry doesn't exist.
+pub type StorageDiff = (Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// Outcome of applying an extrinsic with [`BlockBuilder::dry_run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRun {
+	/// The result the runtime returned for the extrinsic.
+	pub result: ApplyExtrinsicResult,
+	/// Main storage entries changed by the extrinsic.
+	pub storage_changes: Vec<StorageDiff>,
+	/// Entries of default child tries changed by the extrinsic, by unprefixed storage key of
+	/// the child trie. Child tries without changed entries are left out.
+	pub child_storage_changes: Vec<(Vec<u8>, Vec<StorageDiff>)>,
+}
+
+/// Compare the `after` changes of a storage with its `before` changes, falling back to
+/// `read_state` for the entries that `before` doesn't change.
+fn diff<E: sp_state_machine::Error>(
+	before: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	after: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	read_state: impl Fn(&[u8]) -> Result<Option<Vec<u8>>, E>,
+) -> sp_blockchain::Result<Vec<StorageDiff>> {
+	let before = before.into_iter().collect::<HashMap<_, _>>();
+	let mut changes = Vec::new();
+	for (key, value) in after {
+		let old_value = match before.get(&key) {
+			Some(old_value) => old_value.clone(),
+			None => read_state(&key).map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))?,
+		};
+		if old_value != value {
+			changes.push((key, old_value, value));
+		}
+	}
+	Ok(changes)
+}
+
+/// Something that can apply extrinsics without building a block.
+pub trait DryRunProvider<Block: BlockT> {
+	/// Apply `xt` as the first extrinsic of a block built on top of `parent`, without building
+	/// the block or changing any state.
+	fn dry_run_at(
+		&self,
+		parent: &BlockId<Block>,
+		xt: <Block as BlockT>::Extrinsic,
+	) -> sp_blockchain::Result<DryRun>;
+}
+
 /// Block builder provider
 pub trait BlockBuilderProvider<B, Block, RA>
 	where
@@ -221,6 +272,63 @@ where
 		})
 	}
 
+	/// Apply `xt` as the first extrinsic of a block built on top of `parent_hash`, without
+	/// building the block.
+	///
+	/// The returned storage changes are those made by `xt` alone, the changes made by
+	/// initializing the block are left out.
+	pub fn dry_run(
+		api: &'a A,
+		parent_hash: Block::Hash,
+		parent_number: NumberFor<Block>,
+		xt: <Block as BlockT>::Extrinsic,
+		backend: &'a B,
+	) -> Result<DryRun, ApiErrorFor<A, Block>> {
+		let new_builder = || Self::new(
+			api,
+			parent_hash,
+			parent_number,
+			RecordProof::No,
+			Default::default(),
+			backend,
+		);
+		// The changes of a builder that is only initialized are the base to compare with.
+		let initialized = new_builder()?;
+		let applied = new_builder()?;
+		let result = applied.api.apply_extrinsic_with_context(
+			&applied.block_id,
+			ExecutionContext::BlockConstruction,
+			xt,
+		)?;
+
+		let state = backend.state_at(applied.block_id)?;
+		let into_storage_changes = |builder: Self| builder.api
+			.into_storage_changes(&state, None, parent_hash)
+			.map_err(|e| sp_blockchain::Error::StorageChanges(e));
+		let initialized = into_storage_changes(initialized)?;
+		let applied = into_storage_changes(applied)?;
+
+		let storage_changes = diff(
+			initialized.main_storage_changes,
+			applied.main_storage_changes,
+			|key| state.storage(key),
+		)?;
+
+		let mut before_children = initialized.child_storage_changes.into_iter()
+			.collect::<HashMap<_, _>>();
+		let mut child_storage_changes = Vec::new();
+		for (storage_key, changes) in applied.child_storage_changes {
+			let before = before_children.remove(&storage_key).unwrap_or_default();
+			let child_info = ChildInfo::new_default(&storage_key);
+			let changes = diff(before, changes, |key| state.child_storage(&child_info, key))?;
+			if !changes.is_empty() {
+				child_storage_changes.push((storage_key, changes));
+			}
+		}
+
+		Ok(DryRun { result, storage_changes, child_storage_changes })
+	}
+
 	/// Create the inherents for the block.
 	///
 	/// Returns the inherents created by the runtime or an error if something failed.
@@ -245,9 +353,11 @@ where
 mod tests {
 	use super::*;
 	use sp_blockchain::HeaderBackend;
-	use sp_core::Blake2Hasher;
+	use sp_core::{Blake2Hasher, storage::well_known_keys};
 	use sp_state_machine::Backend;
-	use substrate_test_runtime_client::{DefaultTestClientBuilderExt, TestClientBuilderExt};
+	use substrate_test_runtime_client::{
+		DefaultTestClientBuilderExt, TestClientBuilderExt, runtime::Extrinsic,
+	};
 
 	#[test]
 	fn block_building_storage_proof_does_not_include_runtime_by_default() {
@@ -277,4 +387,32 @@ mod tests {
 				.contains("Database missing expected key"),
 		);
 	}
+
+	#[test]
+	fn dry_run_returns_changes_of_extrinsic_only() {
+		let builder = substrate_test_runtime_client::TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = builder.build();
+		let xt = Extrinsic::StorageChange(b"key".to_vec(), Some(b"value".to_vec()));
+
+		let dry_run = BlockBuilder::dry_run(
+			&client,
+			client.info().best_hash,
+			client.info().best_number,
+			xt,
+			&*backend,
+		).unwrap();
+
+		assert_eq!(dry_run.result, Ok(Ok(())));
+		assert!(dry_run.storage_changes.contains(
+			&(b"key".to_vec(), None, Some(b"value".to_vec())),
+		));
+		// Initializing the block sets the extrinsic index, which the extrinsic increments.
+		assert!(dry_run.storage_changes.contains(&(
+			well_known_keys::EXTRINSIC_INDEX.to_vec(),
+			Some(0u32.encode()),
+			Some(1u32.encode()),
+		)));
+		assert_eq!(client.info().best_number, 0);
+	}
 }
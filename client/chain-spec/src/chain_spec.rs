@@ -26,7 +26,9 @@ use sp_runtime::BuildStorage;
 use serde_json as json;
 use crate::{RuntimeGenesis, ChainType, extension::GetExtension, Properties};
 use sc_network::config::MultiaddrWithPeerId;
+use sc_network::warp_sync::WarpSyncFragment;
 use sc_telemetry::TelemetryEndpoints;
+use sp_runtime::Justification;
 use sp_runtime::traits::{Block as BlockT, NumberFor};
 
 enum GenesisSource<G> {
@@ -266,6 +268,11 @@ impl<G, E> ChainSpec<G, E> {
 	fn set_light_sync_state(&mut self, light_sync_state: SerializableLightSyncState) {
 		self.client_spec.light_sync_state = Some(light_sync_state);
 	}
+
+	/// Hardcoded infomation to allow clients to sync quickly, if any.
+	fn light_sync_state(&self) -> Option<&SerializableLightSyncState> {
+		self.client_spec.light_sync_state.as_ref()
+	}
 }
 
 impl<G, E: serde::de::DeserializeOwned> ChainSpec<G, E> {
@@ -395,12 +402,20 @@ where
 	fn set_light_sync_state(&mut self, light_sync_state: SerializableLightSyncState) {
 		ChainSpec::set_light_sync_state(self, light_sync_state)
 	}
+
+	fn light_sync_state(&self) -> Option<&SerializableLightSyncState> {
+		ChainSpec::light_sync_state(self)
+	}
 }
 
 /// Hardcoded infomation that allows light clients to sync quickly.
+///
+/// Full nodes can use it as a trusted checkpoint as well, see [`LightSyncState::to_checkpoint`].
 pub struct LightSyncState<Block: BlockT> {
 	/// The header of the best finalized block.
 	pub finalized_block_header: <Block as BlockT>::Header,
+	/// The justification of the best finalized block, if it has one.
+	pub finalized_block_justification: Option<Justification>,
 	/// The epoch changes tree for babe.
 	pub babe_epoch_changes: sc_consensus_epochs::EpochChangesFor<Block, sc_consensus_babe::Epoch>,
 	/// The babe weight of the finalized block.
@@ -416,6 +431,8 @@ impl<Block: BlockT> LightSyncState<Block> {
 
 		SerializableLightSyncState {
 			finalized_block_header: StorageData(self.finalized_block_header.encode()),
+			finalized_block_justification:
+				self.finalized_block_justification.clone().map(StorageData),
 			babe_epoch_changes:
 				StorageData(self.babe_epoch_changes.encode()),
 			babe_finalized_block_weight:
@@ -429,6 +446,8 @@ impl<Block: BlockT> LightSyncState<Block> {
 	pub fn from_serializable(serialized: &SerializableLightSyncState) -> Result<Self, codec::Error> {
 		Ok(Self {
 			finalized_block_header: codec::Decode::decode(&mut &serialized.finalized_block_header.0[..])?,
+			finalized_block_justification:
+				serialized.finalized_block_justification.as_ref().map(|j| j.0.clone()),
 			babe_epoch_changes:
 				codec::Decode::decode(&mut &serialized.babe_epoch_changes.0[..])?,
 			babe_finalized_block_weight:
@@ -437,6 +456,23 @@ impl<Block: BlockT> LightSyncState<Block> {
 				codec::Decode::decode(&mut &serialized.grandpa_authority_set.0[..])?,
 		})
 	}
+
+	/// The finalized block as a checkpoint that full nodes sync from instead of genesis.
+	///
+	/// The checkpoint is trusted as is, its justification is not verified. Returns `None` if the
+	/// justification is missing.
+	pub fn to_checkpoint(&self) -> Option<WarpSyncFragment<Block>> {
+		let justification = self.finalized_block_justification.clone()?;
+		let (set_id, authorities) =
+			sc_finality_grandpa::SharedAuthoritySet::from(self.grandpa_authority_set.clone())
+				.current_authority_list();
+		Some(WarpSyncFragment {
+			header: self.finalized_block_header.clone(),
+			justification,
+			set_id,
+			authorities,
+		})
+	}
 }
 
 /// The serializable form of `LightSyncState`. Created using `LightSyncState::serialize`.
@@ -445,6 +481,8 @@ impl<Block: BlockT> LightSyncState<Block> {
 #[serde(deny_unknown_fields)]
 pub struct SerializableLightSyncState {
 	finalized_block_header: StorageData,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	finalized_block_justification: Option<StorageData>,
 	babe_epoch_changes: StorageData,
 	babe_finalized_block_weight: sp_consensus_babe::BabeBlockWeight,
 	grandpa_authority_set: StorageData,
@@ -500,4 +538,17 @@ mod tests {
 
 		assert_eq!(spec.extensions().my_property, "Test Extension");
 	}
+
+	#[test]
+	fn should_deserialize_light_sync_state_without_justification() {
+		let sync_state: SerializableLightSyncState = json::from_str(r#"{
+			"finalizedBlockHeader": "0x00",
+			"babeEpochChanges": "0x01",
+			"babeFinalizedBlockWeight": 2,
+			"grandpaAuthoritySet": "0x03"
+		}"#).unwrap();
+
+		assert!(sync_state.finalized_block_justification.is_none());
+		assert!(!json::to_string(&sync_state).unwrap().contains("finalizedBlockJustification"));
+	}
 }
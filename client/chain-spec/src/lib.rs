@@ -161,6 +161,10 @@ pub trait ChainSpec: BuildStorage + Send + Sync {
 	fn set_storage(&mut self, storage: Storage);
 	/// Hardcode infomation to allow light clients to sync quickly into the chain spec.
 	fn set_light_sync_state(&mut self, light_sync_state: SerializableLightSyncState);
+	/// Hardcoded infomation to allow clients to sync quickly, if any.
+	fn light_sync_state(&self) -> Option<&SerializableLightSyncState> {
+		None
+	}
 }
 
 impl std::fmt::Debug for dyn ChainSpec {
@@ -168,7 +168,7 @@ impl Into<sc_service::config::RpcMethods> for RpcMethods {
 arg_enum! {
 	/// Database backend
 	#[allow(missing_docs)]
-	#[derive(Debug, Clone, Copy)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 	pub enum Database {
 		// Facebooks RocksDB
 		RocksDb,
@@ -177,6 +177,27 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// How the node catches up with the chain.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum SyncMode {
+		// Download and import every block.
+		Full,
+		// Skip to the latest finalized block using a GRANDPA warp sync proof, and download its
+		// state.
+		Warp,
+	}
+}
+
+impl Into<sc_network::config::SyncMode> for SyncMode {
+	fn into(self) -> sc_network::config::SyncMode {
+		match self {
+			SyncMode::Full => sc_network::config::SyncMode::Full,
+			SyncMode::Warp => sc_network::config::SyncMode::Warp,
+		}
+	}
+}
 
 arg_enum! {
 	/// Whether off-chain workers are enabled.
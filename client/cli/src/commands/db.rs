@@ -0,0 +1,60 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Offline database maintenance utilities.
+
+use crate::{Error, SubstrateCli};
+use sp_runtime::traits::Block as BlockT;
+use structopt::StructOpt;
+
+use super::{
+	db_import_snapshot_cmd::DbImportSnapshotCmd, db_migrate_cmd::DbMigrateCmd, db_prune_cmd::DbPruneCmd,
+};
+
+/// Database utilities for the cli.
+#[derive(Debug, StructOpt)]
+pub enum DbSubcommand {
+	/// Prune the state of an archive database, turning it into a pruned one.
+	Prune(DbPruneCmd),
+
+	/// Copy the database to a different backend, e.g. from RocksDb to ParityDb.
+	Migrate(DbMigrateCmd),
+
+	/// Initialize an empty database from a state snapshot.
+	ImportSnapshot(DbImportSnapshotCmd),
+}
+
+impl DbSubcommand {
+	/// run the db subcommands
+	pub fn run<B: BlockT, C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		match self {
+			DbSubcommand::Prune(cmd) => {
+				let runner = cli.create_runner(cmd)?;
+				runner.sync_run(|config| cmd.run::<B>(config))
+			},
+			DbSubcommand::Migrate(cmd) => {
+				let runner = cli.create_runner(cmd)?;
+				runner.sync_run(|config| cmd.run::<B>(config))
+			},
+			DbSubcommand::ImportSnapshot(cmd) => {
+				let runner = cli.create_runner(cmd)?;
+				runner.sync_run(|config| cmd.run::<B>(config))
+			},
+		}
+	}
+}
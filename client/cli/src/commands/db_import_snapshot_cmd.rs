@@ -0,0 +1,71 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use sc_service::{Configuration, chain_ops::import_state_snapshot};
+use sp_runtime::traits::Block as BlockT;
+use std::{fs, io::BufReader, path::PathBuf};
+use structopt::StructOpt;
+
+/// The `db import-snapshot` command used to initialize a fresh database from a state snapshot.
+///
+/// Snapshots are created with `export-state --snapshot`. The node continues syncing from the
+/// snapshot block afterwards.
+#[derive(Debug, StructOpt)]
+pub struct DbImportSnapshotCmd {
+	/// Snapshot file to import.
+	#[structopt(parse(from_os_str))]
+	pub input: PathBuf,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl DbImportSnapshotCmd {
+	/// Run the import-snapshot command
+	pub fn run<B: BlockT>(&self, config: Configuration) -> error::Result<()> {
+		let file = BufReader::new(fs::File::open(&self.input)?);
+		import_state_snapshot::<B>(&config, file)?;
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbImportSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -0,0 +1,89 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::arg_enums::Database;
+use crate::error;
+use crate::params::SharedParams;
+use crate::CliConfiguration;
+use sc_service::{Configuration, chain_ops::migrate_database};
+use sp_runtime::traits::Block as BlockT;
+use structopt::StructOpt;
+
+/// The `db migrate` command used to copy the database to a different backend.
+///
+/// The new database is created next to the existing one, which is left untouched. The node
+/// has to be started with `--database` set to the new backend to use it.
+#[derive(Debug, StructOpt)]
+pub struct DbMigrateCmd {
+	/// Database backend to migrate from. Only RocksDb is supported.
+	#[structopt(long, value_name = "DB", case_insensitive = true)]
+	pub from: Database,
+
+	/// Database backend to migrate to.
+	#[structopt(long, value_name = "DB", case_insensitive = true)]
+	pub to: Database,
+
+	/// Limit the memory the database cache can use.
+	#[structopt(long = "db-cache", value_name = "MiB")]
+	pub database_cache_size: Option<usize>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl DbMigrateCmd {
+	/// Run the migrate command
+	pub fn run<B: BlockT>(&self, config: Configuration) -> error::Result<()> {
+		if self.from == self.to {
+			return Err(error::Error::Input("Source and destination backends must differ".into()));
+		}
+		if self.from == Database::ParityDb {
+			return Err(error::Error::Input(
+				"Migrating from ParityDb is not supported, as it does not store the trie paths \
+				of the state that the other backends need".into(),
+			));
+		}
+		let base_path = config.database.path()
+			.and_then(|path| path.parent())
+			.ok_or_else(|| error::Error::Input("Database is not stored on disk".into()))?
+			.to_path_buf();
+		let destination = self.database_config(
+			&base_path,
+			self.database_cache_size.unwrap_or(128),
+			self.to,
+		)?;
+
+		migrate_database::<B>(&config, destination)?;
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbMigrateCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database(&self) -> error::Result<Option<Database>> {
+		Ok(Some(self.from))
+	}
+
+	fn database_cache_size(&self) -> error::Result<Option<usize>> {
+		Ok(self.database_cache_size)
+	}
+}
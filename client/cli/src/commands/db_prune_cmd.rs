@@ -0,0 +1,74 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use sc_service::{Configuration, PruningMode, chain_ops::prune_state};
+use sp_runtime::traits::Block as BlockT;
+use structopt::StructOpt;
+
+/// The `db prune` command used to prune the state of an archive database offline.
+///
+/// The number of blocks whose state is kept has to be given with `--pruning`, the default
+/// pruning mode of the node is not applied. Afterwards the node has to be started with the same
+/// pruning mode.
+#[derive(Debug, StructOpt)]
+pub struct DbPruneCmd {
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl DbPruneCmd {
+	/// Run the prune command
+	pub fn run<B: BlockT>(&self, config: Configuration) -> error::Result<()> {
+		let keep_blocks = match (&self.pruning_params.pruning, &config.state_pruning) {
+			(Some(_), PruningMode::Constrained(constraints)) => constraints.max_blocks.unwrap_or(0),
+			_ => return Err(error::Error::Input(
+				"The number of blocks to keep the state of must be given, e.g. `--pruning 1000`"
+					.into()
+			)),
+		};
+
+		prune_state::<B>(&config, keep_blocks)?;
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbPruneCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+}
@@ -0,0 +1,106 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `encrypt-keystore` subcommand
+
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use structopt::StructOpt;
+use sc_keystore::LocalKeystore;
+use sc_service::config::{KeystoreConfig, BasePath};
+
+/// The `encrypt-keystore` command
+#[derive(Debug, StructOpt)]
+#[structopt(
+	name = "encrypt-keystore",
+	about = "Encrypt the plaintext key files of a keystore with its password."
+)]
+pub struct EncryptKeystoreCmd {
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl EncryptKeystoreCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let base_path = self.shared_params
+			.base_path()
+			.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
+		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
+		let chain_spec = cli.load_spec(&chain_id)?;
+		let config_dir = base_path.config_dir(chain_spec.id());
+
+		self.encrypt(self.keystore_params.keystore_config(&config_dir)?.1)
+	}
+
+	/// Run the command without a chain spec.
+	///
+	/// The keystore has to be given with `--keystore-path`.
+	pub fn run_standalone(&self) -> Result<(), Error> {
+		self.encrypt(self.keystore_params.standalone_keystore_config()?)
+	}
+
+	fn encrypt(&self, keystore_config: KeystoreConfig) -> Result<(), Error> {
+		match keystore_config {
+			KeystoreConfig::Path { password: None, .. } =>
+				Err(Error::Input("A keystore password is required".into())),
+			KeystoreConfig::Path { path, password } => {
+				let encrypted = LocalKeystore::open(path, password)?.encrypt_key_files()?;
+				println!("Encrypted {} key files", encrypted);
+				Ok(())
+			},
+			_ => Err(Error::Input("Only local keystores can be encrypted".into())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+	use sp_core::{sr25519::{self, Pair}, Pair as _, crypto::{CryptoTypePublicPair, KeyTypeId}};
+	use sp_keystore::SyncCryptoStore;
+
+	#[test]
+	fn encrypts_plaintext_keystore() {
+		let path = TempDir::new().unwrap();
+		let path_str = format!("{}", path.path().display());
+		let (key, uri, _) = Pair::generate_with_phrase(Some("password"));
+		let key_type = KeyTypeId(*b"test");
+
+		let keystore = LocalKeystore::open(path.path(), None).unwrap();
+		SyncCryptoStore::insert_unknown(&keystore, key_type, &uri, key.public().as_ref()).unwrap();
+
+		let cmd = EncryptKeystoreCmd::from_iter(&["encrypt-keystore", "--keystore-path", &path_str]);
+		assert!(cmd.run_standalone().is_err());
+
+		let cmd = EncryptKeystoreCmd::from_iter(
+			&["encrypt-keystore", "--keystore-path", &path_str, "--password", "password"],
+		);
+		assert!(cmd.run_standalone().is_ok());
+
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, key.public().to_vec());
+		let keystore = LocalKeystore::open(path.path(), Some("password".parse().unwrap())).unwrap();
+		assert!(SyncCryptoStore::sign_with(&keystore, key_type, &key, b"message").is_ok());
+		let keystore = LocalKeystore::open(path.path(), None).unwrap();
+		assert!(SyncCryptoStore::sign_with(&keystore, key_type, &key, b"message").is_err());
+	}
+}
@@ -21,9 +21,10 @@ use crate::{
 };
 use log::info;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
-use std::{fmt::Debug, str::FromStr, io::Write, sync::Arc};
+use std::{fmt::Debug, str::FromStr, fs, io::{BufWriter, Write}, path::PathBuf, sync::Arc};
 use structopt::StructOpt;
 use sc_client_api::{StorageProvider, UsageProvider};
+use sp_blockchain::HeaderBackend;
 
 /// The `export-state` command used to export the state of a given block into
 /// a chain spec.
@@ -33,6 +34,12 @@ pub struct ExportStateCmd {
 	#[structopt(value_name = "HASH or NUMBER")]
 	pub input: Option<BlockNumberOrHash>,
 
+	/// Write a binary state snapshot to the given file instead of a chain spec.
+	///
+	/// The snapshot can be imported by a fresh node with `db import-snapshot`.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub snapshot: Option<PathBuf>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -45,20 +52,47 @@ pub struct ExportStateCmd {
 impl ExportStateCmd {
 	/// Run the `export-state` command
 	pub async fn run<B, BA, C>(
+		&self,
+		client: Arc<C>,
+		input_spec: Box<dyn sc_service::ChainSpec>,
+	) -> error::Result<()>
+	where
+		B: BlockT,
+		C: UsageProvider<B> + StorageProvider<B, BA> + HeaderBackend<B>,
+		BA: sc_client_api::backend::Backend<B>,
+		B::Hash: FromStr,
+		<B::Hash as FromStr>::Err: Debug,
+		<<B::Header as HeaderT>::Number as FromStr>::Err: Debug,
+	{
+		self.run_with_aux_data(client, input_spec, |_| Ok(Vec::new())).await
+	}
+
+	/// Run the `export-state` command, including the auxiliary entries returned by `aux_data`
+	/// for the exported block in a snapshot.
+	///
+	/// Nodes use this to carry the consensus data needed to continue from the snapshot block.
+	pub async fn run_with_aux_data<B, BA, C>(
 		&self,
 		client: Arc<C>,
 		mut input_spec: Box<dyn sc_service::ChainSpec>,
+		aux_data: impl FnOnce(&B::Header) -> sp_blockchain::Result<Vec<(Vec<u8>, Vec<u8>)>>,
 	) -> error::Result<()>
 	where
 		B: BlockT,
-		C: UsageProvider<B> + StorageProvider<B, BA>,
+		C: UsageProvider<B> + StorageProvider<B, BA> + HeaderBackend<B>,
 		BA: sc_client_api::backend::Backend<B>,
 		B::Hash: FromStr,
 		<B::Hash as FromStr>::Err: Debug,
 		<<B::Header as HeaderT>::Number as FromStr>::Err: Debug,
 	{
-		info!("Exporting raw state...");
 		let block_id = self.input.as_ref().map(|b| b.parse()).transpose()?;
+		if let Some(path) = &self.snapshot {
+			let file = BufWriter::new(fs::File::create(path)?);
+			sc_service::chain_ops::export_state_snapshot(client, block_id, aux_data, file)?;
+			return Ok(())
+		}
+
+		info!("Exporting raw state...");
 		let raw_state = sc_service::chain_ops::export_raw_state(client, block_id)?;
 		input_spec.set_storage(raw_state);
 
@@ -25,7 +25,7 @@ use std::{sync::Arc, convert::TryFrom};
 use structopt::StructOpt;
 use sp_core::{crypto::KeyTypeId, crypto::SecretString};
 use sp_keystore::{SyncCryptoStorePtr, SyncCryptoStore};
-use sc_keystore::LocalKeystore;
+use sc_keystore::{LocalKeystore, RemoteKeystore};
 use sc_service::config::{KeystoreConfig, BasePath};
 
 /// The `insert` command
@@ -61,7 +61,6 @@ pub struct InsertKeyCmd {
 impl InsertKeyCmd {
 	/// Run the command
 	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
-		let suri = utils::read_uri(self.suri.as_ref())?;
 		let base_path = self.shared_params
 			.base_path()
 			.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
@@ -69,8 +68,20 @@ impl InsertKeyCmd {
 		let chain_spec = cli.load_spec(&chain_id)?;
 		let config_dir = base_path.config_dir(chain_spec.id());
 
-		let (keystore, public) = match self.keystore_params.keystore_config(&config_dir)? {
-			(_, KeystoreConfig::Path { path, password }) => {
+		self.insert(self.keystore_params.keystore_config(&config_dir)?.1)
+	}
+
+	/// Run the command without a chain spec.
+	///
+	/// The keystore has to be given with `--keystore-path` or `--keystore-uri`.
+	pub fn run_standalone(&self) -> Result<(), Error> {
+		self.insert(self.keystore_params.standalone_keystore_config()?)
+	}
+
+	fn insert(&self, keystore_config: KeystoreConfig) -> Result<(), Error> {
+		let suri = utils::read_uri(self.suri.as_ref())?;
+		let (keystore, public) = match keystore_config {
+			KeystoreConfig::Path { path, password } => {
 				let public = with_crypto_scheme!(
 					self.crypto_scheme.scheme,
 					to_vec(&suri, password.clone())
@@ -78,7 +89,15 @@ impl InsertKeyCmd {
 				let keystore: SyncCryptoStorePtr = Arc::new(LocalKeystore::open(path, password)?);
 				(keystore, public)
 			},
-			_ => unreachable!("keystore_config always returns path and password; qed")
+			KeystoreConfig::Remote { signer } => {
+				let public = with_crypto_scheme!(
+					self.crypto_scheme.scheme,
+					to_vec(&suri, None)
+				)?;
+				let keystore: SyncCryptoStorePtr = Arc::new(RemoteKeystore::new(signer)?);
+				(keystore, public)
+			},
+			KeystoreConfig::InMemory => unreachable!("keystore_config never returns an in-memory keystore; qed")
 		};
 
 		let key_type = KeyTypeId::try_from(self.key_type.as_str()).map_err(|_| Error::KeyTypeInvalid)?;
@@ -26,6 +26,8 @@ use super::{
 	generate::GenerateCmd,
 	inspect_node_key::InspectNodeKeyCmd,
 	generate_node_key::GenerateNodeKeyCmd,
+	encrypt_keystore::EncryptKeystoreCmd,
+	slashing_protection::{ExportSlashingProtectionCmd, ImportSlashingProtectionCmd},
 };
 
 /// Key utilities for the cli.
@@ -46,6 +48,15 @@ pub enum KeySubcommand {
 
 	/// Insert a key to the keystore of a node.
 	Insert(InsertKeyCmd),
+
+	/// Encrypt the plaintext key files of a keystore with its password.
+	EncryptKeystore(EncryptKeystoreCmd),
+
+	/// Export the signing history of the node's keys.
+	ExportSlashingProtection(ExportSlashingProtectionCmd),
+
+	/// Import the signing history of keys moved from another node.
+	ImportSlashingProtection(ImportSlashingProtectionCmd),
 }
 
 impl KeySubcommand {
@@ -57,6 +68,9 @@ impl KeySubcommand {
 			KeySubcommand::InspectKey(cmd) => cmd.run(),
 			KeySubcommand::Insert(cmd) => cmd.run(cli),
 			KeySubcommand::InspectNodeKey(cmd) => cmd.run(),
+			KeySubcommand::EncryptKeystore(cmd) => cmd.run(cli),
+			KeySubcommand::ExportSlashingProtection(cmd) => cmd.run(cli),
+			KeySubcommand::ImportSlashingProtection(cmd) => cmd.run(cli),
 		}
 	}
 }
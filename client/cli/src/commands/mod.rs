@@ -17,11 +17,17 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 mod build_spec_cmd;
 mod check_block_cmd;
+mod db;
+mod db_import_snapshot_cmd;
+mod db_migrate_cmd;
+mod db_prune_cmd;
 mod export_blocks_cmd;
 mod export_state_cmd;
+mod encrypt_keystore;
 mod import_blocks_cmd;
 mod purge_chain_cmd;
 mod sign;
+mod slashing_protection;
 mod verify;
 mod vanity;
 mod revert_cmd;
@@ -37,11 +43,17 @@ pub mod utils;
 pub use self::{
 	build_spec_cmd::BuildSpecCmd,
 	check_block_cmd::CheckBlockCmd,
+	db::DbSubcommand,
+	db_import_snapshot_cmd::DbImportSnapshotCmd,
+	db_migrate_cmd::DbMigrateCmd,
+	db_prune_cmd::DbPruneCmd,
 	export_blocks_cmd::ExportBlocksCmd,
 	export_state_cmd::ExportStateCmd,
+	encrypt_keystore::EncryptKeystoreCmd,
 	import_blocks_cmd::ImportBlocksCmd,
 	purge_chain_cmd::PurgeChainCmd,
 	sign::SignCmd,
+	slashing_protection::{ExportSlashingProtectionCmd, ImportSlashingProtectionCmd},
 	generate::GenerateCmd,
 	insert_key::InsertKeyCmd,
 	inspect_key::InspectKeyCmd,
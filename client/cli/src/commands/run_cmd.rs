@@ -27,11 +27,15 @@ use crate::params::TransactionPoolParams;
 use crate::CliConfiguration;
 use regex::Regex;
 use sc_service::{
-	config::{BasePath, MultiaddrWithPeerId, PrometheusConfig, TransactionPoolOptions},
+	config::{
+		BasePath, MultiaddrWithPeerId, PrometheusConfig, RpcAccessPolicy, RpcLimits,
+		TransactionPoolOptions,
+	},
 	ChainSpec, Role,
 };
 use sc_telemetry::TelemetryEndpoints;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 /// The `run` command used to run a node.
@@ -103,6 +107,16 @@ pub struct RunCmd {
 	)]
 	pub rpc_methods: RpcMethods,
 
+	/// Path to a JSON file restricting which RPC methods each caller may call over HTTP & WS.
+	///
+	/// The file lists the methods anyone may call, and the methods callers presenting each API
+	/// key may call. Over HTTP the key is passed in an `Authorization: Bearer <key>` header, over
+	/// WebSocket as a `bearer.<key>` sub-protocol. The policy applies on top of `--rpc-methods`:
+	/// letting key holders call unsafe methods on an external interface also requires
+	/// `--rpc-methods Unsafe`.
+	#[structopt(long = "rpc-access-policy", value_name = "PATH", parse(from_os_str))]
+	pub rpc_access_policy: Option<PathBuf>,
+
 	/// Listen to all Websocket interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
@@ -139,6 +153,54 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Maximum number of subscriptions open on a single WS RPC server connection.
+	#[structopt(long = "ws-max-subscriptions-per-connection", value_name = "COUNT")]
+	pub ws_max_subscriptions_per_connection: Option<usize>,
+
+	/// Maximum cost of the RPC calls a single WS connection may make per second.
+	///
+	/// Calls cost 1, except for a few methods that can keep the node busy for a long time, and
+	/// the ones set with `--rpc-method-cost`. Unused allowance accumulates for up to 10 seconds.
+	/// HTTP calls count as one connection per IP address, and calls from unknown addresses as
+	/// a single connection.
+	#[structopt(long = "rpc-rate-limit", value_name = "COST")]
+	pub rpc_rate_limit: Option<u32>,
+
+	/// Maximum cost of the RPC calls a single IP address may make per second.
+	///
+	/// The address is read from the `X-Forwarded-For` or `X-Real-IP` header set by one of the
+	/// `--rpc-trusted-proxy`, so this limit only applies to HTTP calls made through them.
+	#[structopt(long = "rpc-rate-limit-per-ip", value_name = "COST")]
+	pub rpc_rate_limit_per_ip: Option<u32>,
+
+	/// Address of a reverse proxy trusted to report the address of HTTP RPC callers in the
+	/// `X-Forwarded-For` or `X-Real-IP` header.
+	///
+	/// The headers are ignored unless at least one proxy is given. The HTTP RPC server must then
+	/// only be reachable through the proxies.
+	#[structopt(long = "rpc-trusted-proxy", value_name = "IP")]
+	pub rpc_trusted_proxies: Vec<IpAddr>,
+
+	/// Cost of the calls to an RPC method, counted against the rate limits.
+	///
+	/// Given as `<method>=<cost>`, where `<method>` is the name of a method or a `<prefix>*`
+	/// pattern.
+	#[structopt(
+		long = "rpc-method-cost",
+		value_name = "METHOD=COST",
+		parse(try_from_str = parse_method_cost)
+	)]
+	pub rpc_method_costs: Vec<(String, u32)>,
+
+	/// Maximum size in megabytes of the response to an HTTP or WS RPC call.
+	///
+	/// Calls reading many storage entries, such as `state_getPairs` and `state_queryStorage`,
+	/// fail once their response exceeds it. The limit also applies to all the responses of a
+	/// batch together: responses which don't fit are replaced with errors. Large state queries
+	/// can use `state_streamStoragePairs` instead.
+	#[structopt(long = "rpc-max-response-size", value_name = "MB")]
+	pub rpc_max_response_size: Option<usize>,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -315,6 +377,10 @@ impl CliConfiguration for RunCmd {
 		Some(&self.offchain_worker_params)
 	}
 
+	fn transaction_pool_params(&self) -> Option<&TransactionPoolParams> {
+		Some(&self.pool_config)
+	}
+
 	fn node_name(&self) -> Result<String> {
 		let name: String = match (self.name.as_ref(), self.get_keyring()) {
 			(Some(name), _) => name.to_string(),
@@ -406,10 +472,32 @@ impl CliConfiguration for RunCmd {
 		Ok(self.no_grandpa)
 	}
 
+	fn rpc_access_policy(&self) -> Result<Option<RpcAccessPolicy>> {
+		self.rpc_access_policy.as_ref().map(|path| {
+			RpcAccessPolicy::load(path).map_err(|e| Error::Input(format!(
+				"Invalid RPC access policy {}: {}",
+				path.display(),
+				e,
+			)))
+		}).transpose()
+	}
+
 	fn rpc_ws_max_connections(&self) -> Result<Option<usize>> {
 		Ok(self.ws_max_connections)
 	}
 
+	fn rpc_limits(&self) -> Result<RpcLimits> {
+		Ok(RpcLimits {
+			per_connection: self.rpc_rate_limit,
+			per_ip: self.rpc_rate_limit_per_ip,
+			max_subscriptions_per_connection: self.ws_max_subscriptions_per_connection,
+			method_costs: self.rpc_method_costs.iter().cloned().collect(),
+			max_response_size: self.rpc_max_response_size
+				.map(|mb| mb.saturating_mul(1024 * 1024)),
+			trusted_proxies: self.rpc_trusted_proxies.clone(),
+		})
+	}
+
 	fn rpc_cors(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
 		Ok(self
 			.rpc_cors
@@ -603,6 +691,17 @@ fn parse_cors(s: &str) -> std::result::Result<Cors, Box<dyn std::error::Error>>
 	})
 }
 
+fn parse_method_cost(s: &str) -> std::result::Result<(String, u32), String> {
+	let mut parts = s.splitn(2, '=');
+	match (parts.next(), parts.next()) {
+		(Some(method), Some(cost)) if !method.is_empty() => cost
+			.parse()
+			.map(|cost| (method.to_owned(), cost))
+			.map_err(|e| format!("Invalid cost `{}`: {}", cost, e)),
+		_ => Err(format!("Expected `<method>=<cost>`, got `{}`", s)),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -623,4 +722,16 @@ mod tests {
 		assert!(is_node_name_valid("www.visit.me").is_err());
 		assert!(is_node_name_valid("email@domain").is_err());
 	}
+
+	#[test]
+	fn parses_method_costs() {
+		assert_eq!(
+			parse_method_cost("state_queryStorage=100"),
+			Ok(("state_queryStorage".into(), 100)),
+		);
+		assert_eq!(parse_method_cost("state_*=5"), Ok(("state_*".into(), 5)));
+		assert!(parse_method_cost("state_queryStorage").is_err());
+		assert!(parse_method_cost("=5").is_err());
+		assert!(parse_method_cost("state_queryStorage=lots").is_err());
+	}
 }
@@ -0,0 +1,99 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `export-slashing-protection` and `import-slashing-protection`
+//! subcommands
+
+use crate::{Error, SharedParams, SubstrateCli};
+use std::{fs, io::Write, path::PathBuf};
+use structopt::StructOpt;
+use sc_consensus::slashing_protection::{self, SlashingProtection};
+use sc_service::config::{BasePath, SLASHING_PROTECTION_FILE};
+
+/// The `export-slashing-protection` command
+#[derive(Debug, StructOpt)]
+#[structopt(
+	name = "export-slashing-protection",
+	about = "Export the signing history of the node's keys."
+)]
+pub struct ExportSlashingProtectionCmd {
+	/// Output file name or stdout if unspecified.
+	#[structopt(parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportSlashingProtectionCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let store = SlashingProtection::open(store_path(&self.shared_params, cli)?)
+			.map_err(|e| Error::Input(e.to_string()))?;
+		let history = serde_json::to_vec_pretty(&store.export())
+			.map_err(|e| Error::Input(e.to_string()))?;
+
+		match &self.output {
+			Some(output) => fs::write(output, history)?,
+			None => std::io::stdout().write_all(&history)?,
+		}
+		Ok(())
+	}
+}
+
+/// The `import-slashing-protection` command
+#[derive(Debug, StructOpt)]
+#[structopt(
+	name = "import-slashing-protection",
+	about = "Import the signing history of keys moved from another node. \
+		The node must not be running."
+)]
+pub struct ImportSlashingProtectionCmd {
+	/// File with the history exported from the other node.
+	#[structopt(parse(from_os_str))]
+	pub input: PathBuf,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ImportSlashingProtectionCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let history = slashing_protection::parse(&fs::read(&self.input)?)
+			.map_err(|e| Error::Input(e.to_string()))?;
+		let path = store_path(&self.shared_params, cli)?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		SlashingProtection::open(path)
+			.and_then(|store| store.import(history))
+			.map_err(|e| Error::Input(e.to_string()))
+	}
+}
+
+fn store_path<C: SubstrateCli>(shared_params: &SharedParams, cli: &C) -> Result<PathBuf, Error> {
+	let base_path = shared_params
+		.base_path()
+		.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
+	let chain_id = shared_params.chain_id(shared_params.is_dev());
+	let chain_spec = cli.load_spec(&chain_id)?;
+	Ok(base_path.config_dir(chain_spec.id()).join(SLASHING_PROTECTION_FILE))
+}
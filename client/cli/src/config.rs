@@ -22,21 +22,24 @@ use crate::arg_enums::Database;
 use crate::error::Result;
 use crate::{
 	DatabaseParams, ImportParams, KeystoreParams, NetworkParams, NodeKeyParams,
-	OffchainWorkerParams, PruningParams, SharedParams, SubstrateCli,
+	OffchainWorkerParams, PruningParams, SharedParams, SubstrateCli, TransactionPoolParams,
 };
 use log::warn;
 use names::{Generator, Name};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::config::{
 	BasePath, Configuration, DatabaseConfig, ExtTransport, KeystoreConfig, NetworkConfiguration,
-	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-	TaskExecutor, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcAccessPolicy,
+	RpcLimits, RpcMethods, TaskExecutor, TelemetryEndpoints, TransactionPoolOptions,
+	WasmExecutionMethod,
+};
+use sc_service::{
+	ChainSpec, TracingReceiver, KeepBlocks, TransactionStorageMode, EventIndexConfig,
 };
-use sc_service::{ChainSpec, TracingReceiver, KeepBlocks, TransactionStorageMode};
 use sc_telemetry::{TelemetryHandle, TelemetrySpan};
 use sc_tracing::logging::LoggerBuilder;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// The maximum number of characters for a node name.
 pub(crate) const NODE_NAME_MAX_LENGTH: usize = 64;
@@ -44,6 +47,9 @@ pub(crate) const NODE_NAME_MAX_LENGTH: usize = 64;
 /// Default sub directory to store network config.
 pub(crate) const DEFAULT_NETWORK_CONFIG_PATH: &'static str = "network";
 
+/// Default file name of the transaction pool journal.
+pub(crate) const DEFAULT_TRANSACTION_POOL_JOURNAL: &'static str = "txpool_journal";
+
 /// The recommended open file descriptor limit to be configured for the process.
 const RECOMMENDED_OPEN_FILE_DESCRIPTOR_LIMIT: u64 = 10_000;
 
@@ -113,6 +119,11 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		None
 	}
 
+	/// Get the TransactionPoolParams for this object
+	fn transaction_pool_params(&self) -> Option<&TransactionPoolParams> {
+		None
+	}
+
 	/// Get the NodeKeyParams for this object
 	fn node_key_params(&self) -> Option<&NodeKeyParams> {
 		self.network_params().map(|x| &x.node_key_params)
@@ -151,6 +162,14 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the path of the transaction pool journal
+	///
+	/// By default this is retrieved from `TransactionPoolParams` if it is available. Otherwise the
+	/// transaction pool is not journaled.
+	fn transaction_pool_journal(&self, config_dir: &Path) -> Result<Option<PathBuf>> {
+		Ok(self.transaction_pool_params().and_then(|x| x.journal_path(config_dir)))
+	}
+
 	/// Get the network configuration
 	///
 	/// By default this is retrieved from `NetworkParams` if it is available otherwise it creates
@@ -212,6 +231,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			.unwrap_or(TransactionStorageMode::BlockBody))
 	}
 
+	/// Get the event index configuration, if the events of finalized blocks should be indexed.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn database_event_index(&self) -> Result<Option<EventIndexConfig>> {
+		Ok(self.database_params().and_then(|x| x.event_index()))
+	}
+
 	/// Get the database backend variant.
 	///
 	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
@@ -350,6 +376,20 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the per-method access policy of the HTTP & WS RPC servers (`None` if disabled).
+	///
+	/// By default this is `None`.
+	fn rpc_access_policy(&self) -> Result<Option<RpcAccessPolicy>> {
+		Ok(None)
+	}
+
+	/// Get the rate limits and quotas of the HTTP & WS RPC servers.
+	///
+	/// By default there is no limit.
+	fn rpc_limits(&self) -> Result<RpcLimits> {
+		Ok(Default::default())
+	}
+
 	/// Get the RPC websockets maximum connections (`None` if unlimited).
 	///
 	/// By default this is `None`.
@@ -501,11 +541,14 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			.map(|p| p.unsafe_pruning)
 			.unwrap_or(false);
 
+		let mut transaction_pool = self.transaction_pool()?;
+		transaction_pool.journal = self.transaction_pool_journal(&config_dir)?;
+
 		Ok(Configuration {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			task_executor,
-			transaction_pool: self.transaction_pool()?,
+			transaction_pool,
 			network: self.network_config(
 				&chain_spec,
 				is_dev,
@@ -523,6 +566,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			state_pruning: self.state_pruning(unsafe_pruning, &role)?,
 			keep_blocks: self.keep_blocks()?,
 			transaction_storage: self.database_transaction_storage()?,
+			event_index: self.database_event_index()?,
 			wasm_method: self.wasm_method()?,
 			wasm_runtime_overrides: self.wasm_runtime_overrides(),
 			execution_strategies: self.execution_strategies(is_dev, is_validator)?,
@@ -531,6 +575,8 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			rpc_ipc: self.rpc_ipc()?,
 			rpc_methods: self.rpc_methods()?,
 			rpc_ws_max_connections: self.rpc_ws_max_connections()?,
+			rpc_access_policy: self.rpc_access_policy()?,
+			rpc_limits: self.rpc_limits()?,
 			rpc_cors: self.rpc_cors(is_dev)?,
 			prometheus_config: self.prometheus_config(DCV::prometheus_listen_port())?,
 			telemetry_endpoints,
@@ -18,7 +18,7 @@
 
 use crate::arg_enums::Database;
 use structopt::StructOpt;
-use sc_service::TransactionStorageMode;
+use sc_service::{TransactionStorageMode, EventIndexConfig};
 
 /// Parameters for block import.
 #[derive(Debug, StructOpt)]
@@ -44,6 +44,26 @@ pub struct DatabaseParams {
 	/// in the block body column.
 	#[structopt(long)]
 	pub storage_chain: bool,
+
+	/// Index the events of finalized blocks by account and topic.
+	///
+	/// The index is queried with the `chain_getAccountHistory` and `chain_getEventsByTopic`
+	/// RPC methods. Events can only be indexed while the state of their block is available,
+	/// so indexing starts at the oldest block with state and the whole chain is only indexed by
+	/// archive nodes.
+	#[structopt(long)]
+	pub event_index: bool,
+
+	/// Encoding of a runtime type named by the arguments of events, e.g. `Balance=u64`.
+	///
+	/// Extends and overrides the types of the usual FRAME runtime configuration used to decode
+	/// the events to index. Blocks with events of unknown types are not indexed.
+	#[structopt(
+		long = "event-index-type",
+		value_name = "NAME=TYPE",
+		parse(try_from_str = parse_type),
+	)]
+	pub event_index_types: Vec<(String, String)>,
 }
 
 impl DatabaseParams {
@@ -65,4 +85,20 @@ impl DatabaseParams {
 			TransactionStorageMode::BlockBody
 		}
 	}
+
+	/// Event index configuration, if enabled.
+	pub fn event_index(&self) -> Option<EventIndexConfig> {
+		if self.event_index {
+			Some(EventIndexConfig { types: self.event_index_types.clone() })
+		} else {
+			None
+		}
+	}
+}
+
+fn parse_type(s: &str) -> Result<(String, String), String> {
+	match s.find('=') {
+		Some(split) if split > 0 => Ok((s[..split].trim().to_owned(), s[split + 1..].to_owned())),
+		_ => Err(format!("Expected NAME=TYPE, got `{}`", s)),
+	}
 }
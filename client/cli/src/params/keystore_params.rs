@@ -18,6 +18,7 @@
 
 use crate::error::Result;
 use sc_service::config::KeystoreConfig;
+use sc_keystore::{RemoteSigner, SignerToken};
 use std::{fs, path::{PathBuf, Path}};
 use structopt::StructOpt;
 use crate::error;
@@ -29,10 +30,26 @@ const DEFAULT_KEYSTORE_CONFIG_PATH: &'static str = "keystore";
 /// Parameters of the keystore
 #[derive(Debug, StructOpt)]
 pub struct KeystoreParams {
-	/// Specify custom URIs to connect to for keystore-services
+	/// Specify custom URIs to connect to for keystore-services.
+	///
+	/// A remote signer given as `unix:<PATH>` or `https://<HOST>:<PORT>[/<PATH>]` keeps all
+	/// keys of the node instead of the local keystore. Plain `http://` is only accepted for a
+	/// signer on a loopback address.
 	#[structopt(long = "keystore-uri")]
 	pub keystore_uri: Option<String>,
 
+	/// File that contains the bearer token sent to a remote signer reached over HTTP.
+	///
+	/// Required for a signer reached over `https`, since anyone who can send requests to the
+	/// signer can sign with the keys of the node.
+	#[structopt(
+		long = "keystore-token-file",
+		value_name = "PATH",
+		parse(from_os_str),
+		requires = "keystore-uri"
+	)]
+	pub keystore_token_file: Option<PathBuf>,
+
 	/// Specify custom keystore path.
 	#[structopt(long = "keystore-path", value_name = "PATH", parse(from_os_str))]
 	pub keystore_path: Option<PathBuf>,
@@ -72,6 +89,20 @@ impl KeystoreParams {
 	///
 	/// Returns a vector of remote-urls and the local Keystore configuration
 	pub fn keystore_config(&self, config_dir: &Path) -> Result<(Option<String>, KeystoreConfig)> {
+		if let Some(uri) = &self.keystore_uri {
+			let mut signer = uri.parse::<RemoteSigner>().map_err(error::Error::Input)?;
+			if let Some(ref file) = self.keystore_token_file {
+				let token = fs::read_to_string(file).map_err(|e| format!("{}", e))?;
+				let token = SignerToken::new(&token).map_err(error::Error::Input)?;
+				signer = signer.with_token(token).map_err(error::Error::Input)?;
+			} else if let RemoteSigner::Http { tls: true, .. } = signer {
+				return Err(error::Error::Input(
+					"A remote signer reached over https requires `--keystore-token-file`".into(),
+				))
+			}
+			return Ok((None, KeystoreConfig::Remote { signer }))
+		}
+
 		let password = if self.password_interactive {
 			#[cfg(not(target_os = "unknown"))]
 			{
@@ -93,7 +124,20 @@ impl KeystoreParams {
 			.clone()
 			.unwrap_or_else(|| config_dir.join(DEFAULT_KEYSTORE_CONFIG_PATH));
 
-		Ok((self.keystore_uri.clone(), KeystoreConfig::Path { path, password }))
+		Ok((None, KeystoreConfig::Path { path, password }))
+	}
+
+	/// Get the keystore configuration for commands that run without a chain spec.
+	///
+	/// As there is no default location, either `--keystore-path` or a remote signer in
+	/// `--keystore-uri` is required.
+	pub fn standalone_keystore_config(&self) -> Result<KeystoreConfig> {
+		if self.keystore_path.is_none() && self.keystore_uri.is_none() {
+			return Err(error::Error::Input(
+				"Either `--keystore-path` or a signer in `--keystore-uri` is required".into(),
+			));
+		}
+		self.keystore_config(Path::new("")).map(|(_, config)| config)
 	}
 
 	/// helper method to fetch password from `KeyParams` or read from stdin
@@ -16,9 +16,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::arg_enums::SyncMode;
 use crate::params::node_key_params::NodeKeyParams;
 use sc_network::{
-	config::{NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig},
+	PeerId,
+	config::{
+		BandwidthLimits, IpNetwork, NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode,
+		SetConfig, TransportConfig, parse_subnet,
+	},
 	multiaddr::Protocol,
 };
 use sc_service::{ChainSpec, ChainType, config::{Multiaddr, MultiaddrWithPeerId}};
@@ -47,6 +52,19 @@ pub struct NetworkParams {
 	#[structopt(long = "reserved-only")]
 	pub reserved_only: bool,
 
+	/// Never connect to nor accept connections from the node with this PeerId.
+	///
+	/// More nodes can be banned at runtime with the `system_banPeer` RPC call.
+	#[structopt(long = "deny-peer", value_name = "PEER_ID")]
+	pub deny_peers: Vec<PeerId>,
+
+	/// Never connect to nor accept connections from this IP address or subnet, e.g.
+	/// `198.51.100.19` or `198.51.100.0/24`.
+	///
+	/// Addresses dialed by DNS name are not resolved for this check.
+	#[structopt(long = "deny-ip", value_name = "IP_OR_CIDR", parse(try_from_str = parse_subnet))]
+	pub deny_ips: Vec<IpNetwork>,
+
 	/// The public address that other nodes will use to connect to it.
 	/// This can be used if there's a proxy in front of this node.
 	#[structopt(long, value_name = "PUBLIC_ADDR")]
@@ -74,6 +92,26 @@ pub struct NetworkParams {
 	#[structopt(long = "in-peers", value_name = "COUNT", default_value = "25")]
 	pub in_peers: u32,
 
+	/// Maximum rate, in KiB/s, at which data is received from all the nodes combined.
+	#[structopt(long = "in-bandwidth", value_name = "KIB_PER_SEC")]
+	pub in_bandwidth: Option<u64>,
+
+	/// Maximum rate, in KiB/s, at which data is sent to all the nodes combined.
+	#[structopt(long = "out-bandwidth", value_name = "KIB_PER_SEC")]
+	pub out_bandwidth: Option<u64>,
+
+	/// Maximum rate, in KiB/s, at which data is received from each node.
+	///
+	/// Counts the decrypted data, without the overhead of the encryption.
+	#[structopt(long = "in-bandwidth-per-peer", value_name = "KIB_PER_SEC")]
+	pub in_bandwidth_per_peer: Option<u64>,
+
+	/// Maximum rate, in KiB/s, at which data is sent to each node.
+	///
+	/// Counts the data before encryption, without the overhead of the encryption.
+	#[structopt(long = "out-bandwidth-per-peer", value_name = "KIB_PER_SEC")]
+	pub out_bandwidth_per_peer: Option<u64>,
+
 	/// Disable mDNS discovery.
 	///
 	/// By default, the network will use mDNS to discover other nodes on the
@@ -81,6 +119,13 @@ pub struct NetworkParams {
 	#[structopt(long = "no-mdns")]
 	pub no_mdns: bool,
 
+	/// Also accept and open connections over QUIC.
+	///
+	/// Unless `--listen-addr` is passed, the node additionally listens on UDP on the same port as
+	/// for TCP. Requires the node to be compiled with the `quic` feature of `sc-network`.
+	#[structopt(long)]
+	pub quic: bool,
+
 	/// Maximum number of peers from which to ask for the same blocks in parallel.
 	///
 	/// This allows downloading announced blocks from multiple peers. Decrease to save
@@ -92,6 +137,20 @@ pub struct NetworkParams {
 	)]
 	pub max_parallel_downloads: u32,
 
+	/// How to catch up with the chain.
+	///
+	/// `Full` downloads and imports every block. `Warp` first skips to the latest finalized
+	/// block using a GRANDPA warp sync proof and downloads the state of that block, so that
+	/// following blocks can be executed. It only works on an empty database.
+	#[structopt(
+		long = "sync",
+		value_name = "MODE",
+		possible_values = &SyncMode::variants(),
+		case_insensitive = true,
+		default_value = "Full",
+	)]
+	pub sync: SyncMode,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub node_key_params: NodeKeyParams,
@@ -111,7 +170,11 @@ pub struct NetworkParams {
 	#[structopt(long)]
 	pub kademlia_disjoint_query_paths: bool,
 
-	/// Join the IPFS network and serve transactions over bitswap protocol.
+	/// Join the IPFS network and serve transactions, state trie nodes and block bodies over
+	/// bitswap protocol.
+	///
+	/// State trie nodes are only served with a database that counts references, i.e.
+	/// `--database paritydb`. RocksDB doesn't store them under their hash alone.
 	#[structopt(long)]
 	pub ipfs_server: bool,
 }
@@ -131,14 +194,21 @@ impl NetworkParams {
 		let port = self.port.unwrap_or(default_listen_port);
 
 		let listen_addresses = if self.listen_addr.is_empty() {
-			vec![
+			let mut addrs = vec![
 				Multiaddr::empty()
 					.with(Protocol::Ip6([0, 0, 0, 0, 0, 0, 0, 0].into()))
 					.with(Protocol::Tcp(port)),
 				Multiaddr::empty()
 					.with(Protocol::Ip4([0, 0, 0, 0].into()))
 					.with(Protocol::Tcp(port)),
-			]
+			];
+			if self.quic {
+				addrs.push(Multiaddr::empty()
+					.with(Protocol::Ip4([0, 0, 0, 0].into()))
+					.with(Protocol::Udp(port))
+					.with(Protocol::Quic));
+			}
+			addrs
 		} else {
 			self.listen_addr.clone()
 		};
@@ -179,8 +249,18 @@ impl NetworkParams {
 				enable_mdns: !is_dev && !self.no_mdns,
 				allow_private_ipv4: !self.no_private_ipv4,
 				wasm_external_transport: None,
+				enable_quic: self.quic,
 			},
 			max_parallel_downloads: self.max_parallel_downloads,
+			sync_mode: self.sync.into(),
+			denied_peers: self.deny_peers.clone(),
+			denied_subnets: self.deny_ips.clone(),
+			bandwidth_limits: BandwidthLimits {
+				total_inbound: self.in_bandwidth.map(kib_to_bytes),
+				total_outbound: self.out_bandwidth.map(kib_to_bytes),
+				per_peer_inbound: self.in_bandwidth_per_peer.map(kib_to_bytes),
+				per_peer_outbound: self.out_bandwidth_per_peer.map(kib_to_bytes),
+			},
 			enable_dht_random_walk: !self.reserved_only,
 			allow_non_globals_in_dht,
 			kademlia_disjoint_query_paths: self.kademlia_disjoint_query_paths,
@@ -189,3 +269,7 @@ impl NetworkParams {
 		}
 	}
 }
+
+fn kib_to_bytes(kib: u64) -> u64 {
+	kib.saturating_mul(1024)
+}
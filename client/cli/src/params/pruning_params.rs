@@ -30,11 +30,14 @@ pub struct PruningParams {
 	/// 256 blocks.
 	#[structopt(long = "pruning", value_name = "PRUNING_MODE")]
 	pub pruning: Option<String>,
-	/// Specify the number of finalized blocks to keep in the database.
+	/// Specify the block pruning mode, a number of finalized blocks to keep,
+	/// 'archive' or 'headers'.
 	///
-	/// Default is to keep all blocks.
-	#[structopt(long, value_name = "COUNT")]
-	pub keep_blocks: Option<u32>,
+	/// Bodies and justifications of older finalized blocks are removed from the
+	/// database, headers are always kept. With 'headers' only the headers of
+	/// finalized blocks are kept. Default is to keep all blocks.
+	#[structopt(long, value_name = "archive|headers|COUNT")]
+	pub keep_blocks: Option<String>,
 }
 
 impl PruningParams {
@@ -66,9 +69,13 @@ impl PruningParams {
 
 	/// Get the block pruning value from the parameters
 	pub fn keep_blocks(&self) -> error::Result<KeepBlocks> {
-		Ok(match self.keep_blocks {
-			Some(n) => KeepBlocks::Some(n),
+		Ok(match &self.keep_blocks {
+			Some(ref s) if s == "archive" => KeepBlocks::All,
+			Some(ref s) if s == "headers" => KeepBlocks::HeadersOnly,
 			None => KeepBlocks::All,
+			Some(s) => KeepBlocks::Some(s.parse().map_err(|_| {
+				error::Error::Input("Invalid block pruning mode specified".to_string())
+			})?),
 		})
 	}
 }
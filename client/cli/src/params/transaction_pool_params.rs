@@ -16,7 +16,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::config::DEFAULT_TRANSACTION_POOL_JOURNAL;
 use sc_service::config::TransactionPoolOptions;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 /// Parameters used to create the pool configuration.
@@ -29,6 +31,28 @@ pub struct TransactionPoolParams {
 	/// Maximum number of kilobytes of all transactions stored in the pool.
 	#[structopt(long = "pool-kbytes", value_name = "COUNT", default_value = "20480")]
 	pub pool_kbytes: usize,
+
+	/// Maximum number of transactions of a single sender in the transaction pool.
+	///
+	/// The sender of a transaction is the first tag it provides without its trailing nonce
+	/// bytes, e.g. the account of a signed transaction.
+	#[structopt(long = "pool-sender-limit", value_name = "COUNT")]
+	pub pool_sender_limit: Option<usize>,
+
+	/// Number of trailing bytes of the first tag provided by a transaction that hold its nonce.
+	#[structopt(long = "pool-sender-nonce-bytes", value_name = "COUNT", default_value = "4")]
+	pub pool_sender_nonce_bytes: usize,
+
+	/// Minimal priority increase, in percent, for a transaction to replace another one
+	/// in the transaction pool.
+	#[structopt(long = "pool-replacement-bump", value_name = "PERCENT", default_value = "0")]
+	pub pool_replacement_bump: u32,
+
+	/// Persist the transaction pool to disk and restore it on restart.
+	///
+	/// The journal is stored in the chain's configuration directory.
+	#[structopt(long = "pool-journal")]
+	pub pool_journal: bool,
 }
 
 impl TransactionPoolParams {
@@ -45,6 +69,25 @@ impl TransactionPoolParams {
 		opts.future.count = self.pool_limit / factor;
 		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
 
+		// per sender limit
+		opts.sender = self.pool_sender_limit.map(|count| {
+			let mut limit = opts.ready.clone();
+			limit.count = count;
+			limit
+		});
+		opts.sender_nonce_len = self.pool_sender_nonce_bytes;
+
+		opts.min_replacement_bump = self.pool_replacement_bump;
+
 		opts
 	}
+
+	/// Get the path of the transaction pool journal in `config_dir`, if journaling is enabled.
+	pub fn journal_path(&self, config_dir: &Path) -> Option<PathBuf> {
+		if self.pool_journal {
+			Some(config_dir.join(DEFAULT_TRANSACTION_POOL_JOURNAL))
+		} else {
+			None
+		}
+	}
 }
@@ -127,6 +127,45 @@ pub(crate) fn write_block_weight<H: Encode, F, R>(
 	)
 }
 
+/// Auxiliary storage entries for the epoch changes and the chain-weight of a block imported
+/// without its ancestors, e.g. a trusted checkpoint or the target of a warp sync.
+pub fn checkpoint_aux_data<Block: BlockT>(
+	epoch_changes: &EpochChangesFor<Block, Epoch>,
+	block_hash: Block::Hash,
+	block_weight: BabeBlockWeight,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+	let mut aux = write_epoch_changes::<Block, _, _>(epoch_changes, |values| {
+		values.iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect::<Vec<_>>()
+	});
+	aux.extend(write_block_weight(block_hash, block_weight, |values| {
+		values.iter().map(|(k, v)| (k.clone(), v.to_vec())).collect::<Vec<_>>()
+	}));
+	aux
+}
+
+/// Auxiliary storage entries for the epoch changes and the chain-weight of the given block, as
+/// stored by the backend, to include in a state snapshot of the block.
+pub fn snapshot_aux_data<H: Encode, B: AuxStore>(
+	backend: &B,
+	block_hash: H,
+) -> ClientResult<Vec<(Vec<u8>, Vec<u8>)>> {
+	let keys = [
+		BABE_EPOCH_CHANGES_VERSION.to_vec(),
+		BABE_EPOCH_CHANGES_KEY.to_vec(),
+		block_weight_key(block_hash),
+	];
+	let mut aux = Vec::with_capacity(keys.len());
+	for key in keys.iter() {
+		match backend.get_aux(key)? {
+			Some(value) => aux.push((key.clone(), value)),
+			None => return Err(ClientError::Backend(
+				format!("BABE auxiliary entry {:?} not found", String::from_utf8_lossy(key))
+			)),
+		}
+	}
+	Ok(aux)
+}
+
 /// Load the cumulative chain-weight associated with a block.
 pub fn load_block_weight<H: Encode, B: AuxStore>(
 	backend: &B,
@@ -212,4 +251,22 @@ mod test {
 			Some(2),
 		);
 	}
+
+	#[test]
+	fn checkpoint_aux_data_holds_epoch_changes_and_weight() {
+		let client = substrate_test_runtime_client::new();
+		let hash = H256::random();
+
+		let aux = checkpoint_aux_data::<TestBlock>(&Default::default(), hash, 42);
+		client.insert_aux(
+			&aux.iter().map(|(k, v)| (&k[..], &v[..])).collect::<Vec<_>>(),
+			&[],
+		).unwrap();
+
+		assert_eq!(
+			load_decode::<_, u32>(&client, BABE_EPOCH_CHANGES_VERSION).unwrap(),
+			Some(BABE_EPOCH_CHANGES_CURRENT_VERSION),
+		);
+		assert_eq!(load_block_weight(&client, hash).unwrap(), Some(42));
+	}
 }
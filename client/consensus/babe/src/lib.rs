@@ -79,7 +79,7 @@ use std::{
 	any::Any, borrow::Cow, convert::TryInto,
 };
 use sp_consensus::{ImportResult, CanAuthorWith, import_queue::BoxJustificationImport};
-use sp_core::crypto::Public;
+use sp_core::{crypto::Public, hashing::twox_128, storage::Storage};
 use sp_application_crypto::AppKey;
 use sp_keystore::{SyncCryptoStorePtr, SyncCryptoStore};
 use sp_runtime::{
@@ -111,6 +111,7 @@ use sc_consensus_slots::{
 	SlotInfo, SlotCompatible, StorageChanges, CheckedHeader, check_equivocation,
 	BackoffAuthoringBlocksStrategy
 };
+use sc_consensus::SlashingProtection;
 use sc_consensus_epochs::{
 	descendent_query, SharedEpochChanges, EpochChangesFor, Epoch as EpochT, ViableEpochDescriptor,
 };
@@ -360,6 +361,9 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC, CAW, BS> {
 	/// The keystore that manages the keys of the node.
 	pub keystore: SyncCryptoStorePtr,
 
+	/// The store that keeps blocks from being authored twice in a slot, if any.
+	pub slashing_protection: Option<Arc<SlashingProtection>>,
+
 	/// The client to use
 	pub client: Arc<C>,
 
@@ -396,6 +400,7 @@ pub struct BabeParams<B: BlockT, C, E, I, SO, SC, CAW, BS> {
 /// Start the babe worker.
 pub fn start_babe<B, C, SC, E, I, SO, CAW, BS, Error>(BabeParams {
 	keystore,
+	slashing_protection,
 	client,
 	select_chain,
 	env,
@@ -435,6 +440,7 @@ pub fn start_babe<B, C, SC, E, I, SO, CAW, BS, Error>(BabeParams {
 		force_authoring,
 		backoff_authoring_blocks,
 		keystore,
+		slashing_protection,
 		epoch_changes: babe_link.epoch_changes.clone(),
 		slot_notification_sinks: slot_notification_sinks.clone(),
 		config: config.clone(),
@@ -508,6 +514,7 @@ struct BabeSlotWorker<B: BlockT, C, E, I, SO, BS> {
 	force_authoring: bool,
 	backoff_authoring_blocks: Option<BS>,
 	keystore: SyncCryptoStorePtr,
+	slashing_protection: Option<Arc<SlashingProtection>>,
 	epoch_changes: SharedEpochChanges<B, Epoch>,
 	slot_notification_sinks: SlotNotificationSinks<B>,
 	config: Config,
@@ -634,11 +641,16 @@ where
 		sp_consensus::Error> + Send + 'static>
 	{
 		let keystore = self.keystore.clone();
-		Box::new(move |header, header_hash, body, storage_changes, (_, public), epoch_descriptor| {
+		let slashing_protection = self.slashing_protection.clone();
+		Box::new(move |header, header_hash, body, storage_changes, (pre_digest, public), epoch_descriptor| {
 			// sign the pre-sealed hash of the block and then
 			// add it to a digest item.
 			let public_type_pair = public.clone().into();
 			let public = public.to_raw_vec();
+			if let Some(slashing_protection) = &slashing_protection {
+				slashing_protection.check_slot(&public, *pre_digest.slot())
+					.map_err(|e| sp_consensus::Error::CannotSign(public.clone(), e.to_string()))?;
+			}
 			let signature = SyncCryptoStore::sign_with(
 				&*keystore,
 				<AuthorityId as AppKey>::ID,
@@ -831,6 +843,63 @@ impl<Block: BlockT> BabeLink<Block> {
 	}
 }
 
+/// Prefix of the keys of the state that [`import_epochs_from_state`] reads.
+pub fn epochs_state_prefix() -> Vec<u8> {
+	twox_128(b"Babe").to_vec()
+}
+
+/// Prepare the epochs in effect at a block imported without its ancestors, e.g. the target of a
+/// warp sync, from the state of the block as left by the BABE pallet.
+///
+/// Returns the auxiliary storage entries to insert along with the block, and a callback that
+/// updates the epoch changes of `link` once they are committed. The epochs are given the
+/// configuration of the genesis epoch.
+pub fn import_epochs_from_state<Block: BlockT>(
+	link: &BabeLink<Block>,
+	header: &Block::Header,
+	state: &Storage,
+) -> ClientResult<(Vec<(Vec<u8>, Vec<u8>)>, Box<dyn FnOnce() + Send>)> {
+	fn value<T: Decode>(state: &Storage, name: &str) -> ClientResult<T> {
+		let key = [twox_128(b"Babe"), twox_128(name.as_bytes())].concat();
+		let encoded = state.top.get(&key).ok_or_else(|| {
+			ClientError::Backend(format!("Missing Babe::{} in the state", name))
+		})?;
+		T::decode(&mut &encoded[..]).map_err(|e| {
+			ClientError::Backend(format!("Invalid Babe::{} in the state: {}", name, e))
+		})
+	}
+
+	let config = &link.config;
+	let genesis_slot: Slot = value(state, "GenesisSlot")?;
+	let epoch = |
+		epoch_index: u64,
+		authorities: Vec<(AuthorityId, BabeAuthorityWeight)>,
+		randomness: [u8; VRF_OUTPUT_LENGTH],
+	| Epoch {
+		epoch_index,
+		start_slot: Slot::from(*genesis_slot + epoch_index * config.epoch_length),
+		duration: config.epoch_length,
+		authorities,
+		randomness,
+		config: BabeEpochConfiguration { c: config.c, allowed_slots: config.allowed_slots },
+	};
+	let epoch_index: u64 = value(state, "EpochIndex")?;
+	let current = epoch(epoch_index, value(state, "Authorities")?, value(state, "Randomness")?);
+	let next = epoch(
+		epoch_index + 1,
+		value(state, "NextAuthorities")?,
+		value(state, "NextRandomness")?,
+	);
+
+	let hash = header.hash();
+	let mut epoch_changes = EpochChangesFor::<Block, Epoch>::default();
+	epoch_changes.reset(*header.parent_hash(), hash, *header.number(), current, next);
+	// Only the weight of blocks relative to each other matters, and all blocks follow this one.
+	let aux = aux_schema::checkpoint_aux_data::<Block>(&epoch_changes, hash, 0);
+	let shared = link.epoch_changes.clone();
+	Ok((aux, Box::new(move || *shared.lock() = epoch_changes)))
+}
+
 /// A verifier for Babe blocks.
 pub struct BabeVerifier<Block: BlockT, Client, SelectChain, CAW> {
 	client: Arc<Client>,
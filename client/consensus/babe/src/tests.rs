@@ -430,6 +430,7 @@ fn run_one_test(
 			backoff_authoring_blocks: Some(BackoffAuthoringOnFinalizedHeadLagging::default()),
 			babe_link: data.link.clone(),
 			keystore,
+			slashing_protection: Some(Arc::new(sc_consensus::SlashingProtection::in_memory())),
 			can_author_with: sp_consensus::AlwaysCanAuthor,
 		}).expect("Starts babe"));
 	}
@@ -673,6 +674,57 @@ fn importing_block_one_sets_genesis_epoch() {
 	assert_eq!(epoch_for_second_block, genesis_epoch);
 }
 
+#[test]
+fn imports_epochs_from_state() {
+	let mut net = BabeTestNet::new(1);
+
+	let peer = net.peer(0);
+	let data = peer.data.as_ref().expect("babe link set up during initialization");
+	let client = peer.client().as_full().expect("Only full clients are used in tests").clone();
+	let epoch_length = data.link.config.epoch_length;
+
+	let entry = |name: &str, value: Vec<u8>| (
+		[twox_128(b"Babe"), twox_128(name.as_bytes())].concat(),
+		value,
+	);
+	let authorities = data.link.config.genesis_authorities.clone();
+	let mut state = Storage::default();
+	state.top.extend(vec![
+		entry("GenesisSlot", Slot::from(1000).encode()),
+		entry("EpochIndex", 5u64.encode()),
+		entry("Authorities", authorities.encode()),
+		entry("Randomness", [1u8; 32].encode()),
+		entry("NextAuthorities", authorities.encode()),
+		entry("NextRandomness", [2u8; 32].encode()),
+	]);
+	let header = TestHeader {
+		parent_hash: [1; 32].into(),
+		number: 100,
+		state_root: Default::default(),
+		extrinsics_root: Default::default(),
+		digest: Default::default(),
+	};
+
+	let (aux, on_commit) = import_epochs_from_state(&data.link, &header, &state).unwrap();
+	// The epoch changes, their version and the weight of the block.
+	assert_eq!(aux.len(), 3);
+	on_commit();
+
+	let epoch_changes = data.link.epoch_changes.lock();
+	let epoch_at = |slot: u64| epoch_changes.epoch_data_for_child_of(
+		descendent_query(&*client),
+		&header.hash(),
+		100,
+		slot.into(),
+		|_| unreachable!(),
+	).unwrap().unwrap();
+	let current_start = 1000 + 5 * epoch_length;
+	assert_eq!(epoch_at(current_start).epoch_index, 5);
+	assert_eq!(epoch_at(current_start).randomness, [1; 32]);
+	assert_eq!(epoch_at(current_start + epoch_length).epoch_index, 6);
+	assert_eq!(epoch_at(current_start + epoch_length).randomness, [2; 32]);
+}
+
 #[test]
 fn importing_epoch_change_block_prunes_tree() {
 	use sc_client_api::Finalizer;
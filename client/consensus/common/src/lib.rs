@@ -18,5 +18,7 @@
 
 //! Collection of common consensus specific implementations
 mod longest_chain;
+pub mod slashing_protection;
 
 pub use longest_chain::LongestChain;
+pub use slashing_protection::SlashingProtection;
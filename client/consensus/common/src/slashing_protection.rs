@@ -0,0 +1,362 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Local slashing protection for block authors and finality voters.
+//!
+//! Before a key signs a block or a vote, the signature is checked against the history of
+//! what the key has signed so far and recorded. Signatures that could be an equivocation,
+//! i.e. a second block in a slot or a different vote in a round, are refused.
+//!
+//! The history is kept in a JSON file, which is rewritten before the signature is handed
+//! out. It can be exported and imported into the store of another node, so that a key
+//! can be moved between machines without losing its history.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use sp_core::{Bytes, hexdisplay::HexDisplay};
+
+/// Current version of the history format.
+const VERSION: u32 = 1;
+
+/// Slashing protection error.
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum Error {
+	/// IO error.
+	Io(io::Error),
+	/// JSON error.
+	Json(serde_json::Error),
+	/// History has an unsupported version.
+	#[display(fmt="Unsupported slashing protection history version {}", _0)]
+	UnsupportedVersion(u32),
+	/// The key already authored a block in this or a later slot.
+	#[display(fmt="Key already authored a block in slot {}", _0)]
+	SlotAlreadyAuthored(u64),
+	/// The key already cast a different vote, or voted in a later round.
+	#[display(fmt="Key already voted in set {} round {}", set_id, round)]
+	ConflictingVote {
+		/// Set of the recorded vote.
+		set_id: u64,
+		/// Round of the recorded vote.
+		round: u64,
+	},
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(ref err) => Some(err),
+			Error::Json(ref err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+/// Kind of a finality vote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteKind {
+	/// A primary proposal.
+	PrimaryPropose,
+	/// A prevote.
+	Prevote,
+	/// A precommit.
+	Precommit,
+}
+
+/// Signing history of all keys of a store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+	/// Version of the format.
+	pub version: u32,
+	/// History of each key, by hex encoded public key.
+	pub keys: BTreeMap<String, KeyHistory>,
+}
+
+/// Signing history of a single key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyHistory {
+	/// The last slot a block was authored in.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_slot: Option<u64>,
+	/// The votes of the last round voted in.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_votes: Option<RoundVotes>,
+}
+
+/// The votes cast in a round, by the encoded target.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundVotes {
+	/// Authority set of the round.
+	pub set_id: u64,
+	/// Number of the round.
+	pub round: u64,
+	/// Target of the primary proposal.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub propose: Option<Bytes>,
+	/// Target of the prevote.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub prevote: Option<Bytes>,
+	/// Target of the precommit.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub precommit: Option<Bytes>,
+}
+
+impl RoundVotes {
+	fn vote_mut(&mut self, kind: VoteKind) -> &mut Option<Bytes> {
+		match kind {
+			VoteKind::PrimaryPropose => &mut self.propose,
+			VoteKind::Prevote => &mut self.prevote,
+			VoteKind::Precommit => &mut self.precommit,
+		}
+	}
+}
+
+/// A slashing protection store.
+pub struct SlashingProtection {
+	path: Option<PathBuf>,
+	history: Mutex<History>,
+}
+
+impl SlashingProtection {
+	/// Open the store kept in the file at `path`, which is created if it does not exist.
+	pub fn open<T: Into<PathBuf>>(path: T) -> Result<Self, Error> {
+		let path = path.into();
+		let history = match fs::read(&path) {
+			Ok(contents) => parse(&contents)?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => History { version: VERSION, ..Default::default() },
+			Err(e) => return Err(e.into()),
+		};
+		Ok(SlashingProtection { path: Some(path), history: Mutex::new(history) })
+	}
+
+	/// Create a store that is kept in memory only.
+	pub fn in_memory() -> Self {
+		SlashingProtection {
+			path: None,
+			history: Mutex::new(History { version: VERSION, ..Default::default() }),
+		}
+	}
+
+	/// Check that `key` may author a block in `slot` and record it.
+	///
+	/// Fails if the key already authored a block in the same or a later slot.
+	pub fn check_slot(&self, key: &[u8], slot: u64) -> Result<(), Error> {
+		self.update(|history| {
+			let key_history = history.keys.entry(key_name(key)).or_default();
+			match key_history.last_slot {
+				Some(last_slot) if last_slot >= slot => Err(Error::SlotAlreadyAuthored(last_slot)),
+				_ => {
+					key_history.last_slot = Some(slot);
+					Ok(())
+				},
+			}
+		})
+	}
+
+	/// Check that `key` may cast a vote of the given `kind` for `target` in a round and
+	/// record it.
+	///
+	/// Casting the same vote again is allowed. Fails if the key already cast a different vote
+	/// of the same kind in the round, or voted in a later round.
+	pub fn check_vote(
+		&self,
+		key: &[u8],
+		set_id: u64,
+		round: u64,
+		kind: VoteKind,
+		target: &[u8],
+	) -> Result<(), Error> {
+		self.update(|history| {
+			let key_history = history.keys.entry(key_name(key)).or_default();
+			match &key_history.last_votes {
+				Some(last) if (last.set_id, last.round) > (set_id, round) =>
+					return Err(Error::ConflictingVote { set_id: last.set_id, round: last.round }),
+				Some(last) if (last.set_id, last.round) == (set_id, round) => {},
+				_ => key_history.last_votes = Some(RoundVotes { set_id, round, ..Default::default() }),
+			}
+
+			let vote = key_history.last_votes.as_mut()
+				.expect("Votes of the round are set above; qed")
+				.vote_mut(kind);
+			match vote {
+				Some(voted) if &voted[..] != target =>
+					Err(Error::ConflictingVote { set_id, round }),
+				Some(_) => Ok(()),
+				None => {
+					*vote = Some(target.to_vec().into());
+					Ok(())
+				},
+			}
+		})
+	}
+
+	/// Returns the signing history of all keys.
+	pub fn export(&self) -> History {
+		self.history.lock().clone()
+	}
+
+	/// Merge the given signing history into the store.
+	///
+	/// For every key, the later of the two last slots and last voting rounds is kept. Votes
+	/// of the same round are combined, keeping the recorded vote where both differ.
+	pub fn import(&self, imported: History) -> Result<(), Error> {
+		if imported.version != VERSION {
+			return Err(Error::UnsupportedVersion(imported.version));
+		}
+		self.update(|history| {
+			for (key, imported) in imported.keys {
+				let key_history = history.keys.entry(key).or_default();
+				key_history.last_slot = key_history.last_slot.max(imported.last_slot);
+
+				let imported_votes = match imported.last_votes {
+					Some(votes) => votes,
+					None => continue,
+				};
+				match &mut key_history.last_votes {
+					Some(last) if (last.set_id, last.round) == (imported_votes.set_id, imported_votes.round) => {
+						last.propose = last.propose.take().or(imported_votes.propose);
+						last.prevote = last.prevote.take().or(imported_votes.prevote);
+						last.precommit = last.precommit.take().or(imported_votes.precommit);
+					},
+					Some(last) if (last.set_id, last.round) > (imported_votes.set_id, imported_votes.round) => {},
+					last_votes => *last_votes = Some(imported_votes),
+				}
+			}
+			Ok(())
+		})
+	}
+
+	// Applies `f` to the history and persists the result. The history is left untouched if
+	// either fails.
+	fn update<R>(&self, f: impl FnOnce(&mut History) -> Result<R, Error>) -> Result<R, Error> {
+		let mut history = self.history.lock();
+		let mut updated = history.clone();
+		let result = f(&mut updated)?;
+		if updated != *history {
+			if let Some(path) = &self.path {
+				write_atomically(path, &serde_json::to_vec(&updated)?)?;
+			}
+			*history = updated;
+		}
+		Ok(result)
+	}
+}
+
+/// Parse an exported history.
+pub fn parse(contents: &[u8]) -> Result<History, Error> {
+	let history: History = serde_json::from_slice(contents)?;
+	if history.version != VERSION {
+		return Err(Error::UnsupportedVersion(history.version));
+	}
+	Ok(history)
+}
+
+fn key_name(key: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&key))
+}
+
+// Replaces the file at `path`, so that a crash leaves either the old or the new contents.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+	let tmp_path = path.with_extension("tmp");
+	let mut file = fs::File::create(&tmp_path)?;
+	file.write_all(contents)?;
+	file.sync_all()?;
+	fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KEY: &[u8] = &[1; 32];
+	const OTHER_KEY: &[u8] = &[2; 32];
+
+	#[test]
+	fn refuses_second_block_in_slot() {
+		let store = SlashingProtection::in_memory();
+		store.check_slot(KEY, 10).unwrap();
+		assert!(matches!(store.check_slot(KEY, 10), Err(Error::SlotAlreadyAuthored(10))));
+		assert!(matches!(store.check_slot(KEY, 9), Err(Error::SlotAlreadyAuthored(10))));
+		store.check_slot(OTHER_KEY, 10).unwrap();
+		store.check_slot(KEY, 11).unwrap();
+	}
+
+	#[test]
+	fn refuses_conflicting_votes() {
+		let store = SlashingProtection::in_memory();
+		store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"a").unwrap();
+		// Voting the same again is fine, e.g. after a restart.
+		store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"a").unwrap();
+		assert!(store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"b").is_err());
+		store.check_vote(KEY, 1, 5, VoteKind::Precommit, b"b").unwrap();
+		assert!(store.check_vote(KEY, 1, 5, VoteKind::Precommit, b"a").is_err());
+
+		store.check_vote(KEY, 1, 6, VoteKind::Prevote, b"c").unwrap();
+		assert!(store.check_vote(KEY, 1, 5, VoteKind::Precommit, b"b").is_err());
+		store.check_vote(KEY, 2, 1, VoteKind::Prevote, b"d").unwrap();
+		assert!(store.check_vote(KEY, 1, 7, VoteKind::Prevote, b"d").is_err());
+		store.check_vote(OTHER_KEY, 1, 5, VoteKind::Prevote, b"b").unwrap();
+	}
+
+	#[test]
+	fn history_is_persisted() {
+		let dir = tempfile::TempDir::new().unwrap();
+		let path = dir.path().join("slashing_protection.json");
+
+		let store = SlashingProtection::open(&path).unwrap();
+		store.check_slot(KEY, 10).unwrap();
+		store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"a").unwrap();
+		drop(store);
+
+		let store = SlashingProtection::open(&path).unwrap();
+		assert!(store.check_slot(KEY, 10).is_err());
+		assert!(store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"b").is_err());
+		store.check_vote(KEY, 1, 5, VoteKind::Prevote, b"a").unwrap();
+	}
+
+	#[test]
+	fn imports_history() {
+		let source = SlashingProtection::in_memory();
+		source.check_slot(KEY, 10).unwrap();
+		source.check_vote(KEY, 1, 5, VoteKind::Prevote, b"a").unwrap();
+
+		let destination = SlashingProtection::in_memory();
+		destination.check_slot(KEY, 20).unwrap();
+		destination.check_vote(KEY, 1, 5, VoteKind::Precommit, b"a").unwrap();
+		destination.check_slot(OTHER_KEY, 3).unwrap();
+
+		let exported = serde_json::to_vec(&source.export()).unwrap();
+		destination.import(parse(&exported).unwrap()).unwrap();
+
+		assert!(destination.check_slot(KEY, 20).is_err());
+		assert!(destination.check_slot(OTHER_KEY, 3).is_err());
+		assert!(destination.check_vote(KEY, 1, 5, VoteKind::Prevote, b"b").is_err());
+		assert!(destination.check_vote(KEY, 1, 5, VoteKind::Precommit, b"b").is_err());
+
+		let unknown = History { version: 2, ..Default::default() };
+		assert!(matches!(destination.import(unknown), Err(Error::UnsupportedVersion(2))));
+	}
+}
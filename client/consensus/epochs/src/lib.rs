@@ -20,7 +20,7 @@
 
 pub mod migration;
 
-use std::{sync::Arc, ops::Add, collections::BTreeMap, borrow::{Borrow, BorrowMut}};
+use std::{sync::Arc, ops::{Add, Sub}, collections::BTreeMap, borrow::{Borrow, BorrowMut}};
 use parking_lot::Mutex;
 use codec::{Encode, Decode};
 use fork_tree::ForkTree;
@@ -638,6 +638,32 @@ impl<Hash, Number, E: Epoch> EpochChanges<Hash, Number, E> where
 		}
 	}
 
+	/// Reset to the given current and next epoch, for a block imported without its ancestors,
+	/// e.g. the target of a warp sync.
+	///
+	/// The current epoch is recorded as announced at the parent of the block, and the next epoch
+	/// at the block itself. Neither is checked for ancestry, as the parent is not known.
+	pub fn reset(&mut self, parent_hash: Hash, hash: Hash, number: Number, current: E, next: E)
+		where Number: Sub<Output=Number>
+	{
+		self.inner = ForkTree::new();
+		self.epochs.clear();
+
+		let mut import = |hash, number, epoch, is_descendent| {
+			let persisted = PersistedEpoch::Regular(epoch);
+			let header = PersistedEpochHeader::from(&persisted);
+			let _res = self.inner.import(
+				hash,
+				number,
+				header,
+				&|_: &Hash, _: &Hash| Ok::<_, ClientError>(is_descendent),
+			);
+			self.epochs.insert((hash, number), persisted);
+		};
+		import(parent_hash, number - One::one(), current, false);
+		import(hash, number, next, true);
+	}
+
 	/// Return the inner fork tree.
 	pub fn tree(&self) -> &ForkTree<Hash, Number, PersistedEpochHeader<E>> {
 		&self.inner
@@ -969,4 +995,28 @@ mod tests {
 			assert!(epoch_for_x_child_before_genesis.is_none());
 		}
 	}
+
+	#[test]
+	fn reset_to_epochs_of_block_without_ancestors() {
+		// The parent of the block is unknown, so any ancestry query beyond it fails.
+		let is_descendent_of = |_base: &Hash, _block: &Hash| -> Result<bool, TestError> {
+			Err(TestError)
+		};
+
+		let current = Epoch { start_slot: 100, duration: 100 };
+		let next = current.increment(());
+		let mut epoch_changes = EpochChanges::<_, _, Epoch>::new();
+		epoch_changes.reset(*b"A", *b"B", 10, current.clone(), next.clone());
+
+		for (slot, expected) in vec![(150, &current), (250, &next)] {
+			let descriptor = epoch_changes.epoch_descriptor_for_child_of(
+				&is_descendent_of,
+				b"B",
+				10,
+				slot,
+			).unwrap().unwrap();
+			let epoch = epoch_changes.viable_epoch(&descriptor, |_| unreachable!()).unwrap();
+			assert_eq!(epoch.as_ref(), expected);
+		}
+	}
 }
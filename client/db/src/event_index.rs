@@ -0,0 +1,288 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Index of the events of the finalized chain by account and topic.
+//!
+//! The locations of the events of each account and topic are stored per block, under the key of
+//! the subject followed by the block number, and are never rewritten. To find them, the blocks
+//! are grouped in buckets of `BUCKET_SIZE` blocks: each subject has the list of its non-empty
+//! buckets, and each bucket a bitmap of the blocks with events. Both have a bounded size per
+//! bucket, so indexing a block writes an amount of data proportional to its events only.
+//! Lookups read the buckets from the newest to the oldest, so recent events of busy accounts are
+//! found without reading their whole history.
+
+use std::{collections::{BTreeMap, BTreeSet}, marker::PhantomData, sync::Arc};
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sc_client_api::{EventIndex, EventIndexKey, EventLocation, IndexedEvent};
+use sp_blockchain::Result as ClientResult;
+use sp_core::hashing::blake2_256;
+use sp_runtime::{
+	SaturatedConversion,
+	traits::{Block as BlockT, NumberFor, One, UniqueSaturatedInto},
+};
+use crate::{columns, Database, DbHash, Transaction};
+
+/// Key of the number of the first indexed block.
+const FIRST_INDEXED: &[u8] = b"first_indexed";
+
+/// Key of the number of the last indexed block.
+const LAST_INDEXED: &[u8] = b"last_indexed";
+
+/// Prefix of the keys of accounts.
+const ACCOUNT_PREFIX: u8 = 1;
+
+/// Prefix of the keys of topics.
+const TOPIC_PREFIX: u8 = 2;
+
+/// Number of blocks of a bucket.
+const BUCKET_SIZE: u64 = 256;
+
+/// Bitmap of the blocks of a bucket, the lowest bit of the first byte standing for the first
+/// block.
+type BucketBlocks = [u8; BUCKET_SIZE as usize / 8];
+
+type Location<Block> = EventLocation<<Block as BlockT>::Hash, NumberFor<Block>>;
+
+/// Event index stored in the database.
+pub struct DbEventIndex<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	/// Held while indexing a block, since the bucket lists and bitmaps are read before being
+	/// updated.
+	write_lock: Mutex<()>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<Block: BlockT> DbEventIndex<Block> {
+	/// Create the event index stored in `db`.
+	pub fn new(db: Arc<dyn Database<DbHash>>) -> Self {
+		DbEventIndex { db, write_lock: Mutex::new(()), _phantom: PhantomData }
+	}
+
+	fn read<T: Decode>(&self, key: &[u8]) -> ClientResult<Option<T>> {
+		match self.db.get(columns::EVENT_INDEX, key) {
+			Some(value) => T::decode(&mut &value[..]).map(Some).map_err(|err|
+				sp_blockchain::Error::Backend(format!("Error decoding event index: {}", err))
+			),
+			None => Ok(None),
+		}
+	}
+}
+
+/// Database key of the list of buckets of an account or topic.
+fn subject_key(key: EventIndexKey) -> Vec<u8> {
+	let (prefix, subject) = match key {
+		EventIndexKey::Account(account) => (ACCOUNT_PREFIX, account),
+		EventIndexKey::Topic(topic) => (TOPIC_PREFIX, topic),
+	};
+	let mut key = Vec::with_capacity(33);
+	key.push(prefix);
+	key.extend_from_slice(&blake2_256(subject));
+	key
+}
+
+/// Database key of the bitmap of the blocks of a bucket of an account or topic.
+fn bucket_key(subject_key: &[u8], bucket: u32) -> Vec<u8> {
+	let mut key = subject_key.to_vec();
+	key.extend_from_slice(&bucket.to_be_bytes());
+	key
+}
+
+/// Database key of the locations of the events of an account or topic in a block.
+fn block_key(subject_key: &[u8], number: u64) -> Vec<u8> {
+	let mut key = subject_key.to_vec();
+	key.extend_from_slice(&number.to_be_bytes());
+	key
+}
+
+fn bucket_of<N: UniqueSaturatedInto<u64>>(number: N) -> u32 {
+	(number.unique_saturated_into() / BUCKET_SIZE).saturated_into()
+}
+
+impl<Block: BlockT> EventIndex<Block> for DbEventIndex<Block> {
+	fn first_indexed(&self) -> ClientResult<Option<NumberFor<Block>>> {
+		self.read(FIRST_INDEXED)
+	}
+
+	fn last_indexed(&self) -> ClientResult<Option<NumberFor<Block>>> {
+		self.read(LAST_INDEXED)
+	}
+
+	fn index_block(
+		&self,
+		hash: Block::Hash,
+		number: NumberFor<Block>,
+		events: &[IndexedEvent],
+	) -> ClientResult<()> {
+		let _lock = self.write_lock.lock();
+
+		let last_indexed = self.last_indexed()?;
+		if let Some(last_indexed) = last_indexed {
+			if number != last_indexed + One::one() {
+				return Err(sp_blockchain::Error::Backend(format!(
+					"Can't index block #{} after block #{}",
+					number,
+					last_indexed,
+				)));
+			}
+		}
+
+		let mut added = BTreeMap::<Vec<u8>, Vec<Location<Block>>>::new();
+		for (event_index, event) in events.iter().enumerate() {
+			let location = EventLocation {
+				block_hash: hash,
+				block_number: number,
+				extrinsic_index: event.extrinsic_index,
+				event_index: event_index as u32,
+			};
+			// An event refers to the same account several times at most once.
+			let subjects = event.accounts.iter()
+				.map(|account| subject_key(EventIndexKey::Account(account)))
+				.chain(event.topics.iter().map(|topic| subject_key(EventIndexKey::Topic(topic))))
+				.collect::<BTreeSet<_>>();
+			for subject in subjects {
+				added.entry(subject).or_default().push(location.clone());
+			}
+		}
+
+		let bucket = bucket_of(number);
+		let offset = (number.unique_saturated_into() % BUCKET_SIZE) as usize;
+		let mut transaction = Transaction::new();
+		for (subject, locations) in added {
+			let mut buckets = self.read::<Vec<u32>>(&subject)?.unwrap_or_default();
+			if buckets.last() != Some(&bucket) {
+				buckets.push(bucket);
+				transaction.set_from_vec(columns::EVENT_INDEX, &subject, buckets.encode());
+			}
+			let key = bucket_key(&subject, bucket);
+			let mut blocks = self.read::<BucketBlocks>(&key)?.unwrap_or_default();
+			blocks[offset / 8] |= 1 << (offset % 8);
+			transaction.set_from_vec(columns::EVENT_INDEX, &key, blocks.encode());
+			transaction.set_from_vec(
+				columns::EVENT_INDEX,
+				&block_key(&subject, number.unique_saturated_into()),
+				locations.encode(),
+			);
+		}
+		if last_indexed.is_none() {
+			transaction.set_from_vec(columns::EVENT_INDEX, FIRST_INDEXED, number.encode());
+		}
+		transaction.set_from_vec(columns::EVENT_INDEX, LAST_INDEXED, number.encode());
+		self.db.commit(transaction)?;
+		Ok(())
+	}
+
+	fn events(
+		&self,
+		key: EventIndexKey,
+		from: NumberFor<Block>,
+		to: NumberFor<Block>,
+		limit: usize,
+	) -> ClientResult<Vec<Location<Block>>> {
+		let subject = subject_key(key);
+		let buckets = self.read::<Vec<u32>>(&subject)?.unwrap_or_default();
+		let (first, last) = (bucket_of(from), bucket_of(to));
+		let (from, to): (u64, u64) = (from.unique_saturated_into(), to.unique_saturated_into());
+
+		let mut found = Vec::new();
+		for bucket in buckets.into_iter().rev().skip_while(|b| *b > last).take_while(|b| *b >= first) {
+			let blocks = self.read::<BucketBlocks>(&bucket_key(&subject, bucket))?
+				.unwrap_or_default();
+			let start = bucket as u64 * BUCKET_SIZE;
+			for offset in (0..BUCKET_SIZE).rev() {
+				let number = start + offset;
+				let indexed = blocks[offset as usize / 8] & 1 << (offset % 8) != 0;
+				if !indexed || number < from || number > to {
+					continue;
+				}
+				if found.len() >= limit {
+					return Ok(found);
+				}
+				let locations = self.read::<Vec<Location<Block>>>(&block_key(&subject, number))?
+					.unwrap_or_default();
+				let remaining = limit - found.len();
+				found.extend(locations.into_iter().rev().take(remaining));
+			}
+		}
+		Ok(found)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use crate::tests::Block;
+
+	fn event(accounts: &[&[u8]], topics: &[&[u8]]) -> IndexedEvent {
+		IndexedEvent {
+			extrinsic_index: Some(0),
+			accounts: accounts.iter().map(|a| a.to_vec()).collect(),
+			topics: topics.iter().map(|t| t.to_vec()).collect(),
+		}
+	}
+
+	fn numbers(locations: Vec<Location<Block>>) -> Vec<(u64, u32)> {
+		locations.into_iter().map(|l| (l.block_number, l.event_index)).collect()
+	}
+
+	#[test]
+	fn index_and_look_up_events() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let index = DbEventIndex::<Block>::new(db);
+		assert_eq!(index.last_indexed().unwrap(), None);
+
+		for number in 10..=1000u64 {
+			let events = if number % 100 == 0 {
+				vec![event(&[b"alice", b"bob"], &[]), event(&[b"alice", b"alice"], &[b"topic"])]
+			} else {
+				vec![event(&[b"bob"], &[])]
+			};
+			index.index_block(H256::from_low_u64_be(number), number, &events).unwrap();
+		}
+		assert_eq!(index.first_indexed().unwrap(), Some(10));
+		assert_eq!(index.last_indexed().unwrap(), Some(1000));
+		assert!(index.index_block(H256::from_low_u64_be(1002), 1002, &[]).is_err());
+
+		let alice = EventIndexKey::Account(b"alice");
+		assert_eq!(
+			numbers(index.events(alice, 0, 1000, 3).unwrap()),
+			vec![(1000, 1), (1000, 0), (900, 1)],
+		);
+		assert_eq!(
+			numbers(index.events(alice, 150, 350, 10).unwrap()),
+			vec![(300, 1), (300, 0), (200, 1), (200, 0)],
+		);
+		assert_eq!(index.events(alice, 0, 1000, 100).unwrap().len(), 20);
+		assert_eq!(index.events(EventIndexKey::Account(b"bob"), 0, 1000, 2000).unwrap().len(), 991);
+		assert_eq!(
+			numbers(index.events(EventIndexKey::Topic(b"topic"), 0, 250, 10).unwrap()),
+			vec![(200, 1), (100, 1)],
+		);
+		assert!(index.events(EventIndexKey::Topic(b"alice"), 0, 1000, 10).unwrap().is_empty());
+
+		let location = &index.events(alice, 0, 1000, 1).unwrap()[0];
+		assert_eq!(location.block_hash, H256::from_low_u64_be(1000));
+		assert_eq!(location.extrinsic_index, Some(0));
+
+		// The locations of a block are stored on their own, not with the rest of the bucket.
+		let bob = subject_key(EventIndexKey::Account(b"bob"));
+		let stored = index.read::<Vec<Location<Block>>>(&block_key(&bob, 11)).unwrap().unwrap();
+		assert_eq!(numbers(stored), vec![(11, 0)]);
+	}
+}
@@ -37,6 +37,10 @@ pub mod bench;
 mod children;
 mod cache;
 mod changes_tries_storage;
+mod event_index;
+mod migrate;
+mod prune;
+mod snapshot;
 mod storage_cache;
 #[cfg(any(feature = "with-kvdb-rocksdb", test))]
 mod upgrade;
@@ -63,7 +67,7 @@ use sp_blockchain::{
 	well_known_cache_keys, HeaderBackend,
 };
 use codec::{Decode, Encode};
-use hash_db::Prefix;
+use hash_db::{Prefix, EMPTY_PREFIX};
 use sp_trie::{MemoryDB, PrefixedMemoryDB, prefixed_key};
 use sp_database::Transaction;
 use sp_core::{Hasher, ChangesTrieConfiguration};
@@ -77,7 +81,7 @@ use sp_runtime::traits::{
 use sp_state_machine::{
 	DBValue, ChangesTrieTransaction, ChangesTrieCacheAction, UsageInfo as StateUsageInfo,
 	StorageCollection, ChildStorageCollection, OffchainChangesCollection,
-	backend::Backend as StateBackend, StateMachineStats,
+	backend::Backend as StateBackend, StateMachineStats, TrieBackend,
 };
 use crate::utils::{DatabaseType, Meta, meta_keys, read_db, read_meta};
 use crate::changes_tries_storage::{DbChangesTrieStorage, DbChangesTrieStorageTransaction};
@@ -89,6 +93,9 @@ use crate::stats::StateUsageStats;
 // Re-export the Database trait so that one can pass an implementation of it.
 pub use sp_database::Database;
 pub use sc_state_db::PruningMode;
+pub use migrate::{migrate_database, MigrationSummary};
+pub use prune::{prune_state, PruningSummary};
+pub use snapshot::SnapshotImport;
 
 #[cfg(any(feature = "with-kvdb-rocksdb", test))]
 pub use bench::BenchmarkingState;
@@ -281,6 +288,21 @@ pub enum KeepBlocks {
 	All,
 	/// Keep N recent finalized blocks.
 	Some(u32),
+	/// Keep only the headers of finalized blocks.
+	///
+	/// Bodies and justifications are discarded as soon as a block is finalized,
+	/// so the node is unable to serve them (or finality proofs) to its peers.
+	HeadersOnly,
+}
+
+impl KeepBlocks {
+	/// Returns `true` if bodies of finalized blocks are not kept at all.
+	pub fn is_headers_only(&self) -> bool {
+		match self {
+			KeepBlocks::HeadersOnly => true,
+			_ => false,
+		}
+	}
 }
 
 /// Block body storage scheme.
@@ -311,7 +333,14 @@ pub enum DatabaseSettingsSrc {
 	},
 
 	/// Use a custom already-open database.
-	Custom(Arc<dyn Database<DbHash>>),
+	Custom {
+		/// The database.
+		db: Arc<dyn Database<DbHash>>,
+		/// Whether the database counts the references to the entries of the state column
+		/// itself, as ParityDb does. Trie nodes are then stored under their hash alone, with a
+		/// reference for every occurrence.
+		ref_counting: bool,
+	},
 }
 
 impl DatabaseSettingsSrc {
@@ -320,14 +349,15 @@ impl DatabaseSettingsSrc {
 		match self {
 			DatabaseSettingsSrc::RocksDb { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::ParityDb { path, .. } => Some(path.as_path()),
-			DatabaseSettingsSrc::Custom(_) => None,
+			DatabaseSettingsSrc::Custom { .. } => None,
 		}
 	}
 	/// Check if database supports internal ref counting for state data.
 	pub fn supports_ref_counting(&self) -> bool {
 		match self {
 			DatabaseSettingsSrc::ParityDb { .. } => true,
-			_ => false,
+			DatabaseSettingsSrc::Custom { ref_counting, .. } => *ref_counting,
+			DatabaseSettingsSrc::RocksDb { .. } => false,
 		}
 	}
 }
@@ -337,7 +367,7 @@ impl std::fmt::Display for DatabaseSettingsSrc {
 		let name = match self {
 			DatabaseSettingsSrc::RocksDb { .. } => "RocksDb",
 			DatabaseSettingsSrc::ParityDb { .. } => "ParityDb",
-			DatabaseSettingsSrc::Custom(_) => "Custom",
+			DatabaseSettingsSrc::Custom { .. } => "Custom",
 		};
 		write!(f, "{}", name)
 	}
@@ -359,6 +389,8 @@ pub(crate) mod columns {
 	pub const CACHE: u32 = 10;
 	/// Transactions
 	pub const TRANSACTION: u32 = 11;
+	/// Index of the events of the finalized chain
+	pub const EVENT_INDEX: u32 = 12;
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -580,6 +612,22 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 	fn have_extrinsic(&self, hash: &Block::Hash) -> ClientResult<bool> {
 		Ok(self.db.contains(columns::TRANSACTION, hash.as_ref()))
 	}
+
+	fn body_by_content_hash(
+		&self,
+		hash: &Block::Hash,
+	) -> ClientResult<Option<Vec<Block::Extrinsic>>> {
+		let block_hash = match self.db.get(columns::META, &utils::body_index_key(hash)) {
+			Some(block_hash) => match Decode::decode(&mut &block_hash[..]) {
+				Ok(block_hash) => block_hash,
+				Err(err) => return Err(sp_blockchain::Error::Backend(
+					format!("Error decoding body index entry {}: {}", hash, err)
+				)),
+			},
+			None => return Ok(None),
+		};
+		self.body(BlockId::Hash(block_hash))
+	}
 }
 
 impl<Block: BlockT> sc_client_api::blockchain::ProvideCache<Block> for BlockchainDb<Block> {
@@ -680,6 +728,8 @@ pub struct BlockImportOperation<Block: BlockT> {
 	finalized_blocks: Vec<(BlockId<Block>, Option<Justification>)>,
 	set_head: Option<BlockId<Block>>,
 	commit_state: bool,
+	new_base: bool,
+	chunks_root: Option<Block::Hash>,
 }
 
 impl<Block: BlockT> BlockImportOperation<Block> {
@@ -774,6 +824,57 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block> for Bloc
 		Ok(root)
 	}
 
+	fn insert_storage_chunk(
+		&mut self,
+		child_info: Option<&ChildInfo>,
+		chunk: Vec<(Vec<u8>, Vec<u8>)>,
+	) -> ClientResult<Block::Hash> {
+		let is_top = child_info.is_none();
+		if is_top && chunk.iter().any(|(k, _)| well_known_keys::is_child_storage_key(k)) {
+			return Err(sp_blockchain::Error::Backend(
+				"Child trie roots can not be inserted directly".into(),
+			));
+		}
+
+		let root = self.chunks_root.unwrap_or_else(
+			sp_trie::empty_trie_root::<sp_trie::Layout<HashFor<Block>>>
+		);
+		let mut changes_trie_config = self.changes_trie_config_update.take().unwrap_or(None);
+		let backend = TrieBackend::new(std::mem::take(&mut self.db_updates), root);
+		let delta = chunk.iter().map(|(k, v)| (&k[..], Some(&v[..])));
+		let (root, transaction) = match child_info {
+			Some(child_info) => backend.full_storage_root(
+				std::iter::empty(),
+				std::iter::once((child_info, delta)),
+			),
+			None => {
+				if let Some((_, v)) = chunk.iter()
+					.find(|(k, _)| &k[..] == well_known_keys::CHANGES_TRIE_CONFIG)
+				{
+					changes_trie_config = Some(Decode::decode(&mut &v[..]).map_err(|e|
+						sp_blockchain::Error::Backend(format!("Invalid changes trie config: {}", e))
+					)?);
+				}
+				backend.full_storage_root(delta, std::iter::empty())
+			},
+		};
+
+		let mut db_updates = backend.into_storage();
+		db_updates.consolidate(transaction);
+		db_updates.purge();
+
+		self.db_updates = db_updates;
+		self.chunks_root = Some(root);
+		self.changes_trie_config_update = Some(changes_trie_config);
+		self.commit_state = true;
+		Ok(root)
+	}
+
+	fn set_new_base(&mut self) -> ClientResult<()> {
+		self.new_base = true;
+		Ok(())
+	}
+
 	fn update_changes_trie(
 		&mut self,
 		update: ChangesTrieTransaction<HashFor<Block>, NumberFor<Block>>,
@@ -914,6 +1015,7 @@ impl<T: Clone> FrozenForDuration<T> {
 pub struct Backend<Block: BlockT> {
 	storage: Arc<StorageDb<Block>>,
 	offchain_storage: offchain::LocalStorage,
+	event_index: event_index::DbEventIndex<Block>,
 	changes_tries_storage: DbChangesTrieStorage<Block>,
 	blockchain: BlockchainDb<Block>,
 	canonicalization_delay: u64,
@@ -958,7 +1060,7 @@ impl<Block: BlockT> Backend<Block> {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			state_pruning: PruningMode::keep_blocks(keep_blocks),
-			source: DatabaseSettingsSrc::Custom(db),
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: false },
 			keep_blocks: KeepBlocks::Some(keep_blocks),
 			transaction_storage,
 		};
@@ -986,6 +1088,7 @@ impl<Block: BlockT> Backend<Block> {
 			prefix_keys: !config.source.supports_ref_counting(),
 		};
 		let offchain_storage = offchain::LocalStorage::new(db.clone());
+		let event_index = event_index::DbEventIndex::new(db.clone());
 		let changes_tries_storage = DbChangesTrieStorage::new(
 			db,
 			blockchain.header_metadata_cache.clone(),
@@ -1005,6 +1108,7 @@ impl<Block: BlockT> Backend<Block> {
 		Ok(Backend {
 			storage: Arc::new(storage_db),
 			offchain_storage,
+			event_index,
 			changes_tries_storage,
 			blockchain,
 			canonicalization_delay,
@@ -1129,11 +1233,13 @@ impl<Block: BlockT> Backend<Block> {
 		)?;
 
 		if let Some(justification) = justification {
-			transaction.set_from_vec(
-				columns::JUSTIFICATION,
-				&utils::number_and_hash_to_lookup_key(number, hash)?,
-				justification.encode(),
-			);
+			if !self.keep_blocks.is_headers_only() {
+				transaction.set_from_vec(
+					columns::JUSTIFICATION,
+					&utils::number_and_hash_to_lookup_key(number, hash)?,
+					justification.encode(),
+				);
+			}
 		}
 		Ok((*hash, number, false, true))
 	}
@@ -1158,11 +1264,21 @@ impl<Block: BlockT> Backend<Block> {
 			let hash = if new_canonical == number_u64 {
 				hash
 			} else {
-				::sc_client_api::blockchain::HeaderBackend::hash(&self.blockchain, new_canonical.saturated_into())?
-					.expect("existence of block with number `new_canonical` \
-						implies existence of blocks with all numbers before it; qed")
+				match ::sc_client_api::blockchain::HeaderBackend::hash(
+					&self.blockchain,
+					new_canonical.saturated_into(),
+				)? {
+					Some(hash) => hash,
+					// below the base of a chain that was imported without its history.
+					None => return Ok(()),
+				}
 			};
 
+			// blocks imported without state have nothing to canonicalize.
+			if self.storage.state_db.is_pruned(&hash, new_canonical) {
+				return Ok(())
+			}
+
 			trace!(target: "db", "Canonicalize block #{} ({:?})", new_canonical, hash);
 			let commit = self.storage.state_db.canonicalize_block(&hash)
 				.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from_state_db(e))?;
@@ -1210,7 +1326,33 @@ impl<Block: BlockT> Backend<Block> {
 			// blocks are keyed by number + hash.
 			let lookup_key = utils::number_and_hash_to_lookup_key(number, hash)?;
 
-			let (enacted, retracted) = if pending_block.leaf_state.is_best() {
+			// The new base of the chain (e.g. the target of a warp sync) has no route to it from
+			// the current best block and nothing to canonicalize before it.
+			let new_base = operation.new_base;
+			if new_base && (number.is_zero() || !pending_block.leaf_state.is_final()) {
+				return Err(sp_blockchain::Error::Backend(format!(
+					"New chain base #{} ({:?}) must be a final non-genesis block",
+					number,
+					hash,
+				)));
+			}
+
+			let (enacted, retracted) = if new_base {
+				debug!(target: "db", "Importing #{} ({:?}) as the new chain base", number, hash);
+				transaction.set_from_vec(columns::META, meta_keys::BEST_BLOCK, lookup_key.clone());
+				transaction.set_from_vec(
+					columns::META,
+					meta_keys::FINALIZED_BLOCK,
+					lookup_key.clone(),
+				);
+				utils::insert_number_to_key_mapping(
+					&mut transaction,
+					columns::KEY_LOOKUP,
+					number,
+					hash,
+				)?;
+				(Default::default(), Default::default())
+			} else if pending_block.leaf_state.is_best() {
 				self.set_head_with_transaction(&mut transaction, parent_hash, (number, hash))?
 			} else {
 				(Default::default(), Default::default())
@@ -1224,10 +1366,20 @@ impl<Block: BlockT> Backend<Block> {
 			)?;
 
 			transaction.set_from_vec(columns::HEADER, &lookup_key, pending_block.header.encode());
-			if let Some(body) = &pending_block.body {
+
+			// blocks that are imported as finalized would be pruned right away.
+			let discard_body = self.keep_blocks.is_headers_only() &&
+				(number.is_zero() || pending_block.leaf_state.is_final());
+			if let Some(body) = pending_block.body.as_ref().filter(|_| !discard_body) {
+				let encoded_body = body.encode();
+				transaction.set(
+					columns::META,
+					&utils::body_index_key(HashFor::<Block>::hash(&encoded_body)),
+					hash.as_ref(),
+				);
 				match self.transaction_storage {
 					TransactionStorageMode::BlockBody => {
-						transaction.set_from_vec(columns::BODY, &lookup_key, body.encode());
+						transaction.set_from_vec(columns::BODY, &lookup_key, encoded_body);
 					},
 					TransactionStorageMode::StorageChain => {
 						let mut hashes = Vec::with_capacity(body.len());
@@ -1241,7 +1393,7 @@ impl<Block: BlockT> Backend<Block> {
 					},
 				}
 			}
-			if let Some(justification) = pending_block.justification {
+			if let Some(justification) = pending_block.justification.filter(|_| !discard_body) {
 				transaction.set_from_vec(columns::JUSTIFICATION, &lookup_key, justification.encode());
 			}
 
@@ -1304,12 +1456,16 @@ impl<Block: BlockT> Backend<Block> {
 				}
 				self.state_usage.tally_writes(ops, bytes);
 				let number_u64 = number.saturated_into::<u64>();
-				let commit = self.storage.state_db.insert_block(
-					&hash,
-					number_u64,
-					&pending_block.header.parent_hash(),
-					changeset,
-				).map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from_state_db(e))?;
+				let commit = if new_base {
+					self.storage.state_db.insert_base_block(&hash, number_u64, changeset)
+				} else {
+					self.storage.state_db.insert_block(
+						&hash,
+						number_u64,
+						&pending_block.header.parent_hash(),
+						changeset,
+					)
+				}.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from_state_db(e))?;
 				apply_state_commit(&mut transaction, commit);
 
 				// Check if need to finalize. Genesis is always finalized instantly.
@@ -1340,7 +1496,13 @@ impl<Block: BlockT> Backend<Block> {
 			// release state reference so that it can be finalized
 			let cache = operation.old_state.into_cache_changes();
 
-			if finalized {
+			if new_base {
+				let displaced = self.blockchain.leaves.write().finalize_height(number);
+				match finalization_displaced_leaves {
+					Some(ref mut d) => d.merge(displaced),
+					None => finalization_displaced_leaves = Some(displaced),
+				}
+			} else if finalized {
 				// TODO: ensure best chain contains this block.
 				self.ensure_sequential_finalization(header, Some(last_finalized_hash))?;
 				self.note_finalized(
@@ -1381,7 +1543,12 @@ impl<Block: BlockT> Backend<Block> {
 				children,
 			);
 
-			meta_updates.push((hash, number, pending_block.leaf_state.is_best(), finalized));
+			meta_updates.push((
+				hash,
+				number,
+				pending_block.leaf_state.is_best(),
+				finalized || new_base,
+			));
 
 			Some((pending_block.header, number, hash, enacted, retracted, displaced_leaf, is_best, cache))
 		} else {
@@ -1473,9 +1640,12 @@ impl<Block: BlockT> Backend<Block> {
 			let lookup_key = utils::number_and_hash_to_lookup_key(f_num, f_hash.clone())?;
 			transaction.set_from_vec(columns::META, meta_keys::FINALIZED_BLOCK, lookup_key);
 
-			let commit = self.storage.state_db.canonicalize_block(&f_hash)
-				.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from_state_db(e))?;
-			apply_state_commit(transaction, commit);
+			// blocks imported without state have nothing to canonicalize.
+			if !self.storage.state_db.is_pruned(&f_hash, f_num.saturated_into::<u64>()) {
+				let commit = self.storage.state_db.canonicalize_block(&f_hash)
+					.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from_state_db(e))?;
+				apply_state_commit(transaction, commit);
+			}
 
 			if !f_num.is_zero() {
 				let new_changes_trie_cache_ops = self.changes_tries_storage.finalize(
@@ -1490,7 +1660,7 @@ impl<Block: BlockT> Backend<Block> {
 			}
 		}
 
-		self.prune_blocks(transaction, f_num)?;
+		self.prune_blocks(transaction, f_num, f_hash)?;
 		let new_displaced = self.blockchain.leaves.write().finalize_height(f_num);
 		match displaced {
 			x @ &mut None => *x = Some(new_displaced),
@@ -1503,43 +1673,75 @@ impl<Block: BlockT> Backend<Block> {
 	fn prune_blocks(
 		&self,
 		transaction: &mut Transaction<DbHash>,
-		finalized: NumberFor<Block>,
+		finalized_number: NumberFor<Block>,
+		finalized_hash: Block::Hash,
 	) -> ClientResult<()> {
-		if let KeepBlocks::Some(keep_blocks) = self.keep_blocks {
-			// Always keep the last finalized block
-			let keep = std::cmp::max(keep_blocks, 1);
-			if finalized < keep.into() {
-				return Ok(())
+		match self.keep_blocks {
+			KeepBlocks::All => Ok(()),
+			KeepBlocks::Some(keep_blocks) => {
+				// Always keep the last finalized block
+				let keep = std::cmp::max(keep_blocks, 1);
+				if finalized_number < keep.into() {
+					return Ok(())
+				}
+				let number = finalized_number.saturating_sub(keep.into());
+				self.prune_block(transaction, BlockId::<Block>::number(number))
+			},
+			// The finalized block itself may not be in the database yet, so it is
+			// looked up by hash rather than by (possibly non-canonical) number.
+			KeepBlocks::HeadersOnly => self.prune_block(transaction, BlockId::<Block>::hash(finalized_hash)),
+		}
+	}
+
+	fn prune_block(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		id: BlockId<Block>,
+	) -> ClientResult<()> {
+		utils::remove_from_db(
+			transaction,
+			&*self.storage.db,
+			columns::KEY_LOOKUP,
+			columns::JUSTIFICATION,
+			id,
+		)?;
+		// Only drop the body index entry if no other block with the same body took it over.
+		let body = sc_client_api::blockchain::Backend::body(&self.blockchain, id)?;
+		if let Some(extrinsics) = body {
+			let index_key = utils::body_index_key(HashFor::<Block>::hash(&extrinsics.encode()));
+			let block_hash = self.blockchain.expect_block_hash_from_id(&id)?;
+			let indexed = self.storage.db.get(columns::META, &index_key);
+			if indexed.as_deref() == Some(block_hash.as_ref()) {
+				transaction.remove(columns::META, &index_key);
 			}
-			let number = finalized.saturating_sub(keep.into());
-			match read_db(&*self.storage.db, columns::KEY_LOOKUP, columns::BODY, BlockId::<Block>::number(number))? {
-				Some(body) => {
-					debug!(target: "db", "Removing block #{}", number);
-					utils::remove_from_db(
-						transaction,
-						&*self.storage.db,
-						columns::KEY_LOOKUP,
-						columns::BODY,
-						BlockId::<Block>::number(number),
-					)?;
-					match self.transaction_storage {
-						TransactionStorageMode::BlockBody => {},
-						TransactionStorageMode::StorageChain => {
-							match Vec::<Block::Hash>::decode(&mut &body[..]) {
-								Ok(hashes) => {
-									for h in hashes {
-										transaction.remove(columns::TRANSACTION, h.as_ref());
-									}
+		}
+		match read_db(&*self.storage.db, columns::KEY_LOOKUP, columns::BODY, id)? {
+			Some(body) => {
+				debug!(target: "db", "Removing block #{}", id);
+				utils::remove_from_db(
+					transaction,
+					&*self.storage.db,
+					columns::KEY_LOOKUP,
+					columns::BODY,
+					id,
+				)?;
+				match self.transaction_storage {
+					TransactionStorageMode::BlockBody => {},
+					TransactionStorageMode::StorageChain => {
+						match Vec::<Block::Hash>::decode(&mut &body[..]) {
+							Ok(hashes) => {
+								for h in hashes {
+									transaction.remove(columns::TRANSACTION, h.as_ref());
 								}
-								Err(err) => return Err(sp_blockchain::Error::Backend(
-									format!("Error decoding body list: {}", err)
-								)),
 							}
+							Err(err) => return Err(sp_blockchain::Error::Backend(
+								format!("Error decoding body list: {}", err)
+							)),
 						}
 					}
 				}
-				None => return Ok(()),
 			}
+			None => {},
 		}
 		Ok(())
 	}
@@ -1608,6 +1810,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			finalized_blocks: Vec::new(),
 			set_head: None,
 			commit_state: false,
+			new_base: false,
+			chunks_root: None,
 		})
 	}
 
@@ -1676,6 +1880,10 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		Some(self.offchain_storage.clone())
 	}
 
+	fn event_index(&self) -> Option<&dyn sc_client_api::EventIndex<Block>> {
+		Some(&self.event_index)
+	}
+
 	fn usage_info(&self) -> Option<UsageInfo> {
 		let (io_stats, state_stats) = self.io_stats.take_or_else(||
 			(
@@ -1903,6 +2111,17 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		}
 	}
 
+	fn trie_node(&self, hash: &Block::Hash) -> ClientResult<Option<Vec<u8>>> {
+		// Unless the database does reference counting, nodes are stored under their hash prefixed
+		// with their position in the trie. That position can't be recovered from the hash alone,
+		// so no node is looked up rather than only the roots of the tries.
+		if self.storage.prefix_keys {
+			return Ok(None)
+		}
+		sp_state_machine::Storage::get(self.storage.as_ref(), hash, EMPTY_PREFIX)
+			.map_err(sp_blockchain::Error::Backend)
+	}
+
 	fn get_import_lock(&self) -> &RwLock<()> {
 		&*self.import_lock
 	}
@@ -1923,9 +2142,79 @@ pub(crate) mod tests {
 	use sp_runtime::generic::DigestItem;
 	use sp_state_machine::{TrieMut, TrieDBMut};
 	use sp_blockchain::{lowest_common_ancestor, tree_route};
+	use sp_database::{Change, ColumnId};
 
 	pub(crate) type Block = RawBlock<ExtrinsicWrapper<u64>>;
 
+	/// In-memory database that counts the references to the entries of the state column, as
+	/// ParityDb does: every `set` of an entry adds a reference and every `remove` releases one.
+	#[derive(Default)]
+	pub(crate) struct RefCountingDb(RwLock<HashMap<ColumnId, HashMap<Vec<u8>, (Vec<u8>, u32)>>>);
+
+	impl RefCountingDb {
+		/// Total number of references to the entries of the state column.
+		pub(crate) fn state_references(&self) -> u32 {
+			self.0.read().get(&columns::STATE).map_or(0, |column| {
+				column.values().map(|entry| entry.1).sum()
+			})
+		}
+	}
+
+	impl Database<DbHash> for RefCountingDb {
+		fn commit(&self, transaction: Transaction<DbHash>) -> sp_database::error::Result<()> {
+			let mut data = self.0.write();
+			for change in transaction.0.into_iter() {
+				match change {
+					Change::Set(col, key, value) if col == columns::STATE => {
+						let entry = data.entry(col).or_default().entry(key).or_default();
+						// Additional references are inserted with an empty value.
+						if entry.1 == 0 || !value.is_empty() {
+							entry.0 = value;
+						}
+						entry.1 += 1;
+					},
+					Change::Remove(col, key) if col == columns::STATE => {
+						let column = data.entry(col).or_default();
+						if let Some(entry) = column.get_mut(&key) {
+							entry.1 -= 1;
+							if entry.1 == 0 {
+								column.remove(&key);
+							}
+						}
+					},
+					Change::Set(col, key, value) => {
+						data.entry(col).or_default().insert(key, (value, 1));
+					},
+					Change::Remove(col, key) => {
+						data.entry(col).or_default().remove(&key);
+					},
+					Change::Store(..) | Change::Release(..) => unimplemented!(),
+				}
+			}
+			Ok(())
+		}
+
+		fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+			self.0.read().get(&col).and_then(|column| column.get(key)).map(|entry| entry.0.clone())
+		}
+
+		fn iter(
+			&self,
+			col: ColumnId,
+			f: &mut dyn FnMut(&[u8], &[u8]),
+		) -> sp_database::error::Result<()> {
+			let column = self.0.read().get(&col).cloned().unwrap_or_default();
+			for (key, (value, _)) in column.iter() {
+				f(key, value);
+			}
+			Ok(())
+		}
+
+		fn lookup(&self, _hash: &DbHash) -> Option<Vec<u8>> {
+			None
+		}
+	}
+
 	pub fn prepare_changes(changes: Vec<(Vec<u8>, Vec<u8>)>) -> (H256, MemoryDB<BlakeTwo256>) {
 		let mut changes_root = H256::default();
 		let mut changes_trie_update = MemoryDB::<BlakeTwo256>::default();
@@ -2038,7 +2327,7 @@ pub(crate) mod tests {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
 			state_pruning: PruningMode::keep_blocks(1),
-			source: DatabaseSettingsSrc::Custom(backing),
+			source: DatabaseSettingsSrc::Custom { db: backing, ref_counting: false },
 			keep_blocks: KeepBlocks::All,
 			transaction_storage: TransactionStorageMode::BlockBody,
 		}, 0).unwrap();
@@ -2560,6 +2849,161 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn import_final_block_with_unknown_parent_as_new_base() {
+		let backend = Backend::<Block>::new_test(10, 10);
+
+		let block0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let header = Header {
+			number: 100,
+			parent_hash: [1; 32].into(),
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let hash = header.hash();
+		let justification = Some(vec![1, 2, 3]);
+
+		// Without being marked as the new base, the block is not imported.
+		let mut op = backend.begin_operation().unwrap();
+		op.set_block_data(header.clone(), None, justification.clone(), NewBlockState::Final)
+			.unwrap();
+		backend.commit_operation(op).unwrap_err();
+		assert_eq!(backend.blockchain().hash(100).unwrap(), None);
+
+		let mut op = backend.begin_operation().unwrap();
+		op.set_block_data(header, None, justification.clone(), NewBlockState::Final).unwrap();
+		op.set_new_base().unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let info = backend.blockchain().info();
+		assert_eq!((info.best_number, info.best_hash), (100, hash));
+		assert_eq!((info.finalized_number, info.finalized_hash), (100, hash));
+		assert_eq!(info.genesis_hash, block0);
+		assert_eq!(backend.blockchain().hash(100).unwrap(), Some(hash));
+		assert_eq!(backend.blockchain().justification(BlockId::Hash(hash)).unwrap(), justification);
+		assert_eq!(backend.blockchain().leaves().unwrap(), vec![hash]);
+
+		// The chain continues from the new base.
+		let header = Header {
+			number: 101,
+			parent_hash: hash,
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let block101 = header.hash();
+		let mut op = backend.begin_operation().unwrap();
+		op.set_block_data(header, None, None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+		assert_eq!(backend.blockchain().info().best_hash, block101);
+	}
+
+	#[test]
+	fn import_new_base_with_state() {
+		let backend = Backend::<Block>::new_test(10, 10);
+		insert_header(&backend, 0, Default::default(), None, Default::default());
+
+		let storage = vec![
+			(vec![1, 3, 5], vec![2, 4, 6]),
+			(vec![1, 2, 3], vec![9, 9, 9]),
+		];
+		let mut op = backend.begin_operation().unwrap();
+		let mut header = Header {
+			number: 100,
+			parent_hash: [1; 32].into(),
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		header.state_root = op.reset_storage(Storage {
+			top: storage.into_iter().collect(),
+			children_default: Default::default(),
+		}).unwrap();
+		let hash = header.hash();
+		op.set_block_data(header, None, None, NewBlockState::Final).unwrap();
+		op.set_new_base().unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let state = backend.state_at(BlockId::Hash(hash)).unwrap();
+		assert_eq!(state.storage(&[1, 3, 5]).unwrap(), Some(vec![2, 4, 6]));
+		assert_eq!(state.storage(&[1, 2, 3]).unwrap(), Some(vec![9, 9, 9]));
+
+		// Blocks on top of the new base are executed and finalized as usual.
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, BlockId::Hash(hash)).unwrap();
+		let mut header = Header {
+			number: 101,
+			parent_hash: hash,
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let storage = vec![(vec![5, 5, 5], Some(vec![4, 5, 6]))];
+		let (root, overlay) = op.old_state.storage_root(
+			storage.iter()
+				.map(|(k, v)| (&k[..], v.as_ref().map(|v| &v[..])))
+		);
+		op.update_db_storage(overlay).unwrap();
+		header.state_root = root.into();
+		op.update_storage(storage, Vec::new()).unwrap();
+		let block101 = header.hash();
+		op.set_block_data(header, None, None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+		backend.finalize_block(BlockId::Hash(block101), None).unwrap();
+
+		let state = backend.state_at(BlockId::Hash(block101)).unwrap();
+		assert_eq!(state.storage(&[1, 3, 5]).unwrap(), Some(vec![2, 4, 6]));
+		assert_eq!(state.storage(&[5, 5, 5]).unwrap(), Some(vec![4, 5, 6]));
+	}
+
+	#[test]
+	fn import_state_in_chunks() {
+		let backend = Backend::<Block>::new_test(10, 10);
+		insert_header(&backend, 0, Default::default(), None, Default::default());
+
+		let child_info = ChildInfo::new_default(b"child");
+		let top = vec![
+			(vec![1, 2, 3], vec![9, 9, 9]),
+			(vec![1, 3, 5], vec![2, 4, 6]),
+			(vec![7], vec![7]),
+		];
+		let child = vec![(vec![1], vec![1]), (vec![2], vec![2])];
+		let expected_root = backend.begin_operation().unwrap().reset_storage(Storage {
+			top: top.iter().cloned().collect(),
+			children_default: std::iter::once((
+				child_info.storage_key().to_vec(),
+				sp_core::storage::StorageChild {
+					data: child.iter().cloned().collect(),
+					child_info: child_info.clone(),
+				},
+			)).collect(),
+		}).unwrap();
+
+		let mut op = backend.begin_operation().unwrap();
+		op.insert_storage_chunk(None, top[..2].to_vec()).unwrap();
+		op.insert_storage_chunk(Some(&child_info), child[..1].to_vec()).unwrap();
+		op.insert_storage_chunk(None, top[2..].to_vec()).unwrap();
+		let root = op.insert_storage_chunk(Some(&child_info), child[1..].to_vec()).unwrap();
+		assert_eq!(root, expected_root);
+
+		let header = Header {
+			number: 100,
+			parent_hash: [1; 32].into(),
+			state_root: root,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let hash = header.hash();
+		op.set_block_data(header, None, None, NewBlockState::Final).unwrap();
+		op.set_new_base().unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let state = backend.state_at(BlockId::Hash(hash)).unwrap();
+		assert_eq!(state.storage(&[7]).unwrap(), Some(vec![7]));
+		assert_eq!(state.child_storage(&child_info, &[2]).unwrap(), Some(vec![2]));
+	}
+
 	#[test]
 	fn header_cht_root_works() {
 		use sc_client_api::ProvideChtRoots;
@@ -2613,4 +3057,158 @@ pub(crate) mod tests {
 			assert_eq!(Some(vec![4.into()]), bc.body(BlockId::hash(blocks[4])).unwrap());
 		}
 	}
+
+	#[test]
+	fn body_by_content_hash_follows_pruning() {
+		for storage in &[TransactionStorageMode::BlockBody, TransactionStorageMode::StorageChain] {
+			let backend = Backend::<Block>::new_test_with_tx_storage(1, 0, *storage);
+			let mut blocks = Vec::new();
+			let mut prev_hash = Default::default();
+			for i in 0 .. 3 {
+				let hash = insert_block(&backend, i, prev_hash, None, Default::default(), vec![i.into()]);
+				blocks.push(hash);
+				prev_hash = hash;
+			}
+			let content_hash = |i: u64| {
+				BlakeTwo256::hash(&vec![ExtrinsicWrapper::from(i)].encode())
+			};
+
+			let bc = backend.blockchain();
+			assert_eq!(Some(vec![1.into()]), bc.body_by_content_hash(&content_hash(1)).unwrap());
+			assert_eq!(None, bc.body_by_content_hash(&content_hash(3)).unwrap());
+
+			{
+				let mut op = backend.begin_operation().unwrap();
+				backend.begin_state_operation(&mut op, BlockId::Hash(blocks[2])).unwrap();
+				op.mark_finalized(BlockId::Hash(blocks[1]), None).unwrap();
+				op.mark_finalized(BlockId::Hash(blocks[2]), None).unwrap();
+				backend.commit_operation(op).unwrap();
+			}
+			let bc = backend.blockchain();
+			assert_eq!(None, bc.body_by_content_hash(&content_hash(1)).unwrap());
+			assert_eq!(Some(vec![2.into()]), bc.body_by_content_hash(&content_hash(2)).unwrap());
+		}
+	}
+
+	#[test]
+	fn prune_justifications_on_finalize() {
+		let backend = Backend::<Block>::new_test(2, 0);
+		let mut blocks = Vec::new();
+		let mut prev_hash = Default::default();
+		for i in 0 .. 5 {
+			let hash = insert_block(&backend, i, prev_hash, None, Default::default(), vec![i.into()]);
+			blocks.push(hash);
+			prev_hash = hash;
+		}
+
+		{
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(blocks[4])).unwrap();
+			for i in 1 .. 5 {
+				op.mark_finalized(BlockId::Hash(blocks[i]), Some(vec![i as u8])).unwrap();
+			}
+			backend.commit_operation(op).unwrap();
+		}
+		let bc = backend.blockchain();
+		assert_eq!(None, bc.justification(BlockId::hash(blocks[1])).unwrap());
+		assert_eq!(None, bc.justification(BlockId::hash(blocks[2])).unwrap());
+		assert_eq!(Some(vec![3]), bc.justification(BlockId::hash(blocks[3])).unwrap());
+		assert_eq!(Some(vec![4]), bc.justification(BlockId::hash(blocks[4])).unwrap());
+	}
+
+	#[test]
+	fn headers_only_discards_finalized_bodies() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			state_pruning: PruningMode::keep_blocks(2),
+			source: DatabaseSettingsSrc::Custom {
+				db: sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS)),
+				ref_counting: false,
+			},
+			keep_blocks: KeepBlocks::HeadersOnly,
+			transaction_storage: TransactionStorageMode::BlockBody,
+		}, 0).unwrap();
+		let mut blocks = Vec::new();
+		let mut prev_hash = Default::default();
+		for i in 0 .. 4 {
+			let hash = insert_block(&backend, i, prev_hash, None, Default::default(), vec![i.into()]);
+			blocks.push(hash);
+			prev_hash = hash;
+		}
+
+		{
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(blocks[3])).unwrap();
+			for i in 1 .. 3 {
+				op.mark_finalized(BlockId::Hash(blocks[i]), Some(vec![i as u8])).unwrap();
+			}
+			backend.commit_operation(op).unwrap();
+		}
+		let bc = backend.blockchain();
+		for i in 0 .. 3 {
+			assert!(bc.header(BlockId::hash(blocks[i])).unwrap().is_some());
+			assert_eq!(None, bc.body(BlockId::hash(blocks[i])).unwrap());
+			assert_eq!(None, bc.justification(BlockId::hash(blocks[i])).unwrap());
+		}
+		assert_eq!(Some(vec![3.into()]), bc.body(BlockId::hash(blocks[3])).unwrap());
+	}
+
+	#[test]
+	fn trie_node_looks_up_any_node_with_ref_counting() {
+		let import_state = |backend: &Backend<Block>| {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(Default::default())).unwrap();
+			// Values long enough for the leaves not to be inlined in their parent.
+			let storage = (0..16u8).map(|i| (vec![i * 16, i], vec![i; 64])).collect::<Vec<_>>();
+			let (state_root, mut nodes) = op.old_state.storage_root(storage
+				.iter()
+				.map(|(x, y)| (&x[..], Some(&y[..])))
+			);
+			let header = Header {
+				number: 0,
+				parent_hash: Default::default(),
+				state_root,
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			op.reset_storage(Storage {
+				top: storage.into_iter().collect(),
+				children_default: Default::default(),
+			}).unwrap();
+			op.set_block_data(header.clone(), Some(vec![]), None, NewBlockState::Best).unwrap();
+			backend.commit_operation(op).unwrap();
+			// The keys of the nodes are prefixed with their position in the trie.
+			let nodes = nodes.drain().into_iter()
+				.map(|(key, _)| H256::from_slice(&key[key.len() - DB_HASH_LEN..]))
+				.collect::<Vec<_>>();
+			(state_root, nodes)
+		};
+
+		// Nodes stored under prefixed keys can't be found by their hash alone, not even the
+		// root, which is stored without prefix.
+		let backend = Backend::<Block>::new_test(2, 0);
+		assert!(backend.storage.prefix_keys);
+		let (state_root, _) = import_state(&backend);
+		assert_eq!(backend.trie_node(&state_root).unwrap(), None);
+
+		let db = Arc::new(RefCountingDb::default());
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			state_pruning: PruningMode::keep_blocks(2),
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: true },
+			keep_blocks: KeepBlocks::All,
+			transaction_storage: TransactionStorageMode::BlockBody,
+		}, 0).unwrap();
+		assert!(!backend.storage.prefix_keys);
+		let (state_root, nodes) = import_state(&backend);
+		assert!(nodes.contains(&state_root));
+		assert!(nodes.len() > 16);
+		for hash in nodes {
+			let node = backend.trie_node(&hash).unwrap().unwrap();
+			assert_eq!(BlakeTwo256::hash(&node), hash);
+		}
+		assert_eq!(backend.trie_node(&BlakeTwo256::hash(b"unknown")).unwrap(), None);
+	}
 }
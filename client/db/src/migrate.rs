@@ -0,0 +1,434 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Migration of a database to a different backend.
+//!
+//! Every column of the source database is copied to an empty destination database. The source
+//! is only read from, so it stays usable whatever the outcome.
+//!
+//! Databases without internal reference counting store trie nodes under their hash prefixed
+//! with the trie path of the node. Databases with reference counting store them under the hash
+//! alone, with a reference for every occurrence. Keys of the state column and of the state
+//! database journals are converted accordingly.
+
+use std::{collections::BTreeSet, sync::Arc};
+use log::info;
+use sp_database::{ColumnId, Database, Transaction};
+use sp_core::{hexdisplay::HexDisplay, hashing::blake2_256, storage::{well_known_keys, ChildInfo}};
+use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderBackend};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, HashFor, SaturatedConversion};
+use sp_state_machine::{
+	backend::Backend as StateBackend, prove_read_on_trie_backend, prove_child_read_on_trie_backend,
+	read_proof_check, read_child_proof_check,
+};
+use sc_state_db::StateDb;
+use crate::utils::{DatabaseType, NUM_COLUMNS, meta_keys};
+use crate::{
+	BlockchainDb, DatabaseSettings, DbHash, DbState, StateMetaDb, StorageDb, DB_HASH_LEN, columns,
+};
+
+/// Number of changes after which the pending transaction is committed.
+const COPY_BATCH_SIZE: usize = 10_000;
+/// Number of keys of every trie sampled from each database to verify the migrated state.
+const VERIFY_SAMPLE_SIZE: u32 = 256;
+
+/// Outcome of a database migration.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationSummary {
+	/// Number of entries copied, per column.
+	pub entries: Vec<u64>,
+	/// Number of the block whose state root was verified in the destination.
+	pub verified_block: u64,
+}
+
+// Converts keys of the source database into keys of the destination database.
+#[derive(Clone, Copy)]
+struct KeyMapping {
+	strip_state_prefixes: bool,
+}
+
+impl KeyMapping {
+	fn key<'a>(&self, col: ColumnId, key: &'a [u8]) -> &'a [u8] {
+		if self.strip_state_prefixes && col == columns::STATE {
+			&key[key.len().saturating_sub(DB_HASH_LEN)..]
+		} else {
+			key
+		}
+	}
+}
+
+fn read_column(
+	db: &Arc<dyn Database<DbHash>>,
+	col: ColumnId,
+) -> ClientResult<Vec<(Vec<u8>, Vec<u8>)>> {
+	let mut entries = Vec::new();
+	db.iter(col, &mut |key, value| entries.push((key.to_vec(), value.to_vec())))?;
+	Ok(entries)
+}
+
+// Copies a column, returning the number of entries copied.
+fn copy_column(
+	source: &Arc<dyn Database<DbHash>>,
+	destination: &Arc<dyn Database<DbHash>>,
+	col: ColumnId,
+	mapping: KeyMapping,
+) -> ClientResult<u64> {
+	let mut transaction = Transaction::<DbHash>::new();
+	let mut copied = 0;
+	let mut result = Ok(());
+	source.iter(col, &mut |key, value| {
+		transaction.set(col, mapping.key(col, key), value);
+		copied += 1;
+		if transaction.0.len() >= COPY_BATCH_SIZE && result.is_ok() {
+			result = destination.commit(std::mem::take(&mut transaction));
+		}
+	})?;
+	result?;
+	destination.commit(transaction)?;
+	Ok(copied)
+}
+
+// Checks that every entry of the source column is present in the destination.
+fn verify_column(
+	source: &Arc<dyn Database<DbHash>>,
+	destination: &Arc<dyn Database<DbHash>>,
+	col: ColumnId,
+	mapping: KeyMapping,
+) -> ClientResult<u64> {
+	let mut found = 0;
+	let mut missing = 0;
+	source.iter(col, &mut |key, value| {
+		match destination.get(col, mapping.key(col, key)) {
+			Some(v) if v == value => found += 1,
+			_ => missing += 1,
+		}
+	})?;
+	if missing > 0 {
+		return Err(ClientError::Backend(
+			format!("{} entries of column {} were not migrated", missing, col),
+		));
+	}
+	Ok(found)
+}
+
+// Opens the state with the given root.
+fn open_state<Block: BlockT>(
+	db: &Arc<dyn Database<DbHash>>,
+	config: &DatabaseSettings,
+	root: Block::Hash,
+) -> ClientResult<DbState<Block>> {
+	let map_e = |e: sc_state_db::Error<std::io::Error>| ClientError::from_state_db(e);
+	let mode = sc_state_db::fetch_stored_pruning_mode(&StateMetaDb(&**db))
+		.map_err(map_e)?
+		.ok_or_else(|| ClientError::Backend("Database does not contain any state".into()))?;
+	let ref_counting = config.source.supports_ref_counting();
+	let storage = Arc::new(StorageDb {
+		db: db.clone(),
+		state_db: StateDb::new(mode, !ref_counting, &StateMetaDb(&**db)).map_err(map_e)?,
+		prefix_keys: !ref_counting,
+	});
+	Ok(DbState::<Block>::new(storage, root))
+}
+
+// Picks up to `VERIFY_SAMPLE_SIZE + 1` keys of a trie, spread over the key space.
+fn sample_keys(
+	next_key: impl Fn(&[u8]) -> Result<Option<Vec<u8>>, String>,
+) -> ClientResult<BTreeSet<Vec<u8>>> {
+	let starts = std::iter::once(Vec::new())
+		.chain((0..VERIFY_SAMPLE_SIZE).map(|i| blake2_256(&i.to_le_bytes()).to_vec()));
+	let mut keys = BTreeSet::new();
+	for start in starts {
+		if let Some(key) = next_key(&start).map_err(ClientError::Backend)? {
+			keys.insert(key);
+		}
+	}
+	Ok(keys)
+}
+
+// Verifies the state of the last finalized block in the destination database on a sample of
+// keys of every trie, taken from both databases.
+//
+// Every sampled key is read from the destination with a proof, which is checked against the
+// state root of the block, and the value is compared with the one in the source.
+fn verify_state_root<Block: BlockT>(
+	source: &Arc<dyn Database<DbHash>>,
+	from: &DatabaseSettings,
+	destination: &Arc<dyn Database<DbHash>>,
+	to: &DatabaseSettings,
+) -> ClientResult<u64> {
+	let blockchain = BlockchainDb::<Block>::new(destination.clone(), to.transaction_storage)?;
+	let number = blockchain.info().finalized_number;
+	let header = blockchain.expect_header(BlockId::number(number))?;
+	let root = *header.state_root();
+	let source_state = open_state::<Block>(source, from, root)?;
+	let state = open_state::<Block>(destination, to, root)?;
+
+	let mismatch = |e: &dyn std::fmt::Display| ClientError::Backend(format!(
+		"State of block #{} does not match after migration: {}",
+		number,
+		e,
+	));
+
+	let mut keys = sample_keys(|key| state.next_storage_key(key))?;
+	keys.extend(sample_keys(|key| source_state.next_storage_key(key))?);
+	let proof = prove_read_on_trie_backend(&state, &keys).map_err(|e| mismatch(&e))?;
+	let values = read_proof_check::<HashFor<Block>, _>(root, proof, &keys)
+		.map_err(|e| mismatch(&e))?;
+	for key in keys.iter() {
+		let expected = source_state.storage(key).map_err(ClientError::Backend)?;
+		if values.get(key) != Some(&expected) {
+			return Err(mismatch(&format!("value of key 0x{} differs", HexDisplay::from(key))));
+		}
+	}
+
+	for prefixed_key in state.keys(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+		let child_info = ChildInfo::new_default(
+			&prefixed_key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..],
+		);
+		let mut keys = sample_keys(|key| state.next_child_storage_key(&child_info, key))?;
+		keys.extend(sample_keys(|key| source_state.next_child_storage_key(&child_info, key))?);
+		let proof = prove_child_read_on_trie_backend(&state, &child_info, &keys)
+			.map_err(|e| mismatch(&e))?;
+		let values = read_child_proof_check::<HashFor<Block>, _>(root, proof, &child_info, &keys)
+			.map_err(|e| mismatch(&e))?;
+		for key in keys.iter() {
+			let expected = source_state.child_storage(&child_info, key)
+				.map_err(ClientError::Backend)?;
+			if values.get(key) != Some(&expected) {
+				return Err(mismatch(&format!(
+					"value of key 0x{} in child trie 0x{} differs",
+					HexDisplay::from(key),
+					HexDisplay::from(&child_info.storage_key()),
+				)));
+			}
+		}
+	}
+	Ok(number.saturated_into())
+}
+
+/// Copies the database described by `from` into the empty database described by `to`.
+///
+/// After copying, every entry of the source is checked to be present in the destination, and
+/// a sample of the state of the last finalized block is read from the destination with proofs
+/// checked against its state root. The source database is not modified. Migrating from a
+/// database that does reference counting internally, i.e. ParityDb, is not supported: it does
+/// not keep the trie paths that the other databases prefix the trie nodes with.
+pub fn migrate_database<Block: BlockT>(
+	from: &DatabaseSettings,
+	to: &DatabaseSettings,
+) -> ClientResult<MigrationSummary> {
+	if from.source.supports_ref_counting() {
+		return Err(ClientError::Backend(format!(
+			"Migrating from {} is not supported: it does not store the trie paths of the state",
+			from.source,
+		)));
+	}
+	let mapping = KeyMapping {
+		strip_state_prefixes: to.source.supports_ref_counting(),
+	};
+
+	let source = crate::utils::open_database::<Block>(from, DatabaseType::Full)?;
+	if source.get(columns::META, meta_keys::GENESIS_HASH).is_none() {
+		return Err(ClientError::Backend("Source database is empty".into()));
+	}
+	let destination = crate::utils::open_database::<Block>(to, DatabaseType::Full)?;
+	if destination.get(columns::META, meta_keys::GENESIS_HASH).is_some() {
+		return Err(ClientError::Backend("Destination database is not empty".into()));
+	}
+
+	let mut summary = MigrationSummary::default();
+	// The metadata column goes last, so that an interrupted migration leaves a destination
+	// that is not mistaken for a complete database.
+	let copy_order = (1..NUM_COLUMNS).chain(std::iter::once(columns::META));
+	for col in copy_order.clone() {
+		info!(target: "db", "Copying column {} from {} to {}", col, from.source, to.source);
+		let copied = if col == columns::STATE_META && mapping.strip_state_prefixes {
+			let meta = sc_state_db::strip_meta_key_prefixes::<Block::Hash>(
+				read_column(&source, col)?,
+				DB_HASH_LEN,
+			).map_err(|e| ClientError::Backend(format!("Invalid state journal: {:?}", e)))?;
+			let mut transaction = Transaction::<DbHash>::new();
+			for (key, value) in meta.iter() {
+				transaction.set(col, key, value);
+			}
+			destination.commit(transaction)?;
+			meta.len() as u64
+		} else {
+			copy_column(&source, &destination, col, mapping)?
+		};
+		summary.entries.push(copied);
+	}
+	// Entries are reported in column order.
+	let meta_entries = summary.entries.pop().expect("the metadata column is copied; qed");
+	summary.entries.insert(columns::META as usize, meta_entries);
+
+	info!(target: "db", "Verifying migrated database");
+	for col in copy_order {
+		// The journals are rewritten when converting keys, so they are checked through the
+		// state root below.
+		if col == columns::STATE_META && mapping.strip_state_prefixes {
+			continue;
+		}
+		let found = verify_column(&source, &destination, col, mapping)?;
+		if !mapping.strip_state_prefixes && found != summary.entries[col as usize] {
+			return Err(ClientError::Backend(
+				format!("Entry count of column {} does not match after migration", col),
+			));
+		}
+	}
+	summary.verified_block = verify_state_root::<Block>(&source, from, &destination, to)?;
+
+	info!(
+		target: "db",
+		"Migrated {} entries, state of block #{} verified",
+		summary.entries.iter().sum::<u64>(),
+		summary.verified_block,
+	);
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use sp_runtime::testing::Header;
+	use sc_client_api::backend::{Backend as _, BlockImportOperation as _, NewBlockState};
+	use sc_state_db::PruningMode;
+	use crate::{Backend, DatabaseSettingsSrc, KeepBlocks, TransactionStorageMode};
+	use crate::tests::{Block, RefCountingDb};
+
+	fn settings(db: Arc<dyn Database<DbHash>>) -> DatabaseSettings {
+		DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			state_pruning: PruningMode::keep_blocks(2),
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: false },
+			keep_blocks: KeepBlocks::All,
+			transaction_storage: TransactionStorageMode::BlockBody,
+		}
+	}
+
+	fn ref_counting_settings(db: Arc<dyn Database<DbHash>>) -> DatabaseSettings {
+		DatabaseSettings {
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: true },
+			..settings(Arc::new(sp_database::MemDb::new()))
+		}
+	}
+
+	fn source_database() -> Arc<dyn Database<DbHash>> {
+		let source = sp_database::as_database(kvdb_memorydb::create(NUM_COLUMNS));
+		let backend = Backend::<Block>::new(settings(source.clone()), 0).unwrap();
+		let mut parent_hash = Default::default();
+		for number in 0 .. 4 {
+			parent_hash = insert_final_block(&backend, number, parent_hash);
+		}
+		source
+	}
+
+	fn insert_final_block(backend: &Backend<Block>, number: u64, parent_hash: H256) -> H256 {
+		let mut op = backend.begin_operation().unwrap();
+		let parent = if number == 0 { Default::default() } else { parent_hash };
+		backend.begin_state_operation(&mut op, BlockId::Hash(parent)).unwrap();
+		let mut header = Header {
+			number,
+			parent_hash,
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+
+		let storage = vec![(vec![number as u8], Some(vec![number as u8]))];
+		let (root, overlay) = op.old_state.storage_root(
+			storage.iter().map(|(k, v)| (&k[..], v.as_ref().map(|v| &v[..])))
+		);
+		op.update_db_storage(overlay).unwrap();
+		header.state_root = root.into();
+		op.update_storage(storage, Vec::new()).unwrap();
+		op.set_block_data(header.clone(), Some(vec![]), None, NewBlockState::Final).unwrap();
+		backend.commit_operation(op).unwrap();
+		header.hash()
+	}
+
+	#[test]
+	fn migrates_database() {
+		let source = source_database();
+		let destination = sp_database::as_database(kvdb_memorydb::create(NUM_COLUMNS));
+		let summary = migrate_database::<Block>(
+			&settings(source.clone()),
+			&settings(destination.clone()),
+		).unwrap();
+		assert_eq!(summary.entries.len(), NUM_COLUMNS as usize);
+		assert!(summary.entries[columns::HEADER as usize] >= 4);
+		assert_eq!(summary.verified_block, 3);
+
+		// The destination is not empty anymore.
+		assert!(migrate_database::<Block>(&settings(source), &settings(destination.clone())).is_err());
+
+		let backend = Backend::<Block>::new(settings(destination), 0).unwrap();
+		assert_eq!(backend.blockchain().info().finalized_number, 3);
+		assert_eq!(
+			backend.state_at(BlockId::Number(3)).unwrap().storage(&[3]).unwrap(),
+			Some(vec![3]),
+		);
+	}
+
+	#[test]
+	fn migrates_database_to_ref_counting_database() {
+		let source = source_database();
+		let destination = Arc::new(RefCountingDb::default());
+		let summary = migrate_database::<Block>(
+			&settings(source.clone()),
+			&ref_counting_settings(destination.clone()),
+		).unwrap();
+		assert_eq!(summary.verified_block, 3);
+
+		// Trie nodes are stored under their hash alone, with a reference per occurrence.
+		let mut prefixed_keys = 0;
+		source.iter(columns::STATE, &mut |key, value| {
+			prefixed_keys += 1;
+			let key = &key[key.len() - DB_HASH_LEN..];
+			assert_eq!(destination.get(columns::STATE, key), Some(value.to_vec()));
+		}).unwrap();
+		let mut keys = 0;
+		destination.iter(columns::STATE, &mut |key, _| {
+			keys += 1;
+			assert_eq!(key.len(), DB_HASH_LEN);
+		}).unwrap();
+		assert!(keys > 0 && keys <= prefixed_keys);
+		assert_eq!(destination.state_references(), prefixed_keys);
+
+		// A ref counting database can't be migrated from.
+		assert!(migrate_database::<Block>(
+			&ref_counting_settings(destination.clone()),
+			&settings(sp_database::as_database(kvdb_memorydb::create(NUM_COLUMNS))),
+		).is_err());
+
+		// The destination is usable: its journals were converted, and blocks can be imported on
+		// top of the migrated state.
+		let backend = Backend::<Block>::new(ref_counting_settings(destination), 0).unwrap();
+		assert!(!backend.storage.prefix_keys);
+		let parent_hash = backend.blockchain().hash(3).unwrap().unwrap();
+		insert_final_block(&backend, 4, parent_hash);
+		assert_eq!(backend.blockchain().info().finalized_number, 4);
+		let state = backend.state_at(BlockId::Number(4)).unwrap();
+		for number in 0 .. 5u8 {
+			assert_eq!(state.storage(&[number]).unwrap(), Some(vec![number]));
+		}
+	}
+}
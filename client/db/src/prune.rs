@@ -0,0 +1,626 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Offline pruning of archive databases.
+//!
+//! An archive database keeps every trie node ever written, and does not journal which nodes
+//! became obsolete. Pruning it replays the state changes of the canonical chain, comparing the
+//! tries of every block with the ones of its parent. Only the nodes that differ are visited, as
+//! a subtrie that is shared by both states is skipped as a whole. This yields the nodes each
+//! block inserted and deleted, which is what a pruned node journals on import.
+//!
+//! Nodes deleted by blocks up to the start of the kept window are removed right away. Nodes
+//! deleted by blocks in the window are written to the pruning journal of these blocks, so that
+//! the pruned node removes them once the window moves on, exactly as if it had been running
+//! pruned all along.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use log::info;
+use trie_db::{NibbleVec, NodeCodec as _, node::{Node, NodeHandle}};
+use sp_trie::prefixed_key;
+use sp_database::Transaction;
+use sp_core::storage::{well_known_keys, ChildInfo};
+use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderBackend};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, HashFor, SaturatedConversion};
+use sp_state_machine::{DBValue, backend::Backend as StateBackend};
+use sc_client_api::blockchain::Backend as _;
+use sc_state_db::{StateDb, PruningMode, ChangeSet, CommitSet};
+use crate::utils::DatabaseType;
+use crate::{
+	BlockchainDb, DatabaseSettings, DbState, StateMetaDb, StorageDb, apply_state_commit, columns,
+};
+
+/// Number of removals after which the pending transaction is committed.
+const REMOVAL_BATCH_SIZE: usize = 10_000;
+
+/// Number of replayed blocks after which progress is logged.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Outcome of an offline pruning run.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PruningSummary {
+	/// Number of canonical blocks whose state was kept.
+	pub kept_blocks: u64,
+	/// Number of trie nodes removed from the database.
+	pub removed_nodes: u64,
+}
+
+// A reference to a trie node, as found in its parent.
+enum NodeRef<H> {
+	Hash(H),
+	Inline(Vec<u8>),
+}
+
+impl<H: Default + AsMut<[u8]>> NodeRef<H> {
+	fn from_handle(handle: &NodeHandle) -> Self {
+		match handle {
+			NodeHandle::Hash(data) => {
+				let mut hash = H::default();
+				hash.as_mut().copy_from_slice(data);
+				NodeRef::Hash(hash)
+			},
+			NodeHandle::Inline(data) => NodeRef::Inline(data.to_vec()),
+		}
+	}
+}
+
+fn empty_root<Block: BlockT>() -> Block::Hash {
+	sp_trie::NodeCodec::<HashFor<Block>>::hashed_null_node()
+}
+
+// Returns the prefix of the node at `path` of the trie stored under `keyspace`.
+fn db_prefix(keyspace: &[u8], path: &NibbleVec) -> (Vec<u8>, Option<u8>) {
+	let (prefix, padding) = path.as_prefix();
+	let mut result = keyspace.to_vec();
+	result.extend_from_slice(prefix);
+	(result, padding)
+}
+
+fn read_node<Block: BlockT>(
+	storage: &StorageDb<Block>,
+	keyspace: &[u8],
+	path: &NibbleVec,
+	hash: &Block::Hash,
+) -> ClientResult<DBValue> {
+	let (prefix, padding) = db_prefix(keyspace, path);
+	sp_state_machine::Storage::<HashFor<Block>>::get(storage, hash, (&prefix[..], padding))
+		.map_err(ClientError::Backend)?
+		.ok_or_else(|| ClientError::Backend(format!("Trie node {:?} is missing", hash)))
+}
+
+// Returns the partial key and the children of an encoded branch node, or `None` for leaves.
+fn decode_branch<Block: BlockT>(
+	data: &[u8],
+) -> ClientResult<Option<(NibbleVec, Vec<(u8, NodeRef<Block::Hash>)>)>> {
+	let node = sp_trie::NodeCodec::<HashFor<Block>>::decode(data)
+		.map_err(|e| ClientError::Backend(format!("Invalid trie node: {:?}", e)))?;
+	let (partial, children) = match node {
+		Node::Empty | Node::Leaf(..) => return Ok(None),
+		Node::Branch(children, _) => (NibbleVec::new(), children),
+		Node::NibbledBranch(partial, children, _) => (partial.into(), children),
+		Node::Extension(..) => return Err(ClientError::Backend("Unexpected extension node".into())),
+	};
+	let children = children.iter()
+		.enumerate()
+		.filter_map(|(nibble, child)| {
+			child.as_ref().map(|child| (nibble as u8, NodeRef::from_handle(child)))
+		})
+		.collect();
+	Ok(Some((partial, children)))
+}
+
+// Returns the hash of the node that starts at `target` in the trie with the given root, if there
+// is one and it is not inlined in its parent.
+fn node_at<Block: BlockT>(
+	storage: &StorageDb<Block>,
+	keyspace: &[u8],
+	root: Block::Hash,
+	target: &NibbleVec,
+) -> ClientResult<Option<Block::Hash>> {
+	let mut path = NibbleVec::new();
+	let mut node = NodeRef::Hash(root);
+	loop {
+		if path.len() == target.len() {
+			return Ok(match node {
+				NodeRef::Hash(hash) => Some(hash),
+				NodeRef::Inline(_) => None,
+			});
+		}
+		let data = match node {
+			NodeRef::Hash(hash) if hash == empty_root::<Block>() => return Ok(None),
+			NodeRef::Hash(hash) => read_node(storage, keyspace, &path, &hash)?,
+			NodeRef::Inline(data) => data,
+		};
+		let (partial, children) = match decode_branch::<Block>(&data)? {
+			Some(branch) => branch,
+			None => return Ok(None),
+		};
+		path.append(&partial);
+		if path.len() >= target.len() || !target.starts_with(&path) {
+			return Ok(None);
+		}
+		let nibble = target.at(path.len());
+		node = match children.into_iter().find(|(child, _)| *child == nibble) {
+			Some((_, child)) => child,
+			None => return Ok(None),
+		};
+		path.push(nibble);
+	}
+}
+
+// Collects the database keys and values of the nodes below `node` that are not part of any of
+// the tries with roots `others`.
+fn collect_nodes<Block: BlockT>(
+	storage: &StorageDb<Block>,
+	keyspace: &[u8],
+	node: NodeRef<Block::Hash>,
+	path: &mut NibbleVec,
+	others: &[Block::Hash],
+	nodes: &mut Vec<(Vec<u8>, DBValue)>,
+) -> ClientResult<()> {
+	let data = match node {
+		NodeRef::Hash(hash) => {
+			if hash == empty_root::<Block>() {
+				return Ok(());
+			}
+			for other in others {
+				if node_at(storage, keyspace, *other, path)? == Some(hash) {
+					// The whole subtrie is shared.
+					return Ok(());
+				}
+			}
+			let data = read_node(storage, keyspace, path, &hash)?;
+			let (prefix, padding) = db_prefix(keyspace, path);
+			let key = prefixed_key::<HashFor<Block>>(&hash, (&prefix[..], padding));
+			nodes.push((key, data.clone()));
+			data
+		},
+		NodeRef::Inline(data) => data,
+	};
+	if let Some((partial, children)) = decode_branch::<Block>(&data)? {
+		let len = path.len();
+		path.append(&partial);
+		for (nibble, child) in children {
+			path.push(nibble);
+			collect_nodes(storage, keyspace, child, path, others, nodes)?;
+			path.pop();
+		}
+		path.drop_lasts(path.len() - len);
+	}
+	Ok(())
+}
+
+// Returns the roots of the child tries of the state with the given root.
+fn child_roots<Block: BlockT>(
+	storage: &Arc<StorageDb<Block>>,
+	root: Block::Hash,
+) -> HashMap<Vec<u8>, Block::Hash> {
+	let state = DbState::<Block>::new(storage.clone(), root);
+	let prefix = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+	let mut roots = HashMap::new();
+	state.for_key_values_with_prefix(prefix, |key, value| {
+		let mut child_root = Block::Hash::default();
+		if value.len() == child_root.as_ref().len() {
+			child_root.as_mut().copy_from_slice(value);
+			roots.insert(key[prefix.len()..].to_vec(), child_root);
+		}
+	});
+	roots
+}
+
+// Returns the database keys and values of the nodes, including child trie nodes, of the state
+// with the given root that are not part of any of the states with roots `others`.
+fn new_state_nodes<Block: BlockT>(
+	storage: &Arc<StorageDb<Block>>,
+	root: Block::Hash,
+	others: &[Block::Hash],
+) -> ClientResult<Vec<(Vec<u8>, DBValue)>> {
+	let mut nodes = Vec::new();
+	if others.contains(&root) {
+		return Ok(nodes);
+	}
+	collect_nodes(&**storage, &[], NodeRef::Hash(root), &mut NibbleVec::new(), others, &mut nodes)?;
+
+	let other_child_roots: Vec<_> = others.iter()
+		.map(|other| child_roots(storage, *other))
+		.collect();
+	for (storage_key, child_root) in child_roots(storage, root) {
+		let others: Vec<_> = other_child_roots.iter()
+			.filter_map(|roots| roots.get(&storage_key).copied())
+			.collect();
+		let child_info = ChildInfo::new_default(&storage_key);
+		collect_nodes(
+			&**storage,
+			child_info.keyspace(),
+			NodeRef::Hash(child_root),
+			&mut NibbleVec::new(),
+			&others,
+			&mut nodes,
+		)?;
+	}
+	Ok(nodes)
+}
+
+// Returns the nodes a block inserted and deleted compared to its parent.
+fn state_changes<Block: BlockT>(
+	storage: &Arc<StorageDb<Block>>,
+	parent_root: Block::Hash,
+	root: Block::Hash,
+) -> ClientResult<ChangeSet<Vec<u8>>> {
+	Ok(ChangeSet {
+		inserted: new_state_nodes(storage, root, &[parent_root])?,
+		deleted: new_state_nodes(storage, parent_root, &[root])?
+			.into_iter()
+			.map(|(key, _)| key)
+			.collect(),
+	})
+}
+
+/// Prunes the state of an archive database offline.
+///
+/// Only the state of the last `keep_blocks` canonical blocks and of the blocks that are not
+/// canonical yet is kept. The database metadata is rewritten, so that it has to be opened with
+/// `PruningMode::Constrained` afterwards. Databases that do reference counting internally are
+/// not supported.
+pub fn prune_state<Block: BlockT>(
+	config: &DatabaseSettings,
+	keep_blocks: u32,
+) -> ClientResult<PruningSummary> {
+	if config.source.supports_ref_counting() {
+		return Err(ClientError::Backend(
+			format!("Offline pruning is not supported for {} databases", config.source),
+		));
+	}
+
+	let db = crate::utils::open_database::<Block>(config, DatabaseType::Full)?;
+	let map_e = |e: sc_state_db::Error<std::io::Error>| ClientError::from_state_db(e);
+	let mode = match sc_state_db::fetch_stored_pruning_mode(&StateMetaDb(&*db)).map_err(map_e)? {
+		Some(PruningMode::Constrained(_)) =>
+			return Err(ClientError::Backend("Database state is already pruned".into())),
+		Some(mode) => mode,
+		None => return Err(ClientError::Backend("Database does not contain any state".into())),
+	};
+	let blockchain = BlockchainDb::<Block>::new(db.clone(), config.transaction_storage)?;
+	let state_db = StateDb::new(mode.clone(), true, &StateMetaDb(&*db)).map_err(map_e)?;
+	let canonical = state_db.best_canonical()
+		.unwrap_or_else(|| blockchain.info().finalized_number.saturated_into());
+	let storage = Arc::new(StorageDb {
+		db: db.clone(),
+		state_db,
+		prefix_keys: true,
+	});
+
+	let window_start = (canonical + 1).saturating_sub(std::cmp::max(keep_blocks, 1) as u64);
+	info!(target: "db", "Replaying state changes of blocks #0 to #{}", canonical);
+
+	// Maps every node that was deleted from the canonical state, and was not inserted again
+	// since, to the block that deleted it.
+	let mut deleted_by: HashMap<Vec<u8>, u64> = HashMap::new();
+	let mut window = Vec::new();
+	let mut parent_root = None;
+	for number in 0 ..= canonical {
+		let header = blockchain.expect_header(BlockId::number(number.saturated_into()))?;
+		let root = *header.state_root();
+		if let Some(parent_root) = parent_root {
+			let changes = state_changes(&storage, parent_root, root)?;
+			for (key, _) in changes.inserted {
+				deleted_by.remove(&key);
+			}
+			for key in changes.deleted {
+				deleted_by.insert(key, number);
+			}
+		}
+		if number >= window_start {
+			window.push((header.hash(), Vec::new()));
+		}
+		if number % PROGRESS_INTERVAL == 0 && number != 0 {
+			info!(target: "db", "Replayed state changes up to block #{}", number);
+		}
+		parent_root = Some(root);
+	}
+	let canonical_root = parent_root.expect("the loop above runs at least once; qed");
+	let canonical_hash = window.last()
+		.map(|(hash, _)| *hash)
+		.expect("window contains at least one block; qed");
+
+	// Nodes deleted by a block are removed once that block is pruned. The window starts with
+	// the first block whose state is complete, so blocks up to it are pruned right away.
+	let mut obsolete = HashSet::new();
+	for (key, number) in deleted_by.iter() {
+		if *number <= window_start {
+			obsolete.insert(key.clone());
+		} else {
+			window[(*number - window_start) as usize].1.push(key.clone());
+		}
+	}
+
+	// `ArchiveCanonical` journals the blocks that are not canonical yet, and never writes the
+	// state of blocks that are discarded. With `ArchiveAll` all of them are in the database,
+	// but nothing is journaled.
+	let mut overlay = Vec::new();
+	if mode == PruningMode::ArchiveAll {
+		let mut descends_from_canonical: HashMap<Block::Hash, bool> = HashMap::new();
+		for leaf in blockchain.leaves()? {
+			let mut route = Vec::new();
+			let mut hash = leaf;
+			let is_descendant = loop {
+				if let Some(is_descendant) = descends_from_canonical.get(&hash) {
+					break *is_descendant;
+				}
+				let header = blockchain.expect_header(BlockId::hash(hash))?;
+				if blockchain.hash(*header.number())? == Some(hash) &&
+					(*header.number()).saturated_into::<u64>() <= canonical
+				{
+					break hash == canonical_hash;
+				}
+				hash = *header.parent_hash();
+				route.push(header);
+			};
+
+			for header in route.into_iter().rev() {
+				let hash = header.hash();
+				let parent_hash = *header.parent_hash();
+				let parent_root = *blockchain.expect_header(BlockId::hash(parent_hash))?
+					.state_root();
+				descends_from_canonical.insert(hash, is_descendant);
+				if is_descendant {
+					// Journal the changes like a pruned node does on import. Inserted nodes are
+					// written again once the block is canonicalized.
+					let changes = state_changes(&storage, parent_root, *header.state_root())?;
+					let number = (*header.number()).saturated_into::<u64>();
+					overlay.push((hash, number, parent_hash, changes));
+				} else {
+					// The block can't become canonical anymore. Its nodes go, unless the
+					// canonical chain uses them too.
+					let others = [parent_root, canonical_root];
+					for (key, _) in new_state_nodes(&storage, *header.state_root(), &others)? {
+						if !deleted_by.contains_key(&key) {
+							obsolete.insert(key);
+						}
+					}
+				}
+			}
+		}
+		overlay.sort_by_key(|(_, number, _, _)| *number);
+	}
+
+	let summary = PruningSummary {
+		kept_blocks: window.len() as u64,
+		removed_nodes: obsolete.len() as u64,
+	};
+
+	// Write the new metadata first. If the removal below is interrupted, the node is
+	// left with some garbage, but is still consistent.
+	let meta = sc_state_db::constrained_meta(
+		window_start,
+		window,
+		if mode == PruningMode::ArchiveAll { Some(overlay) } else { None },
+	);
+	let mut transaction = Transaction::new();
+	apply_state_commit(&mut transaction, CommitSet { data: Default::default(), meta });
+	db.commit(transaction)?;
+
+	info!(target: "db", "Removing {} obsolete state nodes", obsolete.len());
+	let obsolete: Vec<_> = obsolete.into_iter().collect();
+	for keys in obsolete.chunks(REMOVAL_BATCH_SIZE) {
+		let mut transaction = Transaction::new();
+		for key in keys {
+			transaction.remove(columns::STATE, key);
+		}
+		db.commit(transaction)?;
+	}
+
+	info!(
+		target: "db",
+		"Pruned state to {} blocks, {} nodes removed",
+		summary.kept_blocks,
+		summary.removed_nodes,
+	);
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use sc_client_api::backend::{Backend as _, BlockImportOperation as _, NewBlockState};
+	use sp_runtime::testing::Header;
+	use crate::{Backend, DatabaseSettingsSrc, DbHash, KeepBlocks, TransactionStorageMode};
+	use crate::tests::Block;
+
+	fn settings(db: Arc<dyn sp_database::Database<DbHash>>, state_pruning: PruningMode) -> DatabaseSettings {
+		DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			state_pruning,
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: false },
+			keep_blocks: KeepBlocks::All,
+			transaction_storage: TransactionStorageMode::BlockBody,
+		}
+	}
+
+	fn insert_block(
+		backend: &Backend<Block>,
+		number: u64,
+		parent_hash: H256,
+		value: u8,
+		state: NewBlockState,
+	) -> H256 {
+		let mut op = backend.begin_operation().unwrap();
+		let parent = if number == 0 { Default::default() } else { parent_hash };
+		backend.begin_state_operation(&mut op, BlockId::Hash(parent)).unwrap();
+		let mut header = Header {
+			number,
+			parent_hash,
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+
+		let storage = vec![
+			(b"key".to_vec(), Some(vec![value])),
+			(vec![number as u8], Some(vec![value])),
+		];
+		let (root, overlay) = op.old_state.storage_root(
+			storage.iter().map(|(k, v)| (&k[..], v.as_ref().map(|v| &v[..])))
+		);
+		op.update_db_storage(overlay).unwrap();
+		header.state_root = root.into();
+		op.update_storage(storage, Vec::new()).unwrap();
+		op.set_block_data(header.clone(), Some(vec![]), None, state).unwrap();
+		backend.commit_operation(op).unwrap();
+		header.hash()
+	}
+
+	fn insert_final_block(
+		backend: &Backend<Block>,
+		number: u64,
+		parent_hash: H256,
+		value: u8,
+	) -> H256 {
+		insert_block(backend, number, parent_hash, value, NewBlockState::Final)
+	}
+
+	fn stored_nodes(db: &Arc<dyn sp_database::Database<DbHash>>) -> HashSet<Vec<u8>> {
+		let mut nodes = HashSet::new();
+		db.iter(columns::STATE, &mut |key, _| {
+			nodes.insert(key.to_vec());
+		}).unwrap();
+		nodes
+	}
+
+	// All nodes of the states of the given blocks.
+	fn state_nodes(backend: &Backend<Block>, numbers: &[u64]) -> HashSet<Vec<u8>> {
+		let mut nodes = HashSet::new();
+		for number in numbers {
+			let header = backend.blockchain.expect_header(BlockId::Number(*number)).unwrap();
+			for (key, _) in new_state_nodes(&backend.storage, *header.state_root(), &[]).unwrap() {
+				nodes.insert(key);
+			}
+		}
+		nodes
+	}
+
+	#[test]
+	fn finds_changed_nodes() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let backend = Backend::<Block>::new(
+			settings(db.clone(), PruningMode::ArchiveAll),
+			0,
+		).unwrap();
+		let mut parent_hash = Default::default();
+		for number in 0 .. 3 {
+			parent_hash = insert_final_block(&backend, number, parent_hash, number as u8 + 10);
+		}
+
+		let root = |number| {
+			*backend.blockchain.expect_header(BlockId::Number(number)).unwrap().state_root()
+		};
+		let changes = state_changes(&backend.storage, root(1), root(2)).unwrap();
+		let inserted: HashSet<_> = changes.inserted.into_iter().map(|(key, _)| key).collect();
+		let deleted: HashSet<_> = changes.deleted.into_iter().collect();
+		let (first, second) = (state_nodes(&backend, &[1]), state_nodes(&backend, &[2]));
+		assert!(!inserted.is_empty());
+		assert_eq!(inserted, second.difference(&first).cloned().collect());
+		assert_eq!(deleted, first.difference(&second).cloned().collect());
+	}
+
+	#[test]
+	fn prunes_archive_database() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let mut hashes = Vec::new();
+		{
+			let backend = Backend::<Block>::new(settings(db.clone(), PruningMode::ArchiveAll), 0).unwrap();
+			let mut parent_hash = Default::default();
+			for number in 0 .. 5 {
+				parent_hash = insert_final_block(&backend, number, parent_hash, number as u8 + 10);
+				hashes.push(parent_hash);
+			}
+		}
+		let nodes_before = stored_nodes(&db).len();
+
+		let summary = prune_state::<Block>(&settings(db.clone(), PruningMode::ArchiveAll), 2).unwrap();
+		assert_eq!(summary.kept_blocks, 2);
+		assert!(summary.removed_nodes > 0);
+		assert_eq!(stored_nodes(&db).len(), nodes_before - summary.removed_nodes as usize);
+
+		// Can't be pruned twice.
+		assert!(prune_state::<Block>(&settings(db.clone(), PruningMode::ArchiveAll), 2).is_err());
+		// And can't be opened as an archive anymore.
+		assert!(Backend::<Block>::new(settings(db.clone(), PruningMode::ArchiveAll), 0).is_err());
+
+		let backend = Backend::<Block>::new(settings(db.clone(), PruningMode::keep_blocks(2)), 0).unwrap();
+		assert_eq!(stored_nodes(&db), state_nodes(&backend, &[3, 4]));
+		assert!(backend.state_at(BlockId::Number(2)).is_err());
+		assert_eq!(
+			backend.state_at(BlockId::Number(3)).unwrap().storage(b"key").unwrap(),
+			Some(vec![13]),
+		);
+		assert_eq!(
+			backend.state_at(BlockId::Number(4)).unwrap().storage(&[0]).unwrap(),
+			Some(vec![10]),
+		);
+
+		// The pruned node continues to import and prune blocks.
+		insert_final_block(&backend, 5, hashes[4], 15);
+		assert!(backend.state_at(BlockId::Number(3)).is_err());
+		assert_eq!(
+			backend.state_at(BlockId::Number(5)).unwrap().storage(b"key").unwrap(),
+			Some(vec![15]),
+		);
+	}
+
+	#[test]
+	fn journals_blocks_above_last_finalized() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let mut hashes = Vec::new();
+		let stale;
+		{
+			let backend = Backend::<Block>::new(settings(db.clone(), PruningMode::ArchiveAll), 0).unwrap();
+			let mut parent_hash = Default::default();
+			for number in 0 .. 5 {
+				parent_hash = insert_final_block(&backend, number, parent_hash, number as u8 + 10);
+				hashes.push(parent_hash);
+			}
+			hashes.push(insert_block(&backend, 5, hashes[4], 15, NewBlockState::Best));
+			stale = insert_block(&backend, 4, hashes[3], 24, NewBlockState::Normal);
+		}
+
+		prune_state::<Block>(&settings(db.clone(), PruningMode::ArchiveAll), 2).unwrap();
+
+		let backend = Backend::<Block>::new(settings(db.clone(), PruningMode::keep_blocks(2)), 0).unwrap();
+		// Nodes of the stale fork are gone.
+		assert_eq!(stored_nodes(&db), state_nodes(&backend, &[3, 4, 5]));
+		assert!(backend.state_at(BlockId::Hash(stale)).is_err());
+		assert_eq!(
+			backend.state_at(BlockId::Hash(hashes[5])).unwrap().storage(b"key").unwrap(),
+			Some(vec![15]),
+		);
+
+		// The block above the last finalized one was journaled with the nodes it deleted, so
+		// these are removed once it is pruned.
+		backend.finalize_block(BlockId::Hash(hashes[5]), None).unwrap();
+		let mut parent_hash = hashes[5];
+		for number in 6 .. 8 {
+			parent_hash = insert_final_block(&backend, number, parent_hash, number as u8 + 10);
+		}
+		assert_eq!(stored_nodes(&db), state_nodes(&backend, &[5, 6, 7]));
+	}
+}
@@ -0,0 +1,434 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Initialization of an empty database from a state snapshot.
+//!
+//! The database is set up as if it had been synced up to the snapshot block, with the block
+//! being both best and finalized. Apart from the genesis header, none of the blocks before it
+//! are known.
+//!
+//! The tries are built from their pairs in key order, writing their nodes to the database in
+//! batches, so that the state never needs to be held in memory as a whole.
+
+use std::sync::Arc;
+use codec::Encode;
+use hash_db::{Hasher, Prefix};
+use log::info;
+use sp_database::{Database, Transaction};
+use sp_core::storage::{ChildInfo, well_known_keys};
+use sp_blockchain::{Result as ClientResult, Error as ClientError};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, HashFor, Zero, SaturatedConversion};
+use sp_trie::{ChildReference, Layout, ProcessEncodedNode, prefixed_key, trie_visit};
+use sc_client_api::leaves::LeafSet;
+use sc_state_db::{ChangeSet, CommitSet, PruningMode};
+use crate::utils::{self, DatabaseType, meta_keys};
+use crate::{DatabaseSettings, DbHash, apply_state_commit, columns};
+
+/// Number of trie nodes written to the database at once.
+const NODES_BATCH: usize = 16 * 1024;
+
+/// Initializes an empty database with the state of a block, trie by trie.
+///
+/// The default child tries are imported first, then the top trie, which gets the roots of the
+/// child tries added. The state root is checked against the header of the block before the
+/// database is marked as initialized.
+pub struct SnapshotImport<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	state_pruning: PruningMode,
+	prefix_keys: bool,
+	genesis: Block::Header,
+	header: Block::Header,
+	/// Storage keys and roots of the imported child tries.
+	child_roots: Vec<(Vec<u8>, Block::Hash)>,
+	/// Whether the top trie was imported, and its root checked.
+	top_imported: bool,
+	/// Number of trie nodes written so far.
+	nodes: usize,
+}
+
+impl<Block: BlockT> SnapshotImport<Block> {
+	/// Start importing the state of block `header` into the empty database of `config`.
+	///
+	/// `genesis` is the header of the genesis block of the chain, which is needed to identify it.
+	pub fn new(
+		config: &DatabaseSettings,
+		genesis: Block::Header,
+		header: Block::Header,
+	) -> ClientResult<Self> {
+		if !genesis.number().is_zero() {
+			return Err(ClientError::Backend("Invalid genesis header".into()));
+		}
+		let db = utils::open_database::<Block>(config, DatabaseType::Full)?;
+		if db.get(columns::META, meta_keys::GENESIS_HASH).is_some() {
+			return Err(ClientError::Backend("Database is not empty".into()));
+		}
+		Ok(SnapshotImport {
+			db,
+			state_pruning: config.state_pruning.clone(),
+			prefix_keys: !config.source.supports_ref_counting(),
+			genesis,
+			header,
+			child_roots: Vec::new(),
+			top_imported: false,
+			nodes: 0,
+		})
+	}
+
+	/// Import a default child trie from its pairs, in ascending key order.
+	pub fn import_child(
+		&mut self,
+		storage_key: &[u8],
+		pairs: impl IntoIterator<Item=(Vec<u8>, Vec<u8>)>,
+	) -> ClientResult<()> {
+		if self.top_imported {
+			return Err(ClientError::Backend("Child trie imported after the top trie".into()));
+		}
+		if self.child_roots.iter().any(|(key, _)| &key[..] == storage_key) {
+			return Err(ClientError::Backend("Child trie imported twice".into()));
+		}
+		let child_info = ChildInfo::new_default(storage_key);
+		let root = self.import_trie(child_info.keyspace(), pairs)?;
+		self.child_roots.push((storage_key.to_vec(), root));
+		Ok(())
+	}
+
+	/// Import the top trie from its pairs, in ascending key order, excluding the roots of the
+	/// child tries, which have to be imported before.
+	pub fn import_top(
+		&mut self,
+		pairs: impl IntoIterator<Item=(Vec<u8>, Vec<u8>)>,
+	) -> ClientResult<()> {
+		if self.top_imported {
+			return Err(ClientError::Backend("Top trie imported twice".into()));
+		}
+		let mut child_roots = std::mem::take(&mut self.child_roots);
+		child_roots.sort();
+		let mut roots = child_roots.iter()
+			.map(|(storage_key, root)| (
+				[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, storage_key].concat(),
+				root.encode(),
+			))
+			.peekable();
+		let mut pairs = pairs.into_iter()
+			.filter(|(key, _)| !well_known_keys::is_child_storage_key(key))
+			.peekable();
+		let merged = std::iter::from_fn(|| match (pairs.peek(), roots.peek()) {
+			(Some((key, _)), Some((root_key, _))) if root_key < key => roots.next(),
+			(Some(_), _) => pairs.next(),
+			(None, _) => roots.next(),
+		});
+
+		let root = self.import_trie(&[], merged)?;
+		if root != *self.header.state_root() {
+			return Err(ClientError::Backend(format!(
+				"State root mismatch: header of block #{} expects {}, snapshot has {}. \
+				The database holds a partial state and has to be deleted.",
+				self.header.number(),
+				self.header.state_root(),
+				root,
+			)));
+		}
+		self.child_roots = child_roots;
+		self.top_imported = true;
+		Ok(())
+	}
+
+	/// Mark the database as initialized, with the given auxiliary entries, e.g. the consensus
+	/// data of the block.
+	pub fn commit(self, aux: impl IntoIterator<Item=(Vec<u8>, Vec<u8>)>) -> ClientResult<()> {
+		let genesis_hash = self.genesis.hash();
+		let hash = self.header.hash();
+		let number = *self.header.number();
+		if !self.top_imported {
+			return Err(ClientError::Backend("The state of the block was not imported".into()));
+		}
+
+		let mut transaction = Transaction::new();
+		for known in [&self.genesis, &self.header].iter() {
+			let (known_number, known_hash) = (*known.number(), known.hash());
+			let lookup_key = utils::number_and_hash_to_lookup_key(known_number, known_hash)?;
+			transaction.set_from_vec(columns::HEADER, &lookup_key, known.encode());
+			utils::insert_hash_to_key_mapping(
+				&mut transaction,
+				columns::KEY_LOOKUP,
+				known_number,
+				known_hash,
+			)?;
+			utils::insert_number_to_key_mapping(
+				&mut transaction,
+				columns::KEY_LOOKUP,
+				known_number,
+				known_hash,
+			)?;
+		}
+		for (key, value) in aux {
+			transaction.set_from_vec(columns::AUX, &key, value);
+		}
+
+		let lookup_key = utils::number_and_hash_to_lookup_key(number, hash)?;
+		transaction.set(columns::META, meta_keys::GENESIS_HASH, genesis_hash.as_ref());
+		transaction.set(columns::META, meta_keys::BEST_BLOCK, &lookup_key);
+		transaction.set(columns::META, meta_keys::FINALIZED_BLOCK, &lookup_key);
+		let mut leaves = LeafSet::new();
+		leaves.import(hash, number, *self.header.parent_hash());
+		leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
+
+		let meta = sc_state_db::snapshot_meta::<_, Vec<u8>>(
+			&self.state_pruning,
+			hash,
+			number.saturated_into(),
+		);
+		apply_state_commit(&mut transaction, CommitSet { data: ChangeSet::default(), meta });
+		self.db.commit(transaction)?;
+
+		info!(
+			target: "db",
+			"Imported state of block #{} ({}), {} trie nodes",
+			number,
+			hash,
+			self.nodes,
+		);
+		Ok(())
+	}
+
+	/// Build a trie from its pairs, writing its nodes under `keyspace`, and return its root.
+	fn import_trie(
+		&mut self,
+		keyspace: &[u8],
+		pairs: impl IntoIterator<Item=(Vec<u8>, Vec<u8>)>,
+	) -> ClientResult<Block::Hash> {
+		let mut unsorted = false;
+		let mut last_key: Option<Vec<u8>> = None;
+		let pairs = pairs.into_iter().take_while(|(key, _)| {
+			if last_key.as_ref().map_or(false, |last| last >= key) {
+				unsorted = true;
+				return false;
+			}
+			last_key = Some(key.clone());
+			true
+		});
+
+		let mut writer = NodeWriter::<HashFor<Block>> {
+			db: &*self.db,
+			prefix_keys: self.prefix_keys,
+			keyspace,
+			transaction: Transaction::new(),
+			pending: 0,
+			written: 0,
+			root: None,
+			error: None,
+		};
+		trie_visit::<Layout<HashFor<Block>>, _, _, _, _>(pairs, &mut writer);
+		writer.flush();
+
+		if unsorted {
+			return Err(ClientError::Backend("Keys of the snapshot are not sorted".into()));
+		}
+		if let Some(error) = writer.error {
+			return Err(error.into());
+		}
+		self.nodes += writer.written;
+		writer.root.ok_or_else(|| ClientError::Backend("Trie without root".into()))
+	}
+}
+
+/// Writes the nodes of a trie to the database, in batches.
+struct NodeWriter<'a, H: Hasher> {
+	db: &'a dyn Database<DbHash>,
+	prefix_keys: bool,
+	/// Prefix of the keys of the trie nodes, for child tries.
+	keyspace: &'a [u8],
+	transaction: Transaction<DbHash>,
+	pending: usize,
+	written: usize,
+	root: Option<H::Out>,
+	/// The first error writing to the database, after which nothing else is written.
+	error: Option<sp_database::error::DatabaseError>,
+}
+
+impl<'a, H: Hasher> NodeWriter<'a, H> {
+	fn flush(&mut self) {
+		if self.error.is_none() {
+			if let Err(e) = self.db.commit(std::mem::take(&mut self.transaction)) {
+				self.error = Some(e);
+			}
+		}
+		self.written += self.pending;
+		self.pending = 0;
+	}
+}
+
+impl<'a, H: Hasher> ProcessEncodedNode<H::Out> for NodeWriter<'a, H> {
+	fn process(&mut self, prefix: Prefix, node: Vec<u8>, is_root: bool) -> ChildReference<H::Out> {
+		let len = node.len();
+		if !is_root && len < H::LENGTH {
+			let mut inline = H::Out::default();
+			inline.as_mut()[..len].copy_from_slice(&node);
+			return ChildReference::Inline(inline, len);
+		}
+
+		let hash = H::hash(&node);
+		let key = if self.prefix_keys {
+			let spaced = [self.keyspace, prefix.0].concat();
+			prefixed_key::<H>(&hash, (&spaced, prefix.1))
+		} else {
+			hash.as_ref().to_vec()
+		};
+		// Every reference is written, so that reference counting databases count them all.
+		self.transaction.set_from_vec(columns::STATE, &key, node);
+		self.pending += 1;
+		if self.pending >= NODES_BATCH {
+			self.flush();
+		}
+		if is_root {
+			self.root = Some(hash);
+		}
+		ChildReference::Hash(hash)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use sp_core::storage::{Storage, StorageChild};
+	use sp_runtime::generic::BlockId;
+	use sp_runtime::testing::Header;
+	use sp_state_machine::backend::Backend as StateBackend;
+	use sc_client_api::backend::{AuxStore, Backend as _, BlockImportOperation as _, NewBlockState};
+	use sp_blockchain::HeaderBackend;
+	use crate::{
+		Backend, DatabaseSettingsSrc, DbGenesisStorage, DbState, KeepBlocks, TransactionStorageMode,
+	};
+	use crate::tests::Block;
+
+	fn settings(db: Arc<dyn Database<DbHash>>) -> DatabaseSettings {
+		DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			state_pruning: PruningMode::keep_blocks(2),
+			source: DatabaseSettingsSrc::Custom { db, ref_counting: false },
+			keep_blocks: KeepBlocks::All,
+			transaction_storage: TransactionStorageMode::BlockBody,
+		}
+	}
+
+	fn snapshot_storage() -> Storage {
+		let child_info = ChildInfo::new_default(b"child");
+		let mut storage = Storage::default();
+		for i in 0..1000u32 {
+			storage.top.insert(i.encode(), vec![i as u8; 40]);
+		}
+		storage.children_default.insert(child_info.storage_key().to_vec(), StorageChild {
+			data: vec![(b"child_key".to_vec(), b"child_value".to_vec())].into_iter().collect(),
+			child_info,
+		});
+		storage
+	}
+
+	fn storage_root(storage: &Storage) -> H256 {
+		let empty = DbGenesisStorage::<Block>::new();
+		let empty_root = empty.0;
+		DbState::<Block>::new(Arc::new(empty), empty_root).full_storage_root(
+			storage.top.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+			storage.children_default.values().map(|child| (
+				&child.child_info,
+				child.data.iter().map(|(k, v)| (&k[..], Some(&v[..]))),
+			)),
+		).0
+	}
+
+	fn header(number: u64, parent_hash: H256, state_root: H256) -> Header {
+		Header {
+			number,
+			parent_hash,
+			state_root,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		}
+	}
+
+	fn import(
+		db: Arc<dyn Database<DbHash>>,
+		genesis: &Header,
+		block: &Header,
+		storage: Storage,
+	) -> ClientResult<()> {
+		let mut snapshot =
+			SnapshotImport::<Block>::new(&settings(db), genesis.clone(), block.clone())?;
+		for child in storage.children_default.values() {
+			snapshot.import_child(child.child_info.storage_key(), child.data.clone())?;
+		}
+		snapshot.import_top(storage.top)?;
+		snapshot.commit(vec![(b"aux".to_vec(), b"data".to_vec())])
+	}
+
+	#[test]
+	fn imports_state_and_continues_from_it() {
+		let storage = snapshot_storage();
+		let root = storage_root(&storage);
+		let genesis = header(0, Default::default(), Default::default());
+		let block = header(10, H256::repeat_byte(9), root);
+
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		import(db.clone(), &genesis, &block, storage.clone()).unwrap();
+		// Only works on an empty database.
+		assert!(import(db.clone(), &genesis, &block, storage).is_err());
+
+		let backend = Backend::<Block>::new(settings(db), 0).unwrap();
+		let info = backend.blockchain().info();
+		assert_eq!(info.genesis_hash, genesis.hash());
+		assert_eq!((info.best_hash, info.best_number), (block.hash(), 10));
+		assert_eq!((info.finalized_hash, info.finalized_number), (block.hash(), 10));
+		assert_eq!(backend.get_aux(b"aux").unwrap(), Some(b"data".to_vec()));
+		let state = backend.state_at(BlockId::Number(10)).unwrap();
+		assert_eq!(state.storage(&7u32.encode()).unwrap(), Some(vec![7; 40]));
+		assert_eq!(
+			state.child_storage(&ChildInfo::new_default(b"child"), b"child_key").unwrap(),
+			Some(b"child_value".to_vec()),
+		);
+
+		// The next block is imported on top of the snapshot block.
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, BlockId::Hash(block.hash())).unwrap();
+		op.update_db_storage(Default::default()).unwrap();
+		let next = header(11, block.hash(), root);
+		op.set_block_data(next.clone(), Some(vec![]), None, NewBlockState::Final).unwrap();
+		backend.commit_operation(op).unwrap();
+		assert_eq!(backend.blockchain().info().finalized_hash, next.hash());
+	}
+
+	#[test]
+	fn rejects_state_root_mismatch() {
+		let genesis = header(0, Default::default(), Default::default());
+		let block = header(10, H256::repeat_byte(9), H256::repeat_byte(1));
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		assert!(import(db.clone(), &genesis, &block, snapshot_storage()).is_err());
+		assert!(db.get(columns::META, meta_keys::GENESIS_HASH).is_none());
+	}
+
+	#[test]
+	fn rejects_unsorted_pairs() {
+		let storage = snapshot_storage();
+		let genesis = header(0, Default::default(), Default::default());
+		let block = header(10, H256::repeat_byte(9), storage_root(&storage));
+		let db = sp_database::as_database(kvdb_memorydb::create(utils::NUM_COLUMNS));
+		let mut snapshot = SnapshotImport::<Block>::new(&settings(db), genesis, block).unwrap();
+		assert!(snapshot.import_top(storage.top.into_iter().rev()).is_err());
+	}
+}
@@ -37,7 +37,7 @@ use crate::{DatabaseSettings, DatabaseSettingsSrc, Database, DbHash};
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
 #[cfg(any(feature = "with-kvdb-rocksdb", feature = "with-parity-db", feature = "test-helpers", test))]
-pub const NUM_COLUMNS: u32 = 12;
+pub const NUM_COLUMNS: u32 = 13;
 /// Meta column. The set of keys in the column is shared by full && light storages.
 pub const COLUMN_META: u32 = 0;
 
@@ -59,6 +59,16 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Prefix of the keys mapping the hash of a block body to the block hash.
+	pub const BODY_INDEX_PREFIX: &[u8; 5] = b"bodyh";
+}
+
+/// Key under which the hash of the block whose body hashes to `body_hash` is stored in the meta
+/// column.
+pub fn body_index_key<H: AsRef<[u8]>>(body_hash: H) -> Vec<u8> {
+	let mut key = meta_keys::BODY_INDEX_PREFIX.to_vec();
+	key.extend_from_slice(body_hash.as_ref());
+	key
 }
 
 /// Database metadata.
@@ -284,7 +294,7 @@ pub fn open_database<Block: BlockT>(
 		DatabaseSettingsSrc::ParityDb { .. } => {
 			return Err(db_open_error("with-parity-db"))
 		},
-		DatabaseSettingsSrc::Custom(db) => db.clone(),
+		DatabaseSettingsSrc::Custom { db, .. } => db.clone(),
 	};
 
 	check_database_type(&*db, db_type)?;
@@ -15,20 +15,28 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Helper for handling (i.e. answering) grandpa warp sync requests from a remote peer via the
-//! [`crate::request_responses::RequestResponsesBehaviour`].
+//! [`crate::request_responses::RequestResponsesBehaviour`], and for verifying the answers to our
+//! own requests through a [`WarpSyncProvider`].
 
 use codec::Decode;
-use sc_network::config::{IncomingRequest, OutgoingResponse, ProtocolId, RequestResponseConfig};
-use sc_client_api::Backend;
-use sp_runtime::traits::NumberFor;
+use sc_network::config::{
+	IncomingRequest, OutgoingResponse, ProtocolId, RequestResponseConfig, WarpSyncProvider,
+};
+use sc_network::warp_sync::{StateImport, WarpProofRequest, WarpSyncFragment};
+use sc_client_api::{Backend, BlockImportOperation, NewBlockState};
+use sp_finality_grandpa::{AuthorityList, SetId};
+use sp_core::storage::ChildInfo;
+use sp_runtime::Storage;
+use sp_runtime::traits::{Header as HeaderT, NumberFor};
 use futures::channel::{mpsc, oneshot};
 use futures::stream::StreamExt;
 use log::debug;
 use sp_runtime::traits::Block as BlockT;
+use std::error::Error;
 use std::time::Duration;
 use std::sync::Arc;
 use sc_service::{SpawnTaskHandle, config::{Configuration, Role}};
-use sc_finality_grandpa::WarpSyncFragmentCache;
+use sc_finality_grandpa::{AuthoritySet, SharedAuthoritySet, WarpSyncFragmentCache};
 
 /// Generates the appropriate [`RequestResponseConfig`] for a given chain configuration.
 pub fn request_response_config_for_chain<TBlock: BlockT, TBackend: Backend<TBlock> + 'static>(
@@ -76,11 +84,6 @@ fn generate_protocol_name(protocol_id: ProtocolId) -> String {
 	s
 }
 
-#[derive(codec::Decode)]
-struct Request<B: BlockT> {
-	begin: B::Hash
-}
-
 /// Setting a large fragment limit, allowing client
 /// to define it is possible.
 const WARP_SYNC_FRAGMENTS_LIMIT: usize = 100;
@@ -117,7 +120,7 @@ impl<TBlock: BlockT, TBackend: Backend<TBlock>> GrandpaWarpSyncRequestHandler<TB
 	) -> Result<(), HandleRequestError>
 		where NumberFor<TBlock>: sc_finality_grandpa::BlockNumberOps,
 	{
-		let request = Request::<TBlock>::decode(&mut &payload[..])?;
+		let request = WarpProofRequest::<TBlock>::decode(&mut &payload[..])?;
 
 		let mut cache = self.cache.write();
 		let response = sc_finality_grandpa::prove_warp_sync(
@@ -149,6 +152,204 @@ impl<TBlock: BlockT, TBackend: Backend<TBlock>> GrandpaWarpSyncRequestHandler<TB
 	}
 }
 
+/// Prepares the data of the block production engine to import along with the target of a warp
+/// sync, e.g. with `sc_consensus_babe::import_epochs_from_state`.
+pub struct ConsensusDataImport<TBlock: BlockT> {
+	/// Prefix of the keys of the top trie the data is read from. Only these entries of the state
+	/// are kept in memory while it is downloaded.
+	pub prefix: Vec<u8>,
+	/// Returns the auxiliary storage entries to insert in the same operation as the target, and
+	/// a callback that updates the in-memory data of the engine once they are committed.
+	pub import: Box<
+		dyn Fn(&TBlock::Header, &Storage) -> Result<
+			(Vec<(Vec<u8>, Vec<u8>)>, Box<dyn FnOnce() + Send>),
+			Box<dyn Error + Send + Sync>,
+		> + Send + Sync
+	>,
+}
+
+/// Generates the [`WarpSyncProvider`] and [`RequestResponseConfig`] for a given chain
+/// configuration, as expected by `sc_service::BuildNetworkParams::warp_sync`.
+pub fn warp_sync_for_chain<TBlock: BlockT, TBackend: Backend<TBlock> + 'static>(
+	config: &Configuration,
+	spawn_handle: SpawnTaskHandle,
+	backend: Arc<TBackend>,
+	authority_set: SharedAuthoritySet<TBlock::Hash, NumberFor<TBlock>>,
+	consensus_data: Option<ConsensusDataImport<TBlock>>,
+) -> (Arc<dyn WarpSyncProvider<TBlock>>, RequestResponseConfig)
+	where
+		NumberFor<TBlock>: sc_finality_grandpa::BlockNumberOps,
+		TBackend::BlockImportOperation: Send,
+{
+	let request_response_config = request_response_config_for_chain(
+		config,
+		spawn_handle,
+		backend.clone(),
+	);
+	let provider = Arc::new(GrandpaWarpSyncProvider::new(backend, authority_set, consensus_data));
+	(provider, request_response_config)
+}
+
+/// Verifies grandpa warp sync proofs received from remote peers and imports their target.
+pub struct GrandpaWarpSyncProvider<TBlock: BlockT, TBackend> {
+	backend: Arc<TBackend>,
+	authority_set: SharedAuthoritySet<TBlock::Hash, NumberFor<TBlock>>,
+	consensus_data: Option<Arc<ConsensusDataImport<TBlock>>>,
+}
+
+impl<TBlock: BlockT, TBackend> GrandpaWarpSyncProvider<TBlock, TBackend> {
+	/// Create a new [`GrandpaWarpSyncProvider`].
+	///
+	/// `consensus_data` is required for a chain whose block production engine, unlike GRANDPA,
+	/// keeps data about past blocks, e.g. the BABE epochs.
+	pub fn new(
+		backend: Arc<TBackend>,
+		authority_set: SharedAuthoritySet<TBlock::Hash, NumberFor<TBlock>>,
+		consensus_data: Option<ConsensusDataImport<TBlock>>,
+	) -> Self {
+		Self { backend, authority_set, consensus_data: consensus_data.map(Arc::new) }
+	}
+}
+
+impl<TBlock, TBackend> WarpSyncProvider<TBlock> for GrandpaWarpSyncProvider<TBlock, TBackend>
+	where
+		TBlock: BlockT,
+		TBackend: Backend<TBlock> + 'static,
+		TBackend::BlockImportOperation: Send,
+		NumberFor<TBlock>: sc_finality_grandpa::BlockNumberOps,
+{
+	fn current_authorities(&self) -> (SetId, AuthorityList) {
+		self.authority_set.current_authority_list()
+	}
+
+	fn verify(
+		&self,
+		proof: &[u8],
+		set_id: SetId,
+		authorities: AuthorityList,
+		begin: NumberFor<TBlock>,
+	) -> Result<Option<WarpSyncFragment<TBlock>>, Box<dyn Error + Send + Sync>> {
+		let verified = sc_finality_grandpa::verify_warp_sync_proof::<TBlock>(
+			set_id,
+			authorities,
+			begin,
+			proof,
+		)?;
+		Ok(verified.map(|(header, justification, set_id, authorities)| WarpSyncFragment {
+			header,
+			justification,
+			set_id,
+			authorities,
+		}))
+	}
+
+	fn begin_import(
+		&self,
+		target: WarpSyncFragment<TBlock>,
+	) -> Result<Box<dyn StateImport<TBlock>>, Box<dyn Error + Send + Sync>> {
+		let authority_set = AuthoritySet::checkpoint(target.authorities.clone(), target.set_id)
+			.ok_or("Invalid authority set in warp sync proof")?;
+		Ok(Box::new(GrandpaStateImport {
+			op: self.backend.begin_operation()?,
+			backend: self.backend.clone(),
+			shared_authority_set: self.authority_set.clone(),
+			authority_set,
+			consensus_data: self.consensus_data.clone(),
+			consensus_state: Storage::default(),
+			root: None,
+			target,
+		}))
+	}
+}
+
+/// Import of the target of a warp sync, streaming its state into a single database operation.
+struct GrandpaStateImport<TBlock: BlockT, TBackend: Backend<TBlock>> {
+	op: TBackend::BlockImportOperation,
+	backend: Arc<TBackend>,
+	shared_authority_set: SharedAuthoritySet<TBlock::Hash, NumberFor<TBlock>>,
+	authority_set: AuthoritySet<TBlock::Hash, NumberFor<TBlock>>,
+	consensus_data: Option<Arc<ConsensusDataImport<TBlock>>>,
+	/// Entries of the state the consensus data is read from.
+	consensus_state: Storage,
+	/// Root of the state appended so far.
+	root: Option<TBlock::Hash>,
+	target: WarpSyncFragment<TBlock>,
+}
+
+impl<TBlock, TBackend> StateImport<TBlock> for GrandpaStateImport<TBlock, TBackend>
+	where
+		TBlock: BlockT,
+		TBackend: Backend<TBlock> + 'static,
+		TBackend::BlockImportOperation: Send,
+		NumberFor<TBlock>: sc_finality_grandpa::BlockNumberOps,
+{
+	fn append(
+		&mut self,
+		child_info: Option<&ChildInfo>,
+		chunk: Vec<(Vec<u8>, Vec<u8>)>,
+	) -> Result<TBlock::Hash, Box<dyn Error + Send + Sync>> {
+		if let (None, Some(consensus_data)) = (child_info, &self.consensus_data) {
+			self.consensus_state.top.extend(
+				chunk.iter().filter(|(k, _)| k.starts_with(&consensus_data.prefix)).cloned()
+			);
+		}
+		let root = self.op.insert_storage_chunk(child_info, chunk)?;
+		self.root = Some(root);
+		Ok(root)
+	}
+
+	fn commit(self: Box<Self>) -> Result<(), Box<dyn Error + Send + Sync>> {
+		let GrandpaStateImport {
+			mut op,
+			backend,
+			shared_authority_set,
+			authority_set,
+			consensus_data,
+			consensus_state,
+			root,
+			target,
+		} = *self;
+		if root.as_ref() != Some(target.header.state_root()) {
+			return Err(format!(
+				"State root mismatch: expected {}, got {:?}",
+				target.header.state_root(),
+				root,
+			).into())
+		}
+
+		let checkpoint = (target.header.hash(), *target.header.number());
+		let mut aux =
+			sc_finality_grandpa::checkpoint_aux_data::<TBlock>(&authority_set, checkpoint);
+		let on_commit = match &consensus_data {
+			Some(consensus_data) => {
+				let (consensus_aux, on_commit) =
+					(consensus_data.import)(&target.header, &consensus_state)?;
+				aux.extend(consensus_aux);
+				Some(on_commit)
+			},
+			None => None,
+		};
+
+		// The block, its state and the consensus data are committed together, so that a failure
+		// leaves the database as it was.
+		op.set_block_data(
+			target.header,
+			None,
+			Some(target.justification),
+			NewBlockState::Final,
+		)?;
+		op.set_new_base()?;
+		op.insert_aux(aux.into_iter().map(|(k, v)| (k, Some(v))))?;
+		backend.commit_operation(op)?;
+
+		shared_authority_set.reset(authority_set);
+		if let Some(on_commit) = on_commit {
+			on_commit();
+		}
+		Ok(())
+	}
+}
+
 #[derive(derive_more::Display, derive_more::From)]
 enum HandleRequestError {
 	#[display(fmt = "Failed to decode request: {}.", _0)]
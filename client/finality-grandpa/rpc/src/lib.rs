@@ -144,7 +144,7 @@ where
 
 	fn subscribe_justifications(
 		&self,
-		_metadata: Self::Metadata,
+		metadata: Self::Metadata,
 		subscriber: Subscriber<JustificationNotification>
 	) {
 		let stream = self.justification_stream.subscribe()
@@ -154,9 +154,10 @@ where
 
 		self.manager.add(subscriber, |sink| {
 			let stream = stream.map(|res| Ok(res));
-			sink.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+			let task = sink.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(stream)
-				.map(|_| ())
+				.map(|_| ());
+			metadata.hold_subscription_slot(task)
 		});
 	}
 
@@ -84,6 +84,12 @@ impl<H, N> SharedAuthoritySet<H, N> {
 	pub(crate) fn inner(&self) -> &RwLock<AuthoritySet<H, N>> {
 		&*self.inner
 	}
+
+	/// Replace the authority set, e.g. with the one in effect at a block imported without its
+	/// ancestors.
+	pub fn reset(&self, set: AuthoritySet<H, N>) {
+		*self.inner.write() = set;
+	}
 }
 
 impl<H: Eq, N> SharedAuthoritySet<H, N>
@@ -110,6 +116,12 @@ where N: Add<Output=N> + Ord + Clone + Debug,
 		)
 	}
 
+	/// Get the current set ID together with the list of its authorities and their weights.
+	pub fn current_authority_list(&self) -> (u64, AuthorityList) {
+		let inner = self.inner.read();
+		(inner.set_id, inner.current_authorities.clone())
+	}
+
 	/// Clone the inner `AuthoritySet`.
 	pub fn clone_inner(&self) -> AuthoritySet<H, N> {
 		self.inner.read().clone()
@@ -188,6 +200,12 @@ where
 		})
 	}
 
+	/// Get a set with given authorities and id, without any pending changes or history, as in
+	/// effect at a block imported without its ancestors.
+	pub fn checkpoint(authorities: AuthorityList, set_id: u64) -> Option<Self> {
+		Self::new(authorities, set_id, ForkTree::new(), Vec::new(), AuthoritySetChanges::empty())
+	}
+
 	/// Create a new authority set.
 	pub(crate) fn new(
 		authorities: AuthorityList,
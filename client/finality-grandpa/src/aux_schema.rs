@@ -492,6 +492,34 @@ where
 	}
 }
 
+/// Auxiliary storage entries that overwrite the authority set with the one in effect at a block
+/// imported without its ancestors, and start the voter set state at that block.
+pub(crate) fn checkpoint_aux_data<Block: BlockT>(
+	set: &AuthoritySet<Block::Hash, NumberFor<Block>>,
+	checkpoint: (Block::Hash, NumberFor<Block>),
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+	let set_state = VoterSetState::<Block>::live(set.set_id, set, checkpoint);
+
+	vec![
+		(VERSION_KEY.to_vec(), CURRENT_VERSION.encode()),
+		(AUTHORITY_SET_KEY.to_vec(), set.encode()),
+		(SET_STATE_KEY.to_vec(), set_state.encode()),
+	]
+}
+
+/// Auxiliary storage entries that carry the current authority set over to a state snapshot of
+/// `checkpoint`, with the voter set state starting at that block.
+pub(crate) fn snapshot_aux_data<Block: BlockT, B: AuxStore>(
+	backend: &B,
+	checkpoint: (Block::Hash, NumberFor<Block>),
+) -> ClientResult<Vec<(Vec<u8>, Vec<u8>)>> {
+	let set = load_decode::<_, AuthoritySet<Block::Hash, NumberFor<Block>>>(
+		backend,
+		AUTHORITY_SET_KEY,
+	)?.ok_or_else(|| ClientError::Backend("GRANDPA authority set not found".into()))?;
+	Ok(checkpoint_aux_data::<Block>(&set, checkpoint))
+}
+
 /// Write voter set state.
 pub(crate) fn write_voter_set_state<Block: BlockT, B: AuxStore>(
 	backend: &B,
@@ -792,6 +820,44 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn load_persistent_after_checkpoint() {
+		let client = substrate_test_runtime_client::new();
+
+		let authorities = vec![(AuthorityId::default(), 100)];
+		let set_id = 3;
+		let authority_set = AuthoritySet::<H256, u64>::new(
+			authorities.clone(),
+			set_id,
+			ForkTree::new(),
+			Vec::new(),
+			AuthoritySetChanges::empty(),
+		).unwrap();
+		let checkpoint = (H256::random(), 32);
+
+		let aux = checkpoint_aux_data::<substrate_test_runtime_client::runtime::Block>(
+			&authority_set,
+			checkpoint,
+		);
+		client.insert_aux(
+			&aux.iter().map(|(k, v)| (&k[..], &v[..])).collect::<Vec<_>>(),
+			&[],
+		).unwrap();
+
+		let PersistentData {
+			authority_set: loaded_set,
+			set_state,
+		} = load_persistent::<substrate_test_runtime_client::runtime::Block, _, _>(
+			&client,
+			H256::random(),
+			0,
+			|| unreachable!(),
+		).unwrap();
+
+		assert_eq!(*loaded_set.inner().read(), authority_set);
+		assert_eq!(set_state.read().last_completed_round().base, checkpoint);
+	}
+
 	#[test]
 	fn write_read_concluded_rounds() {
 		let client = substrate_test_runtime_client::new();
@@ -1632,6 +1632,7 @@ mod tests {
 			gossip_duration: Duration::from_millis(10),
 			justification_period: 256,
 			keystore: None,
+			slashing_protection: None,
 			name: None,
 			is_authority: true,
 			observer_enabled: true,
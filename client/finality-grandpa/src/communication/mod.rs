@@ -30,12 +30,13 @@
 //! under certain conditions that are used to un-stick the protocol.
 
 use futures::{prelude::*, channel::mpsc};
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use parking_lot::Mutex;
 use prometheus_endpoint::Registry;
 use std::{pin::Pin, sync::Arc, task::{Context, Poll}};
 
 use sp_keystore::SyncCryptoStorePtr;
+use sc_consensus::slashing_protection::{SlashingProtection, VoteKind};
 use finality_grandpa::Message::{Prevote, Precommit, PrimaryPropose};
 use finality_grandpa::{voter, voter_set::VoterSet};
 use sc_network::{NetworkService, ReputationChange};
@@ -296,6 +297,7 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 	pub(crate) fn round_communication(
 		&self,
 		keystore: Option<LocalIdKeystore>,
+		slashing_protection: Option<Arc<SlashingProtection>>,
 		round: Round,
 		set_id: SetId,
 		voters: Arc<VoterSet<AuthorityId>>,
@@ -374,6 +376,7 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 		let (tx, out_rx) = mpsc::channel(0);
 		let outgoing = OutgoingMessages::<B> {
 			keystore,
+			slashing_protection,
 			round: round.0,
 			set_id: set_id.0,
 			network: self.gossip_engine.clone(),
@@ -652,6 +655,7 @@ pub(crate) struct OutgoingMessages<Block: BlockT> {
 	round: RoundNumber,
 	set_id: SetIdNumber,
 	keystore: Option<LocalIdKeystore>,
+	slashing_protection: Option<Arc<SlashingProtection>>,
 	sender: mpsc::Sender<SignedMessage<Block>>,
 	network: Arc<Mutex<GossipEngine<Block>>>,
 	has_voted: HasVoted<Block>,
@@ -690,6 +694,34 @@ impl<Block: BlockT> Sink<Message<Block>> for OutgoingMessages<Block>
 		// when locals exist, sign messages on import
 		if let Some(ref keystore) = self.keystore {
 			let target_hash = *(msg.target().0);
+
+			// a restored or duplicated voter must not cast a different vote in this round.
+			if let Some(ref slashing_protection) = self.slashing_protection {
+				let kind = match msg {
+					PrimaryPropose(_) => VoteKind::PrimaryPropose,
+					Prevote(_) => VoteKind::Prevote,
+					Precommit(_) => VoteKind::Precommit,
+				};
+				let target = (target_hash, msg.target().1).encode();
+				let checked = slashing_protection.check_vote(
+					keystore.local_id().as_ref(),
+					self.set_id,
+					self.round,
+					kind,
+					&target,
+				);
+				if let Err(e) = checked {
+					warn!(
+						target: "afg",
+						"Refusing to sign GRANDPA vote for round {} in set {}: {}",
+						self.round,
+						self.set_id,
+						e,
+					);
+					return Ok(());
+				}
+			}
+
 			let signed = sp_finality_grandpa::sign_message(
 				keystore.keystore(),
 				msg,
@@ -136,6 +136,7 @@ fn config() -> crate::Config {
 		gossip_duration: std::time::Duration::from_millis(10),
 		justification_period: 256,
 		keystore: None,
+		slashing_protection: None,
 		name: None,
 		is_authority: true,
 		observer_enabled: true,
@@ -787,6 +787,7 @@ where
 
 		let (incoming, outgoing) = self.network.round_communication(
 			keystore,
+			self.config.slashing_protection.clone(),
 			crate::communication::Round(round),
 			crate::communication::SetId(self.set_id),
 			self.voters.clone(),
@@ -412,6 +412,68 @@ where
 		return Err(ClientError::BadJustification("empty proof of authority".into()));
 }
 
+/// Verify the fragments of a warp sync proof, as produced by [`prove_warp_sync`], that follow the
+/// already verified block `begin`.
+///
+/// `set_id` and `authorities` describe the authority set in effect after `begin`. Returns the last
+/// proven header with its justification and the authority set in effect after it, or `None` if the
+/// proof holds nothing past `begin`.
+pub fn verify_warp_sync_proof<Block: BlockT>(
+	set_id: u64,
+	authorities: AuthorityList,
+	begin: NumberFor<Block>,
+	proof: &[u8],
+) -> ClientResult<Option<(Block::Header, Justification, u64, AuthorityList)>>
+where
+	NumberFor<Block>: BlockNumberOps,
+{
+	let verified = check_warp_sync_proof_after::<Block, GrandpaJustification<Block>>(
+		set_id,
+		authorities,
+		begin,
+		proof,
+	)?;
+	Ok(verified.map(|(fragment, set_id, authorities)|
+		(fragment.header, fragment.justification, set_id, authorities)
+	))
+}
+
+/// Check the fragments of a GRANDPA authority change sequence that follow the block `begin`.
+///
+/// Fragments up to `begin` are skipped, since a proof starting at `begin` repeats its fragment.
+fn check_warp_sync_proof_after<Block: BlockT, J>(
+	current_set_id: u64,
+	current_authorities: AuthorityList,
+	begin: NumberFor<Block>,
+	remote_proof: &[u8],
+) -> ClientResult<Option<(AuthoritySetProofFragment<Block::Header>, u64, AuthorityList)>>
+where
+		NumberFor<Block>: BlockNumberOps,
+		J: Decode + ProvableJustification<Block::Header> + BlockJustification<Block::Header>,
+{
+	let proof = AuthoritySetProof::<Block::Header>::decode(&mut &remote_proof[..])
+		.map_err(|_| ClientError::BadJustification("failed to decode authority proof".into()))?;
+	let proof: Vec<_> = proof.into_iter()
+		.filter(|fragment| *fragment.header.number() > begin)
+		.collect();
+
+	let count = proof.len();
+	let mut result = (current_set_id, current_authorities, begin);
+	let mut last = None;
+	for (ix, fragment) in proof.into_iter().enumerate() {
+		result = check_warp_sync_proof_fragment::<Block, J>(
+			result.0,
+			&result.1,
+			&result.2,
+			ix + 1 == count,
+			&fragment,
+		)?;
+		last = Some(fragment);
+	}
+
+	Ok(last.map(|fragment| (fragment, result.0, result.1)))
+}
+
 /// Check finality authority set sequence.
 fn check_warp_sync_proof_fragment<Block: BlockT, J>(
 	current_set_id: u64,
@@ -982,5 +1044,27 @@ pub(crate) mod tests {
 
 		assert_eq!(current_set_id, 2);
 		assert_eq!(current_set, authorities_next);
+
+		// the same proof, fetched a few fragments at a time
+		let mut begin = (1, hashes[1]);
+		let mut set = (0, initial_authorities.clone());
+		loop {
+			let proof = prove_warp_sync(&blockchain, begin.1, Some(2), None).unwrap();
+			match check_warp_sync_proof_after::<Block, TestBlockJustification>(
+				set.0,
+				set.1.clone(),
+				begin.0,
+				&proof,
+			).unwrap() {
+				Some((fragment, set_id, authorities)) => {
+					begin = (*fragment.header.number(), fragment.header.hash());
+					set = (set_id, authorities);
+				},
+				None => break,
+			}
+		}
+
+		assert_eq!(begin, (8, hashes[8]));
+		assert_eq!(set, (2, authorities_next));
 	}
 }
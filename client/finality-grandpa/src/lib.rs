@@ -130,7 +130,7 @@ pub use voting_rule::{
 	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
 };
 pub use finality_grandpa::voter::report;
-pub use finality_proof::{prove_warp_sync, WarpSyncFragmentCache};
+pub use finality_proof::{prove_warp_sync, verify_warp_sync_proof, WarpSyncFragmentCache};
 
 use aux_schema::PersistentData;
 use environment::{Environment, VoterSetState};
@@ -270,6 +270,8 @@ pub struct Config {
 	pub name: Option<String>,
 	/// The keystore that manages the keys of this node.
 	pub keystore: Option<SyncCryptoStorePtr>,
+	/// The store that keeps this node from casting conflicting votes, if any.
+	pub slashing_protection: Option<Arc<sc_consensus::SlashingProtection>>,
 }
 
 impl Config {
@@ -493,6 +495,29 @@ impl<Block: BlockT, E> GenesisAuthoritySetProvider<Block> for Arc<dyn ExecutorPr
 	}
 }
 
+/// Auxiliary storage entries that make `authority_set` the one in effect at `checkpoint`, a block
+/// imported without its ancestors, e.g. the target of a warp sync.
+///
+/// The entries have to be inserted in the same operation as the block. Once committed, the
+/// [`SharedAuthoritySet`] of a running node is updated with [`SharedAuthoritySet::reset`].
+pub fn checkpoint_aux_data<Block: BlockT>(
+	authority_set: &AuthoritySet<Block::Hash, NumberFor<Block>>,
+	checkpoint: (Block::Hash, NumberFor<Block>),
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+	aux_schema::checkpoint_aux_data::<Block>(authority_set, checkpoint)
+}
+
+/// Auxiliary storage entries to include in a state snapshot of `checkpoint`, so that a node
+/// importing the snapshot continues with the current authority set.
+///
+/// The snapshot should be taken at a finalized block, without pending authority set changes.
+pub fn snapshot_aux_data<Block: BlockT, B: AuxStore>(
+	backend: &B,
+	checkpoint: (Block::Hash, NumberFor<Block>),
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ClientError> {
+	aux_schema::snapshot_aux_data::<Block, _>(backend, checkpoint)
+}
+
 /// Make block importer and link half necessary to tie the background voter
 /// to it.
 pub fn block_import<BE, Block: BlockT, Client, SC>(
@@ -251,6 +251,7 @@ fn initialize_grandpa(
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				slashing_protection: Some(Arc::new(sc_consensus::SlashingProtection::in_memory())),
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
@@ -394,6 +395,7 @@ fn finalize_3_voters_1_full_observer() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: None,
+				slashing_protection: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
@@ -487,6 +489,7 @@ fn transition_3_voters_twice_1_full_observer() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				slashing_protection: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
@@ -920,6 +923,7 @@ fn voter_persists_its_votes() {
 			gossip_duration: TEST_GOSSIP_DURATION,
 			justification_period: 32,
 			keystore: Some(bob_keystore.clone()),
+			slashing_protection: None,
 			name: Some(format!("peer#{}", 1)),
 			is_authority: true,
 			observer_enabled: true,
@@ -963,6 +967,7 @@ fn voter_persists_its_votes() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				slashing_protection: None,
 				name: Some(format!("peer#{}", 0)),
 				is_authority: true,
 				observer_enabled: true,
@@ -1005,6 +1010,7 @@ fn voter_persists_its_votes() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore: Some(keystore),
+				slashing_protection: None,
 				name: Some(format!("peer#{}", 0)),
 				is_authority: true,
 				observer_enabled: true,
@@ -1043,6 +1049,7 @@ fn voter_persists_its_votes() {
 	{
 		let (round_rx, round_tx) = bob_network.round_communication(
 			Some((peers[1].public().into(), bob_keystore).into()),
+			None,
 			communication::Round(1),
 			communication::SetId(0),
 			Arc::new(VoterSet::new(voters).unwrap()),
@@ -1164,6 +1171,7 @@ fn finalize_3_voters_1_light_observer() {
 			gossip_duration: TEST_GOSSIP_DURATION,
 			justification_period: 32,
 			keystore: None,
+			slashing_protection: None,
 			name: Some("observer".to_string()),
 			is_authority: false,
 			observer_enabled: true,
@@ -1205,6 +1213,7 @@ fn voter_catches_up_to_latest_round_when_behind() {
 				gossip_duration: TEST_GOSSIP_DURATION,
 				justification_period: 32,
 				keystore,
+				slashing_protection: None,
 				name: Some(format!("peer#{}", peer_id)),
 				is_authority: true,
 				observer_enabled: true,
@@ -1327,6 +1336,7 @@ where
 		gossip_duration: TEST_GOSSIP_DURATION,
 		justification_period: 32,
 		keystore,
+		slashing_protection: None,
 		name: None,
 		is_authority: true,
 		observer_enabled: true,
@@ -0,0 +1,333 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Key file format of the local keystore.
+//!
+//! Without a keystore password, a key file holds the JSON encoded secret phrase or seed. With a
+//! password, it holds a versioned JSON object with the phrase encrypted by ChaCha20-Poly1305,
+//! under a key derived from the password with Argon2id. The key derivation parameters are
+//! stored in the file, so they can be raised later without breaking existing files.
+//!
+//! Argon2id is slow by design. While the keystore is unlocked, the derived keys are cached in
+//! [`Unlocked`], and all files written during that time share one key derivation, each with its
+//! own nonce.
+
+use std::collections::HashMap;
+use chacha20poly1305::{
+	ChaCha20Poly1305,
+	aead::{Aead, NewAead, generic_array::GenericArray},
+};
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use sp_core::{Bytes, crypto::{ExposeSecret, SecretString}};
+use zeroize::Zeroizing;
+
+use crate::{Result, Error};
+
+/// Current version of the encrypted key file format.
+const VERSION: u32 = 1;
+/// Length of the key derivation salt.
+const SALT_LEN: usize = 16;
+/// Length of the ChaCha20-Poly1305 nonce.
+const NONCE_LEN: usize = 12;
+/// Length of the ChaCha20-Poly1305 key.
+const KEY_LEN: u32 = 32;
+/// Argon2id memory cost in KiB for new files.
+const ARGON2_MEMORY_COST: u32 = 19 * 1024;
+/// Argon2id number of passes for new files.
+const ARGON2_TIME_COST: u32 = 2;
+/// Argon2id degree of parallelism for new files.
+const ARGON2_LANES: u32 = 1;
+/// Maximum Argon2id memory cost in KiB accepted from a file.
+const MAX_ARGON2_MEMORY_COST: u32 = 1024 * 1024;
+/// Maximum Argon2id number of passes accepted from a file.
+const MAX_ARGON2_TIME_COST: u32 = 64;
+/// Maximum Argon2id degree of parallelism accepted from a file.
+const MAX_ARGON2_LANES: u32 = 16;
+
+/// An encrypted key file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedKeyFile {
+	version: u32,
+	kdf: Kdf,
+	cipher: Cipher,
+	ciphertext: Bytes,
+}
+
+/// Derivation of the encryption key from the password.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Kdf {
+	#[serde(rename_all = "camelCase")]
+	Argon2id { salt: Bytes, memory_cost: u32, time_cost: u32, lanes: u32 },
+}
+
+/// Authenticated encryption of the phrase.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Cipher {
+	#[serde(rename = "chacha20poly1305")]
+	ChaCha20Poly1305 { nonce: Bytes },
+}
+
+impl Kdf {
+	/// Key derivation with a new random salt.
+	fn generate() -> Self {
+		let mut salt = [0u8; SALT_LEN];
+		rand::thread_rng().fill_bytes(&mut salt);
+		Kdf::Argon2id {
+			salt: salt.to_vec().into(),
+			memory_cost: ARGON2_MEMORY_COST,
+			time_cost: ARGON2_TIME_COST,
+			lanes: ARGON2_LANES,
+		}
+	}
+
+	/// Check that the parameters read from a file can be used without exhausting the resources
+	/// of the node.
+	fn check(&self) -> Result<()> {
+		let Kdf::Argon2id { memory_cost, time_cost, lanes, .. } = self;
+		if *memory_cost > MAX_ARGON2_MEMORY_COST ||
+			*time_cost > MAX_ARGON2_TIME_COST ||
+			*lanes > MAX_ARGON2_LANES
+		{
+			return Err(Error::InvalidKeyFile(format!(
+				"Key derivation parameters exceed the maximum: memory cost {} KiB (max {}), \
+				time cost {} (max {}), lanes {} (max {})",
+				memory_cost, MAX_ARGON2_MEMORY_COST,
+				time_cost, MAX_ARGON2_TIME_COST,
+				lanes, MAX_ARGON2_LANES,
+			)));
+		}
+		Ok(())
+	}
+
+	fn derive(&self, password: &str) -> Result<Zeroizing<Vec<u8>>> {
+		let Kdf::Argon2id { salt, memory_cost, time_cost, lanes } = self;
+		let config = argon2::Config {
+			variant: argon2::Variant::Argon2id,
+			version: argon2::Version::Version13,
+			mem_cost: *memory_cost,
+			time_cost: *time_cost,
+			lanes: *lanes,
+			thread_mode: argon2::ThreadMode::Sequential,
+			secret: &[],
+			ad: &[],
+			hash_length: KEY_LEN,
+		};
+		argon2::hash_raw(password.as_bytes(), salt, &config)
+			.map(Zeroizing::new)
+			.map_err(|e| Error::InvalidKeyFile(e.to_string()))
+	}
+}
+
+/// The password of an unlocked keystore, along with the keys derived from it.
+///
+/// The keys are zeroed when the keystore is locked again by dropping this.
+pub struct Unlocked {
+	password: SecretString,
+	keys: Mutex<DerivedKeys>,
+}
+
+#[derive(Default)]
+struct DerivedKeys {
+	/// Key derivation used for new files.
+	current: Option<Kdf>,
+	/// Keys derived so far.
+	keys: HashMap<Kdf, Zeroizing<Vec<u8>>>,
+}
+
+impl Unlocked {
+	/// Unlock with the given password. Keys are only derived once they are needed.
+	pub fn new(password: SecretString) -> Self {
+		Self { password, keys: Default::default() }
+	}
+
+	/// Returns the password.
+	pub fn password(&self) -> &str {
+		self.password.expose_secret().as_str()
+	}
+
+	/// Returns the cipher for the given key derivation, deriving its key if needed.
+	fn cipher(&self, kdf: &Kdf) -> Result<ChaCha20Poly1305> {
+		let mut derived = self.keys.lock();
+		if !derived.keys.contains_key(kdf) {
+			let key = kdf.derive(self.password())?;
+			derived.keys.insert(kdf.clone(), key);
+		}
+		Ok(ChaCha20Poly1305::new(GenericArray::from_slice(&derived.keys[kdf][..])))
+	}
+
+	/// Returns the key derivation used for new files.
+	fn current_kdf(&self) -> Kdf {
+		self.keys.lock().current.get_or_insert_with(Kdf::generate).clone()
+	}
+}
+
+/// Encode the contents of the key file for `phrase`.
+///
+/// The phrase is encrypted if the keystore is `unlocked` with a password.
+pub fn encode(phrase: &str, unlocked: Option<&Unlocked>) -> Result<Vec<u8>> {
+	let unlocked = match unlocked {
+		Some(unlocked) => unlocked,
+		None => return serde_json::to_vec(phrase).map_err(Into::into),
+	};
+
+	let mut nonce = [0u8; NONCE_LEN];
+	rand::thread_rng().fill_bytes(&mut nonce);
+
+	let kdf = unlocked.current_kdf();
+	let ciphertext = unlocked.cipher(&kdf)?
+		.encrypt(GenericArray::from_slice(&nonce), phrase.as_bytes())
+		.map_err(|_| Error::InvalidKeyFile("Encryption failed".into()))?;
+
+	serde_json::to_vec(&EncryptedKeyFile {
+		version: VERSION,
+		kdf,
+		cipher: Cipher::ChaCha20Poly1305 { nonce: nonce.to_vec().into() },
+		ciphertext: ciphertext.into(),
+	}).map_err(Into::into)
+}
+
+/// Decode the phrase from the contents of a key file.
+pub fn decode(contents: &[u8], unlocked: Option<&Unlocked>) -> Result<String> {
+	let file = match parse(contents)? {
+		Ok(phrase) => return Ok(phrase),
+		Err(file) => file,
+	};
+	let unlocked = unlocked.ok_or(Error::InvalidPassword)?;
+
+	let Cipher::ChaCha20Poly1305 { nonce } = &file.cipher;
+	if nonce.len() != NONCE_LEN {
+		return Err(Error::InvalidKeyFile("Invalid nonce length".into()));
+	}
+	// Authentication fails for a wrong password as well as for a modified file.
+	let phrase = unlocked.cipher(&file.kdf)?
+		.decrypt(GenericArray::from_slice(nonce), &file.ciphertext[..])
+		.map_err(|_| Error::InvalidPassword)?;
+
+	String::from_utf8(phrase).map_err(|_| Error::InvalidKeyFile("Phrase is not valid UTF-8".into()))
+}
+
+/// Returns whether the contents of a key file are encrypted.
+pub fn is_encrypted(contents: &[u8]) -> Result<bool> {
+	parse(contents).map(|file| file.is_err())
+}
+
+/// Parse a key file into either the plaintext phrase or the encrypted file.
+fn parse(contents: &[u8]) -> Result<std::result::Result<String, EncryptedKeyFile>> {
+	match serde_json::from_slice(contents)? {
+		serde_json::Value::String(phrase) => Ok(Ok(phrase)),
+		value => {
+			let version = value.get("version").and_then(|v| v.as_u64());
+			if version != Some(VERSION as u64) {
+				return Err(Error::InvalidKeyFile(format!("Unsupported version {:?}", version)));
+			}
+			let file: EncryptedKeyFile = serde_json::from_value(value)?;
+			file.kdf.check()?;
+			Ok(Err(file))
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+	fn unlocked(password: &str) -> Unlocked {
+		Unlocked::new(SecretString::new(password.to_string()))
+	}
+
+	#[test]
+	fn plaintext_without_password() {
+		let contents = encode(PHRASE, None).unwrap();
+		assert_eq!(contents, serde_json::to_vec(PHRASE).unwrap());
+		assert!(!is_encrypted(&contents).unwrap());
+		assert_eq!(decode(&contents, None).unwrap(), PHRASE);
+		assert_eq!(decode(&contents, Some(&unlocked("password"))).unwrap(), PHRASE);
+	}
+
+	#[test]
+	fn encrypted_with_password() {
+		let contents = encode(PHRASE, Some(&unlocked("password"))).unwrap();
+		assert!(is_encrypted(&contents).unwrap());
+		assert!(!String::from_utf8_lossy(&contents).contains("bottom"));
+		assert_eq!(decode(&contents, Some(&unlocked("password"))).unwrap(), PHRASE);
+
+		assert!(matches!(decode(&contents, None), Err(Error::InvalidPassword)));
+		assert!(matches!(decode(&contents, Some(&unlocked("wrong"))), Err(Error::InvalidPassword)));
+	}
+
+	#[test]
+	fn derives_keys_once_per_unlock() {
+		let password = unlocked("password");
+		let first = encode(PHRASE, Some(&password)).unwrap();
+		let second = encode(PHRASE, Some(&password)).unwrap();
+		assert_eq!(password.keys.lock().keys.len(), 1);
+
+		let (first, second) = match (parse(&first).unwrap(), parse(&second).unwrap()) {
+			(Err(first), Err(second)) => (first, second),
+			_ => panic!("files are encrypted"),
+		};
+		assert!(first.kdf == second.kdf);
+		assert_ne!(first.ciphertext, second.ciphertext);
+
+		// Files written during another unlock need their own key.
+		let other = encode(PHRASE, Some(&unlocked("password"))).unwrap();
+		assert_eq!(decode(&other, Some(&password)).unwrap(), PHRASE);
+		assert_eq!(password.keys.lock().keys.len(), 2);
+		decode(&other, Some(&password)).unwrap();
+		assert_eq!(password.keys.lock().keys.len(), 2);
+	}
+
+	#[test]
+	fn rejects_tampered_and_unknown_files() {
+		let password = unlocked("password");
+		let contents = encode(PHRASE, Some(&password)).unwrap();
+		let mut file: serde_json::Value = serde_json::from_slice(&contents).unwrap();
+
+		file["ciphertext"] = serde_json::to_value(Bytes(vec![0; 80])).unwrap();
+		let tampered = serde_json::to_vec(&file).unwrap();
+		assert!(matches!(decode(&tampered, Some(&password)), Err(Error::InvalidPassword)));
+
+		file["version"] = 2.into();
+		let unknown = serde_json::to_vec(&file).unwrap();
+		assert!(matches!(decode(&unknown, Some(&password)), Err(Error::InvalidKeyFile(_))));
+	}
+
+	#[test]
+	fn rejects_excessive_kdf_parameters() {
+		let password = unlocked("password");
+		let contents = encode(PHRASE, Some(&password)).unwrap();
+		let file: serde_json::Value = serde_json::from_slice(&contents).unwrap();
+
+		let excessive = [("memoryCost", u32::max_value()), ("timeCost", 1000), ("lanes", 64)];
+		for (param, value) in excessive.iter() {
+			let mut file = file.clone();
+			file["kdf"]["argon2id"][*param] = (*value).into();
+			let planted = serde_json::to_vec(&file).unwrap();
+			// Rejected before deriving any key.
+			assert!(matches!(is_encrypted(&planted), Err(Error::InvalidKeyFile(_))));
+			let decoded = decode(&planted, Some(&unlocked("password")));
+			assert!(matches!(decoded, Err(Error::InvalidKeyFile(_))));
+		}
+	}
+}
@@ -23,10 +23,17 @@ use std::io;
 use sp_core::crypto::KeyTypeId;
 use sp_keystore::Error as TraitError;
 
+/// Key file format of the local keystore
+mod key_file;
+
 /// Local keystore implementation
 mod local;
 pub use local::LocalKeystore;
 
+/// Remote signer keystore implementation
+mod remote;
+pub use remote::{RemoteKeystore, RemoteSigner, SignerServer, SignerToken};
+
 /// Keystore error.
 #[derive(Debug, derive_more::Display, derive_more::From)]
 pub enum Error {
@@ -52,6 +59,9 @@ pub enum Error {
 	/// Keystore unavailable
 	#[display(fmt="Keystore unavailable")]
 	Unavailable,
+	/// Key file can not be read
+	#[display(fmt="Invalid key file: {}", "_0")]
+	InvalidKeyFile(String),
 }
 
 /// Keystore Result
@@ -68,6 +78,7 @@ impl From<Error> for TraitError {
 			Error::Unavailable => TraitError::Unavailable,
 			Error::Io(e) => TraitError::Other(e.to_string()),
 			Error::Json(e) => TraitError::Other(e.to_string()),
+			Error::InvalidKeyFile(_) => TraitError::Other(error.to_string()),
 		}
 	}
 }
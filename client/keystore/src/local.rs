@@ -21,13 +21,13 @@ use std::{
 	collections::{HashMap, HashSet},
 	fs::{self, File},
 	io::Write,
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::Arc,
 };
 use async_trait::async_trait;
 use parking_lot::RwLock;
 use sp_core::{
-	crypto::{CryptoTypePublicPair, KeyTypeId, Pair as PairT, ExposeSecret, SecretString, Public},
+	crypto::{CryptoTypePublicPair, KeyTypeId, Pair as PairT, SecretString, Public},
 	sr25519::{Public as Sr25519Public, Pair as Sr25519Pair},
 	Encode,
 };
@@ -40,7 +40,7 @@ use sp_keystore::{
 };
 use sp_application_crypto::{ed25519, sr25519, ecdsa, AppPair, AppKey, IsWrappedBy};
 
-use crate::{Result, Error};
+use crate::{Result, Error, key_file};
 
 /// A local based keystore that is either memory-based or filesystem-based.
 pub struct LocalKeystore(RwLock<KeystoreInner>);
@@ -65,6 +65,14 @@ impl LocalKeystore {
 	pub fn key_pair<Pair: AppPair>(&self, public: &<Pair as AppKey>::Public) -> Result<Pair> {
 		self.0.read().key_pair::<Pair>(public)
 	}
+
+	/// Encrypt all plaintext key files of the keystore with its password.
+	///
+	/// Every key is checked to be derivable with the password before any file is rewritten.
+	/// Returns the number of encrypted files.
+	pub fn encrypt_key_files(&self) -> Result<usize> {
+		self.0.read().encrypt_key_files()
+	}
 }
 
 #[async_trait]
@@ -320,7 +328,7 @@ struct KeystoreInner {
 	path: Option<PathBuf>,
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
-	password: Option<SecretString>,
+	unlocked: Option<key_file::Unlocked>,
 }
 
 impl KeystoreInner {
@@ -331,15 +339,17 @@ impl KeystoreInner {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
 
-		let instance = Self { path: Some(path), additional: HashMap::new(), password };
+		let instance = Self {
+			path: Some(path),
+			additional: HashMap::new(),
+			unlocked: password.map(key_file::Unlocked::new),
+		};
 		Ok(instance)
 	}
 
 	/// Get the password for this store.
 	fn password(&self) -> Option<&str> {
-		self.password.as_ref()
-			.map(|p| p.expose_secret())
-			.map(|p| p.as_str())
+		self.unlocked.as_ref().map(|unlocked| unlocked.password())
 	}
 
 	/// Create a new in-memory store.
@@ -347,7 +357,7 @@ impl KeystoreInner {
 		Self {
 			path: None,
 			additional: HashMap::new(),
-			password: None
+			unlocked: None,
 		}
 	}
 
@@ -374,13 +384,20 @@ impl KeystoreInner {
 	/// Places it into the file system store, if a path is configured.
 	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
 		if let Some(path) = self.key_file_path(public, key_type) {
-			let mut file = File::create(path).map_err(Error::Io)?;
-			serde_json::to_writer(&file, &suri).map_err(Error::Json)?;
-			file.flush().map_err(Error::Io)?;
+			self.write_key_file(&path, suri)?;
 		}
 		Ok(())
 	}
 
+	/// Write the key file for `phrase`, encrypted if the store has a password.
+	fn write_key_file(&self, path: &Path, phrase: &str) -> Result<()> {
+		let contents = key_file::encode(phrase, self.unlocked.as_ref())?;
+		let mut file = File::create(path)?;
+		file.write_all(&contents)?;
+		file.flush()?;
+		Ok(())
+	}
+
 	/// Generate a new key.
 	///
 	/// Places it into the file system store, if a path is configured. Otherwise insert
@@ -388,9 +405,7 @@ impl KeystoreInner {
 	fn generate_by_type<Pair: PairT>(&mut self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password());
 		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			let mut file = File::create(path)?;
-			serde_json::to_writer(&file, &phrase)?;
-			file.flush()?;
+			self.write_key_file(&path, &phrase)?;
 		} else {
 			self.insert_ephemeral_pair(&pair, &phrase, key_type);
 		}
@@ -417,9 +432,9 @@ impl KeystoreInner {
 		}
 
 		let path = self.key_file_path(public, key_type).ok_or_else(|| Error::Unavailable)?;
-		let file = File::open(path)?;
+		let contents = fs::read(path)?;
 
-		serde_json::from_reader(&file).map_err(Into::into)
+		key_file::decode(&contents, self.unlocked.as_ref())
 	}
 
 	/// Get a key pair for the given public key and key type.
@@ -449,6 +464,50 @@ impl KeystoreInner {
 		Some(buf)
 	}
 
+	/// Encrypt all plaintext key files with the password of the store.
+	fn encrypt_key_files(&self) -> Result<usize> {
+		let password = self.password().ok_or(Error::InvalidPassword)?;
+		let dir = match &self.path {
+			Some(dir) => dir,
+			None => return Ok(0),
+		};
+
+		let mut plaintext = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let path = entry?.path();
+			let key = match path.file_name().and_then(|n| n.to_str()).map(hex::decode) {
+				Some(Ok(key)) if key.len() > 4 => key,
+				_ => continue,
+			};
+			let contents = fs::read(&path)?;
+			if key_file::is_encrypted(&contents)? {
+				continue;
+			}
+			let phrase = key_file::decode(&contents, None)?;
+			// The crypto type of a key is not known, so any of them has to match.
+			let public = &key[4..];
+			let derives =
+				sr25519::Pair::from_string(&phrase, Some(password))
+					.map_or(false, |p| p.public().as_slice() == public) ||
+				ed25519::Pair::from_string(&phrase, Some(password))
+					.map_or(false, |p| p.public().as_slice() == public) ||
+				ecdsa::Pair::from_string(&phrase, Some(password))
+					.map_or(false, |p| p.public().as_slice() == public);
+			if !derives {
+				return Err(Error::InvalidPassword);
+			}
+			plaintext.push((path, phrase));
+		}
+
+		for (path, phrase) in &plaintext {
+			// Replace the file atomically, so that the key is never lost.
+			let tmp_path = path.with_extension("tmp");
+			self.write_key_file(&tmp_path, phrase)?;
+			fs::rename(tmp_path, path)?;
+		}
+		Ok(plaintext.len())
+	}
+
 	/// Returns a list of raw public keys filtered by `KeyTypeId`
 	fn raw_public_keys(&self, id: KeyTypeId) -> Result<Vec<Vec<u8>>> {
 		let mut public_keys: Vec<Vec<u8>> = self.additional.keys()
@@ -586,6 +645,46 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn key_files_are_encrypted_with_password() {
+		let password = || Some(FromStr::from_str("password").unwrap());
+		let temp_dir = TempDir::new().unwrap();
+		let store = LocalKeystore::open(temp_dir.path(), password()).unwrap();
+		let public = SyncCryptoStore::sr25519_generate_new(&store, SR25519, None).unwrap();
+
+		let file = store.0.read().key_file_path(public.as_slice(), SR25519).unwrap();
+		assert!(key_file::is_encrypted(&fs::read(file).unwrap()).unwrap());
+		assert!(store.key_pair::<sr25519::AppPair>(&public.into()).is_ok());
+
+		// The same password is needed to read the key back.
+		let store = LocalKeystore::open(temp_dir.path(), None).unwrap();
+		assert!(matches!(store.key_pair::<sr25519::AppPair>(&public.into()), Err(Error::InvalidPassword)));
+	}
+
+	#[test]
+	fn plaintext_key_files_are_migrated() {
+		let password = || Some(FromStr::from_str("password").unwrap());
+		let temp_dir = TempDir::new().unwrap();
+
+		// A key written before encryption was supported.
+		let (pair, phrase, _) = sr25519::Pair::generate_with_phrase(Some("password"));
+		let file = temp_dir.path().join(hex::encode(SR25519.0) + &hex::encode(pair.public()));
+		fs::write(&file, serde_json::to_vec(&phrase).unwrap()).unwrap();
+
+		let store = LocalKeystore::open(temp_dir.path(), None).unwrap();
+		assert!(matches!(store.encrypt_key_files(), Err(Error::InvalidPassword)));
+		let store = LocalKeystore::open(temp_dir.path(), Some(FromStr::from_str("wrong").unwrap())).unwrap();
+		assert!(matches!(store.encrypt_key_files(), Err(Error::InvalidPassword)));
+		assert!(!key_file::is_encrypted(&fs::read(&file).unwrap()).unwrap());
+
+		let store = LocalKeystore::open(temp_dir.path(), password()).unwrap();
+		assert_eq!(store.encrypt_key_files().unwrap(), 1);
+		assert_eq!(store.encrypt_key_files().unwrap(), 0);
+		assert!(key_file::is_encrypted(&fs::read(&file).unwrap()).unwrap());
+		let public = sr25519::AppPublic::from(pair.public());
+		assert_eq!(store.key_pair::<sr25519::AppPair>(&public).unwrap().public(), public);
+	}
+
 	#[test]
 	fn public_keys_are_returned() {
 		let temp_dir = TempDir::new().unwrap();
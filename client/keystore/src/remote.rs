@@ -0,0 +1,947 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Remote signer keystore implementation.
+//!
+//! The keys are kept by a separate signer process. It is reached either over a Unix domain
+//! socket, with one JSON encoded request per line answered by one JSON encoded response per
+//! line, or over HTTPS, with the request `POST`ed as body and the response returned as body
+//! of a `200 OK` reply. Plain HTTP is only accepted for signers on a loopback address.
+//!
+//! Whoever can send requests to the signer can make it sign anything with the keys of the node.
+//! HTTP requests carry a bearer token in their `Authorization` header, that the signer must
+//! check. Unix domain sockets rely on the file permissions of the socket instead.
+//!
+//! [`SignerServer`] is a reference implementation of the signer side that serves the keys of
+//! any other keystore.
+
+use std::{
+	collections::HashSet,
+	convert::TryFrom,
+	fmt,
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{IpAddr, TcpListener, TcpStream, ToSocketAddrs},
+	path::PathBuf,
+	str::FromStr,
+	sync::{Arc, mpsc},
+	thread,
+	time::Duration,
+};
+#[cfg(unix)]
+use std::{fs, os::unix::{fs::PermissionsExt, net::UnixListener}};
+use async_trait::async_trait;
+use futures::{channel::oneshot, executor::block_on};
+use hyper::{Body, Client as HyperClient, client::HttpConnector, header};
+use hyper_rustls::HttpsConnector;
+use log::warn;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use subtle::ConstantTimeEq;
+use sp_core::{
+	Bytes,
+	crypto::{CryptoTypeId, CryptoTypePublicPair, KeyTypeId},
+	sr25519, ed25519, ecdsa,
+};
+use sp_keystore::{
+	CryptoStore,
+	SyncCryptoStorePtr,
+	Error as TraitError,
+	SyncCryptoStore,
+	vrf::{VRFTranscriptData, VRFTranscriptValue, VRFSignature},
+};
+
+/// Timeout of a single request to the signer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Labels of the VRF transcripts built by BABE, the only ones [`SignerServer`] signs.
+///
+/// `VRFTranscriptData` holds `'static` labels, so the server maps the labels it receives to
+/// these.
+const TRANSCRIPT_LABELS: &[&str] = &["BABE", "slot number", "current epoch", "chain randomness"];
+
+/// Bearer token authenticating the requests to an HTTP signer.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SignerToken(String);
+
+impl SignerToken {
+	/// Create a token. Surrounding whitespace, such as the end of line of a token file, is
+	/// ignored.
+	pub fn new(token: &str) -> std::result::Result<Self, String> {
+		let token = token.trim();
+		if token.is_empty() {
+			return Err("The remote signer token is empty".into());
+		}
+		if !token.chars().all(|c| c.is_ascii_graphic()) {
+			return Err("The remote signer token must be printable ASCII without spaces".into());
+		}
+		Ok(SignerToken(token.into()))
+	}
+
+	/// Whether the value of an `Authorization` header presents this token.
+	fn authorizes(&self, authorization: &str) -> bool {
+		let presented = match authorization.trim().strip_prefix("Bearer ") {
+			Some(presented) => presented.trim(),
+			None => return false,
+		};
+		presented.as_bytes().ct_eq(self.0.as_bytes()).into()
+	}
+}
+
+impl fmt::Debug for SignerToken {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("SignerToken(..)")
+	}
+}
+
+/// Address of a remote signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteSigner {
+	/// Signer listening on a Unix domain socket, written as `unix:<path>`.
+	Unix(PathBuf),
+	/// Signer accepting HTTP requests, written as `https://<host>:<port>[/<path>]`, or as
+	/// `http://<host>:<port>[/<path>]` for a loopback host.
+	Http {
+		/// Whether the requests are sent over TLS.
+		tls: bool,
+		/// Host and port of the signer.
+		authority: String,
+		/// Path the requests are posted to.
+		path: String,
+		/// Token sent with the requests. Not part of the written address.
+		token: Option<SignerToken>,
+	},
+}
+
+/// Whether the host of `authority` is a loopback address.
+fn is_loopback(authority: &str) -> bool {
+	let host = match authority.rfind(':') {
+		Some(index) if !authority[index..].contains(']') => &authority[..index],
+		_ => authority,
+	};
+	let host = host.trim_start_matches('[').trim_end_matches(']');
+	host.eq_ignore_ascii_case("localhost") ||
+		host.parse::<IpAddr>().map_or(false, |ip| ip.is_loopback())
+}
+
+impl FromStr for RemoteSigner {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		if let Some(path) = s.strip_prefix("unix:") {
+			return Ok(RemoteSigner::Unix(path.into()));
+		}
+		let (tls, rest) = match (s.strip_prefix("https://"), s.strip_prefix("http://")) {
+			(Some(rest), _) => (true, rest),
+			(None, Some(rest)) => (false, rest),
+			(None, None) => return Err(format!("Invalid remote signer address: {}", s)),
+		};
+		let (authority, path) = match rest.find('/') {
+			Some(index) => rest.split_at(index),
+			None => (rest, "/"),
+		};
+		if authority.is_empty() {
+			return Err(format!("Invalid remote signer address: {}", s));
+		}
+		if !tls && !is_loopback(authority) {
+			return Err(format!(
+				"Remote signer {} is not on a loopback address and has to be reached over https",
+				s,
+			));
+		}
+		Ok(RemoteSigner::Http { tls, authority: authority.into(), path: path.into(), token: None })
+	}
+}
+
+impl fmt::Display for RemoteSigner {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			RemoteSigner::Unix(path) => write!(f, "unix:{}", path.display()),
+			RemoteSigner::Http { tls, authority, path, .. } => {
+				let scheme = if *tls { "https" } else { "http" };
+				write!(f, "{}://{}{}", scheme, authority, path)
+			},
+		}
+	}
+}
+
+type Client = HyperClient<HttpsConnector<HttpConnector>, Body>;
+
+impl RemoteSigner {
+	/// Authenticate the requests to an HTTP signer with `token`.
+	///
+	/// Signers on a Unix domain socket don't take a token.
+	pub fn with_token(self, token: SignerToken) -> std::result::Result<Self, String> {
+		match self {
+			RemoteSigner::Http { tls, authority, path, .. } =>
+				Ok(RemoteSigner::Http { tls, authority, path, token: Some(token) }),
+			RemoteSigner::Unix(_) =>
+				Err(format!("Remote signer {} is reached over a Unix socket, without token", self)),
+		}
+	}
+
+	// Sends an encoded request to the signer and returns the encoded response.
+	async fn call(&self, client: &Client, request: Vec<u8>) -> io::Result<Vec<u8>> {
+		let other = |e: &dyn fmt::Display| io::Error::new(io::ErrorKind::Other, e.to_string());
+		match self {
+			#[cfg(unix)]
+			RemoteSigner::Unix(path) => {
+				use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+				let mut stream = tokio::net::UnixStream::connect(path).await?;
+				stream.write_all(&request).await?;
+				stream.write_all(b"\n").await?;
+				let mut response = Vec::new();
+				tokio::io::BufReader::new(stream).read_until(b'\n', &mut response).await?;
+				Ok(response)
+			},
+			#[cfg(not(unix))]
+			RemoteSigner::Unix(_) => Err(io::Error::new(
+				io::ErrorKind::Other,
+				"Unix sockets are not supported on this platform",
+			)),
+			RemoteSigner::Http { token, .. } => {
+				let mut builder = hyper::Request::post(self.to_string())
+					.header(header::CONTENT_TYPE, "application/json");
+				if let Some(SignerToken(token)) = token {
+					builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+				}
+				let request = builder.body(Body::from(request)).map_err(|e| other(&e))?;
+				let response = client.request(request).await.map_err(|e| other(&e))?;
+				if !response.status().is_success() {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!("Signer returned HTTP status {}", response.status()),
+					));
+				}
+				let body = hyper::body::to_bytes(response.into_body()).await
+					.map_err(|e| other(&e))?;
+				Ok(body.to_vec())
+			},
+		}
+	}
+}
+
+/// VRF transcript as sent to the signer.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transcript {
+	label: Bytes,
+	items: Vec<(String, VRFTranscriptValue)>,
+}
+
+/// A request to the signer.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "method", content = "params")]
+enum Request {
+	/// List all keys of a key type. Returns a list of `CryptoTypePublicPair`.
+	#[serde(rename_all = "camelCase")]
+	Keys { key_type: KeyTypeId },
+	/// Generate a new key, from `seed` if given. Returns the public key.
+	#[serde(rename_all = "camelCase")]
+	Generate { key_type: KeyTypeId, crypto_type: CryptoTypeId, seed: Option<String> },
+	/// Insert a key given by its secret URI. Returns nothing.
+	#[serde(rename_all = "camelCase")]
+	Insert { key_type: KeyTypeId, suri: String, public: Bytes },
+	/// Check whether the signer has all the given keys. Returns a boolean.
+	#[serde(rename_all = "camelCase")]
+	HasKeys { keys: Vec<(Bytes, KeyTypeId)> },
+	/// Sign a message. Returns the SCALE encoded signature.
+	#[serde(rename_all = "camelCase")]
+	Sign { key_type: KeyTypeId, key: CryptoTypePublicPair, message: Bytes },
+	/// Produce a VRF signature with a sr25519 key. Returns a `VRFSignature`.
+	#[serde(rename_all = "camelCase")]
+	VrfSign { key_type: KeyTypeId, public: Bytes, transcript: Transcript },
+}
+
+/// A response of the signer.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Response {
+	/// The request succeeded.
+	Result(serde_json::Value),
+	/// The request failed.
+	Error(String),
+}
+
+/// A keystore that forwards all operations to a remote signer.
+///
+/// The requests run on a dedicated thread, so that they make progress whichever executor waits
+/// for them, if any.
+pub struct RemoteKeystore {
+	signer: RemoteSigner,
+	client: Client,
+	runtime: tokio::runtime::Handle,
+	/// Stops the runtime thread when dropped.
+	_shutdown: oneshot::Sender<()>,
+}
+
+impl RemoteKeystore {
+	/// Create a keystore backed by the given signer.
+	///
+	/// The signer is only contacted once keys are requested.
+	pub fn new(signer: RemoteSigner) -> crate::Result<Self> {
+		let (runtime_tx, runtime_rx) = mpsc::channel();
+		let (shutdown, stopped) = oneshot::channel::<()>();
+		thread::Builder::new()
+			.name("remote-signer".into())
+			.spawn(move || {
+				let runtime = tokio::runtime::Builder::new()
+					.basic_scheduler()
+					.enable_all()
+					.build();
+				let mut runtime = match runtime {
+					Ok(runtime) => runtime,
+					Err(e) => {
+						let _ = runtime_tx.send(Err(e));
+						return
+					},
+				};
+				let _ = runtime_tx.send(Ok(runtime.handle().clone()));
+				// Resolves once the keystore, holding the sender, is dropped.
+				let _ = runtime.block_on(stopped);
+			})?;
+		let runtime = runtime_rx.recv().map_err(|_| io::Error::new(
+			io::ErrorKind::Other,
+			"Remote signer thread exited",
+		))??;
+
+		Ok(RemoteKeystore {
+			signer,
+			client: HyperClient::builder().build(HttpsConnector::new()),
+			runtime,
+			_shutdown: shutdown,
+		})
+	}
+
+	async fn request<T: DeserializeOwned>(
+		&self,
+		request: &Request,
+	) -> std::result::Result<T, TraitError> {
+		let request = serde_json::to_vec(request).map_err(|e| TraitError::Other(e.to_string()))?;
+		let (signer, client) = (self.signer.clone(), self.client.clone());
+		let response = self.runtime.spawn(async move {
+			match tokio::time::timeout(REQUEST_TIMEOUT, signer.call(&client, request)).await {
+				Ok(response) => response,
+				Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Request timed out")),
+			}
+		}).await;
+		let response = response
+			.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+			.and_then(|response| response)
+			.map_err(|e| {
+				warn!("Remote signer {} is unavailable: {}", self.signer, e);
+				TraitError::Unavailable
+			})?;
+		let invalid = |e: serde_json::Error| {
+			TraitError::Other(format!("Invalid signer response: {}", e))
+		};
+		match serde_json::from_slice(&response).map_err(invalid)? {
+			Response::Result(value) => serde_json::from_value(value).map_err(invalid),
+			Response::Error(e) => Err(TraitError::Other(e)),
+		}
+	}
+
+	async fn public_keys<P>(&self, id: KeyTypeId, crypto_type: CryptoTypeId) -> Vec<P>
+		where P: for<'a> TryFrom<&'a [u8]>
+	{
+		CryptoStore::keys(self, id).await
+			.map(|keys| keys.into_iter()
+				.filter(|key| key.0 == crypto_type)
+				.filter_map(|key| P::try_from(&key.1[..]).ok())
+				.collect()
+			)
+			.unwrap_or_default()
+	}
+
+	async fn generate_new<P>(
+		&self,
+		key_type: KeyTypeId,
+		crypto_type: CryptoTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<P, TraitError>
+		where P: for<'a> TryFrom<&'a [u8]>
+	{
+		let public: Bytes = self.request(&Request::Generate {
+			key_type,
+			crypto_type,
+			seed: seed.map(Into::into),
+		}).await?;
+		P::try_from(&public[..])
+			.map_err(|_| TraitError::Other("Signer returned an invalid public key".into()))
+	}
+}
+
+#[async_trait]
+impl CryptoStore for RemoteKeystore {
+	async fn keys(
+		&self,
+		id: KeyTypeId,
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		self.request(&Request::Keys { key_type: id }).await
+	}
+
+	async fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		self.public_keys(id, sr25519::CRYPTO_ID).await
+	}
+
+	async fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<sr25519::Public, TraitError> {
+		self.generate_new(id, sr25519::CRYPTO_ID, seed).await
+	}
+
+	async fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		self.public_keys(id, ed25519::CRYPTO_ID).await
+	}
+
+	async fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ed25519::Public, TraitError> {
+		self.generate_new(id, ed25519::CRYPTO_ID, seed).await
+	}
+
+	async fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.public_keys(id, ecdsa::CRYPTO_ID).await
+	}
+
+	async fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ecdsa::Public, TraitError> {
+		self.generate_new(id, ecdsa::CRYPTO_ID, seed).await
+	}
+
+	async fn insert_unknown(
+		&self,
+		id: KeyTypeId,
+		suri: &str,
+		public: &[u8],
+	) -> std::result::Result<(), ()> {
+		self.request::<()>(&Request::Insert {
+			key_type: id,
+			suri: suri.into(),
+			public: public.to_vec().into(),
+		}).await.map_err(|_| ())
+	}
+
+	async fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		let keys = public_keys.iter().map(|(p, t)| (p.clone().into(), *t)).collect();
+		self.request(&Request::HasKeys { keys }).await.unwrap_or(false)
+	}
+
+	async fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		let all_keys = CryptoStore::keys(self, id).await?
+			.into_iter()
+			.collect::<HashSet<_>>();
+		Ok(keys.into_iter().filter(|key| all_keys.contains(key)).collect::<Vec<_>>())
+	}
+
+	async fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> std::result::Result<Vec<u8>, TraitError> {
+		let signature: Bytes = self.request(&Request::Sign {
+			key_type: id,
+			key: key.clone(),
+			message: msg.to_vec().into(),
+		}).await?;
+		Ok(signature.0)
+	}
+
+	async fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> std::result::Result<VRFSignature, TraitError> {
+		let transcript = Transcript {
+			label: transcript_data.label.to_vec().into(),
+			items: transcript_data.items.into_iter()
+				.map(|(label, value)| (label.into(), value))
+				.collect(),
+		};
+		self.request(&Request::VrfSign {
+			key_type,
+			public: public.as_ref().to_vec().into(),
+			transcript,
+		}).await
+	}
+}
+
+impl SyncCryptoStore for RemoteKeystore {
+	fn keys(
+		&self,
+		id: KeyTypeId
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		block_on(CryptoStore::keys(self, id))
+	}
+
+	fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		block_on(CryptoStore::supported_keys(self, id, keys))
+	}
+
+	fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> std::result::Result<Vec<u8>, TraitError> {
+		block_on(CryptoStore::sign_with(self, id, key, msg))
+	}
+
+	fn sr25519_public_keys(&self, key_type: KeyTypeId) -> Vec<sr25519::Public> {
+		block_on(CryptoStore::sr25519_public_keys(self, key_type))
+	}
+
+	fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<sr25519::Public, TraitError> {
+		block_on(CryptoStore::sr25519_generate_new(self, id, seed))
+	}
+
+	fn ed25519_public_keys(&self, key_type: KeyTypeId) -> Vec<ed25519::Public> {
+		block_on(CryptoStore::ed25519_public_keys(self, key_type))
+	}
+
+	fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ed25519::Public, TraitError> {
+		block_on(CryptoStore::ed25519_generate_new(self, id, seed))
+	}
+
+	fn ecdsa_public_keys(&self, key_type: KeyTypeId) -> Vec<ecdsa::Public> {
+		block_on(CryptoStore::ecdsa_public_keys(self, key_type))
+	}
+
+	fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ecdsa::Public, TraitError> {
+		block_on(CryptoStore::ecdsa_generate_new(self, id, seed))
+	}
+
+	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8])
+		-> std::result::Result<(), ()>
+	{
+		block_on(CryptoStore::insert_unknown(self, key_type, suri, public))
+	}
+
+	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		block_on(CryptoStore::has_keys(self, public_keys))
+	}
+
+	fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> std::result::Result<VRFSignature, TraitError> {
+		block_on(CryptoStore::sr25519_vrf_sign(self, key_type, public, transcript_data))
+	}
+}
+
+/// Serves the keys of a keystore to [`RemoteKeystore`]s.
+///
+/// Each connection is served on its own thread, and is closed once idle for longer than a
+/// request may take. The listening thread runs for as long as the process does.
+pub struct SignerServer {
+	signer: RemoteSigner,
+}
+
+impl SignerServer {
+	/// Serve `keystore` on a Unix domain socket created at `path`.
+	///
+	/// The socket is only accessible to the user running the server.
+	#[cfg(unix)]
+	pub fn bind_unix(path: impl Into<PathBuf>, keystore: SyncCryptoStorePtr) -> io::Result<Self> {
+		let path = path.into();
+		let listener = UnixListener::bind(&path)?;
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+		let handler = Arc::new(RequestHandler::new(keystore, None));
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let handler = handler.clone();
+				spawn_connection(move || {
+					let stream = stream?;
+					stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+					handler.serve_lines(stream)
+				});
+			}
+		});
+		Ok(SignerServer { signer: RemoteSigner::Unix(path) })
+	}
+
+	/// Serve `keystore` over plain HTTP on `address`, to the clients presenting `token`.
+	///
+	/// [`RemoteKeystore`]s only reach a plain HTTP signer on a loopback address. A signer on
+	/// another host has to be put behind a TLS terminating proxy.
+	pub fn bind_http(
+		address: impl ToSocketAddrs,
+		keystore: SyncCryptoStorePtr,
+		token: SignerToken,
+	) -> io::Result<Self> {
+		let listener = TcpListener::bind(address)?;
+		let authority = listener.local_addr()?.to_string();
+		let handler = Arc::new(RequestHandler::new(keystore, Some(token.clone())));
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let handler = handler.clone();
+				spawn_connection(move || {
+					let stream = stream?;
+					stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+					handler.serve_http(stream)
+				});
+			}
+		});
+		let signer = RemoteSigner::Http {
+			tls: false,
+			authority,
+			path: "/".into(),
+			token: Some(token),
+		};
+		Ok(SignerServer { signer })
+	}
+
+	/// The address to reach this server at.
+	pub fn signer(&self) -> RemoteSigner {
+		self.signer.clone()
+	}
+}
+
+/// Serve a connection on a thread of its own.
+fn spawn_connection(serve: impl FnOnce() -> io::Result<()> + Send + 'static) {
+	let spawned = thread::Builder::new()
+		.name("signer-connection".into())
+		.spawn(move || if let Err(e) = serve() {
+			warn!("Signer connection failed: {}", e);
+		});
+	if let Err(e) = spawned {
+		warn!("Failed to spawn signer connection thread: {}", e);
+	}
+}
+
+struct RequestHandler {
+	keystore: SyncCryptoStorePtr,
+	/// Token the HTTP requests must present.
+	token: Option<SignerToken>,
+}
+
+impl RequestHandler {
+	fn new(keystore: SyncCryptoStorePtr, token: Option<SignerToken>) -> Self {
+		RequestHandler { keystore, token }
+	}
+
+	fn serve_lines<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+		let mut reader = BufReader::new(&mut stream);
+		let mut requests = Vec::new();
+		loop {
+			let mut request = Vec::new();
+			if reader.read_until(b'\n', &mut request)? == 0 {
+				break;
+			}
+			requests.push(request);
+			// Requests are answered once the client stops sending.
+			if reader.buffer().is_empty() {
+				break;
+			}
+		}
+		for request in requests {
+			let mut response = self.handle(&request);
+			response.push(b'\n');
+			stream.write_all(&response)?;
+		}
+		Ok(())
+	}
+
+	fn serve_http(&self, mut stream: TcpStream) -> io::Result<()> {
+		let mut reader = BufReader::new(&mut stream);
+		let mut content_length = 0;
+		let mut authorized = false;
+		loop {
+			let mut line = String::new();
+			if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+				break;
+			}
+			let mut parts = line.splitn(2, ':');
+			let name = parts.next().unwrap_or_default();
+			let value = parts.next().unwrap_or_default();
+			if name.eq_ignore_ascii_case("content-length") {
+				content_length = value.trim().parse().unwrap_or(0);
+			} else if name.eq_ignore_ascii_case("authorization") {
+				authorized = self.token.as_ref().map_or(false, |token| token.authorizes(value));
+			}
+		}
+		if !authorized {
+			return stream.write_all(b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+		}
+		let mut request = vec![0; content_length];
+		reader.read_exact(&mut request)?;
+
+		let response = self.handle(&request);
+		write!(
+			stream,
+			"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+			response.len(),
+		)?;
+		stream.write_all(&response)
+	}
+
+	fn handle(&self, request: &[u8]) -> Vec<u8> {
+		let response = match serde_json::from_slice(request) {
+			Ok(request) => match self.dispatch(request) {
+				Ok(result) => Response::Result(result),
+				Err(e) => Response::Error(e),
+			},
+			Err(e) => Response::Error(format!("Invalid request: {}", e)),
+		};
+		serde_json::to_vec(&response).expect("Serializing a JSON value can't fail; qed")
+	}
+
+	fn dispatch(&self, request: Request) -> std::result::Result<serde_json::Value, String> {
+		fn to_value<T: Serialize>(value: T) -> std::result::Result<serde_json::Value, String> {
+			serde_json::to_value(value).map_err(|e| e.to_string())
+		}
+		let store = &*self.keystore;
+
+		match request {
+			Request::Keys { key_type } =>
+				to_value(SyncCryptoStore::keys(store, key_type).map_err(|e| e.to_string())?),
+			Request::Generate { key_type, crypto_type, seed } => {
+				let seed = seed.as_deref();
+				let public = match crypto_type {
+					sr25519::CRYPTO_ID => SyncCryptoStore::sr25519_generate_new(store, key_type, seed)
+						.map(|public| public.as_ref().to_vec()),
+					ed25519::CRYPTO_ID => SyncCryptoStore::ed25519_generate_new(store, key_type, seed)
+						.map(|public| public.as_ref().to_vec()),
+					ecdsa::CRYPTO_ID => SyncCryptoStore::ecdsa_generate_new(store, key_type, seed)
+						.map(|public| public.as_ref().to_vec()),
+					_ => Err(TraitError::KeyNotSupported(key_type)),
+				}.map_err(|e| e.to_string())?;
+				to_value(Bytes(public))
+			},
+			Request::Insert { key_type, suri, public } => {
+				SyncCryptoStore::insert_unknown(store, key_type, &suri, &public)
+					.map_err(|()| "Could not insert key".to_string())?;
+				Ok(serde_json::Value::Null)
+			},
+			Request::HasKeys { keys } => {
+				let keys = keys.into_iter().map(|(public, key_type)| (public.0, key_type)).collect::<Vec<_>>();
+				to_value(SyncCryptoStore::has_keys(store, &keys))
+			},
+			Request::Sign { key_type, key, message } => {
+				let signature = SyncCryptoStore::sign_with(store, key_type, &key, &message)
+					.map_err(|e| e.to_string())?;
+				to_value(Bytes(signature))
+			},
+			Request::VrfSign { key_type, public, transcript } => {
+				let public = sr25519::Public::try_from(&public[..])
+					.map_err(|_| "Invalid public key".to_string())?;
+				let transcript_data = VRFTranscriptData {
+					label: transcript_label(&transcript.label)?.as_bytes(),
+					items: transcript.items.into_iter()
+						.map(|(label, value)| Ok((transcript_label(label.as_bytes())?, value)))
+						.collect::<std::result::Result<_, String>>()?,
+				};
+				let signature = SyncCryptoStore::sr25519_vrf_sign(store, key_type, &public, transcript_data)
+					.map_err(|e| e.to_string())?;
+				to_value(signature)
+			},
+		}
+	}
+}
+
+/// The known transcript label equal to `label`.
+fn transcript_label(label: &[u8]) -> std::result::Result<&'static str, String> {
+	TRANSCRIPT_LABELS.iter()
+		.find(|known| known.as_bytes() == label)
+		.copied()
+		.ok_or_else(|| {
+			format!("Unsupported VRF transcript label: {}", String::from_utf8_lossy(label))
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use sp_core::{Pair, testing::SR25519};
+	use crate::LocalKeystore;
+
+	fn check_keystore(keystore: RemoteKeystore, local: &LocalKeystore) {
+		let public = SyncCryptoStore::sr25519_generate_new(&keystore, SR25519, None).unwrap();
+		assert_eq!(SyncCryptoStore::sr25519_public_keys(&keystore, SR25519), vec![public]);
+		assert_eq!(SyncCryptoStore::sr25519_public_keys(local, SR25519), vec![public]);
+		assert!(SyncCryptoStore::ed25519_public_keys(&keystore, SR25519).is_empty());
+		assert!(SyncCryptoStore::has_keys(&keystore, &[(public.to_vec(), SR25519)]));
+
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, public.to_vec());
+		let signature = SyncCryptoStore::sign_with(&keystore, SR25519, &key, b"message").unwrap();
+		let signature = sr25519::Signature::from_slice(&signature);
+		assert!(sr25519::Pair::verify(&signature, b"message", &public));
+
+		let transcript = || VRFTranscriptData {
+			label: b"BABE",
+			items: vec![("slot number", VRFTranscriptValue::U64(1))],
+		};
+		let remote_vrf = SyncCryptoStore::sr25519_vrf_sign(&keystore, SR25519, &public, transcript()).unwrap();
+		let local_vrf = SyncCryptoStore::sr25519_vrf_sign(local, SR25519, &public, transcript()).unwrap();
+		assert_eq!(remote_vrf.output, local_vrf.output);
+		let unknown = VRFTranscriptData { label: b"unknown", items: vec![] };
+		assert!(SyncCryptoStore::sr25519_vrf_sign(&keystore, SR25519, &public, unknown).is_err());
+
+		let (pair, _) = sr25519::Pair::generate();
+		let suri = format!("0x{}", hex::encode(pair.to_raw_vec()));
+		SyncCryptoStore::insert_unknown(&keystore, SR25519, &suri, pair.public().as_ref()).unwrap();
+		assert!(SyncCryptoStore::has_keys(&keystore, &[(pair.public().to_vec(), SR25519)]));
+		assert!(!SyncCryptoStore::has_keys(&keystore, &[(sr25519::Public::default().to_vec(), SR25519)]));
+	}
+
+	#[test]
+	fn parses_signer_addresses() {
+		assert_eq!(
+			"unix:/run/signer.sock".parse(),
+			Ok(RemoteSigner::Unix("/run/signer.sock".into())),
+		);
+		assert_eq!(
+			"http://127.0.0.1:9000/sign".parse(),
+			Ok(RemoteSigner::Http {
+				tls: false,
+				authority: "127.0.0.1:9000".into(),
+				path: "/sign".into(),
+				token: None,
+			}),
+		);
+		assert_eq!(
+			"https://signer.example:9000".parse(),
+			Ok(RemoteSigner::Http {
+				tls: true,
+				authority: "signer.example:9000".into(),
+				path: "/".into(),
+				token: None,
+			}),
+		);
+		assert_eq!(
+			"http://localhost:9000".parse::<RemoteSigner>().unwrap().to_string(),
+			"http://localhost:9000/",
+		);
+		assert!("http://[::1]:9000".parse::<RemoteSigner>().is_ok());
+		assert!("http://signer.example:9000".parse::<RemoteSigner>().is_err());
+		assert!("http://10.0.0.1:9000".parse::<RemoteSigner>().is_err());
+		assert!("ws://localhost:9000".parse::<RemoteSigner>().is_err());
+
+		// The token is never written out.
+		let token = SignerToken::new("secret\n").unwrap();
+		let signer = "https://signer.example:9000".parse::<RemoteSigner>().unwrap()
+			.with_token(token.clone())
+			.unwrap();
+		assert!(!signer.to_string().contains("secret"));
+		assert!(!format!("{:?}", signer).contains("secret"));
+		assert!(RemoteSigner::Unix("/run/signer.sock".into()).with_token(token).is_err());
+		assert!(SignerToken::new(" \n").is_err());
+		assert!(SignerToken::new("with space").is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn unix_signer() {
+		let dir = tempfile::TempDir::new().unwrap();
+		let local = Arc::new(LocalKeystore::in_memory());
+		let path = dir.path().join("signer.sock");
+		let server = SignerServer::bind_unix(&path, local.clone()).unwrap();
+		assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+		check_keystore(RemoteKeystore::new(server.signer()).unwrap(), &local);
+	}
+
+	#[test]
+	fn http_signer() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		let token = SignerToken::new("secret").unwrap();
+		let server = SignerServer::bind_http("127.0.0.1:0", local.clone(), token).unwrap();
+		check_keystore(RemoteKeystore::new(server.signer()).unwrap(), &local);
+	}
+
+	#[test]
+	fn http_signer_requires_token() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		let token = SignerToken::new("secret").unwrap();
+		let server = SignerServer::bind_http("127.0.0.1:0", local.clone(), token).unwrap();
+		let (authority, path) = match server.signer() {
+			RemoteSigner::Http { authority, path, .. } => (authority, path),
+			RemoteSigner::Unix(_) => unreachable!(),
+		};
+		let signer = |token: Option<&str>| RemoteSigner::Http {
+			tls: false,
+			authority: authority.clone(),
+			path: path.clone(),
+			token: token.map(|token| SignerToken::new(token).unwrap()),
+		};
+
+		for unauthorized in vec![signer(None), signer(Some("wrong"))] {
+			let keystore = RemoteKeystore::new(unauthorized).unwrap();
+			assert!(matches!(
+				SyncCryptoStore::sr25519_generate_new(&keystore, SR25519, None),
+				Err(TraitError::Unavailable),
+			));
+		}
+		assert!(SyncCryptoStore::sr25519_public_keys(&*local, SR25519).is_empty());
+	}
+
+	#[test]
+	fn idle_connection_does_not_block_signer() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		let token = SignerToken::new("secret").unwrap();
+		let server = SignerServer::bind_http("127.0.0.1:0", local.clone(), token).unwrap();
+		let authority = match server.signer() {
+			RemoteSigner::Http { ref authority, .. } => authority.clone(),
+			RemoteSigner::Unix(_) => unreachable!(),
+		};
+		let _idle = TcpStream::connect(&authority).unwrap();
+		check_keystore(RemoteKeystore::new(server.signer()).unwrap(), &local);
+	}
+
+	#[test]
+	fn unavailable_signer() {
+		let keystore = RemoteKeystore::new(
+			"unix:/nonexistent/signer.sock".parse().unwrap(),
+		).unwrap();
+		assert!(SyncCryptoStore::sr25519_public_keys(&keystore, SR25519).is_empty());
+		assert!(matches!(
+			SyncCryptoStore::sr25519_generate_new(&keystore, SR25519, None),
+			Err(TraitError::Unavailable),
+		));
+	}
+}
@@ -0,0 +1,42 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+
+//! Feeds arbitrary bytes to the decoders of the legacy substream messages, sync handshakes,
+//! block announces, and transactions notifications.
+//!
+//! # Running
+//!
+//! From `client/network`, run `cargo +nightly fuzz run legacy_message`. The fuzzer starts from the
+//! inputs in `fuzz/corpus/legacy_message`, which are recorded from the traffic of the
+//! `client/network/test` networks by its ignored `record_fuzzing_corpus` test.
+//!
+//! # Debugging a crash
+//!
+//! The input that made the fuzzer crash is written to `fuzz/artifacts/legacy_message`. Reproduce
+//! the crash by passing that file to `cargo +nightly fuzz run legacy_message`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sc_network::fuzzing;
+use substrate_test_runtime_client::runtime::Block;
+
+fuzz_target!(|data: &[u8]| {
+	fuzzing::legacy_message::<Block>(data);
+});
@@ -18,10 +18,11 @@
 
 use crate::{
 	config::{ProtocolId, Role},
-	bitswap::Bitswap,
+	bitswap::{Bitswap, BitswapRequestFailure, Cid},
 	discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
 	protocol::{message::Roles, CustomMessageOutcome, NotificationsSink, Protocol},
 	peer_info, request_responses, light_client_requests,
+	traffic::TrafficCounters,
 	ObservedRole, DhtEvent, ExHashT,
 };
 
@@ -42,6 +43,7 @@ use std::{
 	borrow::Cow,
 	collections::{HashSet, VecDeque},
 	iter,
+	sync::Arc,
 	task::{Context, Poll},
 	time::Duration,
 };
@@ -83,6 +85,16 @@ pub struct Behaviour<B: BlockT, H: ExHashT> {
 	/// [`request_responses::RequestResponsesBehaviour`].
 	#[behaviour(ignore)]
 	block_request_protocol_name: String,
+
+	/// Protocol name used to send out state requests via
+	/// [`request_responses::RequestResponsesBehaviour`].
+	#[behaviour(ignore)]
+	state_request_protocol_name: String,
+
+	/// Protocol name used to send out warp sync requests via
+	/// [`request_responses::RequestResponsesBehaviour`], if warp sync is supported.
+	#[behaviour(ignore)]
+	warp_sync_protocol_name: Option<String>,
 }
 
 /// Event generated by `Behaviour`.
@@ -186,15 +198,27 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		light_client_request_sender: light_client_requests::sender::LightClientRequestSender<B>,
 		disco_config: DiscoveryConfig,
 		block_request_protocol_config: request_responses::ProtocolConfig,
+		state_request_protocol_config: request_responses::ProtocolConfig,
 		bitswap: Option<Bitswap<B>>,
 		light_client_request_protocol_config: request_responses::ProtocolConfig,
+		warp_sync_protocol_config: Option<request_responses::ProtocolConfig>,
 		// All remaining request protocol configs.
 		mut request_response_protocols: Vec<request_responses::ProtocolConfig>,
+		traffic: Arc<TrafficCounters>,
 	) -> Result<Self, request_responses::RegisterError> {
 		// Extract protocol name and add to `request_response_protocols`.
 		let block_request_protocol_name = block_request_protocol_config.name.to_string();
 		request_response_protocols.push(block_request_protocol_config);
 
+		let state_request_protocol_name = state_request_protocol_config.name.to_string();
+		request_response_protocols.push(state_request_protocol_config);
+
+		let warp_sync_protocol_name = warp_sync_protocol_config.map(|config| {
+			let name = config.name.to_string();
+			request_response_protocols.push(config);
+			name
+		});
+
 		request_response_protocols.push(light_client_request_protocol_config);
 
 		Ok(Behaviour {
@@ -202,13 +226,17 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 			peer_info: peer_info::PeerInfoBehaviour::new(user_agent, local_public_key),
 			discovery: disco_config.finish(),
 			bitswap: bitswap.into(),
-			request_responses:
-				request_responses::RequestResponsesBehaviour::new(request_response_protocols.into_iter())?,
+			request_responses: request_responses::RequestResponsesBehaviour::new(
+				request_response_protocols.into_iter(),
+				traffic,
+			)?,
 			light_client_request_sender,
 			events: VecDeque::new(),
 			role,
 
 			block_request_protocol_name,
+			state_request_protocol_name,
+			warp_sync_protocol_name,
 		})
 	}
 
@@ -249,6 +277,11 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		self.peer_info.node(peer_id)
 	}
 
+	/// Returns the nodes we're connected to, along with the information about them.
+	pub fn connected_nodes(&self) -> impl Iterator<Item = (&PeerId, peer_info::Node)> {
+		self.peer_info.connected_nodes()
+	}
+
 	/// Initiates sending a request.
 	pub fn send_request(
 		&mut self,
@@ -261,6 +294,21 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		self.request_responses.send_request(target, protocol, request, pending_response, connect)
 	}
 
+	/// Initiates a bitswap request for the block identified by `cid`.
+	pub fn bitswap_request(
+		&mut self,
+		target: PeerId,
+		cid: Cid,
+		pending_response: oneshot::Sender<Result<Vec<u8>, BitswapRequestFailure>>,
+	) {
+		match self.bitswap.as_mut() {
+			Some(bitswap) => bitswap.request(target, cid, pending_response),
+			None => {
+				let _ = pending_response.send(Err(BitswapRequestFailure::Disabled));
+			}
+		}
+	}
+
 	/// Returns a shared reference to the user protocol.
 	pub fn user_protocol(&self) -> &Protocol<B, H> {
 		&self.substrate
@@ -336,6 +384,35 @@ Behaviour<B, H> {
 					&target, &self.block_request_protocol_name, buf, pending_response, IfDisconnected::ImmediateError,
 				);
 			},
+			CustomMessageOutcome::StateRequest { target, request, pending_response } => {
+				let mut buf = Vec::with_capacity(request.encoded_len());
+				if let Err(err) = request.encode(&mut buf) {
+					log::warn!(
+						target: "sync",
+						"Failed to encode state request {:?}: {:?}",
+						request, err
+					);
+					return
+				}
+
+				self.request_responses.send_request(
+					&target, &self.state_request_protocol_name, buf, pending_response,
+					IfDisconnected::ImmediateError,
+				);
+			},
+			CustomMessageOutcome::WarpSyncRequest { target, request, pending_response } => {
+				match &self.warp_sync_protocol_name {
+					Some(name) => self.request_responses.send_request(
+						&target, name, codec::Encode::encode(&request), pending_response,
+						IfDisconnected::ImmediateError,
+					),
+					None => log::warn!(
+						target: "sync",
+						"Dropping warp sync request to {}: warp sync protocol not registered",
+						target,
+					),
+				}
+			},
 			CustomMessageOutcome::NotificationStreamOpened { remote, protocol, roles, notifications_sink } => {
 				let role = reported_roles_to_observed_role(&self.role, &remote, roles);
 				self.events.push_back(BehaviourOut::NotificationStreamOpened {
@@ -14,21 +14,29 @@
 // You should have received a copy of the GNU General Public License
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
-//! Bitswap server for substrate.
+//! Bitswap server and client for substrate.
 //!
-//! Allows querying transactions by hash over standard bitswap protocol
+//! Allows querying transactions, state trie nodes and block bodies by hash over standard bitswap
+//! protocol, and fetching blocks from other nodes.
 //! Only supports bitswap 1.2.0.
-//! CID is expected to reference 256-bit Blake2b transaction hash.
+//! CID is expected to reference the 256-bit Blake2b hash of a transaction, a trie node or a
+//! SCALE-encoded block body. Trie nodes are only found if the database of the client counts
+//! references, since other databases don't store them under their hash alone.
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque, hash_map::Entry};
+use std::convert::TryFrom;
 use std::io;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use cid::Version;
+use cid::multihash::{Code, MultihashDigest};
 use codec::Encode;
 use core::pin::Pin;
-use futures::Future;
+use futures::{Future, FutureExt};
+use futures::channel::oneshot;
 use futures::io::{AsyncRead, AsyncWrite};
+use futures_timer::Delay;
 use libp2p::core::{
 	connection::ConnectionId, Multiaddr, PeerId,
 	upgrade, InboundUpgrade, OutboundUpgrade, UpgradeInfo,
@@ -40,13 +48,19 @@ use libp2p::swarm::{
 use log::{error, debug, trace};
 use prost::Message;
 use sp_runtime::traits::{Block as BlockT};
-use unsigned_varint::{encode as varint_encode};
+use unsigned_varint::{decode as varint_decode, encode as varint_encode};
+use wasm_timer::Instant;
 use crate::chain::Client;
 use crate::schema::bitswap::{
 	Message as BitswapMessage,
-	message::{wantlist::WantType, Block as MessageBlock, BlockPresenceType, BlockPresence},
+	message::{
+		wantlist::{Entry as WantlistEntry, WantType}, Block as MessageBlock, BlockPresenceType,
+		BlockPresence, Wantlist,
+	},
 };
 
+pub use cid::Cid;
+
 const LOG_TARGET: &str = "bitswap";
 
 // Undocumented, but according to JS the bitswap messages have a max size of 512*1024 bytes
@@ -59,6 +73,10 @@ const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
 const MAX_RESPONSE_QUEUE: usize = 20;
 // Max number of blocks per wantlist
 const MAX_WANTED_BLOCKS: usize = 16;
+// Time after which a request without answer fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+// Interval at which requests are checked for timeouts.
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 const PROTOCOL_NAME: &'static [u8] = b"/ipfs/bitswap/1.2.0";
 
@@ -172,12 +190,70 @@ impl Prefix {
 		res.extend_from_slice(mh_len);
 		res
 	}
+
+	/// Parse a prefix encoded with `to_bytes`.
+	pub fn from_bytes(data: &[u8]) -> Result<Self, BitswapError> {
+		let (version, data) = varint_decode::u64(data)?;
+		let (codec, data) = varint_decode::u64(data)?;
+		let (mh_type, data) = varint_decode::u64(data)?;
+		let (mh_len, _) = varint_decode::u8(data)?;
+		Ok(Prefix {
+			version: Version::try_from(version)?,
+			codec,
+			mh_type,
+			mh_len,
+		})
+	}
+}
+
+/// Compute the CID of a received block from its prefix and data.
+///
+/// Since the multihash is computed locally, a block can only match a CID we asked for if its
+/// data is genuine.
+fn block_cid(block: &MessageBlock) -> Result<Cid, BitswapError> {
+	let prefix = Prefix::from_bytes(&block.prefix)?;
+	let code = Code::try_from(prefix.mh_type).map_err(cid::Error::from)?;
+	Ok(Cid::new(prefix.version, prefix.codec, code.digest(&block.data))?)
+}
+
+/// Build a message asking for the block identified by `cid`.
+fn want_message(cid: &Cid) -> BitswapMessage {
+	BitswapMessage {
+		wantlist: Some(Wantlist {
+			entries: vec![WantlistEntry {
+				block: cid.to_bytes(),
+				priority: 1,
+				cancel: false,
+				want_type: WantType::Block as i32,
+				send_dont_have: true,
+			}],
+			full: false,
+		}),
+		blocks: Default::default(),
+		payload: Default::default(),
+		block_presences: Default::default(),
+		pending_bytes: 0,
+	}
+}
+
+/// Block requested from a peer.
+struct PendingRequest {
+	/// When the request was sent.
+	started: Instant,
+	/// Where to send the outcome. Identical requests are only sent once.
+	senders: Vec<oneshot::Sender<Result<Vec<u8>, BitswapRequestFailure>>>,
 }
 
 /// Network behaviour that handles sending and receiving IPFS blocks.
 pub struct Bitswap<B> {
 	client: Arc<dyn Client<B>>,
 	ready_blocks: VecDeque<(PeerId, BitswapMessage)>,
+	/// Peers we are connected to.
+	connected: HashSet<PeerId>,
+	/// Blocks we asked for and haven't received yet.
+	pending_requests: HashMap<(PeerId, Cid), PendingRequest>,
+	/// Fires when pending requests should be checked for timeouts.
+	next_timeout_check: Delay,
 }
 
 impl<B: BlockT> Bitswap<B> {
@@ -186,7 +262,99 @@ impl<B: BlockT> Bitswap<B> {
 		Bitswap {
 			client,
 			ready_blocks: Default::default(),
+			connected: Default::default(),
+			pending_requests: Default::default(),
+			next_timeout_check: Delay::new(TIMEOUT_CHECK_INTERVAL),
+		}
+	}
+
+	/// Ask `target` for the block identified by `cid`.
+	///
+	/// The data, which is checked against the CID, or the reason of the failure is sent on
+	/// `pending_response`. `target` must support bitswap, as the connection is closed otherwise.
+	pub fn request(
+		&mut self,
+		target: PeerId,
+		cid: Cid,
+		pending_response: oneshot::Sender<Result<Vec<u8>, BitswapRequestFailure>>,
+	) {
+		if !self.connected.contains(&target) {
+			let _ = pending_response.send(Err(BitswapRequestFailure::NotConnected));
+			return;
+		}
+		let message = want_message(&cid);
+		match self.pending_requests.entry((target.clone(), cid)) {
+			Entry::Occupied(mut entry) => entry.get_mut().senders.push(pending_response),
+			Entry::Vacant(entry) => {
+				entry.insert(PendingRequest {
+					started: Instant::now(),
+					senders: vec![pending_response],
+				});
+				trace!(target: LOG_TARGET, "Requesting {:?} from {}", message, target);
+				self.ready_blocks.push_back((target, message));
+			}
+		}
+	}
+
+	/// Look up the data with the given hash: an indexed transaction, a state trie node or a
+	/// block body, in that order.
+	fn lookup(&self, hash: &B::Hash) -> Result<Option<Vec<u8>>, sp_blockchain::Error> {
+		if let Some(extrinsic) = self.client.extrinsic(hash)? {
+			return Ok(Some(extrinsic.encode()));
+		}
+		if let Some(node) = self.client.trie_node(hash)? {
+			return Ok(Some(node));
+		}
+		Ok(self.client.block_body_by_content_hash(hash)?.map(|body| body.encode()))
+	}
+
+	/// Answer the pending requests the blocks and block presences of `message` relate to.
+	fn on_response(&mut self, peer: &PeerId, message: &BitswapMessage) {
+		for block in &message.payload {
+			let cid = match block_cid(block) {
+				Ok(cid) => cid,
+				Err(e) => {
+					debug!(target: LOG_TARGET, "Bad block from {}: {}", peer, e);
+					continue;
+				}
+			};
+			match self.pending_requests.remove(&(peer.clone(), cid)) {
+				Some(request) => for sender in request.senders {
+					let _ = sender.send(Ok(block.data.clone()));
+				},
+				None => trace!(target: LOG_TARGET, "Unsolicited block {} from {}", cid, peer),
+			}
 		}
+		for presence in &message.block_presences {
+			if presence.r#type != BlockPresenceType::DontHave as i32 {
+				continue;
+			}
+			let cid = match Cid::read_bytes(presence.cid.as_slice()) {
+				Ok(cid) => cid,
+				Err(e) => {
+					trace!(target: LOG_TARGET, "Bad CID {:?}: {:?}", presence.cid, e);
+					continue;
+				}
+			};
+			if let Some(request) = self.pending_requests.remove(&(peer.clone(), cid)) {
+				for sender in request.senders {
+					let _ = sender.send(Err(BitswapRequestFailure::DontHave));
+				}
+			}
+		}
+	}
+
+	/// Fail all the requests sent to `peer` with `failure`.
+	fn fail_requests_to(&mut self, peer: &PeerId, failure: BitswapRequestFailure) {
+		self.pending_requests.retain(|(target, _), request| {
+			if target != peer {
+				return true;
+			}
+			for sender in request.senders.drain(..) {
+				let _ = sender.send(Err(failure.clone()));
+			}
+			false
+		});
 	}
 }
 
@@ -202,10 +370,13 @@ impl<B: BlockT> NetworkBehaviour for Bitswap<B> {
 		Vec::new()
 	}
 
-	fn inject_connected(&mut self, _peer: &PeerId) {
+	fn inject_connected(&mut self, peer: &PeerId) {
+		self.connected.insert(peer.clone());
 	}
 
-	fn inject_disconnected(&mut self, _peer: &PeerId) {
+	fn inject_disconnected(&mut self, peer: &PeerId) {
+		self.connected.remove(peer);
+		self.fail_requests_to(peer, BitswapRequestFailure::ConnectionClosed);
 	}
 
 	fn inject_event(&mut self, peer: PeerId, _connection: ConnectionId, message: HandlerEvent) {
@@ -214,6 +385,11 @@ impl<B: BlockT> NetworkBehaviour for Bitswap<B> {
 			HandlerEvent::Request(msg) => msg,
 		};
 		trace!(target: LOG_TARGET, "Received request: {:?} from {}", request, peer);
+		self.on_response(&peer, &request);
+		let is_response = !request.payload.is_empty() || !request.block_presences.is_empty();
+		if request.wantlist.is_none() && is_response {
+			return;
+		}
 		if self.ready_blocks.len() > MAX_RESPONSE_QUEUE {
 			debug!(target: LOG_TARGET, "Ignored request: queue is full");
 			return;
@@ -257,15 +433,15 @@ impl<B: BlockT> NetworkBehaviour for Bitswap<B> {
 			}
 			let mut hash = B::Hash::default();
 			hash.as_mut().copy_from_slice(&cid.hash().digest()[0..32]);
-			let extrinsic = match self.client.extrinsic(&hash) {
-				Ok(ex) => ex,
+			let data = match self.lookup(&hash) {
+				Ok(data) => data,
 				Err(e) => {
-					error!(target: LOG_TARGET, "Error retrieving extrinsic {}: {}", hash, e);
+					error!(target: LOG_TARGET, "Error retrieving data for {}: {}", hash, e);
 					None
 				}
 			};
-			match extrinsic {
-				Some(extrinsic) => {
+			match data {
+				Some(data) => {
 					trace!(target: LOG_TARGET, "Found CID {:?}, hash {:?}", cid, hash);
 					if entry.want_type == WantType::Block as i32 {
 						let prefix = Prefix {
@@ -276,7 +452,7 @@ impl<B: BlockT> NetworkBehaviour for Bitswap<B> {
 						};
 						response.payload.push(MessageBlock {
 							prefix: prefix.to_bytes(),
-							data: extrinsic.encode(),
+							data,
 						});
 					} else {
 						response.block_presences.push(BlockPresence {
@@ -300,12 +476,26 @@ impl<B: BlockT> NetworkBehaviour for Bitswap<B> {
 		self.ready_blocks.push_back((peer, response));
 	}
 
-	fn poll(&mut self, _ctx: &mut Context, _: &mut impl PollParameters) -> Poll<
+	fn poll(&mut self, cx: &mut Context, _: &mut impl PollParameters) -> Poll<
 		NetworkBehaviourAction<
 			<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent,
 			Self::OutEvent,
 		>,
 	> {
+		while let Poll::Ready(()) = self.next_timeout_check.poll_unpin(cx) {
+			self.next_timeout_check.reset(TIMEOUT_CHECK_INTERVAL);
+			let now = Instant::now();
+			self.pending_requests.retain(|(peer, cid), request| {
+				if now.duration_since(request.started) < REQUEST_TIMEOUT {
+					return true;
+				}
+				debug!(target: LOG_TARGET, "Request for {} to {} timed out", cid, peer);
+				for sender in request.senders.drain(..) {
+					let _ = sender.send(Err(BitswapRequestFailure::Timeout));
+				}
+				false
+			});
+		}
 		if let Some((peer_id, message)) = self.ready_blocks.pop_front() {
 			return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
 				peer_id: peer_id.clone(),
@@ -330,9 +520,67 @@ pub enum BitswapError {
 	Client(sp_blockchain::Error),
 	/// Error parsing CID
 	BadCid(cid::Error),
+	/// Error parsing a CID prefix.
+	BadPrefix(varint_decode::Error),
 	/// Packet read error.
 	Read(upgrade::ReadOneError),
 	/// Error sending response.
 	#[display(fmt = "Failed to send response.")]
 	SendResponse,
 }
+
+/// Reason why a bitswap request failed.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum BitswapRequestFailure {
+	/// Bitswap is disabled on this node.
+	#[display(fmt = "Bitswap is disabled.")]
+	Disabled,
+	/// We aren't connected to the target.
+	#[display(fmt = "Not connected to the peer.")]
+	NotConnected,
+	/// The connection closed before the block was received.
+	#[display(fmt = "Connection closed before the block was received.")]
+	ConnectionClosed,
+	/// The peer doesn't have the block.
+	#[display(fmt = "Block not found on the peer.")]
+	DontHave,
+	/// The peer didn't send the block in time.
+	#[display(fmt = "Request timed out.")]
+	Timeout,
+}
+
+impl std::error::Error for BitswapRequestFailure {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prefix_roundtrip() {
+		let prefix = Prefix {
+			version: Version::V1,
+			codec: 0x55,
+			mh_type: u64::from(Code::Blake2b256),
+			mh_len: 32,
+		};
+		assert_eq!(Prefix::from_bytes(&prefix.to_bytes()).unwrap(), prefix);
+	}
+
+	#[test]
+	fn block_cid_authenticates_data() {
+		let data = b"trie node".to_vec();
+		let cid = Cid::new_v1(0x55, Code::Blake2b256.digest(&data));
+		let prefix = Prefix {
+			version: cid.version(),
+			codec: cid.codec(),
+			mh_type: cid.hash().code(),
+			mh_len: cid.hash().size(),
+		};
+
+		let block = MessageBlock { prefix: prefix.to_bytes(), data };
+		assert_eq!(block_cid(&block).unwrap(), cid);
+
+		let block = MessageBlock { prefix: prefix.to_bytes(), data: b"forged".to_vec() };
+		assert_ne!(block_cid(&block).unwrap(), cid);
+	}
+}
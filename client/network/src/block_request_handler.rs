@@ -101,11 +101,14 @@ impl <B: BlockT> BlockRequestHandler<B> {
 		}
 	}
 
-	fn handle_request(
+	pub(crate) fn handle_request(
 		&self,
 		payload: Vec<u8>,
 		pending_response: oneshot::Sender<OutgoingResponse>
 	) -> Result<(), HandleRequestError> {
+		#[cfg(feature = "fuzzing")]
+		crate::fuzzing::record("block_request", &payload);
+
 		let request = crate::schema::v1::BlockRequest::decode(&payload[..])?;
 
 		let from_block_id = match request.from_block.ok_or(HandleRequestError::MissingFromField)? {
@@ -208,7 +211,7 @@ impl <B: BlockT> BlockRequestHandler<B> {
 }
 
 #[derive(derive_more::Display, derive_more::From)]
-enum HandleRequestError {
+pub(crate) enum HandleRequestError {
 	#[display(fmt = "Failed to decode request: {}.", _0)]
 	DecodeProto(prost::DecodeError),
 	#[display(fmt = "Failed to encode response: {}.", _0)]
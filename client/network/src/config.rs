@@ -28,7 +28,9 @@ pub use crate::request_responses::{
 	OutgoingResponse,
 	ProtocolConfig as RequestResponseConfig,
 };
+pub use ip_network::IpNetwork;
 pub use libp2p::{identity, core::PublicKey, wasm_ext::ExtTransport, build_multiaddr};
+pub use crate::warp_sync::{StateImport, WarpSyncFragment, WarpSyncProvider};
 
 // Note: this re-export shouldn't be part of the public API of the crate and will be removed in
 // the future.
@@ -52,7 +54,7 @@ use std::{
 	error::Error,
 	fs,
 	io::{self, Write},
-	net::Ipv4Addr,
+	net::{IpAddr, Ipv4Addr},
 	path::{Path, PathBuf},
 	str,
 	sync::Arc,
@@ -112,6 +114,14 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// requests.
 	pub block_request_protocol_config: RequestResponseConfig,
 
+	/// Request response configuration for the state request protocol.
+	///
+	/// Can be constructed either via [`state_request_handler::generate_protocol_config`] allowing
+	/// outgoing but not incoming requests, or constructed via
+	/// [`state_request_handler::StateRequestHandler::new`] allowing both outgoing and incoming
+	/// requests.
+	pub state_request_protocol_config: RequestResponseConfig,
+
 	/// Request response configuration for the light client request protocol.
 	///
 	/// Can be constructed either via [`light_client_requests::generate_protocol_config`] allowing
@@ -119,6 +129,22 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// [`light_client_requests::handler::LightClientRequestHandler::new`] allowing both outgoing
 	/// and incoming requests.
 	pub light_client_request_protocol_config: RequestResponseConfig,
+
+	/// Verifier of warp sync proofs, together with the request response configuration for the
+	/// warp sync protocol.
+	///
+	/// Required for [`SyncMode::Warp`]. The configuration is registered like the one of the block
+	/// request protocol, so it should not be part of
+	/// [`NetworkConfiguration::request_response_protocols`] as well.
+	pub warp_sync: Option<(Arc<dyn WarpSyncProvider<B>>, RequestResponseConfig)>,
+
+	/// Trusted finalized block to sync from instead of genesis, e.g. one embedded in the chain
+	/// spec. Nothing below it is ever synced.
+	///
+	/// A fresh full node downloads the state of the checkpoint and imports it through the
+	/// provider of [`Params::warp_sync`], which is thus required. The finality gadget and the
+	/// consensus engine must have been set up to continue from the checkpoint beforehand.
+	pub sync_checkpoint: Option<WarpSyncFragment<B>>,
 }
 
 /// Role of the local node.
@@ -273,6 +299,8 @@ pub fn parse_str_addr(addr_str: &str) -> Result<(PeerId, Multiaddr), ParseErr> {
 }
 
 /// Splits a Multiaddress into a Multiaddress and PeerId.
+///
+/// QUIC addresses are accepted as long as `/quic` directly follows a `/udp/<port>` component.
 pub fn parse_addr(mut addr: Multiaddr)-> Result<(PeerId, Multiaddr), ParseErr> {
 	let who = match addr.pop() {
 		Some(multiaddr::Protocol::P2p(key)) => PeerId::from_multihash(key)
@@ -280,9 +308,32 @@ pub fn parse_addr(mut addr: Multiaddr)-> Result<(PeerId, Multiaddr), ParseErr> {
 		_ => return Err(ParseErr::PeerIdMissing),
 	};
 
+	let has_quic = addr.iter().any(|proto| matches!(proto, multiaddr::Protocol::Quic));
+	if has_quic && !is_quic_addr(&addr) {
+		return Err(ParseErr::InvalidQuicAddress);
+	}
+
 	Ok((who, addr))
 }
 
+/// Returns true if the address ends with `/udp/<port>/quic`, and can thus only be reached
+/// through the QUIC transport.
+///
+/// # Example
+///
+/// ```
+/// # use sc_network::{Multiaddr, config::is_quic_addr};
+/// assert!(is_quic_addr(&"/ip4/198.51.100.19/udp/30333/quic".parse::<Multiaddr>().unwrap()));
+/// assert!(!is_quic_addr(&"/ip4/198.51.100.19/tcp/30333".parse::<Multiaddr>().unwrap()));
+/// ```
+pub fn is_quic_addr(addr: &Multiaddr) -> bool {
+	let mut iter = addr.iter().skip_while(|proto| !matches!(proto, multiaddr::Protocol::Udp(_)));
+	matches!(
+		(iter.next(), iter.next(), iter.next()),
+		(Some(multiaddr::Protocol::Udp(_)), Some(multiaddr::Protocol::Quic), None)
+	)
+}
+
 /// Address of a node, including its identity.
 ///
 /// This struct represents a decoded version of a multiaddress that ends with `/p2p/<peerid>`.
@@ -295,6 +346,11 @@ pub fn parse_addr(mut addr: Multiaddr)-> Result<(PeerId, Multiaddr), ParseErr> {
 /// 	"/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".parse().unwrap();
 /// assert_eq!(addr.peer_id.to_base58(), "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV");
 /// assert_eq!(addr.multiaddr.to_string(), "/ip4/198.51.100.19/tcp/30333");
+///
+/// let addr: MultiaddrWithPeerId =
+/// 	"/ip4/198.51.100.19/udp/30333/quic/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"
+/// 		.parse().unwrap();
+/// assert!(addr.is_quic());
 /// ```
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(try_from = "String", into = "String")]
@@ -311,6 +367,11 @@ impl MultiaddrWithPeerId {
 		let proto = multiaddr::Protocol::P2p(From::from(self.peer_id.clone()));
 		self.multiaddr.clone().with(proto)
 	}
+
+	/// Returns true if the node has to be reached through the QUIC transport.
+	pub fn is_quic(&self) -> bool {
+		is_quic_addr(&self.multiaddr)
+	}
 }
 
 impl fmt::Display for MultiaddrWithPeerId {
@@ -353,6 +414,8 @@ pub enum ParseErr {
 	InvalidPeerId,
 	/// The peer ID is missing from the address.
 	PeerIdMissing,
+	/// The address contains `/quic` somewhere else than right after `/udp/<port>`.
+	InvalidQuicAddress,
 }
 
 impl fmt::Display for ParseErr {
@@ -361,6 +424,8 @@ impl fmt::Display for ParseErr {
 			ParseErr::MultiaddrParse(err) => write!(f, "{}", err),
 			ParseErr::InvalidPeerId => write!(f, "Peer id at the end of the address is invalid"),
 			ParseErr::PeerIdMissing => write!(f, "Peer id is missing from the address"),
+			ParseErr::InvalidQuicAddress =>
+				write!(f, "QUIC addresses must be of the form `.../udp/<port>/quic`"),
 		}
 	}
 }
@@ -371,6 +436,7 @@ impl std::error::Error for ParseErr {
 			ParseErr::MultiaddrParse(err) => Some(err),
 			ParseErr::InvalidPeerId => None,
 			ParseErr::PeerIdMissing => None,
+			ParseErr::InvalidQuicAddress => None,
 		}
 	}
 }
@@ -381,6 +447,69 @@ impl From<multiaddr::Error> for ParseErr {
 	}
 }
 
+/// Parses a string containing either an IP address or a subnet in CIDR notation.
+///
+/// # Example
+///
+/// ```
+/// # use sc_network::config::parse_subnet;
+/// let subnet = parse_subnet("198.51.100.0/24").unwrap();
+/// assert!(subnet.contains("198.51.100.19".parse::<std::net::IpAddr>().unwrap()));
+/// assert_eq!(parse_subnet("198.51.100.19").unwrap().to_string(), "198.51.100.19/32");
+/// ```
+pub fn parse_subnet(subnet_str: &str) -> Result<IpNetwork, InvalidSubnet> {
+	if subnet_str.contains('/') {
+		return subnet_str.parse().map_err(|_| InvalidSubnet)
+	}
+
+	match subnet_str.parse::<IpAddr>().map_err(|_| InvalidSubnet)? {
+		IpAddr::V4(ip) => Ok(IpNetwork::from(ip)),
+		IpAddr::V6(ip) => Ok(IpNetwork::from(ip)),
+	}
+}
+
+/// Error returned by [`parse_subnet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidSubnet;
+
+impl fmt::Display for InvalidSubnet {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Expected an IP address or a subnet in CIDR notation")
+	}
+}
+
+impl std::error::Error for InvalidSubnet {}
+
+/// Nodes to refuse connections to and from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BanTarget {
+	/// A single node, identified by its `PeerId`.
+	Peer(PeerId),
+	/// Any node connecting from, or listening on, an IP address of the subnet.
+	Subnet(IpNetwork),
+}
+
+impl fmt::Display for BanTarget {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BanTarget::Peer(peer_id) => write!(f, "{}", peer_id.to_base58()),
+			BanTarget::Subnet(subnet) => write!(f, "{}", subnet),
+		}
+	}
+}
+
+impl FromStr for BanTarget {
+	type Err = InvalidSubnet;
+
+	/// Parses either a `PeerId`, an IP address or a subnet in CIDR notation.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.parse::<PeerId>() {
+			Ok(peer_id) => Ok(BanTarget::Peer(peer_id)),
+			Err(_) => parse_subnet(s).map(BanTarget::Subnet),
+		}
+	}
+}
+
 /// Network service configuration.
 #[derive(Clone, Debug)]
 pub struct NetworkConfiguration {
@@ -408,6 +537,18 @@ pub struct NetworkConfiguration {
 	pub transport: TransportConfig,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// How a node without any finalized block but genesis should sync the chain.
+	pub sync_mode: SyncMode,
+	/// Nodes we never connect to nor accept connections from.
+	///
+	/// More can be banned at runtime with [`NetworkWorker::ban`](crate::NetworkWorker::ban).
+	pub denied_peers: Vec<PeerId>,
+	/// Subnets whose addresses we never connect to nor accept connections from.
+	///
+	/// Addresses dialed by DNS name are not matched.
+	pub denied_subnets: Vec<IpNetwork>,
+	/// Maximum rates at which data is sent to and received from other nodes.
+	pub bandwidth_limits: BandwidthLimits,
 
 	/// True if Kademlia random discovery should be enabled.
 	///
@@ -420,7 +561,9 @@ pub struct NetworkConfiguration {
 	/// Require iterative Kademlia DHT queries to use disjoint paths for increased resiliency in
 	/// the presence of potentially adversarial nodes.
 	pub kademlia_disjoint_query_paths: bool,
-	/// Enable serving block data over IPFS bitswap.
+	/// Enable serving transactions, state trie nodes and block bodies over IPFS bitswap, and
+	/// fetching blocks from other nodes with it. State trie nodes are only served if the client
+	/// can look them up by hash, which requires a database that counts references.
 	pub ipfs_server: bool,
 
 	/// Size of Yamux receive window of all substreams. `None` for the default (256kiB).
@@ -468,8 +611,13 @@ impl NetworkConfiguration {
 				enable_mdns: false,
 				allow_private_ipv4: true,
 				wasm_external_transport: None,
+				enable_quic: false,
 			},
 			max_parallel_downloads: 5,
+			sync_mode: SyncMode::Full,
+			denied_peers: Vec::new(),
+			denied_subnets: Vec::new(),
+			bandwidth_limits: BandwidthLimits::default(),
 			enable_dht_random_walk: true,
 			allow_non_globals_in_dht: false,
 			kademlia_disjoint_query_paths: false,
@@ -517,6 +665,45 @@ impl NetworkConfiguration {
 	}
 }
 
+/// How to sync the chain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SyncMode {
+	/// Download and import every block since genesis.
+	Full,
+	/// Download and verify the finality proofs of all authority set changes, and the state of
+	/// the latest finalized block, then jump to that block and continue from there. Falls back
+	/// to `Full` if no warp sync provider is configured.
+	Warp,
+}
+
+impl Default for SyncMode {
+	fn default() -> Self {
+		SyncMode::Full
+	}
+}
+
+/// Maximum rates at which data is exchanged with other nodes, in bytes per second.
+///
+/// The total limits apply to all the traffic of the transport, including the overhead of the
+/// encryption and multiplexing layers. The limits of each node apply to the decrypted traffic,
+/// since the node is only known once the connection is encrypted, and don't include the overhead
+/// of the encryption. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthLimits {
+	/// Maximum rate at which data is received from all the nodes combined.
+	pub total_inbound: Option<u64>,
+	/// Maximum rate at which data is sent to all the nodes combined.
+	pub total_outbound: Option<u64>,
+	/// Maximum rate at which data is received from each node.
+	///
+	/// Shared by all the connections to the same node.
+	pub per_peer_inbound: Option<u64>,
+	/// Maximum rate at which data is sent to each node.
+	///
+	/// Shared by all the connections to the same node.
+	pub per_peer_outbound: Option<u64>,
+}
+
 /// Configuration for a set of nodes.
 #[derive(Clone, Debug)]
 pub struct SetConfig {
@@ -579,6 +766,14 @@ pub enum TransportConfig {
 		/// This parameter exists whatever the target platform is, but it is expected to be set to
 		/// `Some` only when compiling for WASM.
 		wasm_external_transport: Option<wasm_ext::ExtTransport>,
+
+		/// If true, also open connections over QUIC. The first `/udp/<port>/quic` address of
+		/// [`NetworkConfiguration::listen_addresses`] is used as the local QUIC endpoint, or an
+		/// unspecified IPv4 address with a random port if there isn't any.
+		///
+		/// Requires compiling `sc-network` with the `quic` feature. Ignored, with a warning,
+		/// otherwise.
+		enable_quic: bool,
 	},
 
 	/// Only allow connections within the same process.
@@ -777,6 +972,40 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_parse_quic_addr() {
+		let peer_id = PeerId::random();
+		let addr: MultiaddrWithPeerId = format!("/ip6/2001:db8::1/udp/30333/quic/p2p/{}", peer_id)
+			.parse()
+			.unwrap();
+		assert!(addr.is_quic());
+		assert_eq!(addr.peer_id, peer_id);
+		assert_eq!(addr.multiaddr.to_string(), "/ip6/2001:db8::1/udp/30333/quic");
+
+		let addr: MultiaddrWithPeerId = format!("/ip4/198.51.100.19/tcp/30333/p2p/{}", peer_id)
+			.parse()
+			.unwrap();
+		assert!(!addr.is_quic());
+
+		assert!(matches!(
+			format!("/ip4/198.51.100.19/tcp/30333/quic/p2p/{}", peer_id)
+				.parse::<MultiaddrWithPeerId>(),
+			Err(ParseErr::InvalidQuicAddress)
+		));
+	}
+
+	#[test]
+	fn test_parse_ban_target() {
+		let peer_id = PeerId::random();
+		assert_eq!(peer_id.to_base58().parse(), Ok(BanTarget::Peer(peer_id)));
+
+		let subnet = "198.51.100.0/24".parse::<BanTarget>().unwrap();
+		assert_eq!(subnet.to_string(), "198.51.100.0/24");
+		assert_eq!("2001:db8::1".parse::<BanTarget>().unwrap().to_string(), "2001:db8::1/128");
+		assert_eq!("198.51.100.0/33".parse::<BanTarget>(), Err(InvalidSubnet));
+		assert_eq!("/ip4/198.51.100.19".parse::<BanTarget>(), Err(InvalidSubnet));
+	}
+
 	#[test]
 	fn test_secret_file() {
 		let tmp = tempdir_with_prefix("x");
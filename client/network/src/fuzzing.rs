@@ -0,0 +1,152 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Entry points of the fuzzers in `client/network/fuzz`.
+//!
+//! Each function feeds untrusted bytes to the code that the network runs on data received from
+//! remotes. Errors are expected and ignored: only panics, hangs and excessive allocations are
+//! bugs.
+//!
+//! The corpus of the fuzzers is recorded from real traffic: when the [`CORPUS_DIR_ENV`]
+//! environment variable is set, every message received by these code paths is written to the
+//! corpus directory of the fuzzer that covers it.
+
+use crate::block_request_handler::BlockRequestHandler;
+use crate::light_client_requests::handler::LightClientRequestHandler;
+use crate::protocol::{decode_block_response, decode_sync_handshake, message, NotificationsIn};
+use crate::schema::v1;
+use crate::state_request_handler::StateRequestHandler;
+use codec::{Decode, DecodeAll};
+use futures::{channel::oneshot, io::{AsyncRead, AsyncWrite}, prelude::*};
+use libp2p::{core::{InboundUpgrade, UpgradeInfo}, PeerId};
+use prost::Message as _;
+use sp_runtime::traits::Block as BlockT;
+use std::{fs, io, path::PathBuf, pin::Pin, task::{Context, Poll}};
+
+/// Environment variable naming the directory in which [`record`] writes the received messages,
+/// in one sub-directory per fuzzer.
+pub const CORPUS_DIR_ENV: &str = "SC_NETWORK_FUZZ_CORPUS";
+
+/// Maximum size of a notification accepted by `notifications_substream`.
+const MAX_NOTIFICATION_SIZE: u64 = 1024 * 1024;
+
+/// Decodes the data received on the legacy substream and on the block announces and
+/// transactions notifications substreams, as done in `protocol.rs`.
+pub fn legacy_message<B: BlockT>(data: &[u8]) {
+	let _ = <message::Message<B> as Decode>::decode(&mut &data[..]);
+	let _ = decode_sync_handshake::<B>(data);
+	let _ = message::Roles::decode_all(data);
+	let _ = message::BlockAnnounce::<B::Header>::decode(&mut &data[..]);
+	let _ = <message::Transactions<B::Extrinsic> as Decode>::decode(&mut &data[..]);
+}
+
+/// Passes a block request to the [`BlockRequestHandler`].
+pub fn block_request<B: BlockT>(handler: &BlockRequestHandler<B>, data: &[u8]) {
+	let (tx, _rx) = oneshot::channel();
+	let _ = handler.handle_request(data.to_vec(), tx);
+}
+
+/// Decodes a block response, as done by `Protocol` when receiving one during sync.
+pub fn block_response<B: BlockT>(data: &[u8]) {
+	if let Ok(response) = v1::BlockResponse::decode(data) {
+		let _ = decode_block_response::<B>(message::BlockAttributes::all(), response);
+	}
+}
+
+/// Passes a state request to the [`StateRequestHandler`].
+pub fn state_request<B: BlockT>(handler: &StateRequestHandler<B>, data: &[u8]) {
+	let (tx, _rx) = oneshot::channel();
+	let _ = handler.handle_request(data.to_vec(), tx);
+}
+
+/// Passes a light client request to the [`LightClientRequestHandler`].
+pub fn light_client_request<B: BlockT>(handler: &mut LightClientRequestHandler<B>, data: &[u8]) {
+	let _ = handler.handle_request(PeerId::random(), data.to_vec());
+}
+
+/// Runs the inbound side of a notifications substream on `data`: reads the handshake, accepts
+/// the substream, then reads notifications until the data is exhausted or invalid.
+pub fn notifications_substream(data: &[u8]) {
+	futures::executor::block_on(async move {
+		let upgrade = NotificationsIn::new("/fuzz/notifications/1", MAX_NOTIFICATION_SIZE);
+		let info = upgrade.protocol_info().next().expect("one protocol name; qed");
+		let socket = InputSocket(io::Cursor::new(data.to_vec()));
+		let (_handshake, mut substream) = match upgrade.upgrade_inbound(socket, info).await {
+			Ok(output) => output,
+			Err(_) => return,
+		};
+		substream.send_handshake(Vec::new());
+		while let Some(Ok(_)) = substream.next().await {}
+	})
+}
+
+/// Writes `data` to the corpus of the `target` fuzzer if [`CORPUS_DIR_ENV`] is set.
+///
+/// Files are named after the hash of their content, so recording the same message twice only
+/// writes it once.
+pub(crate) fn record(target: &str, data: &[u8]) {
+	let dir = match std::env::var_os(CORPUS_DIR_ENV) {
+		Some(dir) => PathBuf::from(dir).join(target),
+		None => return,
+	};
+	let name = format!("{:016x}", u64::from_le_bytes(sp_core::hashing::twox_64(data)));
+	if let Err(err) = fs::create_dir_all(&dir).and_then(|()| fs::write(dir.join(name), data)) {
+		log::warn!(target: "sub-libp2p", "Failed to record {} fuzzing input: {}", target, err);
+	}
+}
+
+/// Records the input of `notifications_substream` that makes a substream receive `handshake`
+/// and then `notification`, if any.
+pub(crate) fn record_notifications_substream(handshake: &[u8], notification: Option<&[u8]>) {
+	let mut data = Vec::new();
+	let mut buf = unsigned_varint::encode::usize_buffer();
+	data.extend_from_slice(unsigned_varint::encode::usize(handshake.len(), &mut buf));
+	data.extend_from_slice(handshake);
+	if let Some(notification) = notification {
+		data.extend_from_slice(unsigned_varint::encode::usize(notification.len(), &mut buf));
+		data.extend_from_slice(notification);
+	}
+	record("notifications_substream", &data);
+}
+
+/// Socket reading from the fuzzer input and discarding everything written to it.
+struct InputSocket(io::Cursor<Vec<u8>>);
+
+impl AsyncRead for InputSocket {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		_: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		Poll::Ready(io::Read::read(&mut self.0, buf))
+	}
+}
+
+impl AsyncWrite for InputSocket {
+	fn poll_write(self: Pin<&mut Self>, _: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		Poll::Ready(Ok(buf.len()))
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+}
@@ -248,25 +248,34 @@
 mod behaviour;
 mod chain;
 mod peer_info;
+mod peer_store;
 mod discovery;
 mod on_demand_layer;
 mod protocol;
 mod request_responses;
 mod schema;
 mod service;
+mod traffic;
 mod transport;
 mod utils;
 
 pub mod block_request_handler;
+pub mod state_request_handler;
 pub mod bitswap;
 pub mod light_client_requests;
 pub mod config;
 pub mod error;
 pub mod gossip;
 pub mod network_state;
+pub mod warp_sync;
+
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
 
 #[doc(inline)]
 pub use libp2p::{multiaddr, Multiaddr, PeerId};
+pub use peer_store::PeerStoreEntry;
 pub use protocol::{event::{DhtEvent, Event, ObservedRole}, sync::SyncState, PeerInfo};
 pub use service::{
 	NetworkService, NetworkWorker, RequestFailure, OutboundFailure, NotificationSender,
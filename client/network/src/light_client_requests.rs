@@ -166,7 +166,9 @@ mod tests {
 			reserved_only: false,
 			reserved_nodes: Default::default(),
 		};
-		sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig { sets: vec![cfg] })
+		sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig {
+			sets: vec![cfg],
+		})
 	}
 
 	pub fn dummy_header() -> sp_test_primitives::Header {
@@ -124,11 +124,14 @@ impl<B: Block> LightClientRequestHandler<B> {
 	}
 
 
-	fn handle_request(
+	pub(crate) fn handle_request(
 		&mut self,
 		peer: PeerId,
 		payload: Vec<u8>,
 	) -> Result<Vec<u8>, HandleRequestError> {
+		#[cfg(feature = "fuzzing")]
+		crate::fuzzing::record("light_client_request", &payload);
+
 		let request = schema::v1::light::Request::decode(&payload[..])?;
 
 		let response = match &request.request {
@@ -371,7 +374,7 @@ impl<B: Block> LightClientRequestHandler<B> {
 }
 
 #[derive(derive_more::Display, derive_more::From)]
-enum HandleRequestError {
+pub(crate) enum HandleRequestError {
 	#[display(fmt = "Failed to decode request: {}.", _0)]
 	DecodeProto(prost::DecodeError),
 	#[display(fmt = "Failed to encode response: {}.", _0)]
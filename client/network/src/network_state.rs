@@ -44,6 +44,20 @@ pub struct NetworkState {
 	pub not_connected_peers: HashMap<String, NotConnectedPeer>,
 	/// State of the peerset manager.
 	pub peerset: serde_json::Value,
+	/// Number of bytes exchanged on each protocol since the network started, by protocol name.
+	pub protocols_traffic: HashMap<String, ProtocolTraffic>,
+}
+
+/// Part of the `NetworkState` struct. Unstable.
+///
+/// Only accounts for the payloads of notifications, requests and responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolTraffic {
+	/// Number of bytes received.
+	pub bytes_in: u64,
+	/// Number of bytes sent.
+	pub bytes_out: u64,
 }
 
 /// Part of the `NetworkState` struct. Unstable.
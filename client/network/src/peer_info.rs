@@ -107,6 +107,13 @@ impl PeerInfoBehaviour {
 		self.nodes_info.get(peer_id).map(Node)
 	}
 
+	/// Returns the nodes we're connected to, along with the information about them.
+	pub fn connected_nodes(&self) -> impl Iterator<Item = (&PeerId, Node)> {
+		self.nodes_info.iter()
+			.filter(|(_, info)| info.info_expire.is_none())
+			.map(|(peer_id, info)| (peer_id, Node(info)))
+	}
+
 	/// Inserts a ping time in the cache. Has no effect if we don't have any entry for that node,
 	/// which shouldn't happen.
 	fn handle_ping_report(&mut self, peer_id: &PeerId, ping_time: Duration) {
@@ -141,6 +148,11 @@ impl<'a> Node<'a> {
 		&self.0.endpoints[0] // `endpoints` are non-empty by definition
 	}
 
+	/// Returns the endpoints of all the established connections to the peer.
+	pub fn endpoints(&self) -> impl Iterator<Item = &'a ConnectedPoint> {
+		self.0.endpoints.iter()
+	}
+
 	/// Returns the latest version information we know of.
 	pub fn client_version(&self) -> Option<&'a str> {
 		self.0.client_version.as_ref().map(|s| &s[..])
@@ -0,0 +1,317 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Peer store, persisting what we know about other nodes across restarts.
+//!
+//! The reputations kept by the peerset and the addresses we successfully dialed are written to a
+//! JSON file in the network configuration directory. On start, the reputations are restored
+//! after being decayed for the time the node was offline, and the addresses are handed to the
+//! discovery mechanism so that the node doesn't have to rely on the bootnodes only.
+
+use libp2p::{Multiaddr, PeerId};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file in the network configuration directory.
+const FILE_NAME: &str = "peers.json";
+/// Maximum number of nodes whose addresses we remember.
+const MAX_PEERS: usize = 1024;
+/// Maximum number of addresses we remember per node.
+const MAX_ADDRESSES_PER_PEER: usize = 4;
+/// Interval at which the peer store is written to disk.
+pub(crate) const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// What the peer store knows about a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerStoreEntry {
+	/// Identity of the node.
+	pub peer_id: PeerId,
+	/// Reputation of the node in the peerset.
+	pub reputation: i32,
+	/// Addresses we successfully dialed the node at.
+	pub addresses: Vec<Multiaddr>,
+}
+
+/// Content of the file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredPeers {
+	/// When the file was written, in seconds since the UNIX epoch.
+	saved_at: u64,
+	peers: Vec<StoredPeer>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredPeer {
+	peer_id: String,
+	reputation: i32,
+	addresses: Vec<String>,
+}
+
+/// Persistent store of the reputations and addresses of other nodes.
+#[derive(Debug)]
+pub(crate) struct PeerStore {
+	/// Path of the file, or `None` if nothing is persisted.
+	path: Option<PathBuf>,
+	/// Addresses we successfully dialed, per node.
+	addresses: HashMap<PeerId, Vec<Multiaddr>>,
+	/// Reputations read from the file, decayed for the time the node was offline.
+	restored_reputations: Vec<(PeerId, i32)>,
+}
+
+impl PeerStore {
+	/// Opens the peer store located in the given network configuration directory, if any.
+	///
+	/// A missing or malformed file results in an empty store.
+	pub(crate) fn open(net_config_path: Option<&Path>) -> Self {
+		let mut store = PeerStore {
+			path: net_config_path.map(|path| path.join(FILE_NAME)),
+			addresses: HashMap::new(),
+			restored_reputations: Vec::new(),
+		};
+
+		let stored = match store.path.as_ref().map(|path| read_stored_peers(path)) {
+			Some(Ok(Some(stored))) => stored,
+			Some(Ok(None)) | None => return store,
+			Some(Err(e)) => {
+				warn!(target: "sub-libp2p", "Failed to read the peer store, ignoring it: {}", e);
+				return store
+			},
+		};
+
+		let offline_secs = unix_time().saturating_sub(stored.saved_at);
+		for peer in stored.peers {
+			let peer_id = match peer.peer_id.parse::<PeerId>() {
+				Ok(peer_id) => peer_id,
+				Err(_) => {
+					debug!(target: "sub-libp2p", "Invalid PeerId in peer store: {}", peer.peer_id);
+					continue
+				},
+			};
+
+			let reputation = sc_peerset::decay_reputation(peer.reputation, offline_secs);
+			if reputation != 0 {
+				store.restored_reputations.push((peer_id.clone(), reputation));
+			}
+			for addr in peer.addresses.iter().filter_map(|addr| addr.parse().ok()) {
+				store.add_address(peer_id.clone(), addr);
+			}
+		}
+
+		debug!(
+			target: "sub-libp2p",
+			"Restored {} reputations and the addresses of {} nodes from the peer store",
+			store.restored_reputations.len(),
+			store.addresses.len(),
+		);
+		store
+	}
+
+	/// Takes the reputations read from the file, to be reported to the peerset.
+	pub(crate) fn take_restored_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		std::mem::take(&mut self.restored_reputations)
+	}
+
+	/// Returns the known addresses of all the nodes.
+	pub(crate) fn addresses(&self) -> impl Iterator<Item = (&PeerId, &Multiaddr)> {
+		self.addresses.iter().flat_map(|(peer_id, addrs)| addrs.iter().map(move |a| (peer_id, a)))
+	}
+
+	/// Remembers an address we successfully dialed a node at.
+	pub(crate) fn add_address(&mut self, peer_id: PeerId, addr: Multiaddr) {
+		if self.addresses.len() >= MAX_PEERS && !self.addresses.contains_key(&peer_id) {
+			return
+		}
+
+		let addrs = self.addresses.entry(peer_id).or_default();
+		if let Some(pos) = addrs.iter().position(|a| *a == addr) {
+			addrs.remove(pos);
+		} else if addrs.len() >= MAX_ADDRESSES_PER_PEER {
+			addrs.remove(0);
+		}
+		// The most recently dialed address goes last.
+		addrs.push(addr);
+	}
+
+	/// Returns all the entries, merging the given reputations with the known addresses.
+	pub(crate) fn entries(
+		&self,
+		reputations: impl IntoIterator<Item = (PeerId, i32)>,
+	) -> Vec<PeerStoreEntry> {
+		let mut entries = self.addresses.iter()
+			.map(|(peer_id, addrs)| (peer_id.clone(), (0, addrs.clone())))
+			.collect::<HashMap<_, _>>();
+		for (peer_id, reputation) in reputations {
+			entries.entry(peer_id).or_insert_with(|| (0, Vec::new())).0 = reputation;
+		}
+
+		let mut entries = entries.into_iter()
+			.map(|(peer_id, (reputation, addresses))| PeerStoreEntry {
+				peer_id,
+				reputation,
+				addresses,
+			})
+			.collect::<Vec<_>>();
+		entries.sort_by_cached_key(|entry| entry.peer_id.to_base58());
+		entries
+	}
+
+	/// Writes the given reputations and the known addresses to disk.
+	pub(crate) fn save(&self, reputations: impl IntoIterator<Item = (PeerId, i32)>) {
+		let path = match &self.path {
+			Some(path) => path,
+			None => return,
+		};
+
+		let stored = StoredPeers {
+			saved_at: unix_time(),
+			peers: self.entries(reputations).into_iter().map(|entry| StoredPeer {
+				peer_id: entry.peer_id.to_base58(),
+				reputation: entry.reputation,
+				addresses: entry.addresses.iter().map(|a| a.to_string()).collect(),
+			}).collect(),
+		};
+
+		if let Err(e) = write_stored_peers(path, &stored) {
+			warn!(target: "sub-libp2p", "Failed to write the peer store: {}", e);
+		}
+	}
+
+	/// Forgets all the known addresses and removes the file.
+	pub(crate) fn clear(&mut self) {
+		self.addresses.clear();
+		self.restored_reputations.clear();
+		if let Some(path) = &self.path {
+			match fs::remove_file(path) {
+				Ok(()) => {},
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+				Err(e) => warn!(target: "sub-libp2p", "Failed to remove the peer store: {}", e),
+			}
+		}
+	}
+}
+
+fn read_stored_peers(path: &Path) -> io::Result<Option<StoredPeers>> {
+	let content = match fs::read(path) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	serde_json::from_slice(&content).map(Some).map_err(Into::into)
+}
+
+fn write_stored_peers(path: &Path, stored: &StoredPeers) -> io::Result<()> {
+	// Write to a temporary file first, so that a crash never leaves a truncated store behind.
+	let tmp_path = path.with_extension("json.tmp");
+	fs::write(&tmp_path, serde_json::to_vec(stored)?)?;
+	fs::rename(&tmp_path, path)
+}
+
+fn unix_time() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn addr(port: u16) -> Multiaddr {
+		format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap()
+	}
+
+	#[test]
+	fn reputations_and_addresses_survive_restart() {
+		let dir = tempfile::tempdir().unwrap();
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+
+		let mut store = PeerStore::open(Some(dir.path()));
+		assert!(store.take_restored_reputations().is_empty());
+		store.add_address(peer1.clone(), addr(1));
+		store.save(vec![(peer2.clone(), -1000)]);
+
+		let mut store = PeerStore::open(Some(dir.path()));
+		let restored = store.take_restored_reputations();
+		assert_eq!(restored.len(), 1);
+		assert_eq!(restored[0].0, peer2);
+		// At most a few seconds of decay were applied.
+		assert!(restored[0].1 < -800 && restored[0].1 >= -1000);
+		assert_eq!(store.addresses().collect::<Vec<_>>(), vec![(&peer1, &addr(1))]);
+	}
+
+	#[test]
+	fn reputations_decay_while_offline() {
+		let dir = tempfile::tempdir().unwrap();
+		let stored = StoredPeers {
+			saved_at: unix_time() - 24 * 3600,
+			peers: vec![StoredPeer {
+				peer_id: PeerId::random().to_base58(),
+				reputation: i32::min_value(),
+				addresses: vec![addr(1).to_string()],
+			}],
+		};
+		write_stored_peers(&dir.path().join(FILE_NAME), &stored).unwrap();
+
+		let mut store = PeerStore::open(Some(dir.path()));
+		assert!(store.take_restored_reputations().is_empty());
+		assert_eq!(store.addresses().count(), 1);
+	}
+
+	#[test]
+	fn malformed_file_is_ignored() {
+		let dir = tempfile::tempdir().unwrap();
+		fs::write(dir.path().join(FILE_NAME), b"not json").unwrap();
+
+		let mut store = PeerStore::open(Some(dir.path()));
+		assert!(store.take_restored_reputations().is_empty());
+		assert_eq!(store.addresses().count(), 0);
+	}
+
+	#[test]
+	fn addresses_are_bounded() {
+		let mut store = PeerStore::open(None);
+		let peer_id = PeerId::random();
+		for port in 0..10 {
+			store.add_address(peer_id.clone(), addr(port));
+		}
+		store.add_address(peer_id.clone(), addr(7));
+
+		let addrs = store.addresses().map(|(_, a)| a.clone()).collect::<Vec<_>>();
+		assert_eq!(addrs, vec![addr(6), addr(8), addr(9), addr(7)]);
+	}
+
+	#[test]
+	fn clear_removes_everything() {
+		let dir = tempfile::tempdir().unwrap();
+		let peer_id = PeerId::random();
+
+		let mut store = PeerStore::open(Some(dir.path()));
+		store.add_address(peer_id.clone(), addr(1));
+		store.save(vec![(peer_id, 100)]);
+		store.clear();
+		assert!(store.entries(Vec::new()).is_empty());
+		assert!(!dir.path().join(FILE_NAME).exists());
+	}
+}
@@ -22,7 +22,9 @@ use crate::{
 	config::{self, ProtocolId, TransactionPool, TransactionImportFuture, TransactionImport},
 	error,
 	request_responses::RequestFailure,
+	traffic::TrafficCounters,
 	utils::{interval, LruHashSet},
+	warp_sync::{WarpProofRequest, WarpSyncFragment, WarpSyncProvider},
 };
 
 use bytes::{Bytes, BytesMut};
@@ -66,6 +68,8 @@ pub mod event;
 pub mod sync;
 
 pub use generic_proto::{NotificationsSink, Ready, NotifsHandlerError};
+#[cfg(feature = "fuzzing")]
+pub(crate) use generic_proto::NotificationsIn;
 
 /// Interval at which we perform time based maintenance
 const TICK_TIMEOUT: time::Duration = time::Duration::from_millis(1100);
@@ -252,12 +256,24 @@ struct Peer<B: BlockT, H: ExHashT> {
 		message::BlockRequest<B>,
 		oneshot::Receiver<Result<Vec<u8>, RequestFailure>>,
 	)>,
+	/// Current warp sync proof or state request, if any. Started by emitting
+	/// [`CustomMessageOutcome::WarpSyncRequest`] or [`CustomMessageOutcome::StateRequest`].
+	sync_request: Option<(SyncRequestKind, oneshot::Receiver<Result<Vec<u8>, RequestFailure>>)>,
 	/// Holds a set of transactions known to this peer.
 	known_transactions: LruHashSet<H>,
 	/// Holds a set of blocks known to this peer.
 	known_blocks: LruHashSet<B::Hash>,
 }
 
+/// Kind of a pending [`Peer::sync_request`].
+#[derive(Clone, Copy, Debug)]
+enum SyncRequestKind {
+	/// Warp sync proof request.
+	Warp,
+	/// State request.
+	State,
+}
+
 /// Info about a peer's known state.
 #[derive(Clone, Debug)]
 pub struct PeerInfo<B: BlockT> {
@@ -289,7 +305,7 @@ impl Default for ProtocolConfig {
 
 /// Handshake sent when we open a block announces substream.
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
-struct BlockAnnouncesHandshake<B: BlockT> {
+pub(crate) struct BlockAnnouncesHandshake<B: BlockT> {
 	/// Roles of the node.
 	roles: Roles,
 	/// Best block number.
@@ -316,6 +332,69 @@ impl<B: BlockT> BlockAnnouncesHandshake<B> {
 	}
 }
 
+/// Decodes the handshake received when a substream of the sync peerset is opened.
+///
+/// It can be either a `Status` message if received from the legacy substream, or a
+/// `BlockAnnouncesHandshake` if received from the block announces substream.
+pub(crate) fn decode_sync_handshake<B: BlockT>(
+	data: &[u8],
+) -> Result<BlockAnnouncesHandshake<B>, String> {
+	match <Message<B> as DecodeAll>::decode_all(data) {
+		Ok(GenericMessage::Status(status)) => Ok(BlockAnnouncesHandshake {
+			roles: status.roles,
+			best_number: status.best_number,
+			best_hash: status.best_hash,
+			genesis_hash: status.genesis_hash,
+		}),
+		Ok(msg) => Err(format!("expected Status message, but got {:?}", msg)),
+		Err(err) => <BlockAnnouncesHandshake<B> as DecodeAll>::decode_all(data)
+			.map_err(|err2| format!("{} & {}", err, err2)),
+	}
+}
+
+/// Converts the blocks of a block response to their SCALE-decoded form.
+///
+/// Bodies are only decoded if `fields` contains [`message::BlockAttributes::BODY`].
+pub(crate) fn decode_block_response<B: BlockT>(
+	fields: message::BlockAttributes,
+	response: crate::schema::v1::BlockResponse,
+) -> Result<Vec<message::BlockData<B>>, codec::Error> {
+	response.blocks.into_iter().map(|block_data| {
+		Ok(message::BlockData::<B> {
+			hash: Decode::decode(&mut block_data.hash.as_ref())?,
+			header: if !block_data.header.is_empty() {
+				Some(Decode::decode(&mut block_data.header.as_ref())?)
+			} else {
+				None
+			},
+			body: if fields.contains(message::BlockAttributes::BODY) {
+				Some(block_data.body.iter().map(|body| {
+					Decode::decode(&mut body.as_ref())
+				}).collect::<Result<Vec<_>, _>>()?)
+			} else {
+				None
+			},
+			receipt: if !block_data.message_queue.is_empty() {
+				Some(block_data.receipt)
+			} else {
+				None
+			},
+			message_queue: if !block_data.message_queue.is_empty() {
+				Some(block_data.message_queue)
+			} else {
+				None
+			},
+			justification: if !block_data.justification.is_empty() {
+				Some(block_data.justification)
+			} else if block_data.is_empty_justification {
+				Some(Vec::new())
+			} else {
+				None
+			},
+		})
+	}).collect()
+}
+
 /// Builds a SCALE-encoded "Status" message to send as handshake for the legacy protocol.
 fn build_status_message<B: BlockT>(
 	protocol_config: &ProtocolConfig,
@@ -347,6 +426,9 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		network_config: &config::NetworkConfiguration,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		metrics_registry: Option<&Registry>,
+		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+		sync_checkpoint: Option<WarpSyncFragment<B>>,
+		traffic: Arc<TrafficCounters>,
 	) -> error::Result<(Protocol<B, H>, sc_peerset::PeersetHandle, Vec<(PeerId, Multiaddr)>)> {
 		let info = chain.info();
 		let sync = ChainSync::new(
@@ -355,6 +437,9 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			&info,
 			block_announce_validator,
 			config.max_parallel_downloads,
+			network_config.sync_mode,
+			warp_sync_provider,
+			sync_checkpoint,
 		);
 
 		let boot_node_ids = {
@@ -450,9 +535,10 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				});
 			}
 
-			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig {
-				sets,
-			})
+			sc_peerset::Peerset::from_config_with_banned_peers(
+				sc_peerset::PeersetConfig { sets },
+				network_config.denied_peers.iter().cloned().collect(),
+			)
 		};
 
 		let transactions_protocol: Cow<'static, str> = Cow::from({
@@ -498,6 +584,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 						handshake_message.clone(),
 						s.max_notification_size
 					))),
+				traffic,
 			)
 		};
 
@@ -565,6 +652,16 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the non-zero reputations known to the peerset manager.
+	pub fn peerset_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.behaviour.peerset_reputations()
+	}
+
+	/// Resets all the reputations known to the peerset manager.
+	pub fn clear_peerset_reputations(&mut self) {
+		self.behaviour.clear_peerset_reputations()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.peers.values().count()
@@ -647,6 +744,9 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		who: PeerId,
 		data: BytesMut,
 	) -> CustomMessageOutcome<B> {
+		#[cfg(feature = "fuzzing")]
+		crate::fuzzing::record("legacy_message", &data);
+
 		let message = match <Message<B> as Decode>::decode(&mut &data[..]) {
 			Ok(message) => message,
 			Err(err) => {
@@ -739,42 +839,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		request: message::BlockRequest<B>,
 		response: crate::schema::v1::BlockResponse,
 	) -> CustomMessageOutcome<B> {
-		let blocks = response.blocks.into_iter().map(|block_data| {
-			Ok(message::BlockData::<B> {
-				hash: Decode::decode(&mut block_data.hash.as_ref())?,
-				header: if !block_data.header.is_empty() {
-					Some(Decode::decode(&mut block_data.header.as_ref())?)
-				} else {
-					None
-				},
-				body: if request.fields.contains(message::BlockAttributes::BODY) {
-					Some(block_data.body.iter().map(|body| {
-						Decode::decode(&mut body.as_ref())
-					}).collect::<Result<Vec<_>, _>>()?)
-				} else {
-					None
-				},
-				receipt: if !block_data.message_queue.is_empty() {
-					Some(block_data.receipt)
-				} else {
-					None
-				},
-				message_queue: if !block_data.message_queue.is_empty() {
-					Some(block_data.message_queue)
-				} else {
-					None
-				},
-				justification: if !block_data.justification.is_empty() {
-					Some(block_data.justification)
-				} else if block_data.is_empty_justification {
-					Some(Vec::new())
-				} else {
-					None
-				},
-			})
-		}).collect::<Result<Vec<_>, codec::Error>>();
-
-		let blocks = match blocks {
+		let blocks = match decode_block_response(request.fields, response) {
 			Ok(blocks) => blocks,
 			Err(err) => {
 				debug!(target: "sync", "Failed to decode block response from {}: {}", peer_id, err);
@@ -830,10 +895,62 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		}
 	}
 
+	/// Must be called in response to a [`CustomMessageOutcome::WarpSyncRequest`] being emitted.
+	fn on_warp_sync_response(&mut self, peer_id: PeerId, response: Vec<u8>) {
+		let result = self.sync.on_warp_sync_data(&peer_id, &response);
+		self.on_sync_data_result(result);
+	}
+
+	/// Must be called in response to a [`CustomMessageOutcome::StateRequest`] being emitted.
+	fn on_state_response(&mut self, peer_id: PeerId, response: Vec<u8>) {
+		let response = match crate::schema::v1::StateResponse::decode(&response[..]) {
+			Ok(response) => response,
+			Err(e) => {
+				trace!(target: "sync", "Failed to decode state response from peer {:?}: {:?}.", peer_id, e);
+				self.peerset_handle.report_peer(peer_id.clone(), rep::BAD_MESSAGE);
+				self.behaviour.disconnect_peer(&peer_id, HARDCODED_PEERSETS_SYNC);
+				return
+			}
+		};
+		let result = self.sync.on_state_data(&peer_id, response);
+		self.on_sync_data_result(result);
+	}
+
+	/// Emit the block requests resulting from a warp sync or state response, or report the peer.
+	fn on_sync_data_result(
+		&mut self,
+		result: Result<
+			Vec<Result<(PeerId, message::BlockRequest<B>), sync::BadPeer>>,
+			sync::BadPeer,
+		>,
+	) {
+		match result {
+			Ok(requests) => {
+				for request in requests {
+					match request {
+						Ok((id, req)) => {
+							let event = self.prepare_block_request(id, req);
+							self.pending_messages.push_back(event);
+						}
+						Err(sync::BadPeer(id, repu)) => {
+							self.behaviour.disconnect_peer(&id, HARDCODED_PEERSETS_SYNC);
+							self.peerset_handle.report_peer(id, repu);
+						}
+					}
+				}
+			},
+			Err(sync::BadPeer(id, repu)) => {
+				self.behaviour.disconnect_peer(&id, HARDCODED_PEERSETS_SYNC);
+				self.peerset_handle.report_peer(id, repu);
+			}
+		}
+	}
+
 	/// Perform time based maintenance.
 	///
 	/// > **Note**: This method normally doesn't have to be called except for testing purposes.
 	pub fn tick(&mut self) {
+		self.sync.reassign_stalled_downloads();
 		self.report_metrics()
 	}
 
@@ -911,6 +1028,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				best_number: status.best_number
 			},
 			block_request: None,
+			sync_request: None,
 			known_transactions: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_TRANSACTIONS)
 				.expect("Constant is nonzero")),
 			known_blocks: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_BLOCKS)
@@ -1467,6 +1585,82 @@ fn prepare_block_request<B: BlockT, H: ExHashT>(
 	}
 }
 
+fn prepare_warp_sync_request<B: BlockT, H: ExHashT>(
+	peers: &mut HashMap<PeerId, Peer<B, H>>,
+	who: PeerId,
+	request: WarpProofRequest<B>,
+) -> CustomMessageOutcome<B> {
+	let (tx, rx) = oneshot::channel();
+
+	if let Some(ref mut peer) = peers.get_mut(&who) {
+		peer.sync_request = Some((SyncRequestKind::Warp, rx));
+	}
+
+	CustomMessageOutcome::WarpSyncRequest {
+		target: who,
+		request,
+		pending_response: tx,
+	}
+}
+
+fn prepare_state_request<B: BlockT, H: ExHashT>(
+	peers: &mut HashMap<PeerId, Peer<B, H>>,
+	who: PeerId,
+	request: crate::schema::v1::StateRequest,
+) -> CustomMessageOutcome<B> {
+	let (tx, rx) = oneshot::channel();
+
+	if let Some(ref mut peer) = peers.get_mut(&who) {
+		peer.sync_request = Some((SyncRequestKind::State, rx));
+	}
+
+	CustomMessageOutcome::StateRequest {
+		target: who,
+		request,
+		pending_response: tx,
+	}
+}
+
+/// Report and disconnect a peer after a failed block or warp sync request.
+fn on_sync_request_failure(
+	peerset_handle: &sc_peerset::PeersetHandle,
+	behaviour: &mut GenericProto,
+	id: &PeerId,
+	e: RequestFailure,
+) {
+	match e {
+		RequestFailure::Network(OutboundFailure::Timeout) => {
+			peerset_handle.report_peer(id.clone(), rep::TIMEOUT);
+			behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+		}
+		RequestFailure::Network(OutboundFailure::UnsupportedProtocols) => {
+			peerset_handle.report_peer(id.clone(), rep::BAD_PROTOCOL);
+			behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+		}
+		RequestFailure::Network(OutboundFailure::DialFailure) => {
+			behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+		}
+		RequestFailure::Refused => {
+			peerset_handle.report_peer(id.clone(), rep::REFUSED);
+			behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+		}
+		RequestFailure::Network(OutboundFailure::ConnectionClosed)
+		| RequestFailure::NotConnected => {
+			behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+		},
+		RequestFailure::UnknownProtocol => {
+			debug_assert!(false, "Sync request protocols should always be known.");
+		}
+		RequestFailure::Obsolete => {
+			debug_assert!(
+				false,
+				"Can not receive `RequestFailure::Obsolete` after dropping the \
+				 response receiver.",
+			);
+		}
+	}
+}
+
 /// Outcome of an incoming custom message.
 #[derive(Debug)]
 #[must_use]
@@ -1496,6 +1690,18 @@ pub enum CustomMessageOutcome<B: BlockT> {
 		request: crate::schema::v1::BlockRequest,
 		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
 	},
+	/// A new warp sync proof request must be emitted.
+	WarpSyncRequest {
+		target: PeerId,
+		request: WarpProofRequest<B>,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	},
+	/// A new state request must be emitted.
+	StateRequest {
+		target: PeerId,
+		request: crate::schema::v1::StateRequest,
+		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	},
 	/// Peer has a reported a new head of chain.
 	PeerNewBest(PeerId, NumberFor<B>),
 	/// Now connected to a new peer for syncing purposes.
@@ -1564,6 +1770,9 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 					Poll::Ready(Ok(Ok(resp))) => {
 						let (req, _) = peer.block_request.take().unwrap();
 
+						#[cfg(feature = "fuzzing")]
+						crate::fuzzing::record("block_response", &resp);
+
 						let protobuf_response = match crate::schema::v1::BlockResponse::decode(&resp[..]) {
 							Ok(proto) => proto,
 							Err(e) => {
@@ -1579,38 +1788,7 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 					Poll::Ready(Ok(Err(e))) => {
 						peer.block_request.take();
 						trace!(target: "sync", "Block request to peer {:?} failed: {:?}.", id, e);
-
-						match e {
-							RequestFailure::Network(OutboundFailure::Timeout) => {
-								self.peerset_handle.report_peer(id.clone(), rep::TIMEOUT);
-								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
-							}
-							RequestFailure::Network(OutboundFailure::UnsupportedProtocols) => {
-								self.peerset_handle.report_peer(id.clone(), rep::BAD_PROTOCOL);
-								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
-							}
-							RequestFailure::Network(OutboundFailure::DialFailure) => {
-								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
-							}
-							RequestFailure::Refused => {
-								self.peerset_handle.report_peer(id.clone(), rep::REFUSED);
-								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
-							}
-							RequestFailure::Network(OutboundFailure::ConnectionClosed)
-							| RequestFailure::NotConnected => {
-								self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
-							},
-							RequestFailure::UnknownProtocol => {
-								debug_assert!(false, "Block request protocol should always be known.");
-							}
-							RequestFailure::Obsolete => {
-								debug_assert!(
-									false,
-									"Can not receive `RequestFailure::Obsolete` after dropping the \
-									 response receiver.",
-								);
-							}
-						}
+						on_sync_request_failure(&self.peerset_handle, &mut self.behaviour, id, e);
 					},
 					Poll::Ready(Err(oneshot::Canceled)) => {
 						peer.block_request.take();
@@ -1630,6 +1808,41 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 			self.pending_messages.push_back(ev);
 		}
 
+		let mut finished_sync_requests = Vec::new();
+		for (id, peer) in self.peers.iter_mut() {
+			if let Peer { sync_request: Some((kind, pending_response)), .. } = peer {
+				let kind = *kind;
+				match pending_response.poll_unpin(cx) {
+					Poll::Ready(Ok(Ok(resp))) => {
+						peer.sync_request.take();
+						finished_sync_requests.push((id.clone(), kind, resp));
+					},
+					Poll::Ready(Ok(Err(e))) => {
+						peer.sync_request.take();
+						trace!(target: "sync", "{:?} request to peer {:?} failed: {:?}.", kind, id, e);
+						on_sync_request_failure(&self.peerset_handle, &mut self.behaviour, id, e);
+					},
+					Poll::Ready(Err(oneshot::Canceled)) => {
+						peer.sync_request.take();
+						trace!(
+							target: "sync",
+							"{:?} request to peer {:?} failed due to oneshot being canceled.",
+							kind,
+							id,
+						);
+						self.behaviour.disconnect_peer(id, HARDCODED_PEERSETS_SYNC);
+					},
+					Poll::Pending => {},
+				}
+			}
+		}
+		for (id, kind, resp) in finished_sync_requests {
+			match kind {
+				SyncRequestKind::Warp => self.on_warp_sync_response(id, resp),
+				SyncRequestKind::State => self.on_state_response(id, resp),
+			}
+		}
+
 		while let Poll::Ready(Some(())) = self.tick_timeout.poll_next_unpin(cx) {
 			self.tick();
 		}
@@ -1646,6 +1859,14 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 			let event = prepare_block_request(&mut self.peers, id, request);
 			self.pending_messages.push_back(event);
 		}
+		if let Some((id, request)) = self.sync.warp_sync_request() {
+			let event = prepare_warp_sync_request(&mut self.peers, id, request);
+			self.pending_messages.push_back(event);
+		}
+		if let Some((id, request)) = self.sync.state_request() {
+			let event = prepare_state_request(&mut self.peers, id, request);
+			self.pending_messages.push_back(event);
+		}
 		if let Poll::Ready(Some((tx_hash, result))) = self.pending_transactions.poll_next_unpin(cx) {
 			if let Some(peers) = self.pending_transactions_peers.remove(&tx_hash) {
 				peers.into_iter().for_each(|p| self.on_handle_transaction_import(p, result));
@@ -1681,20 +1902,13 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 
 		let outcome = match event {
 			GenericProtoOut::CustomProtocolOpen { peer_id, set_id, received_handshake, notifications_sink, .. } => {
+				#[cfg(feature = "fuzzing")]
+				crate::fuzzing::record("legacy_message", &received_handshake);
+
 				// Set number 0 is hardcoded the default set of peers we sync from.
 				if set_id == HARDCODED_PEERSETS_SYNC {
-					// `received_handshake` can be either a `Status` message if received from the
-					// legacy substream ,or a `BlockAnnouncesHandshake` if received from the block
-					// announces substream.
-					match <Message<B> as DecodeAll>::decode_all(&mut &received_handshake[..]) {
-						Ok(GenericMessage::Status(handshake)) => {
-							let handshake = BlockAnnouncesHandshake {
-								roles: handshake.roles,
-								best_number: handshake.best_number,
-								best_hash: handshake.best_hash,
-								genesis_hash: handshake.genesis_hash,
-							};
-
+					match decode_sync_handshake::<B>(&received_handshake) {
+						Ok(handshake) => {
 							if self.on_sync_peer_connected(peer_id.clone(), handshake).is_ok() {
 								// Set 1 is kept in sync with the connected peers of set 0.
 								self.peerset_handle.add_reserved_peer(
@@ -1705,47 +1919,19 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 							} else {
 								CustomMessageOutcome::None
 							}
-						},
-						Ok(msg) => {
+						}
+						Err(err) => {
 							debug!(
 								target: "sync",
-								"Expected Status message from {}, but got {:?}",
+								"Couldn't decode handshake sent by {}: {:?}: {}",
 								peer_id,
-								msg,
+								received_handshake,
+								err,
 							);
 							self.peerset_handle.report_peer(peer_id, rep::BAD_MESSAGE);
 							CustomMessageOutcome::None
 						}
-						Err(err) => {
-							match <BlockAnnouncesHandshake<B> as DecodeAll>::decode_all(&mut &received_handshake[..]) {
-								Ok(handshake) => {
-									if self.on_sync_peer_connected(peer_id.clone(), handshake).is_ok() {
-										// Set 1 is kept in sync with the connected peers of set 0.
-										self.peerset_handle.add_reserved_peer(
-											HARDCODED_PEERSETS_TX,
-											peer_id.clone()
-										);
-										CustomMessageOutcome::SyncConnected(peer_id)
-									} else {
-										CustomMessageOutcome::None
-									}
-								}
-								Err(err2) => {
-									debug!(
-										target: "sync",
-										"Couldn't decode handshake sent by {}: {:?}: {} & {}",
-										peer_id,
-										received_handshake,
-										err,
-										err2,
-									);
-									self.peerset_handle.report_peer(peer_id, rep::BAD_MESSAGE);
-									CustomMessageOutcome::None
-								}
-							}
-						}
 					}
-
 				} else if set_id == HARDCODED_PEERSETS_TX {
 					// Nothing to do.
 					CustomMessageOutcome::None
@@ -1815,6 +2001,9 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 			GenericProtoOut::Notification { peer_id, set_id, message } =>
 				match set_id {
 					HARDCODED_PEERSETS_SYNC if self.peers.contains_key(&peer_id) => {
+						#[cfg(feature = "fuzzing")]
+						crate::fuzzing::record("legacy_message", &message);
+
 						if let Ok(announce) = message::BlockAnnounce::decode(&mut message.as_ref()) {
 							self.push_block_announce_validation(peer_id, announce);
 
@@ -1831,6 +2020,9 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 						}
 					}
 					HARDCODED_PEERSETS_TX if self.peers.contains_key(&peer_id) => {
+						#[cfg(feature = "fuzzing")]
+						crate::fuzzing::record("legacy_message", &message);
+
 						if let Ok(m) = <message::Transactions<B::Extrinsic> as Decode>::decode(
 							&mut message.as_ref(),
 						) {
@@ -24,6 +24,8 @@
 
 pub use self::behaviour::{GenericProto, GenericProtoOut};
 pub use self::handler::{NotifsHandlerError, NotificationsSink, Ready};
+#[cfg(feature = "fuzzing")]
+pub use self::upgrade::NotificationsIn;
 
 mod behaviour;
 mod handler;
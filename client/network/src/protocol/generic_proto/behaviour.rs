@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::config::ProtocolId;
+use crate::traffic::TrafficCounters;
 use crate::protocol::generic_proto::{
 	handler::{NotificationsSink, NotifsHandlerProto, NotifsHandlerOut, NotifsHandlerIn},
 	upgrade::RegisteredProtocol
@@ -105,6 +106,9 @@ pub struct GenericProto {
 	/// initial handshake.
 	notif_protocols: Vec<(Cow<'static, str>, Arc<RwLock<Vec<u8>>>, u64)>,
 
+	/// Number of bytes exchanged on each protocol. Passed to the handlers.
+	traffic: Arc<TrafficCounters>,
+
 	/// Receiver for instructions about who to connect to or disconnect from.
 	peerset: sc_peerset::Peerset,
 
@@ -375,6 +379,7 @@ impl GenericProto {
 		handshake_message: Vec<u8>,
 		peerset: sc_peerset::Peerset,
 		notif_protocols: impl Iterator<Item = (Cow<'static, str>, Vec<u8>, u64)>,
+		traffic: Arc<TrafficCounters>,
 	) -> Self {
 		let notif_protocols = notif_protocols
 			.map(|(n, hs, sz)| (n, Arc::new(RwLock::new(hs)), sz))
@@ -388,6 +393,7 @@ impl GenericProto {
 		GenericProto {
 			legacy_protocol,
 			notif_protocols,
+			traffic,
 			peerset,
 			peers: FnvHashMap::default(),
 			delays: Default::default(),
@@ -628,6 +634,16 @@ impl GenericProto {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the non-zero reputations known to the peerset manager.
+	pub fn peerset_reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.peerset.reputations()
+	}
+
+	/// Resets all the reputations known to the peerset manager.
+	pub fn clear_peerset_reputations(&mut self) {
+		self.peerset.clear_reputations()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a peer.
 	fn peerset_report_connect(&mut self, peer_id: PeerId, set_id: sc_peerset::SetId) {
 		// If `PeerId` is unknown to us, insert an entry, start dialing, and return early.
@@ -1049,6 +1065,7 @@ impl NetworkBehaviour for GenericProto {
 		NotifsHandlerProto::new(
 			self.legacy_protocol.clone(),
 			self.notif_protocols.clone(),
+			self.traffic.clone(),
 		)
 	}
 
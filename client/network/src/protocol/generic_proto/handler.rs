@@ -64,6 +64,7 @@ use crate::protocol::generic_proto::{
 		RegisteredProtocolEvent, UpgradeCollec
 	},
 };
+use crate::traffic::{Direction, TrafficCounters};
 
 use bytes::BytesMut;
 use libp2p::core::{either::EitherOutput, ConnectedPoint, PeerId};
@@ -117,6 +118,9 @@ pub struct NotifsHandlerProto {
 
 	/// Configuration for the legacy protocol upgrade.
 	legacy_protocol: RegisteredProtocol,
+
+	/// Number of bytes exchanged on each protocol.
+	traffic: Arc<TrafficCounters>,
 }
 
 /// The actual handler once the connection has been established.
@@ -138,6 +142,9 @@ pub struct NotifsHandler {
 	/// Configuration for the legacy protocol upgrade.
 	legacy_protocol: RegisteredProtocol,
 
+	/// Number of bytes exchanged on each protocol. Accounts for the notifications.
+	traffic: Arc<TrafficCounters>,
+
 	/// The substreams where bidirectional communications happen.
 	legacy_substreams: SmallVec<[RegisteredProtocolSubstream<NegotiatedSubstream>; 4]>,
 
@@ -252,6 +259,7 @@ impl IntoProtocolsHandler for NotifsHandlerProto {
 			endpoint: connected_point.clone(),
 			when_connection_open: Instant::now(),
 			legacy_protocol: self.legacy_protocol,
+			traffic: self.traffic,
 			legacy_substreams: SmallVec::new(),
 			legacy_shutdown: SmallVec::new(),
 			events_queue: VecDeque::with_capacity(16),
@@ -478,6 +486,7 @@ impl NotifsHandlerProto {
 	pub fn new(
 		legacy_protocol: RegisteredProtocol,
 		list: impl Into<Vec<(Cow<'static, str>, Arc<RwLock<Vec<u8>>>, u64)>>,
+		traffic: Arc<TrafficCounters>,
 	) -> Self {
 		let protocols =	list
 			.into()
@@ -490,6 +499,7 @@ impl NotifsHandlerProto {
 		NotifsHandlerProto {
 			protocols,
 			legacy_protocol,
+			traffic,
 		}
 	}
 }
@@ -789,6 +799,11 @@ impl ProtocolsHandler for NotifsHandler {
 					match Stream::poll_next(Pin::new(in_substream.as_mut().unwrap()), cx) {
 						Poll::Pending => {},
 						Poll::Ready(Some(Ok(message))) => {
+							self.traffic.record(
+								&self.protocols[protocol_index].name,
+								Direction::Inbound,
+								message.len(),
+							);
 							let event = NotifsHandlerOut::Notification {
 								protocol_index,
 								message,
@@ -843,8 +858,9 @@ impl ProtocolsHandler for NotifsHandler {
 				State::OpenDesiredByRemote { .. } => {}
 			}
 
+			let protocol = &mut self.protocols[protocol_index];
 			if let State::Open { notifications_sink_rx, out_substream: Some(out_substream), .. }
-				= &mut self.protocols[protocol_index].state
+				= &mut protocol.state
 			{
 				loop {
 					// Before we poll the notifications sink receiver, check that the substream
@@ -862,6 +878,7 @@ impl ProtocolsHandler for NotifsHandler {
 
 					match message {
 						NotificationsSinkMessage::Notification { message } => {
+							self.traffic.record(&protocol.name, Direction::Outbound, message.len());
 							let _ = out_substream.start_send_unpin(message);
 
 							// Calling `start_send_unpin` only queues the message. Actually
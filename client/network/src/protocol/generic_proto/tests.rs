@@ -82,7 +82,8 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 		let behaviour = CustomProtoWithAddr {
 			inner: GenericProto::new(
 				"test", &[1], vec![], peerset,
-				iter::once(("/foo".into(), Vec::new(), 1024 * 1024))
+				iter::once(("/foo".into(), Vec::new(), 1024 * 1024)),
+				Default::default(),
 			),
 			addrs: addrs
 				.iter()
@@ -153,6 +153,9 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 				socket.read_exact(&mut initial_message).await?;
 			}
 
+			#[cfg(feature = "fuzzing")]
+			crate::fuzzing::record_notifications_substream(&initial_message, None);
+
 			let mut codec = UviBytes::default();
 			codec.set_max_len(usize::try_from(self.max_notification_size).unwrap_or(usize::max_value()));
 
@@ -266,6 +269,10 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 						Poll::Ready(None) => *this.handshake =
 							NotificationsInSubstreamHandshake::ClosingInResponseToRemote,
 						Poll::Ready(Some(msg)) => {
+							#[cfg(feature = "fuzzing")]
+							if let Ok(msg) = &msg {
+								crate::fuzzing::record_notifications_substream(&[], Some(msg));
+							}
 							*this.handshake = NotificationsInSubstreamHandshake::Sent;
 							return Poll::Ready(Some(msg))
 						},
@@ -39,6 +39,9 @@ use sp_consensus::{BlockOrigin, BlockStatus,
 use crate::protocol::message::{
 	self, BlockAnnounce, BlockAttributes, BlockRequest, BlockResponse, Roles,
 };
+use crate::config::SyncMode;
+use crate::schema::v1::{StateRequest, StateResponse};
+use crate::warp_sync::{WarpProofRequest, WarpSyncFragment, WarpSyncProvider};
 use either::Either;
 use extra_requests::ExtraRequests;
 use libp2p::PeerId;
@@ -59,6 +62,13 @@ use futures::{task::Poll, Future, stream::FuturesUnordered, FutureExt, StreamExt
 
 mod blocks;
 mod extra_requests;
+mod state;
+mod throughput;
+mod warp;
+
+use state::{StateSync, StateSyncResult};
+use throughput::PeerThroughput;
+use warp::{WarpSync, WarpSyncResult};
 
 /// Maximum blocks to request in a single packet.
 const MAX_BLOCKS_TO_REQUEST: usize = 128;
@@ -124,6 +134,18 @@ mod rep {
 
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+
+	/// Reputation change for peers which send us a warp sync proof we fail to verify.
+	pub const BAD_WARP_PROOF: Rep = Rep::new(-(1 << 29), "Bad warp sync proof");
+
+	/// Reputation change for peers which send us state we fail to verify.
+	pub const BAD_STATE: Rep = Rep::new(-(1 << 29), "Bad state");
+
+	/// Reputation change for peers which complete a state whose root does not match the block.
+	pub const BAD_MESSAGE: Rep = Rep::new(-(1 << 12), "Bad message");
+
+	/// Peer is on a chain that does not contain our sync checkpoint.
+	pub const CHECKPOINT_MISMATCH: Rep = Rep::new(i32::min_value(), "Sync checkpoint mismatch");
 }
 
 enum PendingRequests {
@@ -209,6 +231,16 @@ pub struct ChainSync<B: BlockT> {
 	>,
 	/// Stats per peer about the number of concurrent block announce validations.
 	block_announce_validation_per_peer_stats: HashMap<PeerId, usize>,
+	/// How to sync the chain.
+	mode: SyncMode,
+	/// Warp sync in progress, if any. No blocks are requested until it completes.
+	warp_sync: Option<WarpSync<B>>,
+	/// Download of the state of the warp sync target or of the sync checkpoint, if any. No blocks
+	/// are requested until it completes.
+	state_sync: Option<StateSync<B>>,
+	/// Number and hash of the trusted block our chain starts at, if any. Nothing below it is
+	/// synced.
+	checkpoint: Option<(NumberFor<B>, B::Hash)>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -226,6 +258,8 @@ pub struct PeerSync<B: BlockT> {
 	/// The state of syncing this peer is in for us, generally categories
 	/// into `Available` or "busy" with something as defined by `PeerSyncState`.
 	pub state: PeerSyncState<B>,
+	/// Block download statistics, used to size the block requests to this peer.
+	pub throughput: PeerThroughput,
 }
 
 impl<B: BlockT> PeerSync<B> {
@@ -281,6 +315,10 @@ pub enum PeerSyncState<B: BlockT> {
 	DownloadingStale(B::Hash),
 	/// Downloading justification for given block hash.
 	DownloadingJustification(B::Hash),
+	/// Downloading a warp sync proof.
+	DownloadingWarpProof,
+	/// Downloading state.
+	DownloadingState,
 }
 
 impl<B: BlockT> PeerSyncState<B> {
@@ -447,6 +485,9 @@ impl<B: BlockT> ChainSync<B> {
 		info: &BlockchainInfo<B>,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
+		mode: SyncMode,
+		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+		checkpoint: Option<WarpSyncFragment<B>>,
 	) -> Self {
 		let mut required_block_attributes = BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION;
 
@@ -454,6 +495,51 @@ impl<B: BlockT> ChainSync<B> {
 			required_block_attributes |= BlockAttributes::BODY
 		}
 
+		// A fresh node starts by downloading the state of the checkpoint, in place of any warp
+		// sync. Once the checkpoint is imported, it only bounds the ancestry we sync.
+		let mut state_sync = None;
+		let checkpoint = checkpoint.filter(|_| role.is_full()).and_then(|target| {
+			let number = *target.header.number();
+			let hash = target.header.hash();
+			if info.finalized_number >= number {
+				return match client.hash(number) {
+					Ok(Some(h)) if h == hash => Some((number, hash)),
+					_ => {
+						warn!(
+							target: "sync",
+							"Ignoring sync checkpoint #{} ({}), not in our finalized chain",
+							number,
+							hash,
+						);
+						None
+					},
+				}
+			}
+			match &warp_sync_provider {
+				Some(provider) if info.best_number.is_zero() => {
+					info!("⏩ Syncing from checkpoint #{} ({})", number, hash);
+					state_sync = Some(StateSync::new(provider.clone(), target));
+					Some((number, hash))
+				},
+				_ => {
+					warn!(
+						target: "sync",
+						"Ignoring sync checkpoint #{} ({}), which requires a fresh node and a warp \
+						 sync provider",
+						number,
+						hash,
+					);
+					None
+				},
+			}
+		});
+
+		// Warp sync only makes sense for a node that has nothing but genesis.
+		let warp_sync = warp_sync_provider
+			.filter(|_| mode != SyncMode::Full && role.is_full() && info.best_number.is_zero())
+			.filter(|_| state_sync.is_none())
+			.map(|provider| WarpSync::new(provider, info.finalized_number, info.finalized_hash));
+
 		ChainSync {
 			client,
 			peers: HashMap::new(),
@@ -471,6 +557,10 @@ impl<B: BlockT> ChainSync<B> {
 			downloaded_blocks: 0,
 			block_announce_validation: Default::default(),
 			block_announce_validation_per_peer_stats: Default::default(),
+			mode,
+			warp_sync,
+			state_sync,
+			checkpoint,
 		}
 	}
 
@@ -536,6 +626,24 @@ impl<B: BlockT> ChainSync<B> {
 					info!("💔 New peer with unknown genesis hash {} ({}).", best_hash, best_number);
 					return Err(BadPeer(who, rep::GENESIS_MISMATCH));
 				}
+				// Peers that are not past our checkpoint yet have nothing for us.
+				if self.checkpoint.map_or(false, |(checkpoint, _)| best_number <= checkpoint) {
+					debug!(
+						target:"sync",
+						"New peer with best hash {} ({}) below the sync checkpoint.",
+						best_hash,
+						best_number,
+					);
+					self.peers.insert(who.clone(), PeerSync {
+						peer_id: who,
+						common_number: Zero::zero(),
+						best_hash,
+						best_number,
+						state: PeerSyncState::Available,
+						throughput: Default::default(),
+					});
+					return Ok(None)
+				}
 				// If there are more than `MAJOR_SYNC_BLOCKS` in the import queue then we have
 				// enough to do in the import queue that it's not worth kicking off
 				// an ancestor search, which is what we do in the next match case below.
@@ -552,6 +660,7 @@ impl<B: BlockT> ChainSync<B> {
 						best_hash,
 						best_number,
 						state: PeerSyncState::Available,
+						throughput: Default::default(),
 					});
 					return Ok(None)
 				}
@@ -593,6 +702,7 @@ impl<B: BlockT> ChainSync<B> {
 					best_hash,
 					best_number,
 					state,
+					throughput: Default::default(),
 				});
 
 				Ok(req)
@@ -610,6 +720,7 @@ impl<B: BlockT> ChainSync<B> {
 					best_hash,
 					best_number,
 					state: PeerSyncState::Available,
+					throughput: Default::default(),
 				});
 				self.pending_requests.add(&who);
 				Ok(None)
@@ -661,6 +772,11 @@ impl<B: BlockT> ChainSync<B> {
 			return;
 		}
 
+		if self.checkpoint.map_or(false, |(checkpoint, _)| number <= checkpoint) {
+			debug!(target: "sync", "Refusing to sync hash {:?} below the sync checkpoint", hash);
+			return;
+		}
+
 		trace!(target: "sync", "Downloading requested old fork {:?}", hash);
 		for peer_id in &peers {
 			if let Some(peer) = self.peers.get_mut(peer_id) {
@@ -710,9 +826,152 @@ impl<B: BlockT> ChainSync<B> {
 		})
 	}
 
+	/// Get the next warp sync proof request to make, if a warp sync is in progress.
+	///
+	/// Only one request is made at a time, to the available peer with the highest best block.
+	pub fn warp_sync_request(&mut self) -> Option<(PeerId, WarpProofRequest<B>)> {
+		let warp_sync = self.warp_sync.as_ref()?;
+		if self.peers.values().any(|p| p.state == PeerSyncState::DownloadingWarpProof) {
+			return None
+		}
+
+		let begin = warp_sync.begin_number();
+		let (id, peer) = self.peers.iter_mut()
+			.filter(|(_, p)| p.state.is_available() && p.best_number > begin)
+			.max_by_key(|(_, p)| p.best_number)?;
+		trace!(target: "sync", "New warp sync proof request for {} from #{}", id, begin);
+		peer.state = PeerSyncState::DownloadingWarpProof;
+		Some((id.clone(), warp_sync.request()))
+	}
+
+	/// Handle a response from the remote to a warp sync proof request that we made.
+	///
+	/// Once the proof holds nothing new, its target is imported and sync restarts from there.
+	/// Returns the block requests to make after such a restart.
+	pub fn on_warp_sync_data(
+		&mut self,
+		who: &PeerId,
+		response: &[u8],
+	) -> Result<Vec<Result<(PeerId, BlockRequest<B>), BadPeer>>, BadPeer> {
+		match self.peers.get_mut(who) {
+			Some(peer) if peer.state == PeerSyncState::DownloadingWarpProof =>
+				peer.state = PeerSyncState::Available,
+			_ => return Err(BadPeer(who.clone(), rep::NOT_REQUESTED)),
+		}
+
+		let result = match self.warp_sync.as_mut() {
+			Some(warp_sync) => warp_sync.on_response(response),
+			None => return Ok(Vec::new()),
+		};
+		match result {
+			WarpSyncResult::Continue => Ok(Vec::new()),
+			WarpSyncResult::BadResponse => Err(BadPeer(who.clone(), rep::BAD_WARP_PROOF)),
+			WarpSyncResult::Complete(target) => Ok(self.finish_warp_sync(target)),
+		}
+	}
+
+	/// Get the next state request to make, if a state download is in progress.
+	///
+	/// Only one request is made at a time, to an available peer that has the block.
+	pub fn state_request(&mut self) -> Option<(PeerId, StateRequest)> {
+		let state_sync = self.state_sync.as_ref()?;
+		if self.peers.values().any(|p| p.state == PeerSyncState::DownloadingState) {
+			return None
+		}
+
+		let target = state_sync.target_number();
+		let (id, peer) = self.peers.iter_mut()
+			.filter(|(_, p)| p.state.is_available() && p.best_number >= target)
+			.max_by_key(|(_, p)| p.best_number)?;
+		trace!(target: "sync", "New state request for #{} to {}", target, id);
+		peer.state = PeerSyncState::DownloadingState;
+		Some((id.clone(), state_sync.request()))
+	}
+
+	/// Handle a response from the remote to a state request that we made.
+	///
+	/// Once the whole state is downloaded, it is imported along with the warp sync target and
+	/// sync restarts from there. Returns the block requests to make after such a restart.
+	pub fn on_state_data(
+		&mut self,
+		who: &PeerId,
+		response: StateResponse,
+	) -> Result<Vec<Result<(PeerId, BlockRequest<B>), BadPeer>>, BadPeer> {
+		match self.peers.get_mut(who) {
+			Some(peer) if peer.state == PeerSyncState::DownloadingState =>
+				peer.state = PeerSyncState::Available,
+			_ => return Err(BadPeer(who.clone(), rep::NOT_REQUESTED)),
+		}
+
+		let result = match self.state_sync.as_mut() {
+			Some(state_sync) => state_sync.on_response(response),
+			None => return Ok(Vec::new()),
+		};
+		let import_error = match result {
+			StateSyncResult::Continue => return Ok(Vec::new()),
+			StateSyncResult::BadResponse => return Err(BadPeer(who.clone(), rep::BAD_STATE)),
+			StateSyncResult::BadState => return Err(BadPeer(who.clone(), rep::BAD_MESSAGE)),
+			StateSyncResult::ImportFailed(e) => Some(e),
+			StateSyncResult::Import => None,
+		};
+
+		let state_sync = match self.state_sync.take() {
+			Some(state_sync) => state_sync,
+			None => return Ok(Vec::new()),
+		};
+		let number = state_sync.target_number();
+		let hash = state_sync.target_hash();
+		let imported = match import_error {
+			Some(e) => Err(e),
+			None => state_sync.import(),
+		};
+		match imported {
+			Ok(()) => info!("⏩ Jumped to finalized block #{} ({})", number, hash),
+			// The import is atomic, so the database is left as it was and regular sync continues
+			// from our own finalized block.
+			Err(e) => error!(
+				target: "sync",
+				"Failed to import finalized block #{} ({}) and its state, nothing imported: {}",
+				number,
+				hash,
+				e,
+			),
+		}
+		Ok(self.restart().collect())
+	}
+
+	/// Download the state of the target of the warp sync, if any, or continue with regular sync.
+	///
+	/// The target is only imported along with its state.
+	fn finish_warp_sync(
+		&mut self,
+		target: Option<WarpSyncFragment<B>>,
+	) -> Vec<Result<(PeerId, BlockRequest<B>), BadPeer>> {
+		let warp_sync = match self.warp_sync.take() {
+			Some(warp_sync) => warp_sync,
+			None => return Vec::new(),
+		};
+
+		match target {
+			Some(target) => {
+				debug!(
+					target: "sync",
+					"Warp sync complete, downloading the state of #{}",
+					target.header.number(),
+				);
+				self.state_sync = Some(warp_sync.into_state_sync(target));
+				Vec::new()
+			},
+			None => {
+				debug!(target: "sync", "Warp sync found no block to jump to.");
+				self.restart().collect()
+			},
+		}
+	}
+
 	/// Get an iterator over all block requests of all peers.
 	pub fn block_requests(&mut self) -> impl Iterator<Item = (&PeerId, BlockRequest<B>)> + '_ {
-		if self.pending_requests.is_empty() {
+		if self.pending_requests.is_empty() || self.warp_sync.is_some() || self.state_sync.is_some() {
 			return Either::Left(std::iter::empty())
 		}
 		if self.queue_blocks.len() > MAX_IMPORTING_BLOCKS {
@@ -758,6 +1017,7 @@ impl<B: BlockT> ChainSync<B> {
 				best_queued,
 			) {
 				peer.state = PeerSyncState::DownloadingNew(range.start);
+				peer.throughput.on_request((range.end - range.start).saturated_into());
 				trace!(
 					target: "sync",
 					"New block request for {}, (best:{}, common:{}) {:?}",
@@ -789,6 +1049,43 @@ impl<B: BlockT> ChainSync<B> {
 		Either::Right(iter)
 	}
 
+	/// Give the ranges of stalled block requests to faster peers.
+	///
+	/// The stalled request is not cancelled: the blocks are taken from whichever peer answers
+	/// first. Must be called periodically; the new requests are then returned by
+	/// [`ChainSync::block_requests`].
+	pub fn reassign_stalled_downloads(&mut self) {
+		let stalled: Vec<_> = self.peers.iter().filter_map(|(id, peer)| match peer.state {
+			PeerSyncState::DownloadingNew(start) if peer.throughput.is_stalled() =>
+				Some((id.clone(), start, peer.throughput.current_rate())),
+			_ => None,
+		}).collect();
+
+		for (who, start, rate) in stalled {
+			let has_faster_peer = self.peers.iter().any(|(id, peer)| {
+				*id != who &&
+					peer.state.is_available() &&
+					peer.best_number >= start &&
+					peer.throughput.is_faster_than(rate)
+			});
+			if !has_faster_peer {
+				continue
+			}
+			if let Some(range) = self.blocks.reassign_peer_download(&who) {
+				debug!(
+					target: "sync",
+					"Block request to {} for {:?} stalled, reassigning to a faster peer.",
+					who,
+					range,
+				);
+				self.pending_requests.set_all();
+			}
+			if let Some(peer) = self.peers.get_mut(&who) {
+				peer.throughput.on_reassigned();
+			}
+		}
+	}
+
 	/// Handle a response from the remote to a block request that we made.
 	///
 	/// `request` must be the original request that triggered `response`.
@@ -817,6 +1114,7 @@ impl<B: BlockT> ChainSync<B> {
 							self.blocks.clear_peer_download(who);
 							let start_block = *start_block;
 							peer.state = PeerSyncState::Available;
+							peer.throughput.on_response(blocks.len());
 							validate_blocks::<B>(&blocks, who, Some(request))?;
 							self.blocks.insert(start_block, blocks, who.clone());
 							self.blocks
@@ -897,9 +1195,22 @@ impl<B: BlockT> ChainSync<B> {
 								trace!(target:"sync", "Ancestry search: genesis mismatch for peer {}", who);
 								return Err(BadPeer(who.clone(), rep::GENESIS_MISMATCH))
 							}
+							let checkpoint = self.checkpoint.map(|(number, _)| number);
+							let below_checkpoint = checkpoint.map_or(false, |c| *current <= c);
+							if matching_hash.is_none() && below_checkpoint {
+								trace!(
+									target: "sync",
+									"Ancestry search: checkpoint mismatch for peer {}",
+									who,
+								);
+								return Err(BadPeer(who.clone(), rep::CHECKPOINT_MISMATCH))
+							}
 							if let Some((next_state, next_num)) =
 								handle_ancestor_search_state(state, *current, matching_hash.is_some())
 							{
+								// We know nothing below the checkpoint to compare against.
+								let next_num = checkpoint
+									.map_or(next_num, |c| std::cmp::max(next_num, c));
 								peer.state = PeerSyncState::AncestorSearch {
 									current: next_num,
 									start: *start,
@@ -945,7 +1256,9 @@ impl<B: BlockT> ChainSync<B> {
 						}
 
 						| PeerSyncState::Available
-						| PeerSyncState::DownloadingJustification(..) => Vec::new()
+						| PeerSyncState::DownloadingJustification(..)
+						| PeerSyncState::DownloadingWarpProof
+						| PeerSyncState::DownloadingState => Vec::new()
 					}
 				} else {
 					// When request.is_none() this is a block announcement. Just accept blocks.
@@ -1491,6 +1804,17 @@ impl<B: BlockT> ChainSync<B> {
 			return PollBlockAnnounceValidation::Nothing { is_best, who, announce }
 		}
 
+		if self.checkpoint.map_or(false, |(checkpoint, _)| number <= checkpoint) {
+			trace!(
+				target: "sync",
+				"Ignored block announced from {} below the sync checkpoint: {} {:?}",
+				who,
+				hash,
+				announce.header,
+			);
+			return PollBlockAnnounceValidation::Nothing { is_best, who, announce }
+		}
+
 		let requires_additional_data = !self.role.is_light() || !known_parent;
 		if !requires_additional_data {
 			trace!(
@@ -1700,7 +2024,7 @@ fn peer_block_request<B: BlockT>(
 	}
 	let range = blocks.needed_blocks(
 		id.clone(),
-		MAX_BLOCKS_TO_REQUEST,
+		peer.throughput.max_blocks_per_request(MAX_BLOCKS_TO_REQUEST),
 		peer.best_number,
 		peer.common_number,
 		max_parallel_downloads,
@@ -1898,13 +2222,21 @@ mod test {
 	use super::*;
 	use sc_block_builder::BlockBuilderProvider;
 	use sp_blockchain::HeaderBackend;
+	use sc_client_api::{ProofProvider, StorageProvider};
 	use sp_consensus::block_validation::DefaultBlockAnnounceValidator;
+	use sp_finality_grandpa::{AuthorityList, SetId};
+	use sp_core::storage::{ChildInfo, StorageChild};
+	use sp_runtime::Storage;
+	use sp_state_machine::{Backend as _, InMemoryBackend};
+	use crate::schema::v1::StateEntry;
+	use crate::warp_sync::StateImport;
 	use substrate_test_runtime_client::{
 		runtime::{Block, Hash, Header},
 		ClientBlockImportExt, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
 		BlockBuilderExt, TestClient, ClientExt,
 	};
 	use futures::{future::poll_fn, executor::block_on};
+	use std::time::Duration;
 
 	#[test]
 	fn processes_empty_response_on_justification_request_for_unknown_block() {
@@ -1923,6 +2255,9 @@ mod test {
 			&info,
 			block_announce_validator,
 			1,
+			SyncMode::Full,
+			None,
+			None,
 		);
 
 		let (a1_hash, a1_number) = {
@@ -1994,6 +2329,9 @@ mod test {
 			&info,
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
+			SyncMode::Full,
+			None,
+			None,
 		);
 
 		let peer_id1 = PeerId::random();
@@ -2138,6 +2476,50 @@ mod test {
 		block
 	}
 
+	#[test]
+	fn reassigns_stalled_download_to_other_peer() {
+		sp_tracing::try_init_simple();
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+
+		let mut sync = ChainSync::new(
+			Roles::AUTHORITY,
+			client.clone(),
+			&info,
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			SyncMode::Full,
+			None,
+			None,
+		);
+
+		let peer_id1 = PeerId::random();
+		let peer_id2 = PeerId::random();
+
+		sync.new_peer(peer_id1.clone(), Hash::random(), 10).unwrap();
+		get_block_request(&mut sync, FromBlock::Number(10), 10, &peer_id1);
+
+		// The range is being downloaded from peer 1, so there is nothing to request from peer 2.
+		sync.new_peer(peer_id2.clone(), Hash::random(), 10).unwrap();
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// Nothing is reassigned while the request to peer 1 is not late.
+		sync.reassign_stalled_downloads();
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// Once it stalls, the range is requested from peer 2, without cancelling the request to
+		// peer 1.
+		sync.peers.get_mut(&peer_id1).unwrap().throughput.backdate_request(Duration::from_secs(60));
+		sync.reassign_stalled_downloads();
+		get_block_request(&mut sync, FromBlock::Number(10), 10, &peer_id2);
+		assert_eq!(sync.peers[&peer_id1].state, PeerSyncState::DownloadingNew(1));
+
+		// The range is only reassigned once.
+		sync.reassign_stalled_downloads();
+		assert_eq!(sync.block_requests().count(), 0);
+	}
+
 	/// This test is a regression test as observed on a real network.
 	///
 	/// The node is connected to multiple peers. Both of these peers are having a best block (1) that
@@ -2160,6 +2542,9 @@ mod test {
 			&info,
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
+			SyncMode::Full,
+			None,
+			None,
 		);
 
 		let peer_id1 = PeerId::random();
@@ -2274,6 +2659,9 @@ mod test {
 			&info,
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
+			SyncMode::Full,
+			None,
+			None,
 		);
 
 		let peer_id1 = PeerId::random();
@@ -2393,6 +2781,9 @@ mod test {
 			&info,
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
+			SyncMode::Full,
+			None,
+			None,
 		);
 
 		let finalized_block = blocks[MAX_BLOCKS_TO_LOOK_BACKWARDS as usize * 2 - 1].clone();
@@ -2489,4 +2880,354 @@ mod test {
 			&peer_id1,
 		);
 	}
+
+	/// Warp sync provider accepting the proof `[1]` as proving block #10 and the empty proof as
+	/// proving nothing new.
+	#[derive(Default)]
+	struct TestWarpSyncProvider {
+		/// Header proven by the proof `[1]`, a block #10 by default.
+		target: Option<Header>,
+		imported: Arc<std::sync::Mutex<Vec<(WarpSyncFragment<Block>, Storage)>>>,
+	}
+
+	/// Import collecting the state in memory, to hand it to its provider on commit.
+	struct TestStateImport {
+		target: WarpSyncFragment<Block>,
+		state: Storage,
+		imported: Arc<std::sync::Mutex<Vec<(WarpSyncFragment<Block>, Storage)>>>,
+	}
+
+	impl StateImport<Block> for TestStateImport {
+		fn append(
+			&mut self,
+			child_info: Option<&ChildInfo>,
+			chunk: Vec<(Vec<u8>, Vec<u8>)>,
+		) -> Result<Hash, Box<dyn std::error::Error + Send + Sync>> {
+			match child_info {
+				Some(child_info) => self.state.children_default
+					.entry(child_info.storage_key().to_vec())
+					.or_insert_with(|| StorageChild {
+						data: Default::default(),
+						child_info: child_info.clone(),
+					})
+					.data
+					.extend(chunk),
+				None => self.state.top.extend(chunk),
+			}
+			let backend = InMemoryBackend::<HashFor<Block>>::from(self.state.clone());
+			Ok(backend.storage_root(std::iter::empty()).0)
+		}
+
+		fn commit(self: Box<Self>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+			self.imported.lock().unwrap().push((self.target, self.state));
+			Ok(())
+		}
+	}
+
+	impl WarpSyncProvider<Block> for TestWarpSyncProvider {
+		fn current_authorities(&self) -> (SetId, AuthorityList) {
+			(0, Vec::new())
+		}
+
+		fn verify(
+			&self,
+			proof: &[u8],
+			_set_id: SetId,
+			_authorities: AuthorityList,
+			_begin: NumberFor<Block>,
+		) -> Result<Option<WarpSyncFragment<Block>>, Box<dyn std::error::Error + Send + Sync>> {
+			match proof {
+				[] => Ok(None),
+				[1] => Ok(Some(WarpSyncFragment {
+					header: self.target.clone().unwrap_or_else(|| Header::new(
+						10,
+						Default::default(),
+						Default::default(),
+						Default::default(),
+						Default::default(),
+					)),
+					justification: Vec::new(),
+					set_id: 1,
+					authorities: Vec::new(),
+				})),
+				_ => Err("bad proof".into()),
+			}
+		}
+
+		fn begin_import(
+			&self,
+			target: WarpSyncFragment<Block>,
+		) -> Result<Box<dyn StateImport<Block>>, Box<dyn std::error::Error + Send + Sync>> {
+			Ok(Box::new(TestStateImport {
+				target,
+				state: Default::default(),
+				imported: self.imported.clone(),
+			}))
+		}
+	}
+
+	#[test]
+	fn warp_sync_downloads_proof_before_blocks() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let provider = Arc::new(TestWarpSyncProvider::default());
+
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			Box::new(DefaultBlockAnnounceValidator),
+			5,
+			SyncMode::Warp,
+			Some(provider.clone()),
+			None,
+		);
+
+		let peer_id1 = PeerId::random();
+		let peer_id2 = PeerId::random();
+		sync.new_peer(peer_id1.clone(), Hash::random(), 42).unwrap();
+
+		// Nothing is downloaded until the warp sync completes.
+		let (peer, request) = sync.warp_sync_request().unwrap();
+		assert_eq!((peer, request.begin), (peer_id1.clone(), info.genesis_hash));
+		assert!(sync.warp_sync_request().is_none());
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// A proof that was not requested, or that fails to verify, is a bad peer.
+		assert!(matches!(
+			sync.on_warp_sync_data(&peer_id2, &[1]),
+			Err(BadPeer(id, r)) if id == peer_id2 && r == rep::NOT_REQUESTED,
+		));
+		assert!(matches!(
+			sync.on_warp_sync_data(&peer_id1, &[2]),
+			Err(BadPeer(id, r)) if id == peer_id1 && r == rep::BAD_WARP_PROOF,
+		));
+
+		// The next request starts at the verified block.
+		let (_, request) = sync.warp_sync_request().unwrap();
+		assert!(sync.on_warp_sync_data(&peer_id1, &[1]).unwrap().is_empty());
+		let (_, request2) = sync.warp_sync_request().unwrap();
+		assert_ne!(request.begin, request2.begin);
+		assert!(provider.imported.lock().unwrap().is_empty());
+
+		// Once there is nothing new, the state of the last verified block is downloaded, and
+		// nothing is imported without it.
+		assert!(sync.on_warp_sync_data(&peer_id1, &[]).unwrap().is_empty());
+		assert!(sync.warp_sync_request().is_none());
+		assert!(provider.imported.lock().unwrap().is_empty());
+		assert_eq!(sync.block_requests().count(), 0);
+		let (_, request) = sync.state_request().unwrap();
+		assert_eq!(request.block, request2.begin.encode());
+	}
+
+	#[test]
+	fn warp_sync_downloads_state_of_target() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let genesis = client.header(&BlockId::Hash(info.genesis_hash)).unwrap().unwrap();
+		let provider = Arc::new(TestWarpSyncProvider {
+			target: Some(genesis.clone()),
+			..Default::default()
+		});
+
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			Box::new(DefaultBlockAnnounceValidator),
+			5,
+			SyncMode::Warp,
+			Some(provider.clone()),
+			None,
+		);
+
+		let peer_id1 = PeerId::random();
+		sync.new_peer(peer_id1.clone(), Hash::random(), 42).unwrap();
+
+		sync.warp_sync_request().unwrap();
+		sync.on_warp_sync_data(&peer_id1, &[1]).unwrap();
+		sync.warp_sync_request().unwrap();
+		assert!(sync.on_warp_sync_data(&peer_id1, &[]).unwrap().is_empty());
+		assert!(provider.imported.lock().unwrap().is_empty());
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// Serve the state in small chunks, the way a remote would.
+		let id = BlockId::Hash(info.genesis_hash);
+		let mut responses = 0;
+		loop {
+			let (peer, request) = sync.state_request().unwrap();
+			assert_eq!(peer, peer_id1);
+			assert!(sync.state_request().is_none());
+			assert!(request.child_trie.is_empty());
+
+			let (entries, proof, complete) =
+				client.read_proof_collection(&id, None, &request.start, 100).unwrap();
+			let response = StateResponse {
+				entries: entries.into_iter().map(|(key, value)| StateEntry { key, value }).collect(),
+				proof: proof.encode(),
+				complete,
+			};
+			responses += 1;
+
+			// Entries that do not match the proof are rejected.
+			if responses == 1 {
+				let mut bad = response.clone();
+				bad.entries[0].value.push(0);
+				assert!(matches!(
+					sync.on_state_data(&peer_id1, bad),
+					Err(BadPeer(id, r)) if id == peer_id1 && r == rep::BAD_STATE,
+				));
+				sync.state_request().unwrap();
+
+				// A state that is claimed complete with keys left out does not match the root,
+				// and is downloaded again from the start.
+				let mut truncated = response.clone();
+				truncated.complete = true;
+				assert!(matches!(
+					sync.on_state_data(&peer_id1, truncated),
+					Err(BadPeer(id, r)) if id == peer_id1 && r == rep::BAD_MESSAGE,
+				));
+				assert!(provider.imported.lock().unwrap().is_empty());
+				let (_, request) = sync.state_request().unwrap();
+				assert!(request.start.is_empty());
+			}
+
+			sync.on_state_data(&peer_id1, response).unwrap();
+			if complete {
+				break
+			}
+		}
+		assert!(responses > 1);
+		assert!(sync.state_request().is_none());
+
+		let imported = provider.imported.lock().unwrap();
+		assert_eq!(imported.len(), 1);
+		assert_eq!(imported[0].0.header, genesis);
+		let state = &imported[0].1;
+		let expected = client.storage_pairs(&id, &Default::default()).unwrap();
+		assert_eq!(state.top.len(), expected.len());
+		for (key, value) in expected {
+			assert_eq!(state.top.get(&key.0), Some(&value.0));
+		}
+	}
+
+	fn checkpoint(header: Header) -> WarpSyncFragment<Block> {
+		WarpSyncFragment {
+			header,
+			justification: Vec::new(),
+			set_id: 0,
+			authorities: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn sync_checkpoint_downloads_its_state() {
+		let mut remote = Arc::new(TestClientBuilder::new().build());
+		let blocks = (0..5).map(|_| build_block(&mut remote, None, false)).collect::<Vec<_>>();
+		let target = blocks[2].header().clone();
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let provider = Arc::new(TestWarpSyncProvider::default());
+
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			Box::new(DefaultBlockAnnounceValidator),
+			5,
+			SyncMode::Warp,
+			Some(provider.clone()),
+			Some(checkpoint(target.clone())),
+		);
+
+		let peer_id1 = PeerId::random();
+		sync.new_peer(peer_id1.clone(), blocks[4].hash(), 5).unwrap();
+
+		// The checkpoint replaces the warp sync, and no blocks are requested until its state is
+		// imported.
+		assert!(sync.warp_sync_request().is_none());
+		assert_eq!(sync.block_requests().count(), 0);
+
+		let id = BlockId::Hash(target.hash());
+		loop {
+			let (peer, request) = sync.state_request().unwrap();
+			assert_eq!(peer, peer_id1);
+			assert_eq!(request.block, target.hash().encode());
+
+			let (entries, proof, complete) =
+				remote.read_proof_collection(&id, None, &request.start, 100).unwrap();
+			let response = StateResponse {
+				entries: entries.into_iter().map(|(key, value)| StateEntry { key, value }).collect(),
+				proof: proof.encode(),
+				complete,
+			};
+			sync.on_state_data(&peer_id1, response).unwrap();
+			if complete {
+				break
+			}
+		}
+
+		let imported = provider.imported.lock().unwrap();
+		assert_eq!(imported.len(), 1);
+		assert_eq!(imported[0].0.header, target);
+	}
+
+	#[test]
+	fn refuses_ancestry_below_sync_checkpoint() {
+		sp_tracing::try_init_simple();
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let blocks = (0..10).map(|_| build_block(&mut client, None, false)).collect::<Vec<_>>();
+		client.finalize_block(BlockId::Hash(blocks[7].hash()), Some(Vec::new())).unwrap();
+
+		// A chain that forks off ours at block #3.
+		let fork_blocks = {
+			let mut client = Arc::new(TestClientBuilder::new().build());
+			for block in &blocks[..3] {
+				client.import(BlockOrigin::Own, block.clone()).unwrap();
+			}
+			blocks[..3].iter().cloned()
+				.chain((0..9).map(|_| build_block(&mut client, None, true)))
+				.collect::<Vec<_>>()
+		};
+
+		let info = client.info();
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			Box::new(DefaultBlockAnnounceValidator),
+			5,
+			SyncMode::Full,
+			None,
+			Some(checkpoint(blocks[4].header().clone())),
+		);
+
+		// Peers that are not past the checkpoint are kept, but nothing is requested from them.
+		let peer_id1 = PeerId::random();
+		assert!(sync.new_peer(peer_id1.clone(), fork_blocks[3].hash(), 4).unwrap().is_none());
+		sync.set_sync_fork_request(vec![peer_id1], &fork_blocks[3].hash(), 4);
+		assert_eq!(sync.num_sync_requests(), 0);
+		assert_eq!(sync.block_requests().count(), 0);
+
+		// The ancestor search stops at the checkpoint.
+		let peer_id2 = PeerId::random();
+		let mut request = sync.new_peer(peer_id2.clone(), fork_blocks[11].hash(), 12)
+			.unwrap()
+			.unwrap();
+		let result = loop {
+			let number = unwrap_from_block_number(request.from.clone());
+			assert!(number >= 5);
+			let response = create_block_response(vec![fork_blocks[number as usize - 1].clone()]);
+			match sync.on_block_data(&peer_id2, Some(request), response) {
+				Ok(OnBlockData::Request(_, next)) => request = next,
+				result => break result,
+			}
+		};
+		assert!(matches!(
+			result,
+			Err(BadPeer(id, r)) if id == peer_id2 && r == rep::CHECKPOINT_MISMATCH,
+		));
+	}
 }
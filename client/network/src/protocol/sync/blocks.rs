@@ -204,6 +204,20 @@ impl<B: BlockT> BlockCollection<B> {
 			}
 		}
 	}
+
+	/// Stop tracking the download of `who` while keeping its range marked as needed, so that the
+	/// range is handed out by the next call to `needed_blocks`. Blocks received later from `who`
+	/// can still be inserted. Returns the range that was being downloaded.
+	pub fn reassign_peer_download(&mut self, who: &PeerId) -> Option<Range<NumberFor<B>>> {
+		let start = self.peer_requests.remove(who)?;
+		match self.blocks.get_mut(&start) {
+			Some(&mut BlockRangeState::Downloading { len, ref mut downloading }) => {
+				*downloading = downloading.saturating_sub(1);
+				Some(start .. start + len)
+			},
+			_ => None,
+		}
+	}
 }
 
 #[cfg(test)]
@@ -304,4 +318,29 @@ mod test {
 		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200), None); // too far ahead
 		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200000), Some(100 + 128 .. 100 + 128 + 128));
 	}
+
+	#[test]
+	fn reassign_download() {
+		let mut bc = BlockCollection::new();
+		let peer0 = PeerId::random();
+		let peer1 = PeerId::random();
+		let blocks = generate_blocks(41);
+
+		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200), Some(1 .. 41));
+		assert_eq!(bc.reassign_peer_download(&peer0), Some(1 .. 41));
+		assert_eq!(bc.reassign_peer_download(&peer0), None);
+
+		// The same range is given to the next peer, even with a smaller count.
+		assert_eq!(bc.needed_blocks(peer1.clone(), 10, 150, 0, 1, 200), Some(1 .. 41));
+
+		// The stalled peer answers first.
+		bc.clear_peer_download(&peer0);
+		bc.insert(1, blocks[1..41].to_vec(), peer0.clone());
+		bc.clear_peer_download(&peer1);
+		bc.insert(1, blocks[1..41].to_vec(), peer1.clone());
+
+		assert_eq!(bc.drain(1), blocks[1..41].iter()
+			.map(|b| BlockData { block: b.clone(), origin: Some(peer0.clone()) }).collect::<Vec<_>>());
+		assert!(is_empty(&bc));
+	}
 }
@@ -0,0 +1,220 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::schema::v1::{StateRequest, StateResponse};
+use crate::warp_sync::{StateImport, WarpSyncFragment, WarpSyncProvider};
+use codec::{Encode, Decode};
+use log::debug;
+use sp_core::storage::{ChildInfo, well_known_keys};
+use sp_runtime::traits::{Block as BlockT, HashFor, Header as HeaderT, NumberFor};
+use sp_state_machine::{read_child_proof_check, read_proof_check, StorageProof};
+use std::{error::Error, sync::Arc};
+
+/// Result of [`StateSync::on_response`].
+pub(crate) enum StateSyncResult {
+	/// The response was verified and more of the state should be requested.
+	Continue,
+	/// The response could not be verified.
+	BadResponse,
+	/// The response completed the state, but the root of the state does not match the one of the
+	/// target: some of it was left out. The download starts over.
+	BadState,
+	/// The verified state could not be handed to the import. The download is abandoned.
+	ImportFailed(Box<dyn Error + Send + Sync>),
+	/// The whole state was downloaded and the target should be imported.
+	Import,
+}
+
+/// Download of the state of a single finalized block, one key range at a time.
+///
+/// Every response is checked against the state root of the block with its read proof, and
+/// streamed into the import of the block. The top trie is downloaded first, followed by the child
+/// tries it references. Whether nothing was left out is only known once the whole state is
+/// downloaded and its root computed.
+pub(crate) struct StateSync<B: BlockT> {
+	provider: Arc<dyn WarpSyncProvider<B>>,
+	target: WarpSyncFragment<B>,
+	/// Import of the target, started with the first response.
+	import: Option<Box<dyn StateImport<B>>>,
+	/// Root of the state imported so far.
+	imported_root: Option<B::Hash>,
+	/// Child trie being downloaded, or `None` for the top trie.
+	current: Option<ChildInfo>,
+	/// Child tries left to download.
+	pending_children: Vec<ChildInfo>,
+	/// Last key received in the trie being downloaded.
+	last_key: Vec<u8>,
+	/// Number of key-value bytes received so far.
+	downloaded_bytes: usize,
+}
+
+impl<B: BlockT> StateSync<B> {
+	/// Start downloading the state of the given warp sync target.
+	pub(crate) fn new(provider: Arc<dyn WarpSyncProvider<B>>, target: WarpSyncFragment<B>) -> Self {
+		StateSync {
+			provider,
+			target,
+			import: None,
+			imported_root: None,
+			current: None,
+			pending_children: Vec::new(),
+			last_key: Vec::new(),
+			downloaded_bytes: 0,
+		}
+	}
+
+	/// Number of the block whose state is downloaded.
+	pub(crate) fn target_number(&self) -> NumberFor<B> {
+		*self.target.header.number()
+	}
+
+	/// Hash of the block whose state is downloaded.
+	pub(crate) fn target_hash(&self) -> B::Hash {
+		self.target.header.hash()
+	}
+
+	/// The next state request to make.
+	pub(crate) fn request(&self) -> StateRequest {
+		StateRequest {
+			block: self.target.header.hash().encode(),
+			start: self.last_key.clone(),
+			child_trie: self.current.as_ref()
+				.map(|child_info| child_info.storage_key().to_vec())
+				.unwrap_or_default(),
+		}
+	}
+
+	/// Verify a response to the last request and pass its entries to the import.
+	pub(crate) fn on_response(&mut self, response: StateResponse) -> StateSyncResult {
+		if response.entries.is_empty() && !response.complete {
+			debug!(target: "sync", "Bad state response: no entries");
+			return StateSyncResult::BadResponse
+		}
+		let mut previous = &self.last_key;
+		for entry in &response.entries {
+			if entry.key <= *previous {
+				debug!(target: "sync", "Bad state response: keys out of order");
+				return StateSyncResult::BadResponse
+			}
+			previous = &entry.key;
+		}
+
+		let proof = match StorageProof::decode(&mut &response.proof[..]) {
+			Ok(proof) => proof,
+			Err(e) => {
+				debug!(target: "sync", "Bad state response: failed to decode proof: {}", e);
+				return StateSyncResult::BadResponse
+			},
+		};
+		let root = *self.target.header.state_root();
+		let keys = response.entries.iter().map(|entry| &entry.key);
+		let checked = match &self.current {
+			Some(child_info) => read_child_proof_check::<HashFor<B>, _>(root, proof, child_info, keys),
+			None => read_proof_check::<HashFor<B>, _>(root, proof, keys),
+		};
+		let checked = match checked {
+			Ok(checked) => checked,
+			Err(e) => {
+				debug!(target: "sync", "Bad state response: invalid proof: {}", e);
+				return StateSyncResult::BadResponse
+			},
+		};
+		let matches_proof = response.entries.iter()
+			.all(|entry| checked.get(&entry.key).map_or(false, |v| v.as_ref() == Some(&entry.value)));
+		if !matches_proof {
+			debug!(target: "sync", "Bad state response: entries do not match the proof");
+			return StateSyncResult::BadResponse
+		}
+
+		if let Some(last) = response.entries.last() {
+			self.last_key = last.key.clone();
+		}
+		let mut chunk = Vec::with_capacity(response.entries.len());
+		for entry in response.entries {
+			self.downloaded_bytes += entry.key.len() + entry.value.len();
+			// The roots of child tries are computed on import rather than taken from the top trie.
+			let prefix = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+			if self.current.is_none() && entry.key.starts_with(prefix) {
+				self.pending_children.push(ChildInfo::new_default(&entry.key[prefix.len()..]));
+			} else {
+				chunk.push((entry.key, entry.value));
+			}
+		}
+		if let Err(e) = self.append(chunk) {
+			return StateSyncResult::ImportFailed(e)
+		}
+		debug!(
+			target: "sync",
+			"Downloaded {} bytes of the state of #{}",
+			self.downloaded_bytes,
+			self.target_number(),
+		);
+
+		if !response.complete {
+			return StateSyncResult::Continue
+		}
+		self.last_key.clear();
+		self.current = self.pending_children.pop();
+		if self.current.is_some() {
+			return StateSyncResult::Continue
+		}
+		if self.imported_root.as_ref() != Some(self.target.header.state_root()) {
+			debug!(
+				target: "sync",
+				"Bad state of #{}: root mismatch, downloading it again",
+				self.target_number(),
+			);
+			self.restart();
+			return StateSyncResult::BadState
+		}
+		StateSyncResult::Import
+	}
+
+	/// Import the target along with its downloaded state.
+	pub(crate) fn import(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+		match self.import {
+			Some(import) => import.commit(),
+			None => Err("No state was downloaded".into()),
+		}
+	}
+
+	fn append(
+		&mut self,
+		chunk: Vec<(Vec<u8>, Vec<u8>)>,
+	) -> Result<(), Box<dyn Error + Send + Sync>> {
+		if self.import.is_none() {
+			self.import = Some(self.provider.begin_import(self.target.clone())?);
+		}
+		let import = self.import.as_mut().expect("import is set above; qed");
+		// An empty trie still has a root, which only the import knows.
+		if !chunk.is_empty() || self.imported_root.is_none() {
+			self.imported_root = Some(import.append(self.current.as_ref(), chunk)?);
+		}
+		Ok(())
+	}
+
+	/// Discard everything downloaded so far and start over.
+	fn restart(&mut self) {
+		self.import = None;
+		self.imported_root = None;
+		self.current = None;
+		self.pending_children.clear();
+		self.last_key.clear();
+		self.downloaded_bytes = 0;
+	}
+}
@@ -0,0 +1,188 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-peer block download throughput, used to size block requests.
+
+use std::time::Duration;
+use wasm_timer::Instant;
+
+/// Minimum number of blocks to request from a single peer at once.
+pub const MIN_BLOCKS_TO_REQUEST: usize = 8;
+
+/// Time we would like a single block request to take. Requests to a peer are sized so that they
+/// complete in about this time given the measured throughput of the peer.
+const TARGET_REQUEST_DURATION: Duration = Duration::from_secs(2);
+
+/// A request is considered stalled after taking this many times longer than expected.
+const STALL_FACTOR: u32 = 4;
+
+/// Minimum time before a request is considered stalled, regardless of the peer throughput.
+const MIN_STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Weight of the most recent measurement in the moving average.
+const SMOOTHING: f64 = 0.3;
+
+/// Block download statistics of a peer.
+#[derive(Debug, Clone, Default)]
+pub struct PeerThroughput {
+	/// Moving average of the blocks per second received from the peer. `None` until the first
+	/// response is received.
+	blocks_per_sec: Option<f64>,
+	/// Start of the block request in flight, and number of blocks requested.
+	in_flight: Option<(Instant, u32)>,
+	/// Whether the range of the request in flight was given to another peer.
+	reassigned: bool,
+}
+
+impl PeerThroughput {
+	/// Maximum number of blocks to request from the peer, between `MIN_BLOCKS_TO_REQUEST` and
+	/// `max`. Peers we have no measurement for get `max`.
+	pub fn max_blocks_per_request(&self, max: usize) -> usize {
+		match self.blocks_per_sec {
+			Some(rate) => {
+				let blocks = (rate * TARGET_REQUEST_DURATION.as_secs_f64()) as usize;
+				blocks.max(MIN_BLOCKS_TO_REQUEST).min(max)
+			},
+			None => max,
+		}
+	}
+
+	/// Note that a request for `count` blocks was sent to the peer.
+	pub fn on_request(&mut self, count: u32) {
+		self.in_flight = Some((Instant::now(), count));
+		self.reassigned = false;
+	}
+
+	/// Note that a response with `count` blocks was received for the request in flight.
+	pub fn on_response(&mut self, count: usize) {
+		let (started, _) = match self.in_flight.take() {
+			Some(in_flight) => in_flight,
+			None => return,
+		};
+		self.reassigned = false;
+		// Guard against instant responses, which would give an infinite rate.
+		let elapsed = started.elapsed().as_secs_f64().max(0.001);
+		let sample = count as f64 / elapsed;
+		self.blocks_per_sec = Some(match self.blocks_per_sec {
+			Some(rate) => rate + SMOOTHING * (sample - rate),
+			None => sample,
+		});
+	}
+
+	/// Upper bound of the current rate of the peer, taking the request in flight into account.
+	///
+	/// A request in flight for longer than the expected time lowers the rate, so that a peer
+	/// which stopped responding is not considered fast because of its past measurements.
+	pub fn current_rate(&self) -> Option<f64> {
+		let in_flight_rate = self.in_flight.map(|(started, count)| {
+			count as f64 / started.elapsed().as_secs_f64().max(0.001)
+		});
+		match (self.blocks_per_sec, in_flight_rate) {
+			(Some(rate), Some(in_flight)) => Some(rate.min(in_flight)),
+			(rate, in_flight) => rate.or(in_flight),
+		}
+	}
+
+	/// Returns `true` if the request in flight takes much longer than expected and its range
+	/// has not been given to another peer yet.
+	pub fn is_stalled(&self) -> bool {
+		let (started, count) = match self.in_flight {
+			Some(in_flight) if !self.reassigned => in_flight,
+			_ => return false,
+		};
+		let expected = self.blocks_per_sec
+			.map(|rate| Duration::from_secs_f64(count as f64 / rate.max(0.001)))
+			.unwrap_or(TARGET_REQUEST_DURATION);
+		started.elapsed() > (expected * STALL_FACTOR).max(MIN_STALL_TIMEOUT)
+	}
+
+	/// Pretends that the request in flight was sent `ago` earlier than it was.
+	#[cfg(test)]
+	pub fn backdate_request(&mut self, ago: Duration) {
+		if let Some((started, _)) = self.in_flight.as_mut() {
+			*started = *started - ago;
+		}
+	}
+
+	/// Note that the range of the request in flight was given to another peer.
+	pub fn on_reassigned(&mut self) {
+		self.reassigned = true;
+	}
+
+	/// Returns `true` if the peer is expected to download blocks faster than a peer with the
+	/// given rate. Peers we have no measurement for are assumed to be faster.
+	pub fn is_faster_than(&self, rate: Option<f64>) -> bool {
+		match (self.blocks_per_sec, rate) {
+			(None, _) => true,
+			(Some(_), None) => false,
+			(Some(own), Some(other)) => own > other,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn with_rate(blocks_per_sec: f64) -> PeerThroughput {
+		PeerThroughput { blocks_per_sec: Some(blocks_per_sec), ..Default::default() }
+	}
+
+	#[test]
+	fn request_size_follows_throughput() {
+		assert_eq!(PeerThroughput::default().max_blocks_per_request(128), 128);
+		assert_eq!(with_rate(1000.0).max_blocks_per_request(128), 128);
+		assert_eq!(with_rate(20.0).max_blocks_per_request(128), 40);
+		assert_eq!(with_rate(0.5).max_blocks_per_request(128), MIN_BLOCKS_TO_REQUEST);
+	}
+
+	#[test]
+	fn response_updates_rate() {
+		let mut throughput = PeerThroughput::default();
+		throughput.on_response(10);
+		assert!(throughput.blocks_per_sec.is_none());
+
+		throughput.on_request(64);
+		assert!(throughput.current_rate().is_some());
+		throughput.on_response(64);
+		assert!(throughput.blocks_per_sec.is_some());
+		assert!(throughput.in_flight.is_none());
+	}
+
+	#[test]
+	fn stall_detection() {
+		let mut throughput = with_rate(1.0);
+		assert!(!throughput.is_stalled());
+
+		throughput.in_flight = Some((Instant::now() - Duration::from_secs(60), 32));
+		assert!(!throughput.is_stalled());
+		throughput.in_flight = Some((Instant::now() - Duration::from_secs(60), 2));
+		assert!(throughput.is_stalled());
+
+		throughput.on_reassigned();
+		assert!(!throughput.is_stalled());
+	}
+
+	#[test]
+	fn unknown_peers_are_faster() {
+		assert!(PeerThroughput::default().is_faster_than(Some(1000.0)));
+		assert!(!with_rate(10.0).is_faster_than(None));
+		assert!(with_rate(10.0).is_faster_than(Some(5.0)));
+		assert!(!with_rate(10.0).is_faster_than(Some(50.0)));
+	}
+}
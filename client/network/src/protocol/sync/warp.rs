@@ -0,0 +1,114 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use super::state::StateSync;
+use crate::warp_sync::{WarpProofRequest, WarpSyncFragment, WarpSyncProvider};
+use log::debug;
+use sp_finality_grandpa::{AuthorityList, SetId};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
+use std::sync::Arc;
+
+/// Result of [`WarpSync::on_response`].
+pub(crate) enum WarpSyncResult<B: BlockT> {
+	/// The proof was verified and more of it should be requested.
+	Continue,
+	/// The proof could not be verified.
+	BadResponse,
+	/// The proof holds nothing new. Contains the last verified fragment, if any.
+	Complete(Option<WarpSyncFragment<B>>),
+}
+
+/// Download of the warp sync proof, one response at a time.
+///
+/// Each request starts at the last verified block, so a response may repeat the fragment of that
+/// block. The download is complete once a response has nothing past it.
+pub(crate) struct WarpSync<B: BlockT> {
+	provider: Arc<dyn WarpSyncProvider<B>>,
+	/// Number and hash of the last verified block.
+	begin: (NumberFor<B>, B::Hash),
+	/// Id of the authority set in effect after `begin`.
+	set_id: SetId,
+	/// Authority set in effect after `begin`.
+	authorities: AuthorityList,
+	/// Last verified fragment.
+	target: Option<WarpSyncFragment<B>>,
+}
+
+impl<B: BlockT> WarpSync<B> {
+	/// Start a warp sync at our last finalized block.
+	pub(crate) fn new(
+		provider: Arc<dyn WarpSyncProvider<B>>,
+		finalized_number: NumberFor<B>,
+		finalized_hash: B::Hash,
+	) -> Self {
+		let (set_id, authorities) = provider.current_authorities();
+		WarpSync {
+			provider,
+			begin: (finalized_number, finalized_hash),
+			set_id,
+			authorities,
+			target: None,
+		}
+	}
+
+	/// Number of the last verified block.
+	pub(crate) fn begin_number(&self) -> NumberFor<B> {
+		self.begin.0
+	}
+
+	/// The next proof request to make.
+	pub(crate) fn request(&self) -> WarpProofRequest<B> {
+		WarpProofRequest { begin: self.begin.1 }
+	}
+
+	/// Verify a response to the last request.
+	pub(crate) fn on_response(&mut self, response: &[u8]) -> WarpSyncResult<B> {
+		let verified = self.provider.verify(
+			response,
+			self.set_id,
+			self.authorities.clone(),
+			self.begin.0,
+		);
+		match verified {
+			Ok(Some(fragment)) => {
+				debug!(
+					target: "sync",
+					"Warp sync proof verified up to #{} ({}), authority set {}",
+					fragment.header.number(),
+					fragment.header.hash(),
+					fragment.set_id,
+				);
+				self.begin = (*fragment.header.number(), fragment.header.hash());
+				self.set_id = fragment.set_id;
+				self.authorities = fragment.authorities.clone();
+				self.target = Some(fragment);
+				WarpSyncResult::Continue
+			},
+			Ok(None) => WarpSyncResult::Complete(self.target.take()),
+			Err(e) => {
+				debug!(target: "sync", "Bad warp sync proof: {}", e);
+				WarpSyncResult::BadResponse
+			},
+		}
+	}
+
+	/// Download the state of the target of the completed warp sync, to import them together.
+	pub(crate) fn into_state_sync(self, target: WarpSyncFragment<B>) -> StateSync<B> {
+		StateSync::new(self.provider, target)
+	}
+}
@@ -52,10 +52,11 @@ use libp2p::{
 };
 use std::{
 	borrow::Cow, collections::{hash_map::Entry, HashMap}, convert::TryFrom as _, io, iter,
-	pin::Pin, task::{Context, Poll}, time::Duration,
+	pin::Pin, sync::Arc, task::{Context, Poll}, time::Duration,
 };
 use wasm_timer::Instant;
 use crate::ReputationChange;
+use crate::traffic::{Direction, TrafficCounters};
 
 pub use libp2p::request_response::{InboundFailure, OutboundFailure, RequestId};
 
@@ -239,6 +240,9 @@ pub struct RequestResponsesBehaviour {
 
 	/// Whenever an incoming request arrives, the arrival [`Instant`] is recorded here.
 	pending_responses_arrival_time: HashMap<ProtocolRequestId, Instant>,
+
+	/// Number of bytes exchanged on each protocol. Accounts for the requests and responses.
+	traffic: Arc<TrafficCounters>,
 }
 
 /// Generated by the response builder and waiting to be processed.
@@ -253,7 +257,10 @@ struct RequestProcessingOutcome {
 impl RequestResponsesBehaviour {
 	/// Creates a new behaviour. Must be passed a list of supported protocols. Returns an error if
 	/// the same protocol is passed twice.
-	pub fn new(list: impl Iterator<Item = ProtocolConfig>) -> Result<Self, RegisterError> {
+	pub fn new(
+		list: impl Iterator<Item = ProtocolConfig>,
+		traffic: Arc<TrafficCounters>,
+	) -> Result<Self, RegisterError> {
 		let mut protocols = HashMap::new();
 		for protocol in list {
 			let mut cfg = RequestResponseConfig::default();
@@ -283,6 +290,7 @@ impl RequestResponsesBehaviour {
 			pending_requests: Default::default(),
 			pending_responses: Default::default(),
 			pending_responses_arrival_time: Default::default(),
+			traffic,
 		})
 	}
 
@@ -301,6 +309,7 @@ impl RequestResponsesBehaviour {
 	) {
 		if let Some((protocol, _)) = self.protocols.get_mut(protocol_name) {
 			if protocol.is_connected(target) || connect.should_connect() {
+				self.traffic.record(protocol_name, Direction::Outbound, request.len());
 				let request_id = protocol.send_request(target, request);
 				let prev_req_id = self.pending_requests.insert(
 					(protocol_name.to_string().into(), request_id).into(),
@@ -472,7 +481,10 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 
 				if let Ok(payload) = result {
 					if let Some((protocol, _)) = self.protocols.get_mut(&*protocol_name) {
-						if let Err(_) = protocol.send_response(inner_channel, Ok(payload)) {
+						let payload_len = payload.len();
+						if protocol.send_response(inner_channel, Ok(payload)).is_ok() {
+							self.traffic.record(&protocol_name, Direction::Outbound, payload_len);
+						} else {
 							// Note: Failure is handled further below when receiving
 							// `InboundFailure` event from `RequestResponse` behaviour.
 							log::debug!(
@@ -543,6 +555,7 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 								(protocol.clone(), request_id.clone()).into(),
 								Instant::now(),
 							);
+							self.traffic.record(protocol, Direction::Inbound, request.len());
 
 							let (tx, rx) = oneshot::channel();
 
@@ -589,6 +602,10 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 							},
 							..
 						} => {
+							if let Ok(payload) = &response {
+								self.traffic.record(protocol, Direction::Inbound, payload.len());
+							}
+
 							let (started, delivered) = match self.pending_requests.remove(
 								&(protocol.clone(), request_id).into(),
 							) {
@@ -892,7 +909,7 @@ mod tests {
 			.multiplex(libp2p::yamux::YamuxConfig::default())
 			.boxed();
 
-		let behaviour = RequestResponsesBehaviour::new(list).unwrap();
+		let behaviour = RequestResponsesBehaviour::new(list, Default::default()).unwrap();
 
 		let mut swarm = Swarm::new(transport, behaviour, keypair.public().into_peer_id());
 		let listen_addr: Multiaddr = format!("/memory/{}", rand::random::<u64>()).parse().unwrap();
@@ -30,7 +30,7 @@
 use crate::{
 	ExHashT, NetworkStateInfo, NetworkStatus,
 	behaviour::{self, Behaviour, BehaviourOut},
-	config::{parse_str_addr, Params, Role, TransportConfig},
+	config::{parse_str_addr, BanTarget, Params, Role, TransportConfig},
 	DhtEvent,
 	discovery::DiscoveryConfig,
 	error::Error,
@@ -39,6 +39,7 @@ use crate::{
 	},
 	on_demand_layer::AlwaysBadChecker,
 	light_client_requests,
+	peer_store::{self, PeerStore, PeerStoreEntry},
 	protocol::{
 		self,
 		NotifsHandlerError,
@@ -49,8 +50,9 @@ use crate::{
 		event::Event,
 		sync::SyncState,
 	},
-	transport, ReputationChange,
-	bitswap::Bitswap,
+	traffic::TrafficCounters,
+	transport, utils, ReputationChange,
+	bitswap::{Bitswap, BitswapRequestFailure, Cid},
 };
 use futures::{channel::oneshot, prelude::*};
 use libp2p::{PeerId, multiaddr, Multiaddr};
@@ -74,7 +76,7 @@ use libp2p::swarm::{
 	SwarmEvent,
 	protocols_handler::NodeHandlerWrapperError
 };
-use log::{error, info, trace, warn};
+use log::{debug, error, info, trace, warn};
 use metrics::{Metrics, MetricSources, Histogram, HistogramVec};
 use parking_lot::Mutex;
 use sc_peerset::PeersetHandle;
@@ -181,7 +183,14 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			local_peer_id.to_base58(),
 		);
 
-		let (protocol, peerset_handle, mut known_addresses) = Protocol::new(
+		let (warp_sync_provider, warp_sync_protocol_config) = match params.warp_sync {
+			Some((provider, protocol_config)) => (Some(provider), Some(protocol_config)),
+			None => (None, None),
+		};
+
+		let traffic = Arc::new(TrafficCounters::default());
+
+		let (mut protocol, peerset_handle, mut known_addresses) = Protocol::new(
 			protocol::ProtocolConfig {
 				roles: From::from(&params.role),
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
@@ -193,6 +202,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			&params.network_config,
 			params.block_announce_validator,
 			params.metrics_registry.as_ref(),
+			warp_sync_provider,
+			params.sync_checkpoint,
+			traffic.clone(),
 		)?;
 
 		// List of multiaddresses that we know in the network.
@@ -225,6 +237,23 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				}
 			)?;
 
+		// Restore what we knew about other nodes before the restart. The reputations are reported
+		// before the nodes are added to the peerset, so that banned nodes are not connected to.
+		let mut peer_store = PeerStore::open(params.network_config.net_config_path.as_deref());
+		for (peer_id, reputation) in peer_store.take_restored_reputations() {
+			peerset_handle.report_peer(
+				peer_id,
+				ReputationChange::new(reputation, "Restored from the peer store"),
+			);
+		}
+		known_addresses.extend(peer_store.addresses().map(|(p, a)| (p.clone(), a.clone())));
+		protocol.add_default_set_discovered_nodes(
+			peer_store.addresses()
+				.map(|(peer_id, _)| peer_id.clone())
+				.collect::<HashSet<_>>()
+				.into_iter()
+		);
+
 		// Print a message about the deprecation of sentry nodes.
 		let print_deprecated_message = match &params.role {
 			Role::Sentry { .. } => true,
@@ -248,6 +277,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		let num_connected = Arc::new(AtomicUsize::new(0));
 		let is_major_syncing = Arc::new(AtomicBool::new(false));
 
+		let deny_list = transport::DenyList::new(
+			params.network_config.denied_peers.iter().cloned().map(BanTarget::Peer)
+				.chain(params.network_config.denied_subnets.iter().cloned().map(BanTarget::Subnet))
+		);
+
 		// Build the swarm.
 		let client = params.chain.clone();
 		let (mut swarm, bandwidth): (Swarm<B, H>, _) = {
@@ -289,10 +323,15 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			};
 
 			let (transport, bandwidth) = {
-				let (config_mem, config_wasm) = match params.network_config.transport {
-					TransportConfig::MemoryOnly => (true, None),
-					TransportConfig::Normal { wasm_external_transport, .. } =>
-						(false, wasm_external_transport)
+				let (config_mem, config_wasm, config_quic) = match params.network_config.transport {
+					TransportConfig::MemoryOnly => (true, None, false),
+					TransportConfig::Normal { wasm_external_transport, enable_quic, .. } =>
+						(false, wasm_external_transport, enable_quic)
+				};
+				let quic_listen_address = if config_quic {
+					Some(transport::quic_listen_address(&params.network_config.listen_addresses))
+				} else {
+					None
 				};
 
 				// The yamux buffer size limit is configured to be equal to the maximum frame size
@@ -331,7 +370,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 					config_mem,
 					config_wasm,
 					params.network_config.yamux_window_size,
-					yamux_maximum_buffer_size
+					yamux_maximum_buffer_size,
+					deny_list.clone(),
+					params.network_config.bandwidth_limits,
+					quic_listen_address,
 				)
 			};
 
@@ -345,9 +387,12 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 					light_client_request_sender,
 					discovery_config,
 					params.block_request_protocol_config,
+					params.state_request_protocol_config,
 					bitswap,
 					params.light_client_request_protocol_config,
+					warp_sync_protocol_config,
 					params.network_config.request_response_protocols,
+					traffic.clone(),
 				);
 
 				match result {
@@ -385,6 +430,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			Some(registry) => {
 				Some(metrics::register(registry, MetricSources {
 					bandwidth: bandwidth.clone(),
+					protocols_traffic: traffic.clone(),
 					major_syncing: is_major_syncing.clone(),
 					connected_peers: num_connected.clone(),
 				})?)
@@ -392,6 +438,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			None => None
 		};
 
+		for peer_id in &params.network_config.denied_peers {
+			Swarm::<B, H>::ban_peer_id(&mut swarm, peer_id.clone());
+		}
+
 		// Listen on multiaddresses.
 		for addr in &params.network_config.listen_addresses {
 			if let Err(err) = Swarm::<B, H>::listen_on(&mut swarm, addr.clone()) {
@@ -434,6 +484,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			peers_notifications_sinks,
 			metrics,
 			boot_node_ids,
+			peer_store,
+			peer_store_save_interval: Box::pin(utils::interval(peer_store::SAVE_INTERVAL)),
+			deny_list,
+			traffic,
 		})
 	}
 
@@ -595,6 +649,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			connected_peers,
 			not_connected_peers,
 			peerset: swarm.user_protocol_mut().peerset_debug_info(),
+			protocols_traffic: self.traffic.snapshot(),
 		}
 	}
 
@@ -616,6 +671,80 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 	pub fn add_reserved_peer(&self, peer: String) -> Result<(), String> {
 		self.service.add_reserved_peer(peer)
 	}
+
+	/// Returns the reputations and known-good addresses of other nodes, as they would be
+	/// persisted to disk.
+	pub fn peer_store(&mut self) -> Vec<PeerStoreEntry> {
+		let reputations = self.network_service.user_protocol_mut().peerset_reputations();
+		self.peer_store.entries(reputations)
+	}
+
+	/// Forgets the reputations and known-good addresses of all the other nodes, both in memory
+	/// and on disk.
+	///
+	/// Nodes that were banned because of their reputation can connect again.
+	pub fn clear_peer_store(&mut self) {
+		self.network_service.user_protocol_mut().clear_peerset_reputations();
+		self.peer_store.clear();
+	}
+
+	/// Refuses all further connections with the given node or subnet, and closes the existing
+	/// ones.
+	///
+	/// Nodes with a connection to or from an address of a banned subnet are disconnected
+	/// altogether.
+	pub fn ban(&mut self, target: BanTarget) {
+		if !self.deny_list.insert(target.clone()) {
+			return;
+		}
+
+		match target {
+			BanTarget::Peer(peer_id) => {
+				self.service.peerset.ban_peer(peer_id.clone());
+				Swarm::<B, H>::ban_peer_id(&mut self.network_service, peer_id);
+			},
+			BanTarget::Subnet(_) => {
+				let deny_list = &self.deny_list;
+				let denied = self.network_service.connected_nodes()
+					.filter(|(_, node)| node.endpoints().any(|endpoint| {
+						deny_list.is_address_denied(endpoint.get_remote_address())
+					}))
+					.map(|(peer_id, _)| peer_id.clone())
+					.collect::<Vec<_>>();
+				for peer_id in denied {
+					disconnect_peer::<B, H>(&mut self.network_service, deny_list, peer_id);
+				}
+			},
+		}
+	}
+
+	/// Lifts a ban put in place with [`NetworkWorker::ban`] or through the network configuration.
+	///
+	/// Returns `false` if the node or subnet wasn't banned.
+	pub fn unban(&mut self, target: &BanTarget) -> bool {
+		if !self.deny_list.remove(target) {
+			return false;
+		}
+
+		if let BanTarget::Peer(peer_id) = target {
+			self.service.peerset.unban_peer(peer_id.clone());
+			Swarm::<B, H>::unban_peer_id(&mut self.network_service, peer_id.clone());
+		}
+		true
+	}
+
+	/// Returns the banned nodes and subnets.
+	pub fn banned(&self) -> Vec<BanTarget> {
+		let mut banned = self.deny_list.targets();
+		banned.sort_by_cached_key(|target| target.to_string());
+		banned
+	}
+
+	/// Writes the reputations and known-good addresses of other nodes to disk.
+	fn save_peer_store(&mut self) {
+		let reputations = self.network_service.user_protocol_mut().peerset_reputations();
+		self.peer_store.save(reputations);
+	}
 }
 
 impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
@@ -873,6 +1002,31 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		});
 	}
 
+	/// Fetches the IPFS block identified by `cid` from `target` over the bitswap protocol.
+	///
+	/// The returned data is checked against the multihash of `cid`. Requires
+	/// [`NetworkConfiguration::ipfs_server`](crate::config::NetworkConfiguration::ipfs_server)
+	/// to be enabled and an existing connection to `target`, which must support bitswap.
+	pub async fn bitswap_request(
+		&self,
+		target: PeerId,
+		cid: Cid,
+	) -> Result<Vec<u8>, BitswapRequestFailure> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::BitswapRequest {
+			target,
+			cid,
+			pending_response: tx,
+		});
+
+		match rx.await {
+			Ok(v) => v,
+			// The network worker no longer exists, and with it all connections.
+			Err(_) => Err(BitswapRequestFailure::ConnectionClosed),
+		}
+	}
+
 	/// You may call this when new transactons are imported by the transaction pool.
 	///
 	/// All transactions will be fetched from the `TransactionPool` that was passed at
@@ -1292,6 +1446,11 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 		pending_response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
 		connect: IfDisconnected,
 	},
+	BitswapRequest {
+		target: PeerId,
+		cid: Cid,
+		pending_response: oneshot::Sender<Result<Vec<u8>, BitswapRequestFailure>>,
+	},
 	DisconnectPeer(PeerId, Cow<'static, str>),
 	NewBestBlockImported(B::Hash, NumberFor<B>),
 }
@@ -1326,6 +1485,14 @@ pub struct NetworkWorker<B: BlockT + 'static, H: ExHashT> {
 	/// For each peer and protocol combination, an object that allows sending notifications to
 	/// that peer. Shared with the [`NetworkService`].
 	peers_notifications_sinks: Arc<Mutex<HashMap<(PeerId, Cow<'static, str>), NotificationsSink>>>,
+	/// Reputations and known-good addresses of other nodes, persisted across restarts.
+	peer_store: PeerStore,
+	/// Interval at which the peer store is written to disk.
+	peer_store_save_interval: Pin<Box<dyn Stream<Item = ()> + Send>>,
+	/// Nodes and subnets we refuse connections with. Shared with the transport.
+	deny_list: transport::DenyList,
+	/// Number of bytes exchanged on each protocol. Shared with the behaviours.
+	traffic: Arc<TrafficCounters>,
 }
 
 impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
@@ -1417,6 +1584,8 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 				ServiceToWorkerMsg::Request { target, protocol, request, pending_response, connect } => {
 					this.network_service.send_request(&target, &protocol, request, pending_response, connect);
 				},
+				ServiceToWorkerMsg::BitswapRequest { target, cid, pending_response } =>
+					this.network_service.bitswap_request(target, cid, pending_response),
 				ServiceToWorkerMsg::DisconnectPeer(who, protocol_name) =>
 					this.network_service.user_protocol_mut().disconnect_peer(&who, &protocol_name),
 				ServiceToWorkerMsg::NewBestBlockImported(hash, number) =>
@@ -1620,6 +1789,24 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 				Poll::Ready(SwarmEvent::ConnectionEstablished { peer_id, endpoint, num_established }) => {
 					trace!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 
+					// The subnet of the remote address may have been banned while the connection
+					// was being negotiated.
+					if this.deny_list.is_address_denied(endpoint.get_remote_address()) {
+						debug!(
+							target: "sub-libp2p",
+							"Closing connection with {:?} at denied address {}",
+							peer_id,
+							endpoint.get_remote_address(),
+						);
+						disconnect_peer::<B, H>(
+							&mut this.network_service,
+							&this.deny_list,
+							peer_id.clone(),
+						);
+					} else if let ConnectedPoint::Dialer { address } = &endpoint {
+						this.peer_store.add_address(peer_id.clone(), address.clone());
+					}
+
 					if let Some(metrics) = this.metrics.as_ref() {
 						let direction = match endpoint {
 							ConnectedPoint::Dialer { .. } => "out",
@@ -1761,6 +1948,10 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 			};
 		}
 
+		while let Poll::Ready(Some(())) = this.peer_store_save_interval.poll_next_unpin(cx) {
+			this.save_peer_store();
+		}
+
 		let num_connected_peers = this.network_service.user_protocol_mut().num_connected_peers();
 
 		// Update the variables shared with the `NetworkService`.
@@ -1808,6 +1999,12 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 impl<B: BlockT + 'static, H: ExHashT> Unpin for NetworkWorker<B, H> {
 }
 
+impl<B: BlockT + 'static, H: ExHashT> Drop for NetworkWorker<B, H> {
+	fn drop(&mut self) {
+		self.save_peer_store();
+	}
+}
+
 /// The libp2p swarm, customized for our needs.
 type Swarm<B, H> = libp2p::swarm::Swarm<Behaviour<B, H>>;
 
@@ -1833,6 +2030,20 @@ impl<'a, B: BlockT, H: ExHashT> Link<B> for NetworkLink<'a, B, H> {
 	}
 }
 
+/// Closes all the connections with `peer_id`.
+fn disconnect_peer<B: BlockT, H: ExHashT>(
+	swarm: &mut Swarm<B, H>,
+	deny_list: &transport::DenyList,
+	peer_id: PeerId,
+) {
+	// Banning a node closes its connections. The ban is lifted right away, unless the node itself
+	// is denied, in which case it is banned already.
+	Swarm::<B, H>::ban_peer_id(swarm, peer_id.clone());
+	if !deny_list.is_peer_denied(&peer_id) {
+		Swarm::<B, H>::unban_peer_id(swarm, peer_id);
+	}
+}
+
 fn ensure_addresses_consistent_with_transport<'a>(
 	addresses: impl Iterator<Item = &'a Multiaddr>,
 	transport: &TransportConfig,
@@ -16,6 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::traffic::TrafficCounters;
 use crate::transport::BandwidthSinks;
 use prometheus_endpoint::{
 	self as prometheus,
@@ -36,6 +37,7 @@ pub use prometheus_endpoint::{Histogram, HistogramVec};
 /// Registers all networking metrics with the given registry.
 pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics, PrometheusError> {
 	BandwidthCounters::register(registry, sources.bandwidth)?;
+	ProtocolBandwidthCounters::register(registry, sources.protocols_traffic)?;
 	MajorSyncingGauge::register(registry, sources.major_syncing)?;
 	NumConnectedGauge::register(registry, sources.connected_peers)?;
 	Metrics::register(registry)
@@ -44,6 +46,7 @@ pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics,
 /// Predefined metric sources that are fed directly into prometheus.
 pub struct MetricSources {
 	pub bandwidth: Arc<BandwidthSinks>,
+	pub protocols_traffic: Arc<TrafficCounters>,
 	pub major_syncing: Arc<AtomicBool>,
 	pub connected_peers: Arc<AtomicUsize>,
 }
@@ -288,6 +291,40 @@ impl MetricSource for BandwidthCounters {
 	}
 }
 
+/// The per-protocol bandwidth counter metric.
+#[derive(Clone)]
+pub struct ProtocolBandwidthCounters(Arc<TrafficCounters>);
+
+impl ProtocolBandwidthCounters {
+	/// Registers the `ProtocolBandwidthCounters` metric whose values are
+	/// obtained from the given counters.
+	fn register(
+		registry: &Registry,
+		counters: Arc<TrafficCounters>,
+	) -> Result<(), PrometheusError> {
+		prometheus::register(SourcedCounter::new(
+			&Opts::new(
+				"sub_libp2p_protocol_bytes_total",
+				"Total size of the notifications, requests and responses exchanged, per protocol"
+			).variable_labels(vec!["protocol".to_owned(), "direction".to_owned()]),
+			ProtocolBandwidthCounters(counters),
+		)?, registry)?;
+
+		Ok(())
+	}
+}
+
+impl MetricSource for ProtocolBandwidthCounters {
+	type N = u64;
+
+	fn collect(&self, mut set: impl FnMut(&[&str], Self::N)) {
+		for (protocol, traffic) in self.0.snapshot() {
+			set(&[protocol.as_str(), "in"], traffic.bytes_in);
+			set(&[protocol.as_str(), "out"], traffic.bytes_out);
+		}
+	}
+}
+
 /// The "major syncing" metric.
 #[derive(Clone)]
 pub struct MajorSyncingGauge(Arc<AtomicBool>);
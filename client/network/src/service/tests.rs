@@ -19,6 +19,7 @@
 use crate::{config, Event, NetworkService, NetworkWorker};
 use crate::block_request_handler::BlockRequestHandler;
 use crate::light_client_requests::handler::LightClientRequestHandler;
+use crate::state_request_handler::StateRequestHandler;
 
 use libp2p::PeerId;
 use futures::prelude::*;
@@ -104,6 +105,15 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		protocol_config
 	};
 
+	let state_request_protocol_config = {
+		let (handler, protocol_config) = StateRequestHandler::new(
+			&protocol_id,
+			client.clone(),
+		);
+		async_std::task::spawn(handler.run().boxed());
+		protocol_config
+	};
+
 	let light_client_request_protocol_config = {
 		let (handler, protocol_config) = LightClientRequestHandler::new(
 			&protocol_id,
@@ -127,7 +137,10 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		),
 		metrics_registry: None,
 		block_request_protocol_config,
+		state_request_protocol_config,
 		light_client_request_protocol_config,
+		warp_sync: None,
+		sync_checkpoint: None,
 	})
 	.unwrap();
 
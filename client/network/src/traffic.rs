@@ -0,0 +1,89 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Accounting of the bytes exchanged on each protocol.
+//!
+//! Contrary to the `BandwidthSinks` of the transport, which measure the raw traffic of all the
+//! connections, the counters here only account for the payloads of notifications, requests and
+//! responses, attributed to the name of the protocol they were exchanged on.
+
+use crate::network_state::ProtocolTraffic;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// Direction of the traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+	/// Received from a remote.
+	Inbound,
+	/// Sent to a remote.
+	Outbound,
+}
+
+/// Number of bytes exchanged on each protocol since the network started.
+///
+/// Shared between the behaviours that exchange data and the consumers of the statistics.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounters {
+	protocols: Mutex<HashMap<String, ProtocolTraffic>>,
+}
+
+impl TrafficCounters {
+	/// Accounts for `bytes` bytes exchanged on the given protocol.
+	pub(crate) fn record(&self, protocol: &str, direction: Direction, bytes: usize) {
+		let mut protocols = self.protocols.lock();
+		// Avoid allocating the name of the protocol except the first time.
+		if !protocols.contains_key(protocol) {
+			protocols.insert(protocol.to_owned(), ProtocolTraffic::default());
+		}
+		let traffic = protocols.get_mut(protocol).expect("inserted above if missing; qed");
+
+		let counter = match direction {
+			Direction::Inbound => &mut traffic.bytes_in,
+			Direction::Outbound => &mut traffic.bytes_out,
+		};
+		*counter = counter.saturating_add(bytes as u64);
+	}
+
+	/// Returns the number of bytes exchanged so far on each protocol.
+	pub(crate) fn snapshot(&self) -> HashMap<String, ProtocolTraffic> {
+		self.protocols.lock().clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counts_per_protocol_and_direction() {
+		let counters = TrafficCounters::default();
+		counters.record("/dot/sync/2", Direction::Outbound, 10);
+		counters.record("/dot/sync/2", Direction::Inbound, 1000);
+		counters.record("/dot/sync/2", Direction::Inbound, 24);
+		counters.record("/dot/block-announces/1", Direction::Inbound, 7);
+
+		let snapshot = counters.snapshot();
+		assert_eq!(snapshot.len(), 2);
+		assert_eq!(snapshot["/dot/sync/2"], ProtocolTraffic { bytes_in: 1024, bytes_out: 10 });
+		assert_eq!(
+			snapshot["/dot/block-announces/1"],
+			ProtocolTraffic { bytes_in: 7, bytes_out: 0 },
+		);
+	}
+}
@@ -16,19 +16,122 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::config::{BanTarget, BandwidthLimits, is_quic_addr};
+#[cfg(all(feature = "quic", not(target_os = "unknown")))]
+use self::quic::QuicTransport;
+use futures::{future, prelude::*, ready};
+use futures_timer::Delay;
 use libp2p::{
-	PeerId, Transport,
+	Multiaddr, PeerId, Transport,
 	core::{
 		self, either::EitherTransport, muxing::StreamMuxerBox,
-		transport::{Boxed, OptionalTransport}, upgrade
+		multiaddr::Protocol, transport::{Boxed, OptionalTransport}, upgrade
 	},
 	mplex, identity, bandwidth, wasm_ext, noise
 };
 #[cfg(not(target_os = "unknown"))]
 use libp2p::{tcp, dns, websocket};
-use std::{sync::Arc, time::Duration};
+use parking_lot::{Mutex, RwLock};
+use std::{
+	cmp, collections::{HashMap, HashSet}, hash::{Hash, Hasher}, io, net::IpAddr, pin::Pin,
+	sync::{Arc, Weak, atomic::{AtomicU64, Ordering}}, task::{Context, Poll}, time::Duration,
+};
+use wasm_timer::Instant;
+
+#[cfg(all(feature = "quic", not(target_os = "unknown")))]
+mod quic;
+
+/// Number of bytes transferred over all the connections of the transport.
+pub struct BandwidthSinks {
+	/// Connections whose raw socket is observed.
+	sockets: Arc<bandwidth::BandwidthSinks>,
+	/// Bytes read from QUIC substreams.
+	quic_inbound: AtomicU64,
+	/// Bytes written to QUIC substreams.
+	quic_outbound: AtomicU64,
+}
+
+impl BandwidthSinks {
+	/// Returns the total number of bytes that have been downloaded.
+	pub fn total_inbound(&self) -> u64 {
+		self.sockets.total_inbound() + self.quic_inbound.load(Ordering::Relaxed)
+	}
+
+	/// Returns the total number of bytes that have been uploaded.
+	pub fn total_outbound(&self) -> u64 {
+		self.sockets.total_outbound() + self.quic_outbound.load(Ordering::Relaxed)
+	}
+}
+
+/// Nodes and subnets we refuse connections to and from.
+///
+/// Shared between the transport, which drops connections with denied addresses before any
+/// upgrade is negotiated, and the network worker, which updates it at runtime.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DenyList {
+	targets: Arc<RwLock<HashSet<BanTarget>>>,
+}
+
+impl DenyList {
+	/// Builds a deny list out of the given targets.
+	pub(crate) fn new(targets: impl IntoIterator<Item = BanTarget>) -> Self {
+		DenyList { targets: Arc::new(RwLock::new(targets.into_iter().collect())) }
+	}
+
+	/// Adds a target. Returns `false` if it was already denied.
+	pub(crate) fn insert(&self, target: BanTarget) -> bool {
+		self.targets.write().insert(target)
+	}
+
+	/// Removes a target. Returns `false` if it wasn't denied.
+	pub(crate) fn remove(&self, target: &BanTarget) -> bool {
+		self.targets.write().remove(target)
+	}
+
+	/// Returns all the denied targets.
+	pub(crate) fn targets(&self) -> Vec<BanTarget> {
+		self.targets.read().iter().cloned().collect()
+	}
 
-pub use self::bandwidth::BandwidthSinks;
+	/// Returns true if the node is denied.
+	pub(crate) fn is_peer_denied(&self, peer_id: &PeerId) -> bool {
+		self.targets.read().contains(&BanTarget::Peer(peer_id.clone()))
+	}
+
+	/// Returns true if connections to or from this address must be refused, i.e. if any IP
+	/// address it contains, such as the one of a relay, belongs to a denied subnet.
+	pub(crate) fn is_address_denied(&self, addr: &Multiaddr) -> bool {
+		let targets = self.targets.read();
+		addr.iter()
+			.filter_map(|protocol| match protocol {
+				Protocol::Ip4(ip) => Some(IpAddr::from(ip)),
+				Protocol::Ip6(ip) => Some(IpAddr::from(ip)),
+				_ => None,
+			})
+			.any(|ip| targets.iter().any(|target| match target {
+				BanTarget::Subnet(subnet) => subnet.contains(ip),
+				BanTarget::Peer(_) => false,
+			}))
+	}
+
+	/// Returns a function refusing the connections of a transport whose remote address is denied.
+	fn check<T>(
+		&self,
+	) -> impl FnOnce(T, core::ConnectedPoint) -> future::Ready<Result<T, io::Error>> + Clone {
+		let deny_list = self.clone();
+		move |socket, endpoint| {
+			let remote_addr = endpoint.get_remote_address();
+			future::ready(if deny_list.is_address_denied(remote_addr) {
+				Err(io::Error::new(
+					io::ErrorKind::PermissionDenied,
+					format!("Connection with {} refused by the deny list", remote_addr),
+				))
+			} else {
+				Ok(socket)
+			})
+		}
+	}
+}
 
 /// Builds the transport that serves as a common ground for all connections.
 ///
@@ -43,14 +146,28 @@ pub use self::bandwidth::BandwidthSinks;
 /// high-level protocols combined, or to some generously high value if you are sure that a maximum
 /// size is enforced on all high-level protocols.
 ///
+/// Connections whose remote address belongs to a subnet of `deny_list` are closed as soon as they
+/// are established, before any protocol is negotiated on them. The address checked is the one
+/// dialed: connections dialed through a DNS address are not matched against the subnets.
+///
+/// Reading from and writing to the connections is slowed down in order to respect
+/// `bandwidth_limits`.
+///
+/// If `quic_listen_address` is `Some` and the `quic` feature is enabled, `/udp/<port>/quic`
+/// addresses are served by a QUIC endpoint bound to this address once the transport first
+/// listens or dials. The deny list and `bandwidth_limits` apply to QUIC connections as well.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
 /// the connections spawned with this transport.
-pub fn build_transport(
+pub(crate) fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
 	wasm_external_transport: Option<wasm_ext::ExtTransport>,
 	yamux_window_size: Option<u32>,
 	yamux_maximum_buffer_size: usize,
+	deny_list: DenyList,
+	bandwidth_limits: BandwidthLimits,
+	quic_listen_address: Option<Multiaddr>,
 ) -> (Boxed<(PeerId, StreamMuxerBox)>, Arc<BandwidthSinks>) {
 	// Build the base layer of the transport.
 	let transport = if let Some(t) = wasm_external_transport {
@@ -78,7 +195,32 @@ pub fn build_transport(
 		OptionalTransport::none()
 	});
 
-	let (transport, bandwidth) = bandwidth::BandwidthLogging::new(transport);
+	#[cfg(not(all(feature = "quic", not(target_os = "unknown"))))]
+	if let Some(addr) = &quic_listen_address {
+		log::warn!(
+			target: "sub-libp2p",
+			"QUIC support isn't compiled in, not listening on {}",
+			addr,
+		);
+	}
+
+	#[cfg(all(feature = "quic", not(target_os = "unknown")))]
+	let quic_deny_list = deny_list.clone();
+	let transport = transport.and_then(deny_list.check());
+
+	let limiters = Limiters::new(bandwidth_limits);
+	let socket_limiters = limiters.clone();
+	let transport = transport.map(move |socket, _| {
+		let (read_limiters, write_limiters) = socket_limiters.total();
+		Throttled::new(socket, read_limiters, write_limiters)
+	});
+
+	let (transport, sockets) = bandwidth::BandwidthLogging::new(transport);
+	let bandwidth = Arc::new(BandwidthSinks {
+		sockets,
+		quic_inbound: AtomicU64::new(0),
+		quic_outbound: AtomicU64::new(0),
+	});
 
 	let authentication_config = {
 		// For more information about these two panics, see in "On the Importance of
@@ -116,11 +258,418 @@ pub fn build_transport(
 		core::upgrade::SelectUpgrade::new(yamux_config, mplex_config)
 	};
 
+	// The remote isn't known before the authentication, so the limits of each node are applied
+	// to the authenticated connection, and don't count the overhead of the encryption.
+	let peer_limiters = limiters.clone();
 	let transport = transport.upgrade(upgrade::Version::V1Lazy)
 		.authenticate(authentication_config)
-		.multiplex(multiplexing_config)
-		.timeout(Duration::from_secs(20))
-		.boxed();
+		.multiplex_ext(move |peer_id, _| {
+			let (read_limiters, write_limiters) = peer_limiters.for_peer(peer_id);
+			ThrottledUpgrade { inner: multiplexing_config, read_limiters, write_limiters }
+		})
+		.timeout(Duration::from_secs(20));
+
+	#[cfg(all(feature = "quic", not(target_os = "unknown")))]
+	let transport = {
+		let quic_transport = quic_listen_address.and_then(|addr| QuicTransport::new(
+			&keypair,
+			addr,
+			quic_deny_list,
+			limiters,
+			bandwidth.clone(),
+		));
+		let quic_transport = match quic_transport {
+			Some(t) => OptionalTransport::some(t),
+			None => OptionalTransport::none(),
+		};
+		quic_transport.or_transport(transport).map(|output, _| match output {
+			core::either::EitherOutput::First(output) |
+			core::either::EitherOutput::Second(output) => output,
+		})
+	};
+
+	(transport.boxed(), bandwidth)
+}
+
+/// Returns the address the QUIC endpoint should be bound to: the first QUIC address among
+/// `listen_addresses`, or `/ip4/0.0.0.0/udp/0/quic` if there isn't any.
+pub(crate) fn quic_listen_address(listen_addresses: &[Multiaddr]) -> Multiaddr {
+	listen_addresses.iter()
+		.find(|addr| is_quic_addr(addr))
+		.cloned()
+		.unwrap_or_else(|| Multiaddr::empty()
+			.with(Protocol::Ip4([0, 0, 0, 0].into()))
+			.with(Protocol::Udp(0))
+			.with(Protocol::Quic))
+}
+
+/// Token bucket limiting the number of bytes transferred per second.
+///
+/// Up to one second worth of unused bandwidth can be accumulated, allowing short bursts.
+///
+/// Rather than the content of the bucket, the time at which it will be full again is stored, so
+/// that it can be updated atomically by all the connections sharing the limiter.
+#[derive(Debug)]
+struct RateLimiter {
+	/// Number of bytes added to the bucket every second.
+	bytes_per_sec: f64,
+	/// Reference point of `full_at`.
+	start: Instant,
+	/// Nanoseconds after `start` at which the bucket will be full again. The bucket holds
+	/// less than nothing if this is more than one second in the future, as multiple connections
+	/// can transfer data at the same time.
+	full_at: AtomicU64,
+}
+
+impl RateLimiter {
+	fn new(bytes_per_sec: u64) -> Self {
+		RateLimiter {
+			bytes_per_sec: cmp::max(bytes_per_sec, 1) as f64,
+			start: Instant::now(),
+			full_at: AtomicU64::new(0),
+		}
+	}
+
+	/// Returns the number of nanoseconds elapsed since `start`.
+	fn now(&self) -> u64 {
+		(Instant::now() - self.start).as_nanos() as u64
+	}
+
+	/// Returns the number of bytes that can be transferred right now, or how long to wait before
+	/// some can.
+	fn available(&self) -> Result<usize, Duration> {
+		let until_full = self.full_at.load(Ordering::Relaxed).saturating_sub(self.now());
+		let available = self.bytes_per_sec * (1.0 - until_full as f64 / 1_000_000_000.0);
+
+		if available >= 1.0 {
+			Ok(available as usize)
+		} else {
+			// Rather than waking up for every single byte, wait for 10ms worth of them.
+			let target = (self.bytes_per_sec / 100.0).max(1.0);
+			Err(Duration::from_secs_f64((target - available) / self.bytes_per_sec))
+		}
+	}
+
+	/// Removes the given number of transferred bytes from the bucket.
+	fn consume(&self, bytes: usize) {
+		let now = self.now();
+		let duration = (bytes as f64 / self.bytes_per_sec * 1_000_000_000.0) as u64;
+		let _ = self.full_at.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |full_at| {
+			Some(cmp::max(full_at, now).saturating_add(duration))
+		});
+	}
+}
+
+/// Number of shards of [`PeerLimiters`]. Connections being established at the same time only
+/// contend if their remotes fall into the same shard.
+const PEER_LIMITERS_SHARDS: usize = 16;
+
+/// Rate limiters of the nodes we are connected to, shared by all the connections to the same
+/// node.
+#[derive(Debug)]
+struct PeerLimiters {
+	/// Rate of the limiters.
+	bytes_per_sec: u64,
+	/// Limiters of each node, sharded by `PeerId`. The limiters are owned by the connections
+	/// and dropped with the last connection to the node.
+	shards: Vec<Mutex<HashMap<PeerId, Weak<RateLimiter>>>>,
+}
+
+impl PeerLimiters {
+	fn new(bytes_per_sec: u64) -> Self {
+		PeerLimiters {
+			bytes_per_sec,
+			shards: (0..PEER_LIMITERS_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+		}
+	}
+
+	/// Returns the limiter of `peer_id`, creating it if there isn't any connection to this node.
+	fn get(&self, peer_id: &PeerId) -> Arc<RateLimiter> {
+		let mut hasher = fnv::FnvHasher::default();
+		peer_id.hash(&mut hasher);
+		let mut shard = self.shards[hasher.finish() as usize % PEER_LIMITERS_SHARDS].lock();
+
+		if let Some(limiter) = shard.get(peer_id).and_then(Weak::upgrade) {
+			return limiter
+		}
+
+		shard.retain(|_, limiter| limiter.strong_count() > 0);
+		let limiter = Arc::new(RateLimiter::new(self.bytes_per_sec));
+		shard.insert(peer_id.clone(), Arc::downgrade(&limiter));
+		limiter
+	}
+}
+
+/// Rate limits of the connections.
+#[derive(Debug, Clone)]
+struct Limiters {
+	/// Shared by all connections.
+	total_inbound: Option<Arc<RateLimiter>>,
+	/// Shared by all connections.
+	total_outbound: Option<Arc<RateLimiter>>,
+	/// Shared by all connections to the same node.
+	per_peer_inbound: Option<Arc<PeerLimiters>>,
+	/// Shared by all connections to the same node.
+	per_peer_outbound: Option<Arc<PeerLimiters>>,
+}
+
+impl Limiters {
+	fn new(limits: BandwidthLimits) -> Self {
+		Limiters {
+			total_inbound: limits.total_inbound.map(|rate| Arc::new(RateLimiter::new(rate))),
+			total_outbound: limits.total_outbound.map(|rate| Arc::new(RateLimiter::new(rate))),
+			per_peer_inbound: limits.per_peer_inbound.map(|rate| Arc::new(PeerLimiters::new(rate))),
+			per_peer_outbound: limits.per_peer_outbound
+				.map(|rate| Arc::new(PeerLimiters::new(rate))),
+		}
+	}
+
+	/// Returns the read and write limiters shared by all the connections.
+	fn total(&self) -> (Vec<Arc<RateLimiter>>, Vec<Arc<RateLimiter>>) {
+		(
+			self.total_inbound.iter().cloned().collect(),
+			self.total_outbound.iter().cloned().collect(),
+		)
+	}
+
+	/// Returns the read and write limiters of the connections to `peer_id`.
+	fn for_peer(&self, peer_id: &PeerId) -> (Vec<Arc<RateLimiter>>, Vec<Arc<RateLimiter>>) {
+		(
+			self.per_peer_inbound.iter().map(|limiters| limiters.get(peer_id)).collect(),
+			self.per_peer_outbound.iter().map(|limiters| limiters.get(peer_id)).collect(),
+		)
+	}
+
+	/// Returns all the read and write limiters of a connection to `peer_id`.
+	#[cfg(all(feature = "quic", not(target_os = "unknown")))]
+	fn for_connection(&self, peer_id: &PeerId) -> (Vec<Arc<RateLimiter>>, Vec<Arc<RateLimiter>>) {
+		let (mut read_limiters, mut write_limiters) = self.total();
+		let (peer_read_limiters, peer_write_limiters) = self.for_peer(peer_id);
+		read_limiters.extend(peer_read_limiters);
+		write_limiters.extend(peer_write_limiters);
+		(read_limiters, write_limiters)
+	}
+}
+
+/// Wraps the connection in [`Throttled`] before handing it to the `inner` upgrade.
+#[derive(Debug, Clone)]
+struct ThrottledUpgrade<U> {
+	inner: U,
+	read_limiters: Vec<Arc<RateLimiter>>,
+	write_limiters: Vec<Arc<RateLimiter>>,
+}
+
+impl<U: upgrade::UpgradeInfo> upgrade::UpgradeInfo for ThrottledUpgrade<U> {
+	type Info = U::Info;
+	type InfoIter = U::InfoIter;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.inner.protocol_info()
+	}
+}
+
+impl<C, U: upgrade::InboundUpgrade<Throttled<C>>> upgrade::InboundUpgrade<C>
+	for ThrottledUpgrade<U>
+{
+	type Output = U::Output;
+	type Error = U::Error;
+	type Future = U::Future;
+
+	fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+		let socket = Throttled::new(socket, self.read_limiters, self.write_limiters);
+		self.inner.upgrade_inbound(socket, info)
+	}
+}
+
+impl<C, U: upgrade::OutboundUpgrade<Throttled<C>>> upgrade::OutboundUpgrade<C>
+	for ThrottledUpgrade<U>
+{
+	type Output = U::Output;
+	type Error = U::Error;
+	type Future = U::Future;
+
+	fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+		let socket = Throttled::new(socket, self.read_limiters, self.write_limiters);
+		self.inner.upgrade_outbound(socket, info)
+	}
+}
+
+/// Wraps around a connection and limits the rate at which data is read and written.
+#[pin_project::pin_project]
+struct Throttled<T> {
+	#[pin]
+	inner: T,
+	read_limiters: Vec<Arc<RateLimiter>>,
+	write_limiters: Vec<Arc<RateLimiter>>,
+	/// Timer to wait for before reading again, if the read limit has been reached.
+	read_delay: Option<Delay>,
+	/// Timer to wait for before writing again, if the write limit has been reached.
+	write_delay: Option<Delay>,
+}
+
+impl<T> Throttled<T> {
+	fn new(
+		inner: T,
+		read_limiters: Vec<Arc<RateLimiter>>,
+		write_limiters: Vec<Arc<RateLimiter>>,
+	) -> Self {
+		Throttled { inner, read_limiters, write_limiters, read_delay: None, write_delay: None }
+	}
+}
+
+/// Waits until all the `limiters` allow transferring some bytes, and returns how many of the
+/// `wanted` bytes can be transferred.
+fn poll_allowance(
+	limiters: &[Arc<RateLimiter>],
+	delay: &mut Option<Delay>,
+	wanted: usize,
+	cx: &mut Context,
+) -> Poll<usize> {
+	'wait: loop {
+		if let Some(timer) = delay.as_mut() {
+			ready!(timer.poll_unpin(cx));
+			*delay = None;
+		}
+
+		let mut allowed = wanted;
+		for limiter in limiters {
+			match limiter.available() {
+				Ok(available) => allowed = cmp::min(allowed, available),
+				Err(wait) => {
+					*delay = Some(Delay::new(wait));
+					continue 'wait;
+				}
+			}
+		}
+
+		return Poll::Ready(allowed)
+	}
+}
+
+impl<T: AsyncRead> AsyncRead for Throttled<T> {
+	fn poll_read(
+		self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let this = self.project();
+		if this.read_limiters.is_empty() || buf.is_empty() {
+			return this.inner.poll_read(cx, buf)
+		}
+
+		let allowed = ready!(poll_allowance(this.read_limiters, this.read_delay, buf.len(), cx));
+		let read = ready!(this.inner.poll_read(cx, &mut buf[..allowed]))?;
+		for limiter in this.read_limiters.iter() {
+			limiter.consume(read);
+		}
+		Poll::Ready(Ok(read))
+	}
+}
+
+impl<T: AsyncWrite> AsyncWrite for Throttled<T> {
+	fn poll_write(
+		self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let this = self.project();
+		if this.write_limiters.is_empty() || buf.is_empty() {
+			return this.inner.poll_write(cx, buf)
+		}
+
+		let allowed = ready!(poll_allowance(this.write_limiters, this.write_delay, buf.len(), cx));
+		let written = ready!(this.inner.poll_write(cx, &buf[..allowed]))?;
+		for limiter in this.write_limiters.iter() {
+			limiter.consume(written);
+		}
+		Poll::Ready(Ok(written))
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_flush(cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_close(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn deny_list_checks_all_addresses() {
+		let peer_id = PeerId::random();
+		let deny_list = DenyList::new(vec![
+			BanTarget::Peer(peer_id.clone()),
+			"198.51.100.0/24".parse().unwrap(),
+		]);
+		assert!(deny_list.is_peer_denied(&peer_id));
+		assert!(!deny_list.is_peer_denied(&PeerId::random()));
+
+		let denied = |addr: &str| deny_list.is_address_denied(&addr.parse().unwrap());
+		assert!(denied("/ip4/198.51.100.19/tcp/30333"));
+		assert!(!denied("/ip4/203.0.113.19/tcp/30333"));
+		assert!(!denied("/dns/example.com/tcp/30333"));
+		// Connections relayed by a denied node are refused as well.
+		assert!(denied("/ip4/203.0.113.19/tcp/30333/p2p-circuit/ip4/198.51.100.19/tcp/30333"));
+		assert!(denied("/ip4/198.51.100.19/tcp/30333/p2p-circuit/ip4/203.0.113.19/tcp/30333"));
+	}
+
+	#[test]
+	fn quic_listen_address_prefers_configured_one() {
+		let tcp: Multiaddr = "/ip4/0.0.0.0/tcp/30333".parse().unwrap();
+		let quic: Multiaddr = "/ip6/::/udp/30333/quic".parse().unwrap();
+		assert_eq!(quic_listen_address(&[tcp.clone(), quic.clone()]), quic);
+		assert_eq!(
+			quic_listen_address(&[tcp]),
+			"/ip4/0.0.0.0/udp/0/quic".parse::<Multiaddr>().unwrap(),
+		);
+	}
+
+	#[test]
+	fn rate_limiter_refills_over_time() {
+		let limiter = RateLimiter::new(1000);
+		assert_eq!(limiter.available(), Ok(1000));
+
+		limiter.consume(1500);
+		let wait = limiter.available().unwrap_err();
+		assert!(wait > Duration::from_millis(500) && wait <= Duration::from_millis(510));
+
+		std::thread::sleep(wait + Duration::from_millis(5));
+		assert!(limiter.available().unwrap() >= 10);
+	}
+
+	#[test]
+	fn peer_limiters_are_shared_by_connections() {
+		let limiters = PeerLimiters::new(1000);
+		let peer_id = PeerId::random();
+
+		let first = limiters.get(&peer_id);
+		first.consume(1000);
+		assert!(Arc::ptr_eq(&first, &limiters.get(&peer_id)));
+		assert!(!Arc::ptr_eq(&first, &limiters.get(&PeerId::random())));
+
+		// Once all the connections to the node are closed, the next one starts afresh.
+		drop(first);
+		assert_eq!(limiters.get(&peer_id).available(), Ok(1000));
+	}
+
+	#[test]
+	fn throttled_read_respects_limit() {
+		let data = vec![0u8; 3000];
+		let mut socket = Throttled::new(
+			&data[..],
+			vec![Arc::new(RateLimiter::new(1000))],
+			Vec::new(),
+		);
 
-	(transport, bandwidth)
+		let started = std::time::Instant::now();
+		let mut read = Vec::new();
+		futures::executor::block_on(socket.read_to_end(&mut read)).unwrap();
+		assert_eq!(read, data);
+		// The first 1000 bytes are available right away, the 2000 others take two seconds.
+		assert!(started.elapsed() >= Duration::from_millis(1900));
+	}
 }
@@ -0,0 +1,336 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! QUIC transport.
+//!
+//! QUIC is provided by `libp2p-quic`, which is built on a more recent `libp2p-core` than the
+//! rest of the networking code. Its addresses, peer ids and connections are converted to the
+//! types used here. As QUIC brings its own encryption and multiplexing, its connections are
+//! throttled and accounted for per substream rather than per socket.
+
+use super::{BandwidthSinks, DenyList, Limiters, RateLimiter, poll_allowance};
+use crate::config::is_quic_addr;
+use futures::{future::{BoxFuture, Shared}, prelude::*, ready, stream::BoxStream};
+use futures_timer::Delay;
+use libp2p::{
+	Multiaddr, PeerId, Transport,
+	core::{
+		muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent},
+		transport::{ListenerEvent, TransportError},
+	},
+	identity,
+};
+use libp2p_core_quic as quic_core;
+use std::{
+	convert::TryFrom, io, sync::{Arc, atomic::Ordering}, task::{Context, Poll},
+};
+
+type Endpoint = libp2p_quic::QuicTransport;
+type Output = (PeerId, StreamMuxerBox);
+type Upgrade = BoxFuture<'static, io::Result<Output>>;
+
+fn other(e: impl ToString) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+fn to_quic_addr(addr: &Multiaddr) -> Option<quic_core::Multiaddr> {
+	quic_core::Multiaddr::try_from(addr.to_vec()).ok()
+}
+
+fn from_quic_addr(addr: &quic_core::Multiaddr) -> io::Result<Multiaddr> {
+	Multiaddr::try_from(addr.to_vec()).map_err(other)
+}
+
+/// Transport for `/udp/<port>/quic` addresses.
+#[derive(Clone)]
+pub(super) struct QuicTransport {
+	/// Binds the QUIC endpoint the first time it is awaited, i.e. when the transport first
+	/// listens or dials.
+	endpoint: Shared<BoxFuture<'static, Result<Endpoint, String>>>,
+	deny_list: DenyList,
+	limiters: Limiters,
+	bandwidth: Arc<BandwidthSinks>,
+}
+
+impl QuicTransport {
+	/// Builds a transport whose endpoint is bound to `listen_address`.
+	///
+	/// Returns `None` if `keypair` isn't an Ed25519 one or `listen_address` can't be used.
+	pub(super) fn new(
+		keypair: &identity::Keypair,
+		listen_address: Multiaddr,
+		deny_list: DenyList,
+		limiters: Limiters,
+		bandwidth: Arc<BandwidthSinks>,
+	) -> Option<Self> {
+		let keypair = match keypair {
+			identity::Keypair::Ed25519(keypair) => {
+				let mut encoded = keypair.encode();
+				quic_core::identity::ed25519::Keypair::decode(&mut encoded).ok()?
+			},
+			_ => {
+				log::warn!(target: "sub-libp2p", "QUIC requires an Ed25519 node key");
+				return None
+			},
+		};
+		let quic_address = to_quic_addr(&listen_address)?;
+		let config = libp2p_quic::QuicConfig::new(quic_core::identity::Keypair::Ed25519(keypair));
+		let endpoint = async move {
+			config.listen_on(quic_address).await.map_err(|err| {
+				log::warn!(
+					target: "sub-libp2p",
+					"Failed to bind QUIC endpoint to {}: {}",
+					listen_address,
+					err,
+				);
+				err.to_string()
+			})
+		};
+
+		Some(QuicTransport {
+			endpoint: endpoint.boxed().shared(),
+			deny_list,
+			limiters,
+			bandwidth,
+		})
+	}
+
+	/// Turns a QUIC connection being established with `remote_addr` into one of ours, unless
+	/// the address is denied.
+	fn connection<E: ToString>(
+		self,
+		upgrade: impl Future<Output = Result<<Endpoint as quic_core::Transport>::Output, E>>
+			+ Send + 'static,
+		remote_addr: Multiaddr,
+	) -> Upgrade {
+		async move {
+			if self.deny_list.is_address_denied(&remote_addr) {
+				return Err(io::Error::new(
+					io::ErrorKind::PermissionDenied,
+					format!("Connection with {} refused by the deny list", remote_addr),
+				))
+			}
+			let (peer_id, muxer) = upgrade.await.map_err(other)?;
+			let peer_id = PeerId::from_bytes(&peer_id.to_bytes()).map_err(other)?;
+			let (read_limiters, write_limiters) = self.limiters.for_connection(&peer_id);
+			let muxer = Muxer {
+				inner: muxer,
+				read_limiters,
+				write_limiters,
+				bandwidth: self.bandwidth,
+			};
+			Ok((peer_id, StreamMuxerBox::new(muxer)))
+		}.boxed()
+	}
+
+	fn listener_event<E: ToString>(
+		self,
+		event: quic_core::transport::ListenerEvent<
+			<Endpoint as quic_core::Transport>::ListenerUpgrade,
+			E,
+		>,
+	) -> io::Result<ListenerEvent<Upgrade, io::Error>> {
+		use quic_core::transport::ListenerEvent as QuicEvent;
+
+		Ok(match event {
+			QuicEvent::NewAddress(addr) => ListenerEvent::NewAddress(from_quic_addr(&addr)?),
+			QuicEvent::AddressExpired(addr) =>
+				ListenerEvent::AddressExpired(from_quic_addr(&addr)?),
+			QuicEvent::Upgrade { upgrade, local_addr, remote_addr } => {
+				let local_addr = from_quic_addr(&local_addr)?;
+				let remote_addr = from_quic_addr(&remote_addr)?;
+				ListenerEvent::Upgrade {
+					upgrade: self.connection(upgrade, remote_addr.clone()),
+					local_addr,
+					remote_addr,
+				}
+			},
+			QuicEvent::Error(err) => ListenerEvent::Error(other(err)),
+		})
+	}
+}
+
+impl Transport for QuicTransport {
+	type Output = Output;
+	type Error = io::Error;
+	type Listener = BoxStream<'static, io::Result<ListenerEvent<Upgrade, io::Error>>>;
+	type ListenerUpgrade = Upgrade;
+	type Dial = Upgrade;
+
+	fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<io::Error>> {
+		let quic_addr = match to_quic_addr(&addr) {
+			Some(quic_addr) if is_quic_addr(&addr) => quic_addr,
+			_ => return Err(TransportError::MultiaddrNotSupported(addr)),
+		};
+		let this = self.clone();
+		let listener = async move {
+			let endpoint = self.endpoint.clone().await.map_err(other)?;
+			let listener = quic_core::Transport::listen_on(endpoint, quic_addr).map_err(other)?;
+			Ok(listener.map_err(other))
+		};
+
+		Ok(listener
+			.try_flatten_stream()
+			.and_then(move |event| future::ready(this.clone().listener_event(event)))
+			.boxed())
+	}
+
+	fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<io::Error>> {
+		let quic_addr = match to_quic_addr(&addr) {
+			Some(quic_addr) if is_quic_addr(&addr) => quic_addr,
+			_ => return Err(TransportError::MultiaddrNotSupported(addr)),
+		};
+		Ok(async move {
+			let endpoint = self.endpoint.clone().await.map_err(other)?;
+			let dial = quic_core::Transport::dial(endpoint, quic_addr).map_err(other)?;
+			self.connection(dial, addr).await
+		}.boxed())
+	}
+
+	fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+		None
+	}
+}
+
+/// A QUIC connection, throttled and accounted for per substream.
+struct Muxer<M> {
+	inner: M,
+	read_limiters: Vec<Arc<RateLimiter>>,
+	write_limiters: Vec<Arc<RateLimiter>>,
+	bandwidth: Arc<BandwidthSinks>,
+}
+
+/// A substream of a [`Muxer`].
+struct Substream<S> {
+	inner: S,
+	/// Timer to wait for before reading again, if the read limit has been reached.
+	read_delay: Option<Delay>,
+	/// Timer to wait for before writing again, if the write limit has been reached.
+	write_delay: Option<Delay>,
+}
+
+impl<S> Substream<S> {
+	fn new(inner: S) -> Self {
+		Substream { inner, read_delay: None, write_delay: None }
+	}
+}
+
+impl<M: quic_core::muxing::StreamMuxer> StreamMuxer for Muxer<M> {
+	type Substream = Substream<M::Substream>;
+	type OutboundSubstream = M::OutboundSubstream;
+	type Error = io::Error;
+
+	fn poll_event(
+		&self,
+		cx: &mut Context,
+	) -> Poll<io::Result<StreamMuxerEvent<Self::Substream>>> {
+		use quic_core::muxing::StreamMuxerEvent as QuicEvent;
+
+		Poll::Ready(Ok(match ready!(self.inner.poll_event(cx)).map_err(Into::into)? {
+			QuicEvent::InboundSubstream(substream) =>
+				StreamMuxerEvent::InboundSubstream(Substream::new(substream)),
+			QuicEvent::AddressChange(addr) =>
+				StreamMuxerEvent::AddressChange(from_quic_addr(&addr)?),
+		}))
+	}
+
+	fn open_outbound(&self) -> Self::OutboundSubstream {
+		self.inner.open_outbound()
+	}
+
+	fn poll_outbound(
+		&self,
+		cx: &mut Context,
+		substream: &mut Self::OutboundSubstream,
+	) -> Poll<io::Result<Self::Substream>> {
+		let substream = ready!(self.inner.poll_outbound(cx, substream)).map_err(Into::into)?;
+		Poll::Ready(Ok(Substream::new(substream)))
+	}
+
+	fn destroy_outbound(&self, substream: Self::OutboundSubstream) {
+		self.inner.destroy_outbound(substream)
+	}
+
+	fn read_substream(
+		&self,
+		cx: &mut Context,
+		substream: &mut Self::Substream,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let allowed = if self.read_limiters.is_empty() || buf.is_empty() {
+			buf.len()
+		} else {
+			ready!(poll_allowance(&self.read_limiters, &mut substream.read_delay, buf.len(), cx))
+		};
+		let read = self.inner.read_substream(cx, &mut substream.inner, &mut buf[..allowed]);
+		let read = ready!(read).map_err(Into::into)?;
+		for limiter in self.read_limiters.iter() {
+			limiter.consume(read);
+		}
+		self.bandwidth.quic_inbound.fetch_add(read as u64, Ordering::Relaxed);
+		Poll::Ready(Ok(read))
+	}
+
+	fn write_substream(
+		&self,
+		cx: &mut Context,
+		substream: &mut Self::Substream,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let allowed = if self.write_limiters.is_empty() || buf.is_empty() {
+			buf.len()
+		} else {
+			ready!(poll_allowance(&self.write_limiters, &mut substream.write_delay, buf.len(), cx))
+		};
+		let written = self.inner.write_substream(cx, &mut substream.inner, &buf[..allowed]);
+		let written = ready!(written).map_err(Into::into)?;
+		for limiter in self.write_limiters.iter() {
+			limiter.consume(written);
+		}
+		self.bandwidth.quic_outbound.fetch_add(written as u64, Ordering::Relaxed);
+		Poll::Ready(Ok(written))
+	}
+
+	fn flush_substream(
+		&self,
+		cx: &mut Context,
+		substream: &mut Self::Substream,
+	) -> Poll<io::Result<()>> {
+		self.inner.flush_substream(cx, &mut substream.inner).map_err(Into::into)
+	}
+
+	fn shutdown_substream(
+		&self,
+		cx: &mut Context,
+		substream: &mut Self::Substream,
+	) -> Poll<io::Result<()>> {
+		self.inner.shutdown_substream(cx, &mut substream.inner).map_err(Into::into)
+	}
+
+	fn destroy_substream(&self, substream: Self::Substream) {
+		self.inner.destroy_substream(substream.inner)
+	}
+
+	fn close(&self, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.inner.close(cx).map_err(Into::into)
+	}
+
+	fn flush_all(&self, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.inner.flush_all(cx).map_err(Into::into)
+	}
+}
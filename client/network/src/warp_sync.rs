@@ -0,0 +1,96 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Client side of the warp sync request protocol.
+//!
+//! Warp sync lets a fresh node skip to the latest finalized block by downloading a proof of every
+//! authority set change since its own last finalized block, instead of importing all the blocks
+//! in between. Proofs are verified, and their target imported, by the finality gadget through a
+//! [`WarpSyncProvider`].
+
+use codec::{Encode, Decode};
+use sp_finality_grandpa::{AuthorityList, SetId};
+use sp_core::storage::ChildInfo;
+use sp_runtime::{Justification, traits::{Block as BlockT, NumberFor}};
+use std::error::Error;
+
+/// Request for a warp sync proof, as sent on the warp sync request-response protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct WarpProofRequest<B: BlockT> {
+	/// Finalized block to start the proof at.
+	pub begin: B::Hash,
+}
+
+/// Last verified fragment of a warp sync proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarpSyncFragment<B: BlockT> {
+	/// The proven finalized header.
+	pub header: B::Header,
+	/// Justification of `header`.
+	pub justification: Justification,
+	/// Id of the authority set in effect after `header`.
+	pub set_id: SetId,
+	/// Authority set in effect after `header`.
+	pub authorities: AuthorityList,
+}
+
+/// Verifies warp sync proofs and imports their target.
+pub trait WarpSyncProvider<B: BlockT>: Send + Sync {
+	/// Id and authorities of the set in effect at our last finalized block, which the
+	/// verification of the first proof starts from.
+	fn current_authorities(&self) -> (SetId, AuthorityList);
+
+	/// Verify the fragments of an encoded proof that follow the already verified block `begin`,
+	/// using the authority set in effect after it.
+	///
+	/// Returns the last fragment, or `None` if the proof holds nothing past `begin`.
+	fn verify(
+		&self,
+		proof: &[u8],
+		set_id: SetId,
+		authorities: AuthorityList,
+		begin: NumberFor<B>,
+	) -> Result<Option<WarpSyncFragment<B>>, Box<dyn Error + Send + Sync>>;
+
+	/// Start importing the target of a completed warp sync as the new best and finalized block,
+	/// together with its state and the consensus data needed to continue from it.
+	///
+	/// The state is then streamed into the returned import as it is downloaded.
+	fn begin_import(
+		&self,
+		target: WarpSyncFragment<B>,
+	) -> Result<Box<dyn StateImport<B>>, Box<dyn Error + Send + Sync>>;
+}
+
+/// Import of the target of a warp sync, fed with its state one verified chunk at a time.
+///
+/// Nothing is written until the import is committed, and dropping it discards everything.
+pub trait StateImport<B: BlockT>: Send {
+	/// Add a chunk of the key-value pairs of the state, of the given child trie if `child_info`
+	/// is set.
+	///
+	/// Returns the root of the state made of all the chunks so far.
+	fn append(
+		&mut self,
+		child_info: Option<&ChildInfo>,
+		chunk: Vec<(Vec<u8>, Vec<u8>)>,
+	) -> Result<B::Hash, Box<dyn Error + Send + Sync>>;
+
+	/// Import the target, its state and its consensus data at once.
+	fn commit(self: Box<Self>) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
@@ -0,0 +1,49 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use sc_network::fuzzing::CORPUS_DIR_ENV;
+
+/// Records the corpus of the fuzzers in `client/network/fuzz` from the messages exchanged by a
+/// syncing network. Run with `cargo test -p sc-network-test record_fuzzing_corpus -- --ignored`.
+///
+/// The test networks make neither state nor light client requests, so the corpora of these
+/// fuzzers are left as they are.
+#[test]
+#[ignore]
+fn record_fuzzing_corpus() {
+	sp_tracing::try_init_simple();
+	std::env::set_var(CORPUS_DIR_ENV, concat!(env!("CARGO_MANIFEST_DIR"), "/../fuzz/corpus"));
+
+	let mut net = TestNet::new(3);
+	net.add_light_peer();
+
+	// Blocks with bodies and transactions, and a fork that makes the peers request blocks by
+	// hash during the ancestry search.
+	net.peer(0).push_blocks(5, false);
+	net.block_until_sync();
+	net.peer(0).push_blocks(10, true);
+	net.peer(1).push_blocks(3, false);
+	net.block_until_sync();
+
+	// Announces of new best blocks.
+	net.peer(2).push_blocks(1, false);
+	net.block_until_sync();
+
+	std::env::remove_var(CORPUS_DIR_ENV);
+}
@@ -20,6 +20,8 @@
 #[cfg(test)]
 mod block_import;
 #[cfg(test)]
+mod fuzzing_corpus;
+#[cfg(test)]
 mod sync;
 
 use std::{
@@ -31,6 +33,7 @@ use libp2p::build_multiaddr;
 use log::trace;
 use sc_network::block_request_handler::{self, BlockRequestHandler};
 use sc_network::light_client_requests::{self, handler::LightClientRequestHandler};
+use sc_network::state_request_handler::{self, StateRequestHandler};
 use sp_blockchain::{
 	HeaderBackend, Result as ClientResult,
 	well_known_cache_keys::{self, Id as CacheKeyId},
@@ -732,6 +735,12 @@ pub trait TestNetFactory: Sized {
 			protocol_config
 		};
 
+		let state_request_protocol_config = {
+			let (handler, protocol_config) = StateRequestHandler::new(&protocol_id, client.clone());
+			self.spawn_task(handler.run().boxed());
+			protocol_config
+		};
+
 		let light_client_request_protocol_config = {
 			let (handler, protocol_config) = LightClientRequestHandler::new(&protocol_id, client.clone());
 			self.spawn_task(handler.run().boxed());
@@ -751,7 +760,10 @@ pub trait TestNetFactory: Sized {
 				.unwrap_or_else(|| Box::new(DefaultBlockAnnounceValidator)),
 			metrics_registry: None,
 			block_request_protocol_config,
+			state_request_protocol_config,
 			light_client_request_protocol_config,
+			warp_sync: None,
+			sync_checkpoint: None,
 		}).unwrap();
 
 		trace!(target: "test_network", "Peer identifier: {}", network.service().local_peer_id());
@@ -825,6 +837,10 @@ pub trait TestNetFactory: Sized {
 			&protocol_id,
 		);
 
+		let state_request_protocol_config = state_request_handler::generate_protocol_config(
+			&protocol_id,
+		);
+
 		let light_client_request_protocol_config =
 			light_client_requests::generate_protocol_config(&protocol_id);
 
@@ -840,7 +856,10 @@ pub trait TestNetFactory: Sized {
 			block_announce_validator: Box::new(DefaultBlockAnnounceValidator),
 			metrics_registry: None,
 			block_request_protocol_config,
+			state_request_protocol_config,
 			light_client_request_protocol_config,
+			warp_sync: None,
+			sync_checkpoint: None,
 		}).unwrap();
 
 		self.mut_peers(|peers| {
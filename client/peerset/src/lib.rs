@@ -61,6 +61,8 @@ enum Action {
 	ReportPeer(PeerId, ReputationChange),
 	AddToPeersSet(SetId, PeerId),
 	RemoveFromPeersSet(SetId, PeerId),
+	BanPeer(PeerId),
+	UnbanPeer(PeerId),
 }
 
 /// Identifier of a set in the peerset.
@@ -161,6 +163,20 @@ impl PeersetHandle {
 	pub fn remove_from_peers_set(&self, set_id: SetId, peer_id: PeerId) {
 		let _ = self.tx.unbounded_send(Action::RemoveFromPeersSet(set_id, peer_id));
 	}
+
+	/// Disconnects from the given peer in all the sets and refuses any further connection with
+	/// it, even if it is a reserved peer, until [`PeersetHandle::unban_peer`] is called.
+	pub fn ban_peer(&self, peer_id: PeerId) {
+		let _ = self.tx.unbounded_send(Action::BanPeer(peer_id));
+	}
+
+	/// Lifts a ban previously put in place with [`PeersetHandle::ban_peer`] or
+	/// [`Peerset::from_config_with_banned_peers`].
+	///
+	/// Has no effect if the node wasn't banned.
+	pub fn unban_peer(&self, peer_id: PeerId) {
+		let _ = self.tx.unbounded_send(Action::UnbanPeer(peer_id));
+	}
 }
 
 /// Message that can be sent by the peer set manager (PSM).
@@ -242,6 +258,8 @@ pub struct Peerset {
 	/// connected to, and whether only reserved nodes are accepted. Is kept in sync with the list
 	/// of non-slot-occupying nodes in [`Peerset::data`].
 	reserved_nodes: Vec<(HashSet<PeerId>, bool)>,
+	/// Nodes banned by the user, which are never part of any set in [`Peerset::data`].
+	banned_peers: HashSet<PeerId>,
 	/// Receiver for messages from the `PeersetHandle` and from `tx`.
 	rx: TracingUnboundedReceiver<Action>,
 	/// Sending side of `rx`.
@@ -257,6 +275,15 @@ pub struct Peerset {
 impl Peerset {
 	/// Builds a new peerset from the given configuration.
 	pub fn from_config(config: PeersetConfig) -> (Peerset, PeersetHandle) {
+		Self::from_config_with_banned_peers(config, HashSet::new())
+	}
+
+	/// Builds a new peerset from the given configuration, never connecting to nor accepting
+	/// connections from `banned_peers` in any set until they are unbanned.
+	pub fn from_config_with_banned_peers(
+		config: PeersetConfig,
+		banned_peers: HashSet<PeerId>,
+	) -> (Peerset, PeersetHandle) {
 		let (tx, rx) = tracing_unbounded("mpsc_peerset_messages");
 
 		let handle = PeersetHandle {
@@ -276,6 +303,7 @@ impl Peerset {
 				reserved_nodes: config.sets.iter().map(|set| {
 					(set.reserved_nodes.clone(), set.reserved_only)
 				}).collect(),
+				banned_peers,
 				message_queue: VecDeque::new(),
 				created: now,
 				latest_time_update: now,
@@ -288,6 +316,10 @@ impl Peerset {
 			}
 
 			for peer_id in set_config.bootnodes {
+				if peerset.banned_peers.contains(&peer_id) {
+					continue;
+				}
+
 				if let peersstate::Peer::Unknown(entry) = peerset.data.peer(set, &peer_id) {
 					entry.discover();
 				} else {
@@ -381,6 +413,10 @@ impl Peerset {
 	///
 	/// > **Note**: This has the same effect as [`PeersetHandle::add_to_peers_set`].
 	pub fn add_to_peers_set(&mut self, set_id: SetId, peer_id: PeerId) {
+		if self.banned_peers.contains(&peer_id) {
+			return;
+		}
+
 		if let peersstate::Peer::Unknown(entry) = self.data.peer(set_id.0, &peer_id) {
 			entry.discover();
 			self.alloc_slots();
@@ -406,6 +442,41 @@ impl Peerset {
 		}
 	}
 
+	fn on_ban_peer(&mut self, peer_id: PeerId) {
+		if !self.banned_peers.insert(peer_id.clone()) {
+			return;
+		}
+
+		debug!(target: "peerset", "Banning {}", peer_id);
+
+		for set_index in 0..self.data.num_sets() {
+			match self.data.peer(set_index, &peer_id) {
+				peersstate::Peer::Connected(peer) => {
+					self.message_queue.push_back(Message::Drop {
+						set_id: SetId(set_index),
+						peer_id: peer.peer_id().clone(),
+					});
+					peer.disconnect().forget_peer();
+				}
+				peersstate::Peer::NotConnected(peer) => { peer.forget_peer(); }
+				peersstate::Peer::Unknown(_) => {}
+			}
+		}
+
+		self.alloc_slots();
+	}
+
+	fn on_unban_peer(&mut self, peer_id: PeerId) {
+		if !self.banned_peers.remove(&peer_id) {
+			return;
+		}
+
+		debug!(target: "peerset", "Unbanning {}", peer_id);
+
+		// Reconnects to the node if it is reserved. Otherwise, it has to be discovered again.
+		self.alloc_slots();
+	}
+
 	fn on_report_peer(&mut self, peer_id: PeerId, change: ReputationChange) {
 		// We want reputations to be up-to-date before adjusting them.
 		self.update_time();
@@ -451,23 +522,8 @@ impl Peerset {
 		};
 
 		// For each elapsed second, move the node reputation towards zero.
-		// If we multiply each second the reputation by `k` (where `k` is between 0 and 1), it
-		// takes `ln(0.5) / ln(k)` seconds to reduce the reputation by half. Use this formula to
-		// empirically determine a value of `k` that looks correct.
 		for _ in 0..secs_diff {
 			for peer_id in self.data.peers().cloned().collect::<Vec<_>>() {
-				// We use `k = 0.98`, so we divide by `50`. With that value, it takes 34.3 seconds
-				// to reduce the reputation by half.
-				fn reput_tick(reput: i32) -> i32 {
-					let mut diff = reput / 50;
-					if diff == 0 && reput < 0 {
-						diff = -1;
-					} else if diff == 0 && reput > 0 {
-						diff = 1;
-					}
-					reput.saturating_sub(diff)
-				}
-
 				let mut peer_reputation = self.data.peer_reputation(peer_id.clone());
 
 				let before = peer_reputation.reputation();
@@ -507,6 +563,10 @@ impl Peerset {
 		// Try to connect to all the reserved nodes that we are not connected to.
 		for set_index in 0..self.data.num_sets() {
 			for reserved_node in &self.reserved_nodes[set_index].0 {
+				if self.banned_peers.contains(reserved_node) {
+					continue;
+				}
+
 				let entry = match self.data.peer(set_index, reserved_node) {
 					peersstate::Peer::Unknown(n) => n.discover(),
 					peersstate::Peer::NotConnected(n) => n,
@@ -572,6 +632,11 @@ impl Peerset {
 
 		self.update_time();
 
+		if self.banned_peers.contains(&peer_id) {
+			self.message_queue.push_back(Message::Reject(index));
+			return;
+		}
+
 		if self.reserved_nodes[set_id.0].1 {
 			if !self.reserved_nodes[set_id.0].0.contains(&peer_id) {
 				self.message_queue.push_back(Message::Reject(index));
@@ -671,6 +736,60 @@ impl Peerset {
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
 	}
+
+	/// Returns the up-to-date reputation of all the nodes whose reputation isn't zero.
+	pub fn reputations(&mut self) -> Vec<(PeerId, i32)> {
+		self.update_time();
+
+		self.data.peers().cloned().collect::<Vec<_>>().into_iter().filter_map(|peer_id| {
+			let reputation = self.data.peer_reputation(peer_id.clone()).reputation();
+			if reputation == 0 {
+				None
+			} else {
+				Some((peer_id, reputation))
+			}
+		}).collect()
+	}
+
+	/// Resets the reputation of all the nodes to zero.
+	///
+	/// Nodes that were banned because of their reputation can connect again.
+	pub fn clear_reputations(&mut self) {
+		for peer_id in self.data.peers().cloned().collect::<Vec<_>>() {
+			self.data.peer_reputation(peer_id).set_reputation(0);
+		}
+		self.alloc_slots();
+	}
+}
+
+/// Moves a reputation towards zero by one second's worth of decay.
+///
+/// If we multiply each second the reputation by `k` (where `k` is between 0 and 1), it takes
+/// `ln(0.5) / ln(k)` seconds to reduce the reputation by half. We use `k = 0.98`, so we divide
+/// by `50`. With that value, it takes 34.3 seconds to reduce the reputation by half.
+fn reput_tick(reput: i32) -> i32 {
+	let mut diff = reput / 50;
+	if diff == 0 && reput < 0 {
+		diff = -1;
+	} else if diff == 0 && reput > 0 {
+		diff = 1;
+	}
+	reput.saturating_sub(diff)
+}
+
+/// Returns the reputation a node would have after `secs` seconds of decay.
+///
+/// Applies the same decay as the [`Peerset`] does while running, for example to account for the
+/// time the node was offline when reputations are restored from disk.
+pub fn decay_reputation(reputation: i32, secs: u64) -> i32 {
+	let mut reputation = reputation;
+	for _ in 0..secs {
+		if reputation == 0 {
+			break
+		}
+		reputation = reput_tick(reputation);
+	}
+	reputation
 }
 
 impl Stream for Peerset {
@@ -703,6 +822,10 @@ impl Stream for Peerset {
 					self.add_to_peers_set(sets_name, peer_id),
 				Action::RemoveFromPeersSet(sets_name, peer_id) =>
 					self.on_remove_from_peers_set(sets_name, peer_id),
+				Action::BanPeer(peer_id) =>
+					self.on_ban_peer(peer_id),
+				Action::UnbanPeer(peer_id) =>
+					self.on_unban_peer(peer_id),
 			}
 		}
 	}
@@ -723,7 +846,10 @@ pub enum DropReason {
 mod tests {
 	use libp2p::PeerId;
 	use futures::prelude::*;
-	use super::{PeersetConfig, Peerset, Message, IncomingIndex, ReputationChange, SetConfig, SetId, BANNED_THRESHOLD};
+	use super::{
+		PeersetConfig, Peerset, Message, IncomingIndex, ReputationChange, SetConfig, SetId,
+		BANNED_THRESHOLD, decay_reputation,
+	};
 	use std::{pin::Pin, task::Poll, thread, time::Duration};
 
 	fn assert_messages(mut peerset: Peerset, messages: Vec<Message>) -> Peerset {
@@ -888,6 +1014,118 @@ mod tests {
 			Poll::Ready(())
 		});
 
+		futures::executor::block_on(fut);
+	}
+	#[test]
+	fn test_decay_reputation() {
+		assert_eq!(decay_reputation(1000, 0), 1000);
+		assert_eq!(decay_reputation(1000, 1), 980);
+		assert_eq!(decay_reputation(-1000, 1), -980);
+		assert_eq!(decay_reputation(30, 10), 20);
+		assert_eq!(decay_reputation(i32::min_value(), u64::max_value()), 0);
+		assert_eq!(decay_reputation(BANNED_THRESHOLD - 1, 10_000), 0);
+	}
+
+	#[test]
+	fn test_peerset_clear_reputations() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		handle.report_peer(peer_id.clone(), ReputationChange::new(BANNED_THRESHOLD - 1, ""));
+
+		let fut = futures::future::poll_fn(move |cx| {
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+			let reputations = peerset.reputations();
+			assert_eq!(reputations.len(), 1);
+			assert_eq!(reputations[0].0, peer_id);
+
+			peerset.clear_reputations();
+			assert!(peerset.reputations().is_empty());
+
+			// The node is no longer banned.
+			peerset.incoming(SetId::from(0), peer_id.clone(), IncomingIndex(1));
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Accept(IncomingIndex(1)))),
+			);
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
+
+	#[test]
+	fn test_peerset_ban_and_unban() {
+		let reserved_peer = PeerId::random();
+		let denied_peer = PeerId::random();
+		let (mut peerset, handle) = Peerset::from_config_with_banned_peers(
+			PeersetConfig {
+				sets: vec![SetConfig {
+					in_peers: 25,
+					out_peers: 25,
+					bootnodes: vec![denied_peer.clone()],
+					reserved_nodes: vec![reserved_peer.clone()].into_iter().collect(),
+					reserved_only: false,
+				}],
+			},
+			vec![denied_peer.clone()].into_iter().collect(),
+		);
+
+		let fut = futures::future::poll_fn(move |cx| {
+			// Banned bootnodes aren't dialed.
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Connect {
+					set_id: SetId::from(0),
+					peer_id: reserved_peer.clone(),
+				})),
+			);
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			peerset.incoming(SetId::from(0), denied_peer.clone(), IncomingIndex(1));
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Reject(IncomingIndex(1)))),
+			);
+
+			// Banning a reserved peer disconnects it, and unbanning it reconnects it.
+			handle.ban_peer(reserved_peer.clone());
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Drop {
+					set_id: SetId::from(0),
+					peer_id: reserved_peer.clone(),
+				})),
+			);
+			handle.unban_peer(reserved_peer.clone());
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Connect {
+					set_id: SetId::from(0),
+					peer_id: reserved_peer.clone(),
+				})),
+			);
+
+			handle.unban_peer(denied_peer.clone());
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+			peerset.incoming(SetId::from(0), denied_peer.clone(), IncomingIndex(2));
+			assert_eq!(
+				Stream::poll_next(Pin::new(&mut peerset), cx),
+				Poll::Ready(Some(Message::Accept(IncomingIndex(2)))),
+			);
+
+			Poll::Ready(())
+		});
+
 		futures::executor::block_on(fut);
 	}
 }
@@ -102,6 +102,12 @@ const UNSUPPORTED_KEY_TYPE: i64 = POOL_INVALID_TX + 7;
 /// The transaction was not included to the pool since it is unactionable,
 /// it is not propagable and the local node does not author blocks.
 const POOL_UNACTIONABLE: i64 = POOL_INVALID_TX + 8;
+/// Transaction priority is not high enough to replace existing one in the pool.
+const POOL_TOO_LOW_PRIORITY_BUMP: i64 = POOL_INVALID_TX + 9;
+/// The transaction to replace is not in the pool.
+const REPLACED_NOT_FOUND: i64 = POOL_INVALID_TX + 10;
+/// The extrinsic does not replace the given transaction.
+const NOT_REPLACING: i64 = POOL_INVALID_TX + 11;
 
 impl From<Error> for rpc::Error {
 	fn from(e: Error) -> Self {
@@ -151,6 +157,14 @@ impl From<Error> for rpc::Error {
 				message: format!("Priority is too low: ({} vs {})", old, new),
 				data: Some("The transaction has too low priority to replace another transaction already in the pool.".into()),
 			},
+			Error::Pool(PoolError::TooLowPriorityBump { old, new, required }) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(POOL_TOO_LOW_PRIORITY_BUMP),
+				message: format!("Priority bump is too low: ({} vs {}, {} required)", old, new, required),
+				data: Some(
+					"The transaction priority is not high enough to replace another transaction \
+					 already in the pool.".into(),
+				),
+			},
 			Error::Pool(PoolError::CycleDetected) => rpc::Error {
 				code: rpc::ErrorCode::ServerError(POOL_CYCLE_DETECTED),
 				message: "Cycle Detected".into(),
@@ -177,6 +191,18 @@ impl From<Error> for rpc::Error {
 					request to insert the key successfully.".into()
 				),
 			},
+			Error::Pool(PoolError::ReplacedNotFound) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(REPLACED_NOT_FOUND),
+				message: "Transaction Not Found".into(),
+				data: Some("The transaction to replace is not in the pool".into()),
+			},
+			Error::Pool(PoolError::NotReplacing) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(NOT_REPLACING),
+				message: "Not Replacing".into(),
+				data: Some(
+					"The extrinsic does not provide the tags of the transaction to replace".into(),
+				),
+			},
 			Error::UnsafeRpcCalled(e) => e.into(),
 			e => errors::internal(e),
 		}
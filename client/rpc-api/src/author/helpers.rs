@@ -0,0 +1,65 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Substrate block-author API helpers.
+
+use sp_core::{Bytes, storage::{PrefixedStorageKey, StorageKey, StorageData}};
+use serde::{Serialize, Deserialize};
+
+/// Outcome of `author_dryRun`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun<Hash> {
+	/// Block hash the extrinsic was applied on top of
+	pub at: Hash,
+	/// SCALE encoded `ApplyExtrinsicResult` returned by the runtime
+	pub result: Bytes,
+	/// Weight consumed by the extrinsic, `None` if the runtime doesn't keep track of it in
+	/// `System::BlockWeight`
+	pub weight: Option<u64>,
+	/// SCALE encoded records of the events emitted by the extrinsic, as in `System::Events`.
+	/// `None` if the extrinsic didn't change `System::Events`
+	pub events: Option<Bytes>,
+	/// Storage entries changed by the extrinsic, in lexicographic order of the keys
+	pub storage_diff: Vec<StorageDiff>,
+	/// Entries of child tries changed by the extrinsic, in lexicographic order of the child
+	/// storage keys
+	pub child_storage_diff: Vec<ChildStorageDiff>,
+}
+
+/// Storage entry changed by an extrinsic
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiff {
+	/// Key of the entry
+	pub key: StorageKey,
+	/// Value before applying the extrinsic, `None` if the entry didn't exist
+	pub old: Option<StorageData>,
+	/// Value after applying the extrinsic, `None` if the entry was removed
+	pub new: Option<StorageData>,
+}
+
+/// Entries of a child trie changed by an extrinsic
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildStorageDiff {
+	/// Prefixed storage key of the child trie
+	pub child_storage_key: PrefixedStorageKey,
+	/// Entries changed in the child trie, in lexicographic order of the keys
+	pub storage_diff: Vec<StorageDiff>,
+}
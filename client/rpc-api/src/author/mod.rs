@@ -20,6 +20,7 @@
 
 pub mod error;
 pub mod hash;
+pub mod helpers;
 
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
@@ -28,6 +29,7 @@ use sp_transaction_pool::TransactionStatus;
 use self::error::{FutureResult, Result};
 
 pub use self::gen_client::Client as AuthorClient;
+pub use self::helpers::{ChildStorageDiff, DryRun, StorageDiff};
 
 /// Substrate authoring RPC API
 #[rpc]
@@ -39,6 +41,14 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[rpc(name = "author_submitExtrinsic")]
 	fn submit_extrinsic(&self, extrinsic: Bytes) -> FutureResult<Hash>;
 
+	/// Submit hex-encoded extrinsic replacing the transaction with given hash in the pool.
+	///
+	/// The extrinsic has to provide the same tags as the replaced transaction (e.g. same sender
+	/// and nonce) and a priority high enough to replace it. Watchers of the replaced transaction
+	/// are notified with a `replaced` event.
+	#[rpc(name = "author_replaceExtrinsic")]
+	fn replace_extrinsic(&self, replaced: Hash, extrinsic: Bytes) -> FutureResult<Hash>;
+
 	/// Insert a key into the keystore.
 	#[rpc(name = "author_insertKey")]
 	fn insert_key(
@@ -76,6 +86,14 @@ pub trait AuthorApi<Hash, BlockHash> {
 		bytes_or_hash: Vec<hash::ExtrinsicOrHash<Hash>>
 	) -> Result<Vec<Hash>>;
 
+	/// Apply hex-encoded extrinsic on top of the given block, the best block by default, without
+	/// submitting it.
+	///
+	/// The extrinsic is applied as the first extrinsic of a new block. Returns the result, the
+	/// consumed weight, the emitted events and the storage entries changed by the extrinsic.
+	#[rpc(name = "author_dryRun")]
+	fn dry_run(&self, extrinsic: Bytes, at: Option<BlockHash>) -> Result<DryRun<BlockHash>>;
+
 	/// Submit an extrinsic to watch.
 	///
 	/// See [`TransactionStatus`](sp_transaction_pool::TransactionStatus) for details on transaction
@@ -0,0 +1,47 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Substrate blockchain API helpers.
+
+use serde::{Serialize, Deserialize};
+
+/// Location of an event returned by the event index RPCs
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLocation<Number, Hash> {
+	/// Hash of the block that emitted the event
+	pub block_hash: Hash,
+	/// Number of the block that emitted the event
+	pub block_number: Number,
+	/// Index of the extrinsic that emitted the event, `None` while initializing or finalizing
+	pub extrinsic_index: Option<u32>,
+	/// Index of the event among the events of the block
+	pub event_index: u32,
+}
+
+/// Event locations returned by the event index RPCs
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedEvents<Number, Hash> {
+	/// First block whose events are indexed, the events of earlier blocks are not searched
+	pub first_indexed: Number,
+	/// Last block whose events are indexed
+	pub last_indexed: Number,
+	/// Locations of the events, newest first
+	pub events: Vec<EventLocation<Number, Hash>>,
+}
@@ -19,14 +19,17 @@
 //! Substrate blockchain API.
 
 pub mod error;
+pub mod helpers;
 
 use jsonrpc_core::Result as RpcResult;
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use sp_core::Bytes;
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use self::error::{FutureResult, Result};
 
 pub use self::gen_client::Client as ChainClient;
+pub use self::helpers::{EventLocation, IndexedEvents};
 
 /// Substrate blockchain API
 #[rpc]
@@ -55,6 +58,35 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 	#[rpc(name = "chain_getFinalizedHead", alias("chain_getFinalisedHead"))]
 	fn finalized_head(&self) -> Result<Hash>;
 
+	/// Get the locations of the events referring to the encoded account, newest first.
+	///
+	/// Only finalized blocks `from..=to` are searched, by default all of them. At most `limit`
+	/// locations are returned, 100 by default. Requires the node to index events.
+	///
+	/// The range of indexed blocks is returned along with the events: nodes that don't keep the
+	/// state of old blocks only index the events of the blocks whose state they had.
+	#[rpc(name = "chain_getAccountHistory")]
+	fn account_history(
+		&self,
+		account: Bytes,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<Number, Hash>>;
+
+	/// Get the locations of the events with the given topic, newest first.
+	///
+	/// Takes the same range and limit as `chain_getAccountHistory`. Requires the node to index
+	/// events.
+	#[rpc(name = "chain_getEventsByTopic")]
+	fn events_by_topic(
+		&self,
+		topic: Hash,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<Number, Hash>>;
+
 	/// All head subscription
 	#[pubsub(subscription = "chain_allHead", subscribe, name = "chain_subscribeAllHeads")]
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
@@ -29,7 +29,7 @@ mod policy;
 
 pub use helpers::Receiver;
 pub use jsonrpc_core::IoHandlerExtension as RpcExtension;
-pub use metadata::Metadata;
+pub use metadata::{Metadata, SubscriptionSlot};
 pub use policy::DenyUnsafe;
 
 pub mod author;
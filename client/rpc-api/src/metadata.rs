@@ -17,11 +17,33 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! RPC Metadata
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
-use jsonrpc_core::futures::sync::mpsc;
+use jsonrpc_core::futures::{Future, sync::mpsc};
 use jsonrpc_pubsub::{Session, PubSubMetadata};
 
+/// Slot taken by a subscription in the subscription quota of its connection.
+///
+/// The slot is released once dropped. Subscriptions keep the slot of the call that opened them
+/// with [`Metadata::hold_subscription_slot`], so that it is released when they end, whether the
+/// caller unsubscribed or the server ended them.
+pub struct SubscriptionSlot(Option<Box<dyn FnOnce() + Send + Sync>>);
+
+impl SubscriptionSlot {
+	/// Create a slot calling `release` once dropped.
+	pub fn new(release: impl FnOnce() + Send + Sync + 'static) -> Self {
+		SubscriptionSlot(Some(Box::new(release)))
+	}
+}
+
+impl Drop for SubscriptionSlot {
+	fn drop(&mut self) {
+		if let Some(release) = self.0.take() {
+			release()
+		}
+	}
+}
+
 /// RPC Metadata.
 ///
 /// Manages persistent session for transports that support it
@@ -30,6 +52,9 @@ use jsonrpc_pubsub::{Session, PubSubMetadata};
 #[derive(Default, Clone)]
 pub struct Metadata {
 	session: Option<Arc<Session>>,
+	api_key: Option<Arc<str>>,
+	remote_ip: Option<IpAddr>,
+	subscription_slot: Option<Arc<SubscriptionSlot>>,
 }
 
 impl jsonrpc_core::Metadata for Metadata {}
@@ -44,9 +69,53 @@ impl Metadata {
 	pub fn new(transport: mpsc::Sender<String>) -> Self {
 		Metadata {
 			session: Some(Arc::new(Session::new(transport))),
+			api_key: None,
+			remote_ip: None,
+			subscription_slot: None,
 		}
 	}
 
+	/// Attach the API key presented by the caller.
+	pub fn with_api_key(mut self, api_key: String) -> Self {
+		self.api_key = Some(api_key.into());
+		self
+	}
+
+	/// The API key presented by the caller, if any.
+	pub fn api_key(&self) -> Option<&str> {
+		self.api_key.as_deref()
+	}
+
+	/// Attach the IP address of the caller.
+	pub fn with_remote_ip(mut self, ip: IpAddr) -> Self {
+		self.remote_ip = Some(ip);
+		self
+	}
+
+	/// The IP address of the caller, if known.
+	pub fn remote_ip(&self) -> Option<IpAddr> {
+		self.remote_ip
+	}
+
+	/// Attach the slot taken by the subscription the call opens.
+	pub fn with_subscription_slot(mut self, slot: SubscriptionSlot) -> Self {
+		self.subscription_slot = Some(Arc::new(slot));
+		self
+	}
+
+	/// Keep the subscription slot of the call, if any, until `task`, the task of the
+	/// subscription opened by the call, ends or is cancelled.
+	pub fn hold_subscription_slot<F: Future>(
+		&self,
+		task: F,
+	) -> impl Future<Item = F::Item, Error = F::Error> {
+		let slot = self.subscription_slot.clone();
+		task.then(move |result| {
+			drop(slot);
+			result
+		})
+	}
+
 	/// Create new `Metadata` for tests.
 	#[cfg(test)]
 	pub fn new_test() -> (mpsc::Receiver<String>, Self) {
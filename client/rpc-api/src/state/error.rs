@@ -43,14 +43,20 @@ pub enum Error {
 		/// Details of the error message.
 		details: String,
 	},
-	/// Provided count exceeds maximum value.
-	#[display(fmt = "count exceeds maximum value. value: {}, max: {}", value, max)]
+	/// Provided count is zero or exceeds maximum value.
+	#[display(fmt = "count is zero or exceeds maximum value. value: {}, max: {}", value, max)]
 	InvalidCount {
 		/// Provided value
 		value: u32,
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// The response would exceed the maximum response size.
+	#[display(fmt = "Response exceeds the maximum size of {} bytes", max)]
+	ResponseTooLarge {
+		/// Maximum response size
+		max: usize,
+	},
 	/// Call to an unsafe RPC was denied.
 	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
 }
@@ -80,6 +86,11 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::ResponseTooLarge { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
 			e => errors::internal(e),
 		}
 	}
@@ -18,7 +18,7 @@
 
 //! Substrate state API helpers.
 
-use sp_core::Bytes;
+use sp_core::{Bytes, storage::{StorageKey, StorageData}};
 use serde::{Serialize, Deserialize};
 
 /// ReadProof struct returned by the RPC
@@ -30,3 +30,15 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Page of storage pairs sent by `state_streamStoragePairs`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoragePairsPage<Hash> {
+	/// Block hash the pairs were read at
+	pub at: Hash,
+	/// Storage pairs of the page, in lexicographic order of the keys
+	pub pairs: Vec<(StorageKey, StorageData)>,
+	/// Key to pass as `start_key` to continue after this page, `None` on the last page
+	pub next_key: Option<StorageKey>,
+}
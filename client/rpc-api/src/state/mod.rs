@@ -30,7 +30,7 @@ use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
-pub use self::helpers::ReadProof;
+pub use self::helpers::{ReadProof, StoragePairsPage};
 
 /// Substrate state API
 #[rpc]
@@ -136,4 +136,32 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_storage(
 		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
 	) -> RpcResult<bool>;
+
+	/// Streams the pairs with prefix, in pages of up to `count` pairs, `count` being at least 1.
+	/// If `start_key` is passed, starts after it in lexicographic order.
+	/// The subscription ends after the page without `nextKey`.
+	#[pubsub(
+		subscription = "state_storagePairs",
+		subscribe,
+		name = "state_streamStoragePairs"
+	)]
+	fn subscribe_storage_pairs(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StoragePairsPage<Hash>>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		hash: Option<Hash>,
+	);
+
+	/// Stop streaming storage pairs
+	#[pubsub(
+		subscription = "state_storagePairs",
+		unsubscribe,
+		name = "state_unsubscribeStoragePairs"
+	)]
+	fn unsubscribe_storage_pairs(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
 }
@@ -32,6 +32,9 @@ pub enum Error {
 	NotHealthy(Health),
 	/// Peer argument is malformatted.
 	MalformattedPeerArg(String),
+	/// The node or subnet to unban wasn't banned.
+	#[display(fmt = "{} is not banned", _0)]
+	NotBanned(String),
 }
 
 impl std::error::Error for Error {}
@@ -51,7 +54,12 @@ impl From<Error> for rpc::Error {
 				code :rpc::ErrorCode::ServerError(BASE_ERROR + 2),
 				message: e.clone(),
 				data: None,
-			}
+			},
+			Error::NotBanned(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
 		}
 	}
 }
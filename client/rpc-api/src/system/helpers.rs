@@ -98,6 +98,18 @@ pub struct SyncState<Number> {
 	#[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
 	pub highest_block: Option<Number>,
 }
+
+/// What the node remembers about another node across restarts.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownPeer {
+	/// Peer ID
+	pub peer_id: String,
+	/// Reputation of the peer
+	pub reputation: i32,
+	/// Addresses the peer was successfully dialed at
+	pub addresses: Vec<String>,
+}
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -114,6 +126,18 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_serialize_known_peer() {
+		assert_eq!(
+			::serde_json::to_string(&KnownPeer {
+				peer_id: "2".into(),
+				reputation: -3,
+				addresses: vec!["/ip4/127.0.0.1/tcp/30333".into()],
+			}).unwrap(),
+			r#"{"peerId":"2","reputation":-3,"addresses":["/ip4/127.0.0.1/tcp/30333"]}"#,
+		);
+	}
+
 	#[test]
 	fn should_serialize_peer_info() {
 		assert_eq!(
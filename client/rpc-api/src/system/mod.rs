@@ -27,7 +27,7 @@ use futures::{future::BoxFuture, compat::Compat};
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole, SyncState};
+pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole, SyncState, KnownPeer};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -102,6 +102,40 @@ pub trait SystemApi<Hash, Number> {
 	fn system_remove_reserved_peer(&self, peer_id: String)
 		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
 
+	/// Returns the reputations and known-good addresses of other nodes that are persisted
+	/// across restarts.
+	#[rpc(name = "system_peerStore", returns = "Vec<KnownPeer>")]
+	fn system_peer_store(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<KnownPeer>>>>;
+
+	/// Forgets the reputations and known-good addresses of all the other nodes, both in memory
+	/// and on disk. Nodes that were banned because of their reputation can connect again.
+	#[rpc(name = "system_clearPeerStore", returns = "()")]
+	fn system_clear_peer_store(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<()>>>;
+
+	/// Bans a node or a subnet: all further connections with it are refused. Returns the empty
+	/// string or an error. The string should encode either a PeerId, e.g.
+	/// `QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`, an IP address, e.g. `198.51.100.19`, or
+	/// a subnet in CIDR notation, e.g. `198.51.100.0/24`.
+	///
+	/// Existing connections with a banned node, or with an address of a banned subnet, are
+	/// closed.
+	#[rpc(name = "system_banPeer", returns = "()")]
+	fn system_ban_peer(&self, target: String)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<()>>>;
+
+	/// Lifts a ban put in place with `system_banPeer` or on the command line. The string should
+	/// be formatted as for `system_banPeer`. Returns an error if the node or subnet isn't banned.
+	#[rpc(name = "system_unbanPeer", returns = "()")]
+	fn system_unban_peer(&self, target: String)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<()>>>;
+
+	/// Returns the banned PeerIds and subnets.
+	#[rpc(name = "system_listBanned", returns = "Vec<String>")]
+	fn system_list_banned(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<String>>>>;
+
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
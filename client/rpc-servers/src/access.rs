@@ -0,0 +1,189 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-method access control for the HTTP and WS RPC servers.
+//!
+//! An [`RpcAccessPolicy`] lists the methods anyone may call, and the methods callers presenting
+//! a given API key may call. Over HTTP, the key is passed in an `Authorization: Bearer <key>`
+//! header. Browsers can't set headers on WebSocket connections, so over WS the key is passed as
+//! a `bearer.<key>` sub-protocol in the handshake.
+//!
+//! Methods are matched by rules, which are either:
+//!
+//! - `*`, matching every method,
+//! - `<namespace>_*`, matching every method of a namespace, for example `author_*`,
+//! - the exact name of a method, for example `author_submitExtrinsic`.
+
+use std::{collections::HashMap, fs, io, path::Path};
+use jsonrpc_core::{Error, ErrorCode};
+use serde::Deserialize;
+
+/// Prefix of the WebSocket sub-protocol carrying the API key.
+pub const WS_API_KEY_PROTOCOL_PREFIX: &str = "bearer.";
+
+/// Error code returned for calls denied by the access policy.
+const ACCESS_DENIED: i64 = -32_001;
+
+/// Methods a caller is allowed to call.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct MethodSet(Vec<String>);
+
+impl MethodSet {
+	/// Returns `true` if `method` matches one of the rules of the set.
+	pub fn allows(&self, method: &str) -> bool {
+		self.0.iter().any(|rule| match rule.strip_suffix('*') {
+			Some(prefix) => method.starts_with(prefix),
+			None => rule == method,
+		})
+	}
+}
+
+impl<T: Into<String>> std::iter::FromIterator<T> for MethodSet {
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		MethodSet(iter.into_iter().map(Into::into).collect())
+	}
+}
+
+/// Access policy of the RPC servers.
+///
+/// Loaded from a JSON file of the form:
+///
+/// ```json
+/// {
+///     "public": ["chain_*", "state_getStorage", "author_submitExtrinsic"],
+///     "keys": {
+///         "<operator key>": ["*"],
+///         "<monitoring key>": ["system_*"]
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcAccessPolicy {
+	/// Methods callable without an API key.
+	#[serde(default)]
+	pub public: MethodSet,
+	/// Methods callable by the holders of each API key, in addition to the public ones.
+	#[serde(default)]
+	pub keys: HashMap<String, MethodSet>,
+}
+
+impl RpcAccessPolicy {
+	/// Load the policy from a JSON file.
+	pub fn load(path: &Path) -> io::Result<Self> {
+		let file = fs::File::open(path)?;
+		serde_json::from_reader(io::BufReader::new(file))
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	/// Check whether a caller presenting `api_key` may call `method`.
+	///
+	/// Calls presenting an unknown API key are always denied.
+	pub fn check(&self, method: &str, api_key: Option<&str>) -> Result<(), Error> {
+		let allowed = match api_key {
+			Some(key) => match self.keys.get(key) {
+				Some(methods) => methods.allows(method) || self.public.allows(method),
+				None => return Err(Error {
+					code: ErrorCode::ServerError(ACCESS_DENIED),
+					message: "Invalid API key".into(),
+					data: None,
+				}),
+			},
+			None => self.public.allows(method),
+		};
+		if allowed {
+			Ok(())
+		} else {
+			Err(Error {
+				code: ErrorCode::ServerError(ACCESS_DENIED),
+				message: format!("Access to {} denied", method),
+				data: None,
+			})
+		}
+	}
+}
+
+/// Extract the API key from the value of an `Authorization` header.
+pub fn api_key_from_authorization(header: &str) -> Option<&str> {
+	let mut parts = header.splitn(2, ' ');
+	match (parts.next(), parts.next()) {
+		(Some(scheme), Some(key)) if scheme.eq_ignore_ascii_case("bearer") => {
+			Some(key.trim()).filter(|key| !key.is_empty())
+		},
+		_ => None,
+	}
+}
+
+/// Extract the API key from the sub-protocols requested by a WebSocket client.
+pub fn api_key_from_ws_protocols(protocols: &[String]) -> Option<&str> {
+	protocols.iter()
+		.find_map(|protocol| protocol.strip_prefix(WS_API_KEY_PROTOCOL_PREFIX))
+		.filter(|key| !key.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn policy() -> RpcAccessPolicy {
+		serde_json::from_str(r#"{
+			"public": ["chain_*", "author_submitExtrinsic"],
+			"keys": {
+				"operator": ["*"],
+				"monitoring": ["system_*"]
+			}
+		}"#).unwrap()
+	}
+
+	#[test]
+	fn public_methods() {
+		let policy = policy();
+		assert!(policy.check("chain_getBlock", None).is_ok());
+		assert!(policy.check("author_submitExtrinsic", None).is_ok());
+		assert!(policy.check("author_insertKey", None).is_err());
+		assert!(policy.check("system_addLogFilter", None).is_err());
+	}
+
+	#[test]
+	fn api_key_methods() {
+		let policy = policy();
+		assert!(policy.check("author_insertKey", Some("operator")).is_ok());
+		assert!(policy.check("system_addLogFilter", Some("monitoring")).is_ok());
+		assert!(policy.check("chain_getBlock", Some("monitoring")).is_ok());
+		assert!(policy.check("author_insertKey", Some("monitoring")).is_err());
+		assert!(policy.check("chain_getBlock", Some("unknown")).is_err());
+	}
+
+	#[test]
+	fn unknown_fields_are_rejected() {
+		assert!(serde_json::from_str::<RpcAccessPolicy>(r#"{ "pubic": ["*"] }"#).is_err());
+	}
+
+	#[test]
+	fn extract_api_keys() {
+		assert_eq!(api_key_from_authorization("Bearer abc"), Some("abc"));
+		assert_eq!(api_key_from_authorization("bearer  abc "), Some("abc"));
+		assert_eq!(api_key_from_authorization("Basic abc"), None);
+		assert_eq!(api_key_from_authorization("Bearer "), None);
+
+		let protocols = vec!["json".to_string(), "bearer.abc".to_string()];
+		assert_eq!(api_key_from_ws_protocols(&protocols), Some("abc"));
+		assert_eq!(api_key_from_ws_protocols(&protocols[..1]), None);
+	}
+}
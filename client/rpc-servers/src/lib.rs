@@ -20,6 +20,8 @@
 
 #![warn(missing_docs)]
 
+mod access;
+mod limits;
 mod middleware;
 
 use std::io;
@@ -37,10 +39,12 @@ const WS_MAX_CONNECTIONS: usize = 100;
 pub type RpcHandler<T> = pubsub::PubSubHandler<T, RpcMiddleware>;
 
 pub use self::inner::*;
-pub use middleware::{RpcMiddleware, RpcMetrics};
+pub use access::{MethodSet, RpcAccessPolicy, WS_API_KEY_PROTOCOL_PREFIX};
+pub use limits::{RateLimiter, RpcLimits, DEFAULT_METHOD_COSTS};
+pub use middleware::{CallerMetadata, RpcMiddleware, RpcMetrics};
 
 /// Construct rpc `IoHandler`
-pub fn rpc_handler<M: PubSubMetadata>(
+pub fn rpc_handler<M: PubSubMetadata + CallerMetadata>(
 	extension: impl IoHandlerExtension<M>,
 	rpc_middleware: RpcMiddleware,
 ) -> RpcHandler<M> {
@@ -76,13 +80,20 @@ mod inner {
 
 	/// Start HTTP server listening on given address.
 	///
+	/// The IP address of callers is read from the headers set by `trusted_proxies`, if any.
+	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
+	pub fn start_http<M: pubsub::PubSubMetadata + CallerMetadata + Default>(
 		addr: &std::net::SocketAddr,
 		cors: Option<&Vec<String>>,
+		trusted_proxies: &[std::net::IpAddr],
 		io: RpcHandler<M>,
 	) -> io::Result<http::Server> {
-		http::ServerBuilder::new(io)
+		let trusted_proxies = trusted_proxies.to_vec();
+		let meta_extractor = move |request: &http::hyper::Request<http::hyper::Body>| {
+			http_metadata::<M>(request, &trusted_proxies)
+		};
+		http::ServerBuilder::with_meta_extractor(io, meta_extractor)
 			.threads(4)
 			.health_api(("/health", "system_health"))
 			.allowed_hosts(hosts_filtering(cors.is_some()))
@@ -99,7 +110,7 @@ mod inner {
 	/// Start IPC server listening on given path.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ipc<M: pubsub::PubSubMetadata + Default>(
+	pub fn start_ipc<M: pubsub::PubSubMetadata + CallerMetadata + Default>(
 		addr: &str,
 		io: RpcHandler<M>,
 	) -> io::Result<ipc::Server> {
@@ -116,13 +127,23 @@ mod inner {
 	/// Start WS server listening on given address.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
-	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
+	pub fn start_ws<
+		M: pubsub::PubSubMetadata
+			+ CallerMetadata
+			+ From<jsonrpc_core::futures::sync::mpsc::Sender<String>>
+	> (
 		addr: &std::net::SocketAddr,
 		max_connections: Option<usize>,
 		cors: Option<&Vec<String>>,
 		io: RpcHandler<M>,
 	) -> io::Result<ws::Server> {
-		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
+		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| {
+			let meta = M::from(context.sender());
+			match access::api_key_from_ws_protocols(&context.protocols) {
+				Some(api_key) => meta.with_api_key(api_key.into()),
+				None => meta,
+			}
+		})
 			.max_payload(MAX_PAYLOAD)
 			.max_connections(max_connections.unwrap_or(WS_MAX_CONNECTIONS))
 			.allowed_origins(map_cors(cors))
@@ -138,6 +159,27 @@ mod inner {
 			})
 	}
 
+	fn http_metadata<M: CallerMetadata + Default>(
+		request: &http::hyper::Request<http::hyper::Body>,
+		trusted_proxies: &[std::net::IpAddr],
+	) -> M {
+		let header = |name| request.headers().get(name).and_then(|value| value.to_str().ok());
+		let mut meta = M::default();
+		let api_key = header("authorization").and_then(access::api_key_from_authorization);
+		if let Some(api_key) = api_key {
+			meta = meta.with_api_key(api_key.into());
+		}
+		let remote_ip = limits::forwarded_ip(
+			trusted_proxies,
+			header("x-forwarded-for"),
+			header("x-real-ip"),
+		);
+		if let Some(ip) = remote_ip {
+			meta = meta.with_remote_ip(ip);
+		}
+		meta
+	}
+
 	fn map_cors<T: for<'a> From<&'a str>>(
 		cors: Option<&Vec<String>>
 	) -> http::DomainsValidation<T> {
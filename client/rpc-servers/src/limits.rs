@@ -0,0 +1,609 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate limits and quotas of the RPC servers.
+//!
+//! Every method has a cost, 1 unless configured otherwise. Each caller gets a token bucket that
+//! refills at the configured rate of cost units per second and holds up to
+//! `BURST_SECONDS` seconds worth of units. Calls are rejected while the bucket is empty.
+//!
+//! WS connections can also be limited in the number of subscriptions they keep open. A subscribe
+//! call takes a slot in the quota of its connection, attached to the metadata of the call, and
+//! the subscription must hold it until it ends with `Metadata::hold_subscription_slot`, whether
+//! the caller unsubscribed or the server ended it.
+//!
+//! Callers are identified by their WS connection and by their IP address. HTTP calls don't have a
+//! connection that outlives them: calls from the same IP address count as one connection, and
+//! calls from unknown addresses as a single shared one. A single limiter is shared by all the
+//! servers, so that callers can't multiply their allowance by using several transports.
+//!
+//! The servers don't expose the socket address of callers, so the IP address is read from the
+//! `X-Forwarded-For` or `X-Real-IP` header, and is only known for HTTP calls. The headers are
+//! ignored unless trusted reverse proxies are configured, and the server must then only be
+//! reachable through them. The address of the caller is the last one of `X-Forwarded-For` that
+//! is not a trusted proxy.
+//!
+//! Responses can also be limited in size. The calls that read many storage entries at once
+//! enforce the limit themselves, while reading, and fail once it is exceeded. On top of that, the
+//! limit applies to whole batches, by truncation only: the responses of a batch, already
+//! computed, are kept in order until their total size exceeds the limit, and the remaining ones
+//! are replaced with errors. Notifications of subscriptions are not limited, but
+//! `state_streamStoragePairs` bounds the size of its pages.
+
+use std::{
+	collections::HashMap, hash::Hash, io, net::IpAddr, time::Instant,
+	sync::{Arc, atomic::{AtomicUsize, Ordering}},
+};
+use jsonrpc_core::{Error, ErrorCode, Output, Response};
+use lru::LruCache;
+use parking_lot::Mutex;
+use sc_rpc_api::SubscriptionSlot;
+
+/// Error code returned for calls rejected because of a rate limit.
+const RATE_LIMITED: i64 = -32_002;
+
+/// Error code returned for subscriptions rejected because of the subscription quota.
+const TOO_MANY_SUBSCRIPTIONS: i64 = -32_003;
+
+/// Error code returned in place of responses exceeding the maximum response size.
+const RESPONSE_TOO_LARGE: i64 = -32_004;
+
+/// Number of seconds of calls a token bucket holds.
+const BURST_SECONDS: u32 = 10;
+
+/// Maximum number of IP addresses to track. The least recently seen are forgotten beyond this.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+/// Cost of methods that aren't configured otherwise, and aren't in `DEFAULT_METHOD_COSTS`.
+const DEFAULT_METHOD_COST: u32 = 1;
+
+/// Cost of the methods that can keep the node busy for a long time.
+pub const DEFAULT_METHOD_COSTS: &[(&str, u32)] = &[
+	("state_queryStorage", 100),
+	("state_queryStorageAt", 20),
+	("state_getKeys", 50),
+	("state_getPairs", 50),
+	("state_getKeysPaged", 10),
+	("state_getKeysPagedAt", 10),
+	("state_streamStoragePairs", 10),
+	("chain_getAccountHistory", 10),
+	("chain_getEventsByTopic", 10),
+	("author_dryRun", 20),
+];
+
+/// Rate limits and quotas of an RPC server. The default has no limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RpcLimits {
+	/// Cost units per second each connection may spend. `None` if unlimited.
+	///
+	/// HTTP calls count as one connection per IP address.
+	pub per_connection: Option<u32>,
+	/// Cost units per second each IP address may spend. `None` if unlimited.
+	pub per_ip: Option<u32>,
+	/// Maximum number of subscriptions opened on a single connection. `None` if unlimited.
+	pub max_subscriptions_per_connection: Option<usize>,
+	/// Cost of methods, overriding `DEFAULT_METHOD_COSTS`. Keys are either the name of a method
+	/// or a `<prefix>*` pattern.
+	pub method_costs: HashMap<String, u32>,
+	/// Maximum size in bytes of the responses of a batch, and of the responses of the calls
+	/// reading many storage entries. `None` if unlimited.
+	pub max_response_size: Option<usize>,
+	/// Addresses of the reverse proxies trusted to report the address of HTTP callers in the
+	/// `X-Forwarded-For` or `X-Real-IP` header. The headers are ignored if empty.
+	pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl RpcLimits {
+	/// Returns `true` if any rate limit or quota is set. The response size is limited
+	/// separately.
+	pub fn is_enabled(&self) -> bool {
+		self.per_connection.is_some() ||
+			self.per_ip.is_some() ||
+			self.max_subscriptions_per_connection.is_some()
+	}
+
+	/// Cost of a call to `method`.
+	///
+	/// An exact match in `method_costs` is used first, then the longest matching pattern, then
+	/// `DEFAULT_METHOD_COSTS`.
+	pub fn method_cost(&self, method: &str) -> u32 {
+		if let Some(cost) = self.method_costs.get(method) {
+			return *cost
+		}
+		let pattern_cost = self.method_costs.iter()
+			.filter_map(|(rule, cost)| rule.strip_suffix('*').map(|prefix| (prefix, cost)))
+			.filter(|(prefix, _)| method.starts_with(prefix))
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map(|(_, cost)| *cost);
+		pattern_cost
+			.or_else(|| DEFAULT_METHOD_COSTS.iter().find(|(m, _)| *m == method).map(|(_, c)| *c))
+			.unwrap_or(DEFAULT_METHOD_COST)
+	}
+}
+
+/// IP address of an HTTP caller, given the values of the `X-Forwarded-For` and `X-Real-IP`
+/// headers of its request and the `trusted_proxies` allowed to set them.
+///
+/// Each proxy appends the address of its own peer to `X-Forwarded-For`, so the caller is the
+/// last address that is not a trusted proxy. `X-Real-IP` is only used without
+/// `X-Forwarded-For`.
+pub fn forwarded_ip(
+	trusted_proxies: &[IpAddr],
+	forwarded_for: Option<&str>,
+	real_ip: Option<&str>,
+) -> Option<IpAddr> {
+	if trusted_proxies.is_empty() {
+		return None
+	}
+	let parse = |ip: &str| ip.trim().parse::<IpAddr>().ok();
+	match forwarded_for {
+		Some(forwarded_for) => forwarded_for.rsplit(',')
+			.map(parse)
+			// an unparsable address can't be trusted, and hides the ones before it
+			.take_while(Option::is_some)
+			.flatten()
+			.find(|ip| !trusted_proxies.contains(ip)),
+		None => real_ip.and_then(parse),
+	}
+}
+
+/// Reason a call was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+	/// The connection exceeded its rate limit.
+	ConnectionRateLimit,
+	/// The IP address exceeded its rate limit.
+	IpRateLimit,
+	/// The connection has too many subscriptions.
+	Subscriptions,
+}
+
+impl Rejection {
+	/// Label of the rejection in the metrics.
+	pub fn label(&self) -> &'static str {
+		match self {
+			Rejection::ConnectionRateLimit => "connection_rate_limit",
+			Rejection::IpRateLimit => "ip_rate_limit",
+			Rejection::Subscriptions => "subscriptions",
+		}
+	}
+
+	/// Error returned to the caller.
+	pub fn error(&self) -> Error {
+		let (code, message) = match self {
+			Rejection::ConnectionRateLimit | Rejection::IpRateLimit =>
+				(RATE_LIMITED, "Too many requests"),
+			Rejection::Subscriptions => (TOO_MANY_SUBSCRIPTIONS, "Too many subscriptions"),
+		};
+		Error { code: ErrorCode::ServerError(code), message: message.into(), data: None }
+	}
+}
+
+/// Subscription methods whose name doesn't say so, as `(subscribe, unsubscribe)` pairs.
+const SUBSCRIPTION_METHODS: &[(&str, &str)] = &[
+	("author_submitAndWatchExtrinsic", "author_unwatchExtrinsic"),
+];
+
+/// Kind of a call, as far as the subscription quota is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+	/// Opens a subscription.
+	Subscribe,
+	/// Closes a subscription.
+	Unsubscribe,
+	/// Anything else.
+	Other,
+}
+
+impl CallKind {
+	/// Guess the kind of a call from the name of its method.
+	///
+	/// Methods are looked up in `SUBSCRIPTION_METHODS` first, then recognized by their name.
+	/// Streaming methods such as `state_streamStoragePairs` deliver their results through a
+	/// subscription, and count as subscriptions.
+	pub fn of(method: &str) -> Self {
+		for (subscribe, unsubscribe) in SUBSCRIPTION_METHODS {
+			if method == *subscribe {
+				return CallKind::Subscribe
+			} else if method == *unsubscribe {
+				return CallKind::Unsubscribe
+			}
+		}
+		let method = method.to_ascii_lowercase();
+		if method.contains("unsubscribe") {
+			CallKind::Unsubscribe
+		} else if method.contains("subscribe") || method.contains("_stream") {
+			CallKind::Subscribe
+		} else {
+			CallKind::Other
+		}
+	}
+}
+
+/// Replace the outputs of a batch `response` which don't fit in `max` bytes with errors.
+///
+/// A single output is left as it is: the calls that may return large results limit their
+/// response themselves, without serializing it first.
+///
+/// Returns the new response and the number of outputs replaced.
+pub fn limit_response_size(response: Response, max: usize) -> (Response, usize) {
+	let outputs = match response {
+		Response::Batch(outputs) => outputs,
+		single @ Response::Single(_) => return (single, 0),
+	};
+	let mut remaining = max;
+	let mut replaced = 0;
+	let mut limit = |output: Output| {
+		let size = serialized_size(&output);
+		if size <= remaining {
+			remaining -= size;
+			output
+		} else {
+			replaced += 1;
+			let error = Error {
+				code: ErrorCode::ServerError(RESPONSE_TOO_LARGE),
+				message: format!("Response exceeds the maximum size of {} bytes", max),
+				data: None,
+			};
+			Output::from(Err(error), output.id().clone(), output.version())
+		}
+	};
+	let outputs = outputs.into_iter().map(limit).collect();
+	(Response::Batch(outputs), replaced)
+}
+
+/// Size of `output` once serialized, without keeping the serialized bytes around.
+fn serialized_size(output: &Output) -> usize {
+	struct Counter(usize);
+
+	impl io::Write for Counter {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0 += buf.len();
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	let mut counter = Counter(0);
+	// Serializing an `Output` can't fail, and a failure would only make the size smaller.
+	let _ = serde_json::to_writer(&mut counter, output);
+	counter.0
+}
+
+/// Token bucket refilling at `rate` units per second.
+#[derive(Debug)]
+struct TokenBucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+impl TokenBucket {
+	fn new(rate: u32, now: Instant) -> Self {
+		TokenBucket { tokens: capacity(rate), updated: now }
+	}
+
+	fn refill(&mut self, rate: u32, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * f64::from(rate)).min(capacity(rate));
+		self.updated = now;
+	}
+
+	/// Take `cost` units, capped to the capacity so that expensive calls are possible at all.
+	fn try_take(&mut self, rate: u32, cost: u32, now: Instant) -> bool {
+		self.refill(rate, now);
+		let cost = f64::from(cost).min(capacity(rate));
+		if self.tokens >= cost {
+			self.tokens -= cost;
+			true
+		} else {
+			false
+		}
+	}
+}
+
+fn capacity(rate: u32) -> f64 {
+	f64::from(rate) * f64::from(BURST_SECONDS)
+}
+
+/// State of a connection.
+#[derive(Debug)]
+struct Connection {
+	bucket: Option<TokenBucket>,
+	/// Number of subscriptions opened, shared with their slots which decrement it once
+	/// released.
+	subscriptions: Arc<AtomicUsize>,
+}
+
+/// Identifier of a connection, unique as long as the connection is open.
+pub type ConnectionId = usize;
+
+/// Bucket of `key` in `buckets`, created full if missing.
+fn bucket<K: Hash + Eq + Copy>(
+	buckets: &mut LruCache<K, TokenBucket>,
+	key: K,
+	rate: u32,
+	now: Instant,
+) -> &mut TokenBucket {
+	if !buckets.contains(&key) {
+		buckets.put(key, TokenBucket::new(rate, now));
+	}
+	buckets.get_mut(&key).expect("inserted above if missing; qed")
+}
+
+/// Enforces `RpcLimits`, for all the servers sharing it.
+#[derive(Debug)]
+pub struct RateLimiter {
+	limits: RpcLimits,
+	connections: Arc<Mutex<HashMap<ConnectionId, Connection>>>,
+	/// Buckets of IP addresses, for `RpcLimits::per_ip`.
+	ips: Mutex<LruCache<IpAddr, TokenBucket>>,
+	/// Buckets of HTTP callers, by IP address if known, for `RpcLimits::per_connection`.
+	http_callers: Mutex<LruCache<Option<IpAddr>, TokenBucket>>,
+}
+
+impl RateLimiter {
+	/// Create a new limiter enforcing `limits`.
+	pub fn new(limits: RpcLimits) -> Self {
+		RateLimiter {
+			limits,
+			connections: Default::default(),
+			ips: Mutex::new(LruCache::new(MAX_TRACKED_IPS)),
+			http_callers: Mutex::new(LruCache::new(MAX_TRACKED_IPS + 1)),
+		}
+	}
+
+	/// The limits enforced.
+	pub fn limits(&self) -> &RpcLimits {
+		&self.limits
+	}
+
+	/// Account for a call to `method` and check that it is within the limits.
+	///
+	/// `connection` is `None` for HTTP calls. `on_new_connection` is called with a function to
+	/// call once `connection` is closed, the first time the connection is seen.
+	///
+	/// Subscriptions are counted as soon as they are accepted, and get the slot they take in the
+	/// quota of the connection. The slot must be kept as long as the subscription is open: it is
+	/// released once dropped.
+	pub fn check(
+		&self,
+		method: &str,
+		connection: Option<ConnectionId>,
+		ip: Option<IpAddr>,
+		on_new_connection: impl FnOnce(Box<dyn FnOnce() + Send>),
+	) -> Result<Option<SubscriptionSlot>, Rejection> {
+		let cost = self.limits.method_cost(method);
+		let now = Instant::now();
+
+		if let (Some(rate), Some(ip)) = (self.limits.per_ip, ip) {
+			if !bucket(&mut self.ips.lock(), ip, rate, now).try_take(rate, cost, now) {
+				return Err(Rejection::IpRateLimit)
+			}
+		}
+
+		let id = match (connection, self.limits.per_connection) {
+			(Some(id), _) => id,
+			(None, Some(rate)) => {
+				let mut http_callers = self.http_callers.lock();
+				if !bucket(&mut http_callers, ip, rate, now).try_take(rate, cost, now) {
+					return Err(Rejection::ConnectionRateLimit)
+				}
+				return Ok(None)
+			},
+			(None, None) => return Ok(None),
+		};
+		let mut connections = self.connections.lock();
+		let state = connections.entry(id).or_insert_with(|| {
+			let connections = self.connections.clone();
+			on_new_connection(Box::new(move || {
+				connections.lock().remove(&id);
+			}));
+			Connection {
+				bucket: self.limits.per_connection.map(|rate| TokenBucket::new(rate, now)),
+				subscriptions: Default::default(),
+			}
+		});
+		if let (Some(rate), Some(bucket)) = (self.limits.per_connection, state.bucket.as_mut()) {
+			if !bucket.try_take(rate, cost, now) {
+				return Err(Rejection::ConnectionRateLimit)
+			}
+		}
+		match (self.limits.max_subscriptions_per_connection, CallKind::of(method)) {
+			(Some(max), CallKind::Subscribe) => {
+				// Only incremented under the lock of `connections`, so there is no race.
+				if state.subscriptions.load(Ordering::Relaxed) >= max {
+					return Err(Rejection::Subscriptions)
+				}
+				state.subscriptions.fetch_add(1, Ordering::Relaxed);
+				let subscriptions = state.subscriptions.clone();
+				Ok(Some(SubscriptionSlot::new(move || {
+					subscriptions.fetch_sub(1, Ordering::Relaxed);
+				})))
+			},
+			_ => Ok(None),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{net::Ipv4Addr, time::Duration};
+
+	#[test]
+	fn method_costs() {
+		let mut limits = RpcLimits::default();
+		assert_eq!(limits.method_cost("system_health"), 1);
+		assert_eq!(limits.method_cost("state_queryStorage"), 100);
+
+		limits.method_costs.insert("state_*".into(), 5);
+		limits.method_costs.insert("state_get*".into(), 7);
+		limits.method_costs.insert("state_getKeys".into(), 9);
+		assert_eq!(limits.method_cost("state_queryStorage"), 5);
+		assert_eq!(limits.method_cost("state_getStorage"), 7);
+		assert_eq!(limits.method_cost("state_getKeys"), 9);
+		assert_eq!(limits.method_cost("system_health"), 1);
+	}
+
+	#[test]
+	fn call_kinds() {
+		assert_eq!(CallKind::of("chain_subscribeNewHeads"), CallKind::Subscribe);
+		assert_eq!(CallKind::of("subscribe_newHead"), CallKind::Subscribe);
+		assert_eq!(CallKind::of("chain_unsubscribeNewHeads"), CallKind::Unsubscribe);
+		assert_eq!(CallKind::of("unsubscribe_newHead"), CallKind::Unsubscribe);
+		assert_eq!(CallKind::of("state_streamStoragePairs"), CallKind::Subscribe);
+		assert_eq!(CallKind::of("state_unsubscribeStoragePairs"), CallKind::Unsubscribe);
+		assert_eq!(CallKind::of("author_submitAndWatchExtrinsic"), CallKind::Subscribe);
+		assert_eq!(CallKind::of("author_unwatchExtrinsic"), CallKind::Unsubscribe);
+		assert_eq!(CallKind::of("author_submitExtrinsic"), CallKind::Other);
+		assert_eq!(CallKind::of("chain_getHeader"), CallKind::Other);
+	}
+
+	#[test]
+	fn token_bucket() {
+		let now = Instant::now();
+		let mut bucket = TokenBucket::new(1, now);
+		assert!(bucket.try_take(1, 10, now));
+		assert!(!bucket.try_take(1, 1, now));
+		assert!(bucket.try_take(1, 1, now + Duration::from_secs(1)));
+		// Costs above the capacity drain the full bucket.
+		assert!(bucket.try_take(1, 100, now + Duration::from_secs(20)));
+		assert!(bucket.tokens < capacity(1));
+	}
+
+	#[test]
+	fn ip_rate_limit() {
+		let limiter = RateLimiter::new(RpcLimits { per_ip: Some(1), ..Default::default() });
+		let ip = Some(Ipv4Addr::LOCALHOST.into());
+		assert!(limiter.check("state_getKeys", None, ip, |_| {}).is_ok());
+		let rejection = limiter.check("system_health", None, ip, |_| {}).err();
+		assert_eq!(rejection, Some(Rejection::IpRateLimit));
+		assert!(limiter.check("system_health", None, None, |_| {}).is_ok());
+	}
+
+	#[test]
+	fn http_callers_count_as_connections() {
+		let limiter = RateLimiter::new(RpcLimits { per_connection: Some(1), ..Default::default() });
+		let ip = Some(Ipv4Addr::LOCALHOST.into());
+		let other = Some(Ipv4Addr::BROADCAST.into());
+		let health = "system_health";
+		assert!(limiter.check("state_getKeys", None, ip, |_| {}).is_ok());
+		let rejection = limiter.check(health, None, ip, |_| {}).err();
+		assert_eq!(rejection, Some(Rejection::ConnectionRateLimit));
+		assert!(limiter.check(health, None, other, |_| {}).is_ok());
+		// Callers with an unknown address share a connection.
+		assert!(limiter.check("state_getKeys", None, None, |_| {}).is_ok());
+		let rejection = limiter.check(health, None, None, |_| {}).err();
+		assert_eq!(rejection, Some(Rejection::ConnectionRateLimit));
+		// WS connections have their own.
+		assert!(limiter.check(health, Some(1), ip, |_| {}).is_ok());
+	}
+
+	#[test]
+	fn tracked_ips_are_bounded() {
+		let limiter = RateLimiter::new(RpcLimits { per_ip: Some(1), ..Default::default() });
+		for i in 0..MAX_TRACKED_IPS as u32 + 10 {
+			let ip = Some(Ipv4Addr::from(i).into());
+			assert!(limiter.check("system_health", None, ip, |_| {}).is_ok());
+		}
+		assert_eq!(limiter.ips.lock().len(), MAX_TRACKED_IPS);
+	}
+
+	#[test]
+	fn forwarded_ips() {
+		let proxy: IpAddr = Ipv4Addr::new(10, 0, 0, 1).into();
+		let client: IpAddr = Ipv4Addr::new(1, 2, 3, 4).into();
+		// Headers are ignored without trusted proxies.
+		assert_eq!(forwarded_ip(&[], Some("1.2.3.4"), Some("1.2.3.4")), None);
+
+		let trusted = &[proxy];
+		assert_eq!(forwarded_ip(trusted, Some("1.2.3.4"), None), Some(client));
+		assert_eq!(forwarded_ip(trusted, Some("5.6.7.8, 1.2.3.4, 10.0.0.1"), None), Some(client));
+		assert_eq!(forwarded_ip(trusted, Some("1.2.3.4, junk, 10.0.0.1"), None), None);
+		assert_eq!(forwarded_ip(trusted, Some("10.0.0.1"), Some("1.2.3.4")), None);
+		assert_eq!(forwarded_ip(trusted, None, Some("1.2.3.4")), Some(client));
+	}
+
+	#[test]
+	fn subscription_quota() {
+		let limiter = RateLimiter::new(RpcLimits {
+			max_subscriptions_per_connection: Some(1),
+			..Default::default()
+		});
+		let mut on_close = None;
+		let sub = "chain_subscribeNewHeads";
+		let slot = limiter.check(sub, Some(1), None, |f| on_close = Some(f)).unwrap();
+		assert!(slot.is_some());
+		assert_eq!(limiter.check(sub, Some(1), None, |_| {}).err(), Some(Rejection::Subscriptions));
+		let other = limiter.check(sub, Some(2), None, |_| {}).unwrap();
+
+		// The slot is released once the subscription ends, whoever ended it.
+		drop(slot);
+		let unsubscribe = "chain_unsubscribeNewHeads";
+		assert!(limiter.check(unsubscribe, Some(1), None, |_| {}).unwrap().is_none());
+		let slot = limiter.check(sub, Some(1), None, |_| {}).unwrap();
+		assert_eq!(limiter.check(sub, Some(1), None, |_| {}).err(), Some(Rejection::Subscriptions));
+
+		// A subscription that failed releases its slot along with the metadata of the call.
+		let meta = sc_rpc_api::Metadata::default().with_subscription_slot(other.unwrap());
+		drop(meta);
+		assert!(limiter.check(sub, Some(2), None, |_| {}).is_ok());
+
+		// Closing the connection forgets it.
+		on_close.expect("first call on the connection")();
+		assert!(!limiter.connections.lock().contains_key(&1));
+		drop(slot);
+	}
+
+	#[test]
+	fn response_size() {
+		use jsonrpc_core::{Id, Success, Value, Version};
+
+		let output = |id| Output::Success(Success {
+			jsonrpc: Some(Version::V2),
+			result: Value::String("x".repeat(100)),
+			id: Id::Num(id),
+		});
+		let size = serialized_size(&output(1));
+		assert!(size > 100);
+
+		let (response, replaced) = limit_response_size(Response::Single(output(1)), size - 1);
+		assert_eq!(replaced, 0);
+		assert_eq!(response, Response::Single(output(1)));
+
+		let batch = Response::Batch(vec![output(1), output(2), output(3)]);
+		let (response, replaced) = limit_response_size(batch, 2 * size + 10);
+		assert_eq!(replaced, 1);
+		match response {
+			Response::Batch(outputs) => {
+				assert_eq!(outputs[..2], [output(1), output(2)]);
+				match outputs[2] {
+					Output::Failure(ref failure) => {
+						assert_eq!(failure.id, Id::Num(3));
+						assert_eq!(failure.error.code, ErrorCode::ServerError(RESPONSE_TOO_LARGE));
+					},
+					ref other => panic!("Unexpected output: {:?}", other),
+				}
+			},
+			other => panic!("Unexpected response: {:?}", other),
+		}
+	}
+}
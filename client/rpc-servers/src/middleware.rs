@@ -18,21 +18,67 @@
 
 //! Middleware for RPC requests.
 
+use std::{net::IpAddr, sync::Arc};
 use jsonrpc_core::{
-	Middleware as RequestMiddleware, Metadata,
+	Middleware as RequestMiddleware, Call, Error, Id, Output, Version,
 	Request, Response, FutureResponse, FutureOutput
 };
 use prometheus_endpoint::{
 	Registry, CounterVec, PrometheusError,
 	Opts, register, U64
 };
+use pubsub::PubSubMetadata;
 
-use futures::{future::Either, Future};
+use futures::{future::{self, Either}, Future};
+use crate::access::RpcAccessPolicy;
+use sc_rpc_api::SubscriptionSlot;
+use crate::limits::{ConnectionId, RateLimiter, limit_response_size};
+
+/// Metadata of an RPC call carrying what is known about the caller.
+pub trait CallerMetadata: PubSubMetadata {
+	/// The API key presented by the caller, if any.
+	fn api_key(&self) -> Option<&str>;
+
+	/// Attach the API key presented by the caller.
+	fn with_api_key(self, api_key: String) -> Self;
+
+	/// The IP address of the caller, if known.
+	fn remote_ip(&self) -> Option<IpAddr>;
+
+	/// Attach the IP address of the caller.
+	fn with_remote_ip(self, ip: IpAddr) -> Self;
+
+	/// Attach the slot taken by the call in the subscription quota of its connection.
+	fn with_subscription_slot(self, slot: SubscriptionSlot) -> Self;
+}
+
+impl CallerMetadata for sc_rpc_api::Metadata {
+	fn api_key(&self) -> Option<&str> {
+		sc_rpc_api::Metadata::api_key(self)
+	}
+
+	fn with_api_key(self, api_key: String) -> Self {
+		sc_rpc_api::Metadata::with_api_key(self, api_key)
+	}
+
+	fn remote_ip(&self) -> Option<IpAddr> {
+		sc_rpc_api::Metadata::remote_ip(self)
+	}
+
+	fn with_remote_ip(self, ip: IpAddr) -> Self {
+		sc_rpc_api::Metadata::with_remote_ip(self, ip)
+	}
+
+	fn with_subscription_slot(self, slot: SubscriptionSlot) -> Self {
+		sc_rpc_api::Metadata::with_subscription_slot(self, slot)
+	}
+}
 
 /// Metrics for RPC middleware
 #[derive(Debug, Clone)]
 pub struct RpcMetrics {
 	rpc_calls: Option<CounterVec<U64>>,
+	rpc_calls_rejected: Option<CounterVec<U64>>,
 }
 
 impl RpcMetrics {
@@ -51,6 +97,19 @@ impl RpcMetrics {
 					r,
 				)
 			).transpose()?,
+			rpc_calls_rejected: metrics_registry.map(|r|
+				register(
+					CounterVec::new(
+						Opts::new(
+							"rpc_calls_rejected_total",
+							"Number of rpc calls rejected by the access policy, the rate limits or \
+							the response size limit",
+						),
+						&["protocol", "reason"]
+					)?,
+					r,
+				)
+			).transpose()?,
 		})
 	}
 }
@@ -59,6 +118,9 @@ impl RpcMetrics {
 pub struct RpcMiddleware {
 	metrics: RpcMetrics,
 	transport_label: String,
+	access_policy: Option<Arc<RpcAccessPolicy>>,
+	limiter: Option<Arc<RateLimiter>>,
+	max_response_size: Option<usize>,
 }
 
 impl RpcMiddleware {
@@ -70,11 +132,47 @@ impl RpcMiddleware {
 		RpcMiddleware {
 			metrics,
 			transport_label: String::from(transport_label),
+			access_policy: None,
+			limiter: None,
+			max_response_size: None,
 		}
 	}
+
+	/// Restrict the methods callers may call according to `policy`.
+	pub fn with_access_policy(mut self, policy: Arc<RpcAccessPolicy>) -> Self {
+		self.access_policy = Some(policy);
+		self
+	}
+
+	/// Enforce the rate limits, quotas and maximum response size of `limiter`.
+	///
+	/// Callers of servers sharing the limiter share their limits.
+	pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+		self.max_response_size = limiter.limits().max_response_size;
+		self.limiter = Some(limiter).filter(|limiter| limiter.limits().is_enabled());
+		self
+	}
+
+	fn reject(&self, method: &str, reason: &str, error: Error, id: Option<(Id, Option<Version>)>)
+		-> FutureOutput
+	{
+		log::debug!(
+			target: "rpc",
+			"Rejected call to {} over {}: {}",
+			method,
+			self.transport_label,
+			error.message,
+		);
+		if let Some(ref rpc_calls_rejected) = self.metrics.rpc_calls_rejected {
+			rpc_calls_rejected.with_label_values(&[self.transport_label.as_str(), reason]).inc();
+		}
+		// Notifications don't get a response.
+		let output = id.map(|(id, jsonrpc)| Output::from(Err(error), id, jsonrpc));
+		Box::new(future::ok(output))
+	}
 }
 
-impl<M: Metadata> RequestMiddleware<M> for RpcMiddleware {
+impl<M: CallerMetadata> RequestMiddleware<M> for RpcMiddleware {
 	type Future = FutureResponse;
 	type CallFuture = FutureOutput;
 
@@ -87,6 +185,130 @@ impl<M: Metadata> RequestMiddleware<M> for RpcMiddleware {
 			rpc_calls.with_label_values(&[self.transport_label.as_str()]).inc();
 		}
 
-		Either::B(next(request, meta))
+		// Single calls limit their own response, only batches are truncated here.
+		let max_response_size = match (self.max_response_size, &request) {
+			(Some(max), Request::Batch(_)) => max,
+			_ => return Either::B(next(request, meta)),
+		};
+		let rpc_calls_rejected = self.metrics.rpc_calls_rejected.clone();
+		let transport_label = self.transport_label.clone();
+		Either::A(Box::new(next(request, meta).map(move |response| {
+			let (response, replaced) = limit_response_size(response?, max_response_size);
+			if replaced > 0 {
+				log::debug!(
+					target: "rpc",
+					"Replaced {} responses over {} exceeding {} bytes",
+					replaced,
+					transport_label,
+					max_response_size,
+				);
+				if let Some(rpc_calls_rejected) = rpc_calls_rejected {
+					rpc_calls_rejected
+						.with_label_values(&[transport_label.as_str(), "response_size"])
+						.inc_by(replaced as u64);
+				}
+			}
+			Some(response)
+		})))
+	}
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<FutureOutput, X>
+	where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		if self.access_policy.is_none() && self.limiter.is_none() {
+			return Either::B(next(call, meta))
+		}
+		let (method, id) = match call {
+			Call::MethodCall(ref call) => (&call.method, Some((call.id.clone(), call.jsonrpc))),
+			Call::Notification(ref notification) => (&notification.method, None),
+			Call::Invalid { .. } => return Either::B(next(call, meta)),
+		};
+
+		if let Some(ref policy) = self.access_policy {
+			if let Err(error) = policy.check(method, meta.api_key()) {
+				return Either::A(self.reject(method, "access_denied", error, id))
+			}
+		}
+
+		let limiter = match self.limiter {
+			Some(ref limiter) => limiter,
+			None => return Either::B(next(call, meta)),
+		};
+		// The session lives as long as the connection, its address identifies the connection.
+		let session = meta.session();
+		let connection = session.as_ref().map(|session| Arc::as_ptr(session) as ConnectionId);
+		let checked = limiter.check(method, connection, meta.remote_ip(), |on_close| {
+			if let Some(ref session) = session {
+				session.on_drop(on_close);
+			}
+		});
+		match checked {
+			Err(rejection) =>
+				Either::A(self.reject(method, rejection.label(), rejection.error(), id)),
+			// The subscription holds the slot until it ends. If it isn't opened, the slot is
+			// released along with the metadata.
+			Ok(Some(slot)) => Either::B(next(call, meta.with_subscription_slot(slot))),
+			Ok(None) => Either::B(next(call, meta)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{MetaIoHandler, Value};
+	use sc_rpc_api::Metadata;
+
+	fn handler(metrics: RpcMetrics) -> MetaIoHandler<Metadata, RpcMiddleware> {
+		let policy: RpcAccessPolicy = serde_json::from_str(r#"{
+			"public": ["chain_*"],
+			"keys": { "operator": ["*"] }
+		}"#).unwrap();
+		let middleware = RpcMiddleware::new(metrics, "http").with_access_policy(Arc::new(policy));
+		let mut io = MetaIoHandler::with_middleware(middleware);
+		io.add_method("chain_getHead", |_| Ok(Value::String("head".into())));
+		io.add_method("author_insertKey", |_| Ok(Value::Null));
+		io
+	}
+
+	fn call(io: &MetaIoHandler<Metadata, RpcMiddleware>, method: &str, key: Option<&str>) -> Value {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		let meta = match key {
+			Some(key) => Metadata::default().with_api_key(key.into()),
+			None => Metadata::default(),
+		};
+		serde_json::from_str(&io.handle_request_sync(&request, meta).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn access_policy_rejects_calls() {
+		let registry = Registry::new();
+		let metrics = RpcMetrics::new(Some(&registry)).unwrap();
+		let io = handler(metrics.clone());
+		let denied = || metrics.rpc_calls_rejected.as_ref().unwrap()
+			.with_label_values(&["http", "access_denied"])
+			.get();
+
+		assert_eq!(call(&io, "chain_getHead", None)["result"], "head");
+		assert_eq!(call(&io, "author_insertKey", Some("operator"))["result"], Value::Null);
+		assert_eq!(denied(), 0);
+
+		let response = call(&io, "author_insertKey", None);
+		assert_eq!(response["error"]["code"], -32_001);
+		assert_eq!(response["error"]["message"], "Access to author_insertKey denied");
+		assert!(response.get("result").is_none());
+		assert_eq!(denied(), 1);
+
+		let response = call(&io, "chain_getHead", Some("wrong"));
+		assert_eq!(response["error"]["code"], -32_001);
+		assert_eq!(response["error"]["message"], "Invalid API key");
+		assert_eq!(denied(), 2);
+
+		// Notifications are rejected without a response.
+		let notification = r#"{"jsonrpc":"2.0","method":"author_insertKey","params":[]}"#;
+		assert_eq!(io.handle_request_sync(notification, Metadata::default()), None);
+		assert_eq!(denied(), 3);
 	}
 }
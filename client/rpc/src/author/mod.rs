@@ -34,8 +34,11 @@ use futures::{StreamExt as _, compat::Compat};
 use futures::future::{ready, FutureExt, TryFutureExt};
 use sc_rpc_api::DenyUnsafe;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
-use codec::{Encode, Decode};
-use sp_core::Bytes;
+use codec::{Compact, Encode, Decode};
+use sc_block_builder::DryRunProvider;
+use sp_core::{
+	Bytes, hashing::twox_128, storage::{ChildInfo, StorageKey, StorageData},
+};
 use sp_keystore::{SyncCryptoStorePtr, SyncCryptoStore};
 use sp_api::ProvideRuntimeApi;
 use sp_runtime::generic;
@@ -89,10 +92,47 @@ impl<P, Client> Author<P, Client> {
 /// some unique transactions via RPC and have them included in the pool.
 const TX_SOURCE: TransactionSource = TransactionSource::External;
 
+/// Storage key of a storage value of a FRAME pallet.
+fn pallet_storage_key(pallet: &[u8], storage: &[u8]) -> Vec<u8> {
+	[twox_128(pallet), twox_128(storage)].concat()
+}
+
+/// Records of the events appended to `System::Events`, encoded as a list of records.
+fn emitted_events(old: Option<&[u8]>, new: Option<&[u8]>) -> Option<Vec<u8>> {
+	// The events are a SCALE encoded `Vec`, which is empty if it doesn't exist.
+	fn split(events: Option<&[u8]>) -> Option<(u32, &[u8])> {
+		let mut events = events.unwrap_or(&[0u8][..]);
+		<Compact<u32>>::decode(&mut events).ok().map(|count| (count.0, events))
+	}
+	let (old_count, old_records) = split(old)?;
+	let (new_count, new_records) = split(new)?;
+	if !new_records.starts_with(old_records) {
+		return None;
+	}
+	let mut events = Compact(new_count.checked_sub(old_count)?).encode();
+	events.extend_from_slice(&new_records[old_records.len()..]);
+	Some(events)
+}
+
+/// Weight added to `System::BlockWeight`.
+fn consumed_weight(old: Option<&[u8]>, new: Option<&[u8]>) -> Option<u64> {
+	// The block weight is kept per dispatch class.
+	let total = |weight: Option<&[u8]>| match weight {
+		Some(mut weight) => <(u64, u64, u64)>::decode(&mut weight)
+			.ok()
+			.map(|(normal, operational, mandatory)| {
+				normal.saturating_add(operational).saturating_add(mandatory)
+			}),
+		None => Some(0),
+	};
+	total(new)?.checked_sub(total(old)?)
+}
+
 impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 	where
 		P: TransactionPool + Sync + Send + 'static,
-		Client: HeaderBackend<P::Block> + ProvideRuntimeApi<P::Block> + Send + Sync + 'static,
+		Client: HeaderBackend<P::Block> + ProvideRuntimeApi<P::Block> + DryRunProvider<P::Block> +
+			Send + Sync + 'static,
 		Client::Api: SessionKeys<P::Block, Error = ClientError>,
 {
 	type Metadata = crate::Metadata;
@@ -156,6 +196,21 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		)
 	}
 
+	fn replace_extrinsic(&self, replaced: TxHash<P>, ext: Bytes) -> FutureResult<TxHash<P>> {
+		let xt = match Decode::decode(&mut &ext[..]) {
+			Ok(xt) => xt,
+			Err(err) => return Box::new(result(Err(err.into()))),
+		};
+		let best_block_hash = self.client.info().best_hash;
+		Box::new(self.pool
+			.replace_one(&generic::BlockId::hash(best_block_hash), TX_SOURCE, replaced, xt)
+			.compat()
+			.map_err(|e| e.into_pool_error()
+				.map(Into::into)
+				.unwrap_or_else(|e| error::Error::Verification(Box::new(e)).into()))
+		)
+	}
+
 	fn pending_extrinsics(&self) -> Result<Vec<Bytes>> {
 		Ok(self.pool.ready().map(|tx| tx.data().encode().into()).collect())
 	}
@@ -185,8 +240,47 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		)
 	}
 
+	fn dry_run(&self, ext: Bytes, at: Option<BlockHash<P>>) -> Result<DryRun<BlockHash<P>>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let xt = Decode::decode(&mut &ext[..])?;
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let dry_run = self.client.dry_run_at(&generic::BlockId::hash(at), xt)
+			.map_err(|e| Error::Client(Box::new(e)))?;
+
+		let changed = |key: Vec<u8>| dry_run.storage_changes.iter()
+			.find(|(changed, _, _)| *changed == key)
+			.map(|(_, old, new)| (old.as_deref(), new.as_deref()));
+		let weight = changed(pallet_storage_key(b"System", b"BlockWeight"))
+			.and_then(|(old, new)| consumed_weight(old, new));
+		let events = changed(pallet_storage_key(b"System", b"Events"))
+			.and_then(|(old, new)| emitted_events(old, new))
+			.map(Into::into);
+
+		let storage_diff = |changes: Vec<sc_block_builder::StorageDiff>| changes.into_iter()
+			.map(|(key, old, new)| StorageDiff {
+				key: StorageKey(key),
+				old: old.map(StorageData),
+				new: new.map(StorageData),
+			})
+			.collect();
+		Ok(DryRun {
+			at,
+			result: dry_run.result.encode().into(),
+			weight,
+			events,
+			storage_diff: storage_diff(dry_run.storage_changes),
+			child_storage_diff: dry_run.child_storage_changes.into_iter()
+				.map(|(storage_key, changes)| ChildStorageDiff {
+					child_storage_key: ChildInfo::new_default(&storage_key).prefixed_storage_key(),
+					storage_diff: storage_diff(changes),
+				})
+				.collect(),
+		})
+	}
+
 	fn watch_extrinsic(&self,
-		_metadata: Self::Metadata,
+		metadata: Self::Metadata,
 		subscriber: Subscriber<TransactionStatus<TxHash<P>, BlockHash<P>>>,
 		xt: Bytes,
 	) {
@@ -214,10 +308,12 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 			.map(move |result| match result {
 				Ok(watcher) => {
 					subscriptions.add(subscriber, move |sink| {
-						sink
+						let task = sink
 							.sink_map_err(|e| log::debug!("Subscription sink failed: {:?}", e))
 							.send_all(Compat::new(watcher))
-							.map(|_| ())
+							.map(|_| ());
+						// the subscription ends once the transaction is finalized or dropped
+						metadata.hold_subscription_slot(task)
 					});
 				},
 				Err(err) => {
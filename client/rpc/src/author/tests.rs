@@ -33,6 +33,7 @@ use substrate_test_runtime_client::{
 	DefaultTestClientBuilderExt, TestClientBuilderExt, Backend, Client,
 };
 use sc_transaction_pool::{BasicPool, FullChainApi};
+use sp_transaction_pool::error::Error as PoolError;
 use futures::{executor, compat::Future01CompatExt};
 
 fn uxt(sender: AccountKeyring, nonce: u64) -> Extrinsic {
@@ -173,6 +174,41 @@ fn should_watch_extrinsic() {
 	assert_eq!(res, expected);
 }
 
+#[test]
+fn should_replace_extrinsic() {
+	// given
+	let setup = TestSetup::default();
+	let p = setup.author();
+	let xt = uxt(AccountKeyring::Alice, 0);
+	let hash = AuthorApi::submit_extrinsic(&p, xt.encode().into()).wait().unwrap();
+
+	let replacement = Transfer {
+		amount: 5,
+		nonce: 0,
+		from: AccountKeyring::Alice.into(),
+		to: Default::default(),
+	}.into_signed_tx();
+	let unrelated = uxt(AccountKeyring::Alice, 1);
+
+	// when
+	let unknown = AuthorApi::replace_extrinsic(&p, H256::zero(), replacement.encode().into()).wait();
+	let not_replacing = AuthorApi::replace_extrinsic(&p, hash, unrelated.encode().into()).wait();
+	let replaced = AuthorApi::replace_extrinsic(&p, hash, replacement.encode().into()).wait();
+
+	// then
+	assert_matches!(unknown, Err(Error::Pool(PoolError::ReplacedNotFound)));
+	assert_matches!(not_replacing, Err(Error::Pool(PoolError::NotReplacing)));
+	let replacement_hash: H256 = blake2_256(&replacement.encode()).into();
+	assert_matches!(replaced, Ok(h) if h == replacement_hash);
+	assert_eq!(setup.pool.status().ready, 1);
+	// a rejected replacement is not banned and can still be submitted on its own
+	assert_matches!(
+		AuthorApi::submit_extrinsic(&p, unrelated.encode().into()).wait(),
+		Ok(_)
+	);
+	assert_eq!(setup.pool.status().ready, 2);
+}
+
 #[test]
 fn should_return_watch_validation_error() {
 	//given
@@ -314,3 +350,38 @@ fn test_has_key() {
 		);
 	}
 }
+
+#[test]
+fn should_dry_run_extrinsic() {
+	let setup = TestSetup::default();
+	let p = setup.author();
+	let xt: Bytes = uxt(AccountKeyring::Alice, 0).encode().into();
+	let applied: sp_runtime::ApplyExtrinsicResult = Ok(Ok(()));
+
+	let dry_run = p.dry_run(xt.clone(), None).unwrap();
+	assert_eq!(dry_run.at, setup.client.info().best_hash);
+	assert_eq!(dry_run.result, applied.encode().into());
+	assert!(!dry_run.storage_diff.is_empty());
+	assert!(dry_run.child_storage_diff.is_empty());
+	// The test runtime doesn't use FRAME.
+	assert_eq!(dry_run.weight, None);
+	assert_eq!(dry_run.events, None);
+
+	// Nothing was submitted or changed.
+	assert_eq!(setup.pool.status().ready, 0);
+	assert_eq!(p.dry_run(xt, None).unwrap(), dry_run);
+}
+
+#[test]
+fn should_extract_emitted_events_and_consumed_weight() {
+	let old = vec![1u8, 2].encode();
+	let new = vec![1u8, 2, 3].encode();
+	assert_eq!(emitted_events(Some(&old[..]), Some(&new[..])), Some(vec![3u8].encode()));
+	assert_eq!(emitted_events(None, Some(&old[..])), Some(old.clone()));
+	assert_eq!(emitted_events(Some(&new[..]), Some(&old[..])), None);
+
+	let old = (1u64, 2u64, 0u64).encode();
+	let new = (4u64, 2u64, 1u64).encode();
+	assert_eq!(consumed_weight(Some(&old[..]), Some(&new[..])), Some(4));
+	assert_eq!(consumed_weight(None, Some(&[1u8][..])), None);
+}
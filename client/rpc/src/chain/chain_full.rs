@@ -22,10 +22,17 @@ use std::sync::Arc;
 use rpc::futures::future::result;
 use jsonrpc_pubsub::manager::SubscriptionManager;
 
-use sc_client_api::{BlockchainEvents, BlockBackend};
-use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT}};
+use sc_client_api::{BlockchainEvents, BlockBackend, EventIndexKey, EventIndexProvider};
+use sp_rpc::number::NumberOrHex;
+use sp_runtime::{
+	generic::{BlockId, SignedBlock},
+	traits::{Block as BlockT, NumberFor},
+};
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use super::{
+	ChainBackend, EventLocation, IndexedEvents, block_number, client_err, events_limit,
+	error::{Error, FutureResult, Result},
+};
 use std::marker::PhantomData;
 use sp_blockchain::HeaderBackend;
 
@@ -52,7 +59,8 @@ impl<Block: BlockT, Client> FullChain<Block, Client> {
 
 impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> where
 	Block: BlockT + 'static,
-	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> +
+		EventIndexProvider<Block> + 'static,
 {
 	fn client(&self) -> &Arc<Client> {
 		&self.client
@@ -77,4 +85,46 @@ impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> whe
 			.map_err(client_err)
 		))
 	}
+
+	fn events(
+		&self,
+		key: EventIndexKey,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<NumberFor<Block>, Block::Hash>> {
+		let limit = events_limit(limit)?;
+		let not_indexed = || Error::from(
+			"Events are not indexed by this node, see `--event-index`.".to_owned()
+		);
+		let index = self.client.event_index().ok_or_else(not_indexed)?;
+		let first_indexed = index.first_indexed().map_err(client_err)?.ok_or_else(not_indexed)?;
+		let last_indexed = index.last_indexed().map_err(client_err)?.ok_or_else(not_indexed)?;
+
+		let from = match from {
+			Some(from) => block_number::<Block>(from)?.max(first_indexed),
+			None => first_indexed,
+		};
+		let to = match to {
+			Some(to) => block_number::<Block>(to)?.min(last_indexed),
+			None => last_indexed,
+		};
+		let events = if from > to {
+			Vec::new()
+		} else {
+			index.events(key, from, to, limit).map_err(client_err)?
+		};
+		Ok(IndexedEvents {
+			first_indexed,
+			last_indexed,
+			events: events.into_iter()
+				.map(|location| EventLocation {
+					block_hash: location.block_hash,
+					block_number: location.block_number,
+					extrinsic_index: location.extrinsic_index,
+					event_index: location.event_index,
+				})
+				.collect(),
+		})
+	}
 }
@@ -23,13 +23,14 @@ use futures::{future::ready, FutureExt, TryFutureExt};
 use rpc::futures::future::{result, Future, Either};
 use jsonrpc_pubsub::manager::SubscriptionManager;
 
-use sc_client_api::light::{Fetcher, RemoteBodyRequest, RemoteBlockchain};
+use sc_client_api::{EventIndexKey, light::{Fetcher, RemoteBodyRequest, RemoteBlockchain}};
+use sp_rpc::number::NumberOrHex;
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
-	traits::{Block as BlockT},
+	traits::{Block as BlockT, NumberFor},
 };
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use super::{ChainBackend, IndexedEvents, client_err, error::{Error, FutureResult, Result}};
 use sp_blockchain::HeaderBackend;
 use sc_client_api::BlockchainEvents;
 
@@ -115,4 +116,14 @@ impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client,
 
 		Box::new(block)
 	}
+
+	fn events(
+		&self,
+		_key: EventIndexKey,
+		_from: Option<NumberOrHex>,
+		_to: Option<NumberOrHex>,
+		_limit: Option<u32>,
+	) -> Result<IndexedEvents<NumberFor<Block>, Block::Hash>> {
+		Err(Error::from("Events are not indexed by light clients.".to_owned()))
+	}
 }
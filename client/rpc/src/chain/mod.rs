@@ -32,8 +32,11 @@ use rpc::{
 	futures::{stream, Future, Sink, Stream},
 };
 
-use sc_client_api::{BlockchainEvents, light::{Fetcher, RemoteBlockchain}};
+use sc_client_api::{
+	BlockchainEvents, EventIndexKey, EventIndexProvider, light::{Fetcher, RemoteBlockchain},
+};
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
+use sp_core::Bytes;
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
@@ -46,6 +49,12 @@ pub use sc_rpc_api::chain::*;
 use sp_blockchain::HeaderBackend;
 use sc_client_api::BlockBackend;
 
+/// Number of event locations returned by the event index RPCs by default.
+const EVENTS_DEFAULT_LIMIT: u32 = 100;
+
+/// Maximum number of event locations returned by the event index RPCs.
+const EVENTS_MAX_LIMIT: u32 = 1000;
+
 /// Blockchain backend API
 trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	where
@@ -103,15 +112,25 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 		Ok(self.client().info().finalized_hash)
 	}
 
+	/// Get the locations of the indexed events with the given key, newest first.
+	fn events(
+		&self,
+		key: EventIndexKey,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<NumberFor<Block>, Block::Hash>>;
+
 	/// All new head subscription
 	fn subscribe_all_heads(
 		&self,
-		_metadata: crate::Metadata,
+		metadata: crate::Metadata,
 		subscriber: Subscriber<Block::Header>,
 	) {
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
+			&metadata,
 			subscriber,
 			|| self.client().info().best_hash,
 			|| self.client().import_notification_stream()
@@ -132,12 +151,13 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	/// New best head subscription
 	fn subscribe_new_heads(
 		&self,
-		_metadata: crate::Metadata,
+		metadata: crate::Metadata,
 		subscriber: Subscriber<Block::Header>,
 	) {
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
+			&metadata,
 			subscriber,
 			|| self.client().info().best_hash,
 			|| self.client().import_notification_stream()
@@ -159,12 +179,13 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 	/// Finalized head subscription
 	fn subscribe_finalized_heads(
 		&self,
-		_metadata: crate::Metadata,
+		metadata: crate::Metadata,
 		subscriber: Subscriber<Block::Header>,
 	) {
 		subscribe_headers(
 			self.client(),
 			self.subscriptions(),
+			&metadata,
 			subscriber,
 			|| self.client().info().finalized_hash,
 			|| self.client().finality_notification_stream()
@@ -190,7 +211,8 @@ pub fn new_full<Block: BlockT, Client>(
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
-		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> +
+			EventIndexProvider<Block> + 'static,
 {
 	Chain {
 		backend: Box::new(self::chain_full::FullChain::new(client, subscriptions)),
@@ -260,6 +282,26 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 		self.backend.finalized_head()
 	}
 
+	fn account_history(
+		&self,
+		account: Bytes,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<NumberFor<Block>, Block::Hash>> {
+		self.backend.events(EventIndexKey::Account(&account), from, to, limit)
+	}
+
+	fn events_by_topic(
+		&self,
+		topic: Block::Hash,
+		from: Option<NumberOrHex>,
+		to: Option<NumberOrHex>,
+		limit: Option<u32>,
+	) -> Result<IndexedEvents<NumberFor<Block>, Block::Hash>> {
+		self.backend.events(EventIndexKey::Topic(topic.as_ref()), from, to, limit)
+	}
+
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
 		self.backend.subscribe_all_heads(metadata, subscriber)
 	}
@@ -289,6 +331,7 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 fn subscribe_headers<Block, Client, F, G, S, ERR>(
 	client: &Arc<Client>,
 	subscriptions: &SubscriptionManager,
+	metadata: &crate::Metadata,
 	subscriber: Subscriber<Block::Header>,
 	best_block_hash: G,
 	stream: F,
@@ -314,17 +357,40 @@ fn subscribe_headers<Block, Client, F, G, S, ERR>(
 			.map(|res| Ok(res))
 			.map_err(|e| warn!("Block notification stream error: {:?}", e));
 
-		sink
+		let task = sink
 			.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 			.send_all(
 				stream::iter_result(vec![Ok(header)])
 					.chain(stream)
 			)
 			// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
-			.map(|_| ())
+			.map(|_| ());
+		metadata.hold_subscription_slot(task)
 	});
 }
 
+/// Convert the block number of an event index RPC.
+fn block_number<Block: BlockT>(number: NumberOrHex) -> Result<NumberFor<Block>> {
+	use std::convert::TryInto;
+
+	let number: u32 = number.try_into().map_err(|_| {
+		Error::from(format!("`{:?}` > u32::max_value(), the max block number is u32.", number))
+	})?;
+	Ok(number.into())
+}
+
+/// Check the limit of an event index RPC, applying the default.
+fn events_limit(limit: Option<u32>) -> Result<usize> {
+	match limit.unwrap_or(EVENTS_DEFAULT_LIMIT) {
+		limit if limit > EVENTS_MAX_LIMIT => Err(Error::from(format!(
+			"Limit {} exceeds the maximum of {} events.",
+			limit,
+			EVENTS_MAX_LIMIT,
+		))),
+		limit => Ok(limit as usize),
+	}
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
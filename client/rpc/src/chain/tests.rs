@@ -24,6 +24,7 @@ use substrate_test_runtime_client::{
 	runtime::{H256, Block, Header},
 };
 use sp_rpc::list::ListOrValue;
+use sc_client_api::{EventIndex, IndexedEvent};
 use sc_block_builder::BlockBuilderProvider;
 use futures::{executor, compat::{Future01CompatExt, Stream01CompatExt}};
 use crate::testing::TaskExecutor;
@@ -272,3 +273,51 @@ fn should_notify_about_finalized_block() {
 	// no more notifications on this channel
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
+
+#[test]
+fn should_return_indexed_events() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), SubscriptionManager::new(Arc::new(TaskExecutor)));
+	let account = Bytes(vec![1; 32]);
+	let topic = H256::repeat_byte(2);
+
+	// Nothing is indexed yet.
+	assert_matches!(api.account_history(account.clone(), None, None, None), Err(_));
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_hash = block.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+	// The state of the genesis block was pruned, say.
+	let index = client.event_index().unwrap();
+	index.index_block(block_hash, 1, &[
+		IndexedEvent { extrinsic_index: Some(0), accounts: vec![account.to_vec()], topics: vec![] },
+		IndexedEvent {
+			extrinsic_index: None,
+			accounts: vec![],
+			topics: vec![topic.as_ref().to_vec()],
+		},
+	]).unwrap();
+
+	assert_eq!(
+		api.account_history(account.clone(), None, None, None).unwrap(),
+		IndexedEvents {
+			first_indexed: 1,
+			last_indexed: 1,
+			events: vec![EventLocation {
+				block_hash,
+				block_number: 1,
+				extrinsic_index: Some(0),
+				event_index: 0,
+			}],
+		},
+	);
+	assert_eq!(
+		api.events_by_topic(topic, Some(1u64.into()), None, Some(1)).unwrap().events,
+		vec![EventLocation { block_hash, block_number: 1, extrinsic_index: None, event_index: 1 }],
+	);
+	assert_eq!(
+		api.account_history(account.clone(), None, Some(0u64.into()), None).unwrap().events,
+		vec![],
+	);
+	assert_matches!(api.account_history(account, None, None, Some(1001)), Err(_));
+}
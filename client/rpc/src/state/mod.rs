@@ -28,7 +28,7 @@ use std::sync::Arc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use rpc::{Result as RpcResult, futures::{Future, future::result}};
 
-use sc_rpc_api::{DenyUnsafe, state::ReadProof};
+use sc_rpc_api::{DenyUnsafe, state::{ReadProof, StoragePairsPage}};
 use sc_client_api::light::{RemoteBlockchain, Fetcher};
 use sp_core::{Bytes, storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet}};
 use sp_version::RuntimeVersion;
@@ -165,13 +165,35 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		_meta: Option<crate::Metadata>,
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
+
+	/// Stream the storage pairs with prefix, in pages of up to `count` pairs.
+	fn subscribe_storage_pairs(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StoragePairsPage<Block::Hash>>,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	);
+
+	/// Stop streaming storage pairs
+	fn unsubscribe_storage_pairs(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
 }
 
 /// Create new state API that works on full node.
+///
+/// The responses of the calls reading many storage entries at once are limited to
+/// `rpc_max_response_size` bytes of keys and values, if set.
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
 	deny_unsafe: DenyUnsafe,
+	rpc_max_response_size: Option<usize>,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
@@ -182,10 +204,14 @@ pub fn new_full<BE, Block: BlockT, Client>(
 			+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
 		Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
-	let child_backend = Box::new(
-		self::state_full::FullState::new(client.clone(), subscriptions.clone())
+	let child_backend = Box::new(self::state_full::FullState::new(
+		client.clone(),
+		subscriptions.clone(),
+		rpc_max_response_size,
+	));
+	let backend = Box::new(
+		self::state_full::FullState::new(client, subscriptions, rpc_max_response_size)
 	);
-	let backend = Box::new(self::state_full::FullState::new(client, subscriptions));
 	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
@@ -347,6 +373,38 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 	) -> RpcResult<bool> {
 		self.backend.unsubscribe_runtime_version(meta, id)
 	}
+
+	fn subscribe_storage_pairs(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StoragePairsPage<Block::Hash>>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		block: Option<Block::Hash>,
+	) {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			let _ = subscriber.reject(err.into());
+			return
+		}
+		// an empty page would look like an empty result
+		if count == 0 || count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			let _ = subscriber.reject(Error::InvalidCount {
+				value: count,
+				max: STORAGE_KEYS_PAGED_MAX_COUNT,
+			}.into());
+			return
+		}
+		self.backend.subscribe_storage_pairs(meta, subscriber, block, prefix, count, start_key);
+	}
+
+	fn unsubscribe_storage_pairs(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage_pairs(meta, id)
+	}
 }
 
 /// Child state backend API.
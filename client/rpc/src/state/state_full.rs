@@ -26,7 +26,7 @@ use log::warn;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use rpc::{Result as RpcResult, futures::{stream, Future, Sink, Stream, future::result}};
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{ReadProof, StoragePairsPage};
 use sc_client_api::backend::Backend;
 use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata, HeaderBackend};
 use sc_client_api::BlockchainEvents;
@@ -63,6 +63,7 @@ struct QueryStorageRange<Block: BlockT> {
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
+	max_response_size: Option<usize>,
 	_phantom: PhantomData<(BE, Block)>
 }
 
@@ -74,8 +75,23 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Block: BlockT + 'static,
 {
 	/// Create new state API backend for full nodes.
-	pub fn new(client: Arc<Client>, subscriptions: SubscriptionManager) -> Self {
-		Self { client, subscriptions, _phantom: PhantomData }
+	///
+	/// Calls reading many storage entries fail, or return pages, once their response reaches
+	/// `max_response_size` bytes.
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: SubscriptionManager,
+		max_response_size: Option<usize>,
+	) -> Self {
+		Self { client, subscriptions, max_response_size, _phantom: PhantomData }
+	}
+
+	/// Fails if `size` bytes exceed the maximum response size.
+	fn check_response_size(&self, size: usize) -> Result<()> {
+		match self.max_response_size {
+			Some(max) if size > max => Err(Error::ResponseTooLarge { max }),
+			_ => Ok(()),
+		}
 	}
 
 	/// Returns given block hash or best block hash if None is passed.
@@ -265,10 +281,27 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		block: Option<Block::Hash>,
 		prefix: StorageKey,
 	) -> FutureResult<Vec<(StorageKey, StorageData)>> {
-		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| self.client.storage_pairs(&BlockId::Hash(block), &prefix))
-				.map_err(client_err)))
+		let call_fn = move || {
+			let id = BlockId::Hash(self.block_or_best(block).map_err(client_err)?);
+			if self.max_response_size.is_none() {
+				return self.client.storage_pairs(&id, &prefix).map_err(client_err)
+			}
+
+			// read the pairs one at a time to stop as soon as the response is too large
+			let keys = self.client.storage_keys_iter(&id, Some(&prefix), None)
+				.map_err(client_err)?;
+			let mut pairs = Vec::new();
+			let mut size = 0;
+			for key in keys {
+				if let Some(value) = self.client.storage(&id, &key).map_err(client_err)? {
+					size += json_size(&key.0) + json_size(&value.0);
+					self.check_response_size(size)?;
+					pairs.push((key, value));
+				}
+			}
+			Ok(pairs)
+		};
+		Box::new(result(call_fn()))
 	}
 
 	fn storage_keys_paged(
@@ -368,7 +401,9 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 			let mut changes = Vec::new();
 			let mut last_values = HashMap::new();
 			self.query_storage_unfiltered(&range, &keys, &mut last_values, &mut changes)?;
+			self.check_response_size(changes_size(&changes))?;
 			self.query_storage_filtered(&range, &keys, &last_values, &mut changes)?;
+			self.check_response_size(changes_size(&changes))?;
 			Ok(changes)
 		};
 		Box::new(result(call_fn()))
@@ -405,7 +440,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 
 	fn subscribe_runtime_version(
 		&self,
-		_meta: crate::Metadata,
+		meta: crate::Metadata,
 		subscriber: Subscriber<RuntimeVersion>,
 	) {
 		let stream = match self.client.storage_changes_notification_stream(
@@ -443,14 +478,15 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 				})
 				.compat();
 
-			sink
+			let task = sink
 				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(
 					stream::iter_result(vec![Ok(version)])
 					.chain(stream)
 				)
 				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
-				.map(|_| ())
+				.map(|_| ());
+			meta.hold_subscription_slot(task)
 		});
 	}
 
@@ -464,7 +500,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 
 	fn subscribe_storage(
 		&self,
-		_meta: crate::Metadata,
+		meta: crate::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>,
 	) {
@@ -506,11 +542,12 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 				})))
 				.compat();
 
-			sink
+			let task = sink
 				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(initial.chain(stream))
 				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
-				.map(|_| ())
+				.map(|_| ());
+			meta.hold_subscription_slot(task)
 		});
 	}
 
@@ -521,6 +558,63 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	) -> RpcResult<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_storage_pairs(
+		&self,
+		meta: crate::Metadata,
+		subscriber: Subscriber<StoragePairsPage<Block::Hash>>,
+		block: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) {
+		let block = match self.block_or_best(block) {
+			Ok(block) => block,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+
+		// pages are only read when the previous one was sent, so that a slow subscriber
+		// doesn't make us buffer the whole state
+		let client = self.client.clone();
+		let max_response_size = self.max_response_size;
+		let pages = stream::unfold(Some(start_key), move |start_key| {
+			// `None` once the last page was read
+			let start_key = start_key?;
+			let page = storage_pairs_page::<BE, Block, _>(
+				&*client,
+				block,
+				prefix.as_ref(),
+				start_key.as_ref(),
+				count as usize,
+				max_response_size,
+			);
+			let next = match page {
+				Ok(ref page) => page.next_key.clone().map(Some),
+				Err(_) => None,
+			};
+			Some(Ok::<_, ()>((page.map_err(|e| rpc::Error::from(client_err(e))), next)))
+		});
+
+		self.subscriptions.add(subscriber, |sink| {
+			let task = sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(pages)
+				// the subscription ends with the last page
+				.map(|_| ());
+			meta.hold_subscription_slot(task)
+		});
+	}
+
+	fn unsubscribe_storage_pairs(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client> where
@@ -645,3 +739,56 @@ fn invalid_block<B: BlockT>(
 		details,
 	}
 }
+
+/// Reads up to `count` storage pairs with `prefix` following `start_key`.
+///
+/// The page ends early once it reaches `max_size` bytes, but always holds at least one pair.
+fn storage_pairs_page<BE, Block, Client>(
+	client: &Client,
+	block: Block::Hash,
+	prefix: Option<&StorageKey>,
+	start_key: Option<&StorageKey>,
+	count: usize,
+	max_size: Option<usize>,
+) -> ClientResult<StoragePairsPage<Block::Hash>> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE>,
+{
+	let id = BlockId::Hash(block);
+	let mut pairs = Vec::new();
+	let mut size = 0;
+	let mut read = 0;
+	let mut last_key = None;
+	let mut next_key = None;
+	for key in client.storage_keys_iter(&id, prefix, start_key)? {
+		// only a key following a full page tells that there is a next page
+		if read == count || max_size.map_or(false, |max| size >= max) {
+			next_key = last_key;
+			break
+		}
+		read += 1;
+		if let Some(value) = client.storage(&id, &key)? {
+			size += json_size(&key.0) + json_size(&value.0);
+			pairs.push((key.clone(), value));
+		}
+		last_key = Some(key);
+	}
+	Ok(StoragePairsPage { at: block, pairs, next_key })
+}
+
+/// Approximate size of `data` in a JSON response, where it is hex encoded.
+fn json_size(data: &[u8]) -> usize {
+	2 * data.len() + 4
+}
+
+/// Approximate size of `changes` in a JSON response.
+fn changes_size<Hash: AsRef<[u8]>>(changes: &[StorageChangeSet<Hash>]) -> usize {
+	changes.iter()
+		.map(|change_set| {
+			json_size(change_set.block.as_ref()) + change_set.changes.iter()
+				.map(|(key, data)| json_size(&key.0) + data.as_ref().map_or(4, |d| json_size(&d.0)))
+				.sum::<usize>()
+		})
+		.sum()
+}
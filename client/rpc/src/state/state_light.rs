@@ -40,7 +40,7 @@ use rpc::{
 	futures::stream::Stream,
 };
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{ReadProof, StoragePairsPage};
 use sp_blockchain::{Error as ClientError, HeaderBackend};
 use sc_client_api::{
 	BlockchainEvents,
@@ -299,7 +299,7 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 
 	fn subscribe_storage(
 		&self,
-		_meta: crate::Metadata,
+		meta: crate::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>
 	) {
@@ -373,11 +373,12 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 				}
 			);
 
-			sink
+			let task = sink
 				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(changes_stream.map(|changes| Ok(changes)))
 				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
-				.map(|_| ())
+				.map(|_| ());
+			meta.hold_subscription_slot(task)
 		});
 
 		// remember keys associated with this subscription
@@ -422,7 +423,7 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 
 	fn subscribe_runtime_version(
 		&self,
-		_meta: crate::Metadata,
+		meta: crate::Metadata,
 		subscriber: Subscriber<RuntimeVersion>,
 	) {
 		self.subscriptions.add(subscriber, move |sink| {
@@ -459,11 +460,12 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 				}
 			);
 
-			sink
+			let task = sink
 				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(versions_stream.map(|version| Ok(version)))
 				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
-				.map(|_| ())
+				.map(|_| ());
+			meta.hold_subscription_slot(task)
 		});
 	}
 
@@ -474,6 +476,26 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 	) -> RpcResult<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_storage_pairs(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StoragePairsPage<Block::Hash>>,
+		_block: Option<Block::Hash>,
+		_prefix: Option<StorageKey>,
+		_count: u32,
+		_start_key: Option<StorageKey>,
+	) {
+		let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+	}
+
+	fn unsubscribe_storage_pairs(
+		&self,
+		_meta: Option<crate::Metadata>,
+		_id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(false)
+	}
 }
 
 impl<Block, F, Client> ChildStateBackend<Block, Client> for LightState<Block, F, Client>
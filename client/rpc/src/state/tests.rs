@@ -63,6 +63,7 @@ fn should_return_storage() {
 		Arc::new(client),
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 	let key = StorageKey(KEY.to_vec());
 
@@ -105,6 +106,7 @@ fn should_return_child_storage() {
 		client,
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
@@ -144,6 +146,7 @@ fn should_call_contract() {
 		client,
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 
 	assert_matches!(
@@ -162,6 +165,7 @@ fn should_notify_about_storage_changes() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		api.subscribe_storage(Default::default(), subscriber, None.into());
@@ -200,6 +204,7 @@ fn should_send_initial_storage_changes_and_notifications() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
@@ -235,6 +240,122 @@ fn should_send_initial_storage_changes_and_notifications() {
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
+#[test]
+fn should_stream_storage_pairs() {
+	let (subscriber, id, mut transport) = Subscriber::new_test("test");
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let keys = api.storage_keys(StorageKey(vec![]), None).wait().unwrap();
+	assert!(keys.len() > 2);
+
+	// the subscription keeps its slot in the subscription quota until the last page is sent
+	let (release, released) = futures::channel::oneshot::channel();
+	let slot = sc_rpc_api::SubscriptionSlot::new(move || {
+		let _ = release.send(());
+	});
+	let meta = crate::Metadata::default().with_subscription_slot(slot);
+	api.subscribe_storage_pairs(meta, subscriber, None, 2, None, None);
+
+	// assert id assigned
+	assert!(matches!(
+		executor::block_on(id.compat()),
+		Ok(Ok(SubscriptionId::String(_)))
+	));
+
+	// assert all pairs sent in pages, each page continuing after the previous one
+	let mut streamed = Vec::new();
+	loop {
+		let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		let page = &notification["params"]["result"];
+		let pairs = page["pairs"].as_array().unwrap();
+		assert!(pairs.len() <= 2);
+		streamed.extend(pairs.iter().map(|pair| pair[0].clone()));
+		transport = next;
+		if page["nextKey"].is_null() {
+			break
+		}
+		assert_eq!(page["nextKey"], streamed[streamed.len() - 1]);
+	}
+	let keys = keys.into_iter().map(|key| serde_json::to_value(key).unwrap()).collect::<Vec<_>>();
+	assert_eq!(streamed, keys);
+	// the subscription ends after the last page, and releases its slot
+	assert_eq!(executor::block_on(transport.into_future().compat()).unwrap().0, None);
+	assert_eq!(executor::block_on(released), Ok(()));
+}
+
+#[test]
+fn should_reject_storage_pairs_stream_with_invalid_count() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	for &count in &[0, STORAGE_KEYS_PAGED_MAX_COUNT + 1] {
+		let (subscriber, id, _transport) = Subscriber::new_test("test");
+		api.subscribe_storage_pairs(Default::default(), subscriber, None, count, None, None);
+		assert!(matches!(executor::block_on(id.compat()), Ok(Err(_))));
+	}
+}
+
+#[test]
+fn should_deny_unsafe_storage_pairs_stream() {
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		None,
+	);
+
+	api.subscribe_storage_pairs(Default::default(), subscriber, None, 2, None, None);
+
+	assert!(matches!(executor::block_on(id.compat()), Ok(Err(_))));
+}
+
+#[test]
+fn should_limit_storage_pairs_response_size() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Some(1),
+	);
+	assert_matches!(
+		api.storage_pairs(StorageKey(vec![]), None).wait(),
+		Err(Error::ResponseTooLarge { max: 1 })
+	);
+
+	// streamed pages end early, with a single pair each
+	let (subscriber, _id, mut transport) = Subscriber::new_test("test");
+	api.subscribe_storage_pairs(Default::default(), subscriber, None, 100, None, None);
+	let mut pages = 0;
+	loop {
+		let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		let page = &notification["params"]["result"];
+		assert_eq!(page["pairs"].as_array().unwrap().len(), 1);
+		pages += 1;
+		transport = next;
+		if page["nextKey"].is_null() {
+			break
+		}
+	}
+	let keys = api.storage_keys(StorageKey(vec![]), None).wait().unwrap();
+	assert_eq!(pages, keys.len());
+}
+
 #[test]
 fn should_query_storage() {
 	fn run_tests(mut client: Arc<TestClient>, has_changes_trie_config: bool) {
@@ -242,6 +363,7 @@ fn should_query_storage() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		let mut add_block = |nonce| {
@@ -463,6 +585,7 @@ fn should_return_runtime_version() {
 		client.clone(),
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
@@ -490,6 +613,7 @@ fn should_notify_on_runtime_version_initially() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
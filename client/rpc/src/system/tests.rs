@@ -29,6 +29,9 @@ use std::{
 	sync::{Arc, Mutex}, thread, time::Duration
 };
 
+/// Nodes and subnets the test network reports as banned.
+const BANNED: &[&str] = &["198.51.100.0/24", "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"];
+
 struct Status {
 	pub peers: usize,
 	pub is_syncing: bool,
@@ -90,6 +93,7 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						connected_peers: Default::default(),
 						not_connected_peers: Default::default(),
 						peerset: serde_json::Value::Null,
+						protocols_traffic: Default::default(),
 					}).unwrap());
 				},
 				Request::NetworkAddReservedPeer(peer, sender) => {
@@ -104,6 +108,33 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
 					};
 				}
+				Request::PeerStore(sender) => {
+					let _ = sender.send(vec![KnownPeer {
+						peer_id: status.peer_id.to_base58(),
+						reputation: -10,
+						addresses: vec!["/ip4/198.51.100.19/tcp/30333".to_string()],
+					}]);
+				}
+				Request::ClearPeerStore(sender) => {
+					let _ = sender.send(());
+				}
+				Request::NetworkBanPeer(target, sender) => {
+					let _ = match target.parse::<sc_network::config::BanTarget>() {
+						Ok(_) => sender.send(Ok(())),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
+					};
+				}
+				Request::NetworkUnbanPeer(target, sender) => {
+					let _ = match target.parse::<sc_network::config::BanTarget>() {
+						Ok(target) if BANNED.contains(&&target.to_string()[..]) =>
+							sender.send(Ok(())),
+						Ok(target) => sender.send(Err(error::Error::NotBanned(target.to_string()))),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
+					};
+				}
+				Request::NetworkListBanned(sender) => {
+					let _ = sender.send(BANNED.iter().map(|target| target.to_string()).collect());
+				}
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
@@ -289,10 +320,30 @@ fn system_network_state() {
 			connected_peers: Default::default(),
 			not_connected_peers: Default::default(),
 			peerset: serde_json::Value::Null,
+			protocols_traffic: Default::default(),
 		}
 	);
 }
 
+#[test]
+fn system_peer_store() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let peer_id = PeerId::random();
+	let req = api(Status { peer_id: peer_id.clone(), ..Default::default() }).system_peer_store();
+	let res = runtime.block_on(req).unwrap();
+
+	assert_eq!(
+		res,
+		vec![KnownPeer {
+			peer_id: peer_id.to_base58(),
+			reputation: -10,
+			addresses: vec!["/ip4/198.51.100.19/tcp/30333".to_string()],
+		}]
+	);
+	assert_eq!(runtime.block_on(api(None).system_clear_peer_store()), Ok(()));
+}
+
 #[test]
 fn system_node_roles() {
 	assert_eq!(
@@ -337,6 +388,43 @@ fn system_network_remove_reserved() {
 	assert!(runtime.block_on(bad_fut).is_err());
 }
 
+#[test]
+fn system_network_ban_and_unban() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let good_targets = [
+		"QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV",
+		"198.51.100.19",
+		"2001:db8::/32",
+	];
+	for target in &good_targets {
+		assert_eq!(runtime.block_on(api(None).system_ban_peer(target.to_string())), Ok(()));
+	}
+	for target in BANNED {
+		assert_eq!(runtime.block_on(api(None).system_unban_peer(target.to_string())), Ok(()));
+	}
+
+	let not_banned = "198.51.100.20";
+	assert!(runtime.block_on(api(None).system_unban_peer(not_banned.into())).is_err());
+
+	let bad_target = "/ip4/198.51.100.19/tcp/30333";
+	assert!(runtime.block_on(api(None).system_ban_peer(bad_target.into())).is_err());
+	assert!(runtime.block_on(api(None).system_unban_peer(bad_target.into())).is_err());
+}
+
+#[test]
+fn system_network_list_banned() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	assert_eq!(
+		runtime.block_on(api(None).system_list_banned()),
+		Ok(vec![
+			"198.51.100.0/24".to_string(),
+			"QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+		]),
+	);
+}
+
 #[test]
 fn test_add_reset_log_filter() {
 	const EXPECTED_BEFORE_ADD: &'static str = "EXPECTED_BEFORE_ADD";
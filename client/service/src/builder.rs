@@ -38,11 +38,14 @@ use futures::{
 	future::ready,
 	channel::oneshot,
 };
-use sc_keystore::LocalKeystore;
+use sc_keystore::{LocalKeystore, RemoteKeystore};
 use log::{info, warn};
-use sc_network::config::{Role, OnDemand};
+use sc_network::config::{
+	Role, OnDemand, RequestResponseConfig, WarpSyncFragment, WarpSyncProvider,
+};
 use sc_network::NetworkService;
 use sc_network::block_request_handler::{self, BlockRequestHandler};
+use sc_network::state_request_handler::{self, StateRequestHandler};
 use sc_network::light_client_requests::{self, handler::LightClientRequestHandler};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
@@ -68,12 +71,13 @@ use sp_core::traits::{
 use sp_keystore::{CryptoStore, SyncCryptoStore, SyncCryptoStorePtr};
 use sp_runtime::BuildStorage;
 use sc_client_api::{
-	BlockBackend, BlockchainEvents,
+	BlockBackend, BlockchainEvents, EventIndexProvider,
 	backend::StorageProvider,
 	proof_provider::ProofProvider,
 	execution_extensions::ExecutionExtensions
 };
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
+use sc_block_builder::DryRunProvider;
 
 /// A utility trait for building an RPC extension given a `DenyUnsafe` instance.
 /// This is useful since at service definition time we don't know whether the
@@ -228,21 +232,26 @@ impl<T> AsCryptoStoreRef for Arc<T> where T: CryptoStore + SyncCryptoStore + 'st
 /// Construct and hold different layers of Keystore wrappers
 pub struct KeystoreContainer {
 	remote: Option<Box<dyn AsCryptoStoreRef>>,
-	local: Arc<LocalKeystore>,
+	local: Option<Arc<LocalKeystore>>,
 }
 
 impl KeystoreContainer {
 	/// Construct KeystoreContainer
 	pub fn new(config: &KeystoreConfig) -> Result<Self, Error> {
-		let keystore = Arc::new(match config {
+		let keystore = match config {
 			KeystoreConfig::Path { path, password } => LocalKeystore::open(
 				path.clone(),
 				password.clone(),
 			)?,
 			KeystoreConfig::InMemory => LocalKeystore::in_memory(),
-		});
+			KeystoreConfig::Remote { signer } => {
+				let remote: Box<dyn AsCryptoStoreRef> =
+					Box::new(Arc::new(RemoteKeystore::new(signer.clone())?));
+				return Ok(Self{remote: Some(remote), local: None})
+			},
+		};
 
-		Ok(Self{remote: Default::default(), local: keystore})
+		Ok(Self{remote: Default::default(), local: Some(Arc::new(keystore))})
 	}
 
 	/// Set the remote keystore.
@@ -261,7 +270,7 @@ impl KeystoreContainer {
 		if let Some(c) = self.remote.as_ref() {
 			c.keystore_ref()
 		} else {
-			self.local.clone()
+			self.local.clone().expect("There is a local keystore if there is no remote one; qed")
 		}
 	}
 
@@ -270,7 +279,8 @@ impl KeystoreContainer {
 		if let Some(c) = self.remote.as_ref() {
 			c.sync_keystore_ref()
 		} else {
-			self.local.clone() as SyncCryptoStorePtr
+			self.local.clone().expect("There is a local keystore if there is no remote one; qed")
+				as SyncCryptoStorePtr
 		}
 	}
 
@@ -283,7 +293,7 @@ impl KeystoreContainer {
 	/// Using the [`LocalKeystore`] will result in loosing the ability to use any other keystore implementation, like
 	/// a remote keystore for example. Only use this if you a certain that you require it!
 	pub fn local_keystore(&self) -> Option<Arc<LocalKeystore>> {
-		Some(self.local.clone())
+		self.local.clone()
 	}
 }
 
@@ -543,7 +553,7 @@ pub fn spawn_tasks<TBl, TBackend, TExPool, TRpc, TCl>(
 		BlockBackend<TBl> + BlockIdTo<TBl, Error=sp_blockchain::Error> + ProofProvider<TBl> +
 		HeaderBackend<TBl> + BlockchainEvents<TBl> + ExecutorProvider<TBl> + UsageProvider<TBl> +
 		StorageProvider<TBl, TBackend> + CallApiAt<TBl, Error=sp_blockchain::Error> +
-		Send + 'static,
+		EventIndexProvider<TBl> + DryRunProvider<TBl> + Send + 'static,
 		<TCl as ProvideRuntimeApi<TBl>>::Api:
 			sp_api::Metadata<TBl> +
 			sc_offchain::OffchainWorkerApi<TBl> +
@@ -601,6 +611,13 @@ pub fn spawn_tasks<TBl, TBackend, TExPool, TRpc, TCl>(
 		transaction_notifications(transaction_pool.clone(), network.clone()),
 	);
 
+	if let (Some(event_index), Some(_)) = (&config.event_index, backend.event_index()) {
+		spawn_handle.spawn_blocking(
+			"event-index",
+			crate::event_index::run(client.clone(), backend.clone(), event_index.clone()),
+		);
+	}
+
 	// Prometheus metrics.
 	let metrics_service = if let Some(PrometheusConfig { port, registry }) =
 		config.prometheus_config.clone()
@@ -730,7 +747,8 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 		TCl: ProvideRuntimeApi<TBl> + BlockchainEvents<TBl> + HeaderBackend<TBl> +
 		HeaderMetadata<TBl, Error=sp_blockchain::Error> + ExecutorProvider<TBl> +
 		CallApiAt<TBl, Error=sp_blockchain::Error> + ProofProvider<TBl> +
-		StorageProvider<TBl, TBackend> + BlockBackend<TBl> + Send + Sync + 'static,
+		StorageProvider<TBl, TBackend> + BlockBackend<TBl> + EventIndexProvider<TBl> +
+		DryRunProvider<TBl> + Send + Sync + 'static,
 		TExPool: MaintainedTransactionPool<Block=TBl, Hash = <TBl as BlockT>::Hash> + 'static,
 		TBackend: sc_client_api::backend::Backend<TBl> + 'static,
 		TRpc: sc_rpc::RpcExtension<sc_rpc::Metadata>,
@@ -776,6 +794,7 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 			client.clone(),
 			subscriptions.clone(),
 			deny_unsafe,
+			config.rpc_limits.max_response_size,
 		);
 		(chain, state, child_state)
 	};
@@ -826,6 +845,11 @@ pub struct BuildNetworkParams<'a, TBl: BlockT, TExPool, TImpQu, TCl> {
 	pub block_announce_validator_builder: Option<Box<
 		dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send
 	>>,
+	/// Verifier and protocol configuration for warp sync, if the finality gadget supports it.
+	pub warp_sync: Option<(Arc<dyn WarpSyncProvider<TBl>>, RequestResponseConfig)>,
+	/// Trusted finalized block to sync from instead of genesis, see
+	/// `sc_network::config::Params::sync_checkpoint`.
+	pub sync_checkpoint: Option<WarpSyncFragment<TBl>>,
 }
 
 /// Build the network service, the network status sinks and an RPC sender.
@@ -850,7 +874,7 @@ pub fn build_network<TBl, TExPool, TImpQu, TCl>(
 {
 	let BuildNetworkParams {
 		config, client, transaction_pool, spawn_handle, import_queue, on_demand,
-		block_announce_validator_builder,
+		block_announce_validator_builder, warp_sync, sync_checkpoint,
 	} = params;
 
 	let transaction_pool_adapter = Arc::new(TransactionPoolAdapter {
@@ -882,6 +906,21 @@ pub fn build_network<TBl, TExPool, TImpQu, TCl>(
 		}
 	};
 
+	let state_request_protocol_config = {
+		if matches!(config.role, Role::Light) {
+			// Allow outgoing requests but deny incoming requests.
+			state_request_handler::generate_protocol_config(&protocol_id)
+		} else {
+			// Allow both outgoing and incoming requests.
+			let (handler, protocol_config) = StateRequestHandler::new(
+				&protocol_id,
+				client.clone(),
+			);
+			spawn_handle.spawn("state_request_handler", handler.run());
+			protocol_config
+		}
+	};
+
 	let light_client_request_protocol_config = {
 		if matches!(config.role, Role::Light) {
 			// Allow outgoing requests but deny incoming requests.
@@ -914,7 +953,10 @@ pub fn build_network<TBl, TExPool, TImpQu, TCl>(
 		block_announce_validator,
 		metrics_registry: config.prometheus_config.as_ref().map(|config| config.registry.clone()),
 		block_request_protocol_config,
+		state_request_protocol_config,
 		light_client_request_protocol_config,
+		warp_sync,
+		sync_checkpoint,
 	};
 
 	let has_bootnodes = !network_params.network_config.boot_nodes.is_empty();
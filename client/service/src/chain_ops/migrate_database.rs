@@ -0,0 +1,50 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use crate::config::Configuration;
+use log::info;
+use sc_client_db::DatabaseSettingsSrc;
+use sp_runtime::traits::Block as BlockT;
+
+/// Copies the configured database into the empty database `destination`,
+/// leaving the configured one untouched.
+pub fn migrate_database<B: BlockT>(
+	config: &Configuration,
+	destination: DatabaseSettingsSrc,
+) -> Result<(), Error> {
+	let db_config = |source| sc_client_db::DatabaseSettings {
+		state_cache_size: config.state_cache_size,
+		state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+		state_pruning: config.state_pruning.clone(),
+		source,
+		keep_blocks: config.keep_blocks.clone(),
+		transaction_storage: config.transaction_storage.clone(),
+	};
+
+	let summary = sc_client_db::migrate_database::<B>(
+		&db_config(config.database.clone()),
+		&db_config(destination),
+	)?;
+	info!(
+		"Migrated {} database entries, state of block #{} verified.",
+		summary.entries.iter().sum::<u64>(),
+		summary.verified_block,
+	);
+	Ok(())
+}
@@ -22,10 +22,16 @@ mod check_block;
 mod export_blocks;
 mod export_raw_state;
 mod import_blocks;
+mod migrate_database;
+mod prune_state;
 mod revert_chain;
+mod state_snapshot;
 
 pub use check_block::*;
 pub use export_blocks::*;
 pub use export_raw_state::*;
 pub use import_blocks::*;
+pub use migrate_database::*;
+pub use prune_state::*;
 pub use revert_chain::*;
+pub use state_snapshot::*;
@@ -0,0 +1,43 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use crate::config::Configuration;
+use log::info;
+use sp_runtime::traits::Block as BlockT;
+
+/// Prunes the state of an archive database offline, keeping the state of the
+/// last `keep_blocks` finalized blocks.
+pub fn prune_state<B: BlockT>(config: &Configuration, keep_blocks: u32) -> Result<(), Error> {
+	let db_config = sc_client_db::DatabaseSettings {
+		state_cache_size: config.state_cache_size,
+		state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+		state_pruning: config.state_pruning.clone(),
+		source: config.database.clone(),
+		keep_blocks: config.keep_blocks.clone(),
+		transaction_storage: config.transaction_storage.clone(),
+	};
+
+	let summary = sc_client_db::prune_state::<B>(&db_config, keep_blocks)?;
+	info!(
+		"Kept the state of {} blocks. Removed {} trie nodes.",
+		summary.kept_blocks,
+		summary.removed_nodes,
+	);
+	Ok(())
+}
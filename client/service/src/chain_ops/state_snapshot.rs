@@ -0,0 +1,271 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use crate::config::Configuration;
+use crate::client::genesis::construct_genesis_block;
+use codec::{Decode, Encode, IoReader as CodecIoReader};
+use log::info;
+use sp_runtime::BuildStorage;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, HashFor, Zero};
+use sp_runtime::generic::BlockId;
+use sp_state_machine::InMemoryBackend;
+use sp_core::storage::{StorageKey, well_known_keys, ChildInfo};
+use sp_blockchain::HeaderBackend;
+use sc_client_api::{StorageProvider, UsageProvider};
+
+use std::{io::{Read, Write}, sync::Arc};
+
+/// Magic bytes at the start of a state snapshot.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"sss\x02";
+/// Number of key/value pairs in a chunk.
+const CHUNK_SIZE: usize = 1024;
+
+/// An item of a state snapshot.
+///
+/// A snapshot is a stream of SCALE encoded items, starting with `Headers`, followed by the
+/// default child trie chunks, the top trie chunks and the auxiliary data chunks, and terminated
+/// by `End`. The pairs of each trie are in ascending key order, so that the tries can be built
+/// while reading the snapshot.
+#[derive(Encode, Decode)]
+enum SnapshotItem<Header> {
+	/// The genesis header and the header of the block the state belongs to.
+	Headers { genesis: Header, block: Header },
+	/// Key/value pairs of the top trie, excluding child trie roots.
+	Top(Vec<(Vec<u8>, Vec<u8>)>),
+	/// Key/value pairs of a default child trie.
+	Child { storage_key: Vec<u8>, pairs: Vec<(Vec<u8>, Vec<u8>)> },
+	/// Auxiliary database entries of the block, such as the consensus data.
+	Aux(Vec<(Vec<u8>, Vec<u8>)>),
+	/// End of the snapshot.
+	End,
+}
+
+/// Export the state at the given `block` as a binary snapshot. If `block` is `None`, the
+/// best block will be used.
+///
+/// The auxiliary entries returned by `aux_data` for the header of the block, e.g. the consensus
+/// data needed to continue from the block, are included in the snapshot. The state is written
+/// in chunks, so that it never needs to be held in memory as a whole.
+pub fn export_state_snapshot<B, BA, C>(
+	client: Arc<C>,
+	block: Option<BlockId<B>>,
+	aux_data: impl FnOnce(&B::Header) -> sp_blockchain::Result<Vec<(Vec<u8>, Vec<u8>)>>,
+	mut output: impl Write,
+) -> Result<(), Error>
+where
+	C: UsageProvider<B> + StorageProvider<B, BA> + HeaderBackend<B>,
+	B: BlockT,
+	BA: sc_client_api::backend::Backend<B>,
+{
+	let block = block.unwrap_or_else(
+		|| BlockId::Hash(client.usage_info().chain.best_hash)
+	);
+	let header_of = |id: BlockId<B>| client.header(id)?
+		.ok_or_else(|| Error::Other(format!("Header of block {} not found", id)));
+	let genesis = header_of(BlockId::Number(Zero::zero()))?;
+	let header = header_of(block.clone())?;
+	let aux = aux_data(&header)?;
+	info!("Exporting state snapshot of block #{} ({})", header.number(), header.hash());
+
+	output.write_all(SNAPSHOT_MAGIC)?;
+	SnapshotItem::Headers { genesis, block: header }.encode_to(&mut output);
+
+	let child_prefix = StorageKey(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec());
+	let empty_key = StorageKey(Vec::new());
+	for key in client.storage_keys_iter(&block, Some(&child_prefix), None)? {
+		let storage_key = key.0[child_prefix.0.len()..].to_vec();
+		let child_info = ChildInfo::new_default(&storage_key);
+		let keys = client.child_storage_keys(&block, &child_info, &empty_key)?;
+		for keys in keys.chunks(CHUNK_SIZE) {
+			let mut pairs = Vec::with_capacity(keys.len());
+			for key in keys {
+				if let Some(value) = client.child_storage(&block, &child_info, key)? {
+					pairs.push((key.0.clone(), value.0));
+				}
+			}
+			SnapshotItem::<B::Header>::Child { storage_key: storage_key.clone(), pairs }
+				.encode_to(&mut output);
+		}
+	}
+
+	let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+	for key in client.storage_keys_iter(&block, None, None)? {
+		if well_known_keys::is_child_storage_key(&key.0) {
+			continue;
+		}
+		if let Some(value) = client.storage(&block, &key)? {
+			chunk.push((key.0, value.0));
+		}
+		if chunk.len() == CHUNK_SIZE {
+			SnapshotItem::<B::Header>::Top(std::mem::take(&mut chunk)).encode_to(&mut output);
+		}
+	}
+	if !chunk.is_empty() {
+		SnapshotItem::<B::Header>::Top(chunk).encode_to(&mut output);
+	}
+
+	for chunk in aux.chunks(CHUNK_SIZE) {
+		SnapshotItem::<B::Header>::Aux(chunk.to_vec()).encode_to(&mut output);
+	}
+
+	SnapshotItem::<B::Header>::End.encode_to(&mut output);
+	output.flush()?;
+	Ok(())
+}
+
+/// Reads the items of a snapshot.
+struct SnapshotReader<R, Header> {
+	input: CodecIoReader<R>,
+	/// The next item, once read.
+	next: Option<SnapshotItem<Header>>,
+	/// The first error reading the snapshot.
+	error: Option<codec::Error>,
+}
+
+impl<R: Read, Header: Decode> SnapshotReader<R, Header> {
+	/// The next item, `None` on error.
+	fn peek(&mut self) -> Option<&SnapshotItem<Header>> {
+		if self.next.is_none() && self.error.is_none() {
+			match SnapshotItem::decode(&mut self.input) {
+				Ok(item) => self.next = Some(item),
+				Err(e) => self.error = Some(e),
+			}
+		}
+		self.next.as_ref()
+	}
+
+	/// Take the next item.
+	fn next(&mut self) -> Result<SnapshotItem<Header>, Error> {
+		self.peek();
+		self.check()?;
+		Ok(self.next.take().expect("Either an item or an error was read above; qed"))
+	}
+
+	/// The pairs of the top trie, or of the given child trie, starting at the next item.
+	fn pairs(&mut self, child: Option<Vec<u8>>) -> SnapshotPairs<R, Header> {
+		SnapshotPairs { reader: self, child, chunk: Vec::new().into_iter() }
+	}
+
+	fn check(&mut self) -> Result<(), Error> {
+		match self.error.take() {
+			Some(e) => Err(Error::Other(format!("Invalid state snapshot: {}", e))),
+			None => Ok(()),
+		}
+	}
+}
+
+/// The pairs of a trie in a snapshot, read as they are iterated.
+struct SnapshotPairs<'a, R, Header> {
+	reader: &'a mut SnapshotReader<R, Header>,
+	child: Option<Vec<u8>>,
+	chunk: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<'a, R: Read, Header: Decode> Iterator for SnapshotPairs<'a, R, Header> {
+	type Item = (Vec<u8>, Vec<u8>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(pair) = self.chunk.next() {
+				return Some(pair);
+			}
+			let same_trie = match self.reader.peek()? {
+				SnapshotItem::Top(_) => self.child.is_none(),
+				SnapshotItem::Child { storage_key, .. } => self.child.as_ref() == Some(storage_key),
+				_ => false,
+			};
+			if !same_trie {
+				return None;
+			}
+			self.chunk = match self.reader.next.take() {
+				Some(SnapshotItem::Top(pairs)) | Some(SnapshotItem::Child { pairs, .. }) =>
+					pairs.into_iter(),
+				_ => unreachable!("A chunk of the trie was peeked above; qed"),
+			};
+		}
+	}
+}
+
+/// Initialize the empty database of the given configuration from a binary state snapshot.
+///
+/// The node starts from the snapshot block afterwards. The state root of the snapshot is
+/// verified against the block header, and the genesis header against the chain spec. The
+/// snapshot is streamed into the database, so a failed import leaves a partially written
+/// database behind, which has to be deleted.
+pub fn import_state_snapshot<B: BlockT>(
+	config: &Configuration,
+	input: impl Read,
+) -> Result<(), Error> {
+	let mut reader = SnapshotReader::<_, B::Header> {
+		input: CodecIoReader(input),
+		next: None,
+		error: None,
+	};
+
+	let mut magic = [0u8; 4];
+	reader.input.0.read_exact(&mut magic)?;
+	if &magic != SNAPSHOT_MAGIC {
+		return Err(Error::Other("Not a state snapshot".into()));
+	}
+
+	let (genesis, header) = match reader.next()? {
+		SnapshotItem::Headers { genesis, block } => (genesis, block),
+		_ => return Err(Error::Other("State snapshot does not start with headers".into())),
+	};
+	let genesis_state: InMemoryBackend<HashFor<B>> = config.chain_spec.as_storage_builder()
+		.build_storage()
+		.map_err(Error::Other)?
+		.into();
+	let expected_genesis = construct_genesis_block::<B>(*genesis_state.root());
+	if genesis.hash() != expected_genesis.header().hash() {
+		return Err(Error::Other("State snapshot belongs to a different chain".into()));
+	}
+
+	info!("Importing state of block #{} ({})", header.number(), header.hash());
+	let db_config = sc_client_db::DatabaseSettings {
+		state_cache_size: config.state_cache_size,
+		state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+		state_pruning: config.state_pruning.clone(),
+		source: config.database.clone(),
+		keep_blocks: config.keep_blocks.clone(),
+		transaction_storage: config.transaction_storage.clone(),
+	};
+	let mut import = sc_client_db::SnapshotImport::<B>::new(&db_config, genesis, header)?;
+
+	while let Some(SnapshotItem::Child { storage_key, .. }) = reader.peek() {
+		let storage_key = storage_key.clone();
+		let result = import.import_child(&storage_key, reader.pairs(Some(storage_key.clone())));
+		reader.check()?;
+		result?;
+	}
+	let result = import.import_top(reader.pairs(None));
+	reader.check()?;
+	result?;
+
+	let mut aux = Vec::new();
+	loop {
+		match reader.next()? {
+			SnapshotItem::Aux(pairs) => aux.extend(pairs),
+			SnapshotItem::End => break,
+			_ => return Err(Error::Other("Unexpected item in state snapshot".into())),
+		}
+	}
+	import.commit(aux)?;
+	Ok(())
+}
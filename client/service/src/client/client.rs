@@ -66,7 +66,7 @@ use sp_api::{
 	CallApiAt, ConstructRuntimeApi, Core as CoreApi, ApiExt, ApiRef, ProvideRuntimeApi,
 	CallApiAtParams,
 };
-use sc_block_builder::{BlockBuilderApi, BlockBuilderProvider};
+use sc_block_builder::{BlockBuilderApi, BlockBuilderProvider, DryRun, DryRunProvider};
 use sc_client_api::{
 	backend::{
 		self, BlockImportOperation, PrunableStateChangesTrieStorage,
@@ -82,7 +82,7 @@ use sc_client_api::{
 	execution_extensions::ExecutionExtensions,
 	notifications::{StorageNotifications, StorageEventStream},
 	KeyIterator, CallExecutor, ExecutorProvider, ProofProvider,
-	cht, UsageProvider
+	cht, UsageProvider, EventIndex, EventIndexProvider,
 };
 use sp_utils::mpsc::{TracingUnboundedSender, tracing_unbounded};
 use sp_blockchain::Error;
@@ -1211,6 +1211,53 @@ impl<B, E, Block, RA> ProofProvider<Block> for Client<B, E, Block, RA> where
 				.map_err(Into::into))
 	}
 
+	fn read_proof_collection(
+		&self,
+		id: &BlockId<Block>,
+		child_info: Option<&ChildInfo>,
+		start_key: &[u8],
+		size_limit: usize,
+	) -> sp_blockchain::Result<(Vec<(Vec<u8>, Vec<u8>)>, StorageProof, bool)> {
+		let state = self.state_at(id)?;
+		let read = |key: &[u8]| {
+			let value = match child_info {
+				Some(child_info) => state.child_storage(child_info, key),
+				None => state.storage(key),
+			};
+			value.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+		};
+		let next = |key: &[u8]| {
+			let next_key = match child_info {
+				Some(child_info) => state.next_child_storage_key(child_info, key),
+				None => state.next_storage_key(key),
+			};
+			next_key.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+		};
+
+		let mut entries = Vec::new();
+		let mut size = 0;
+		let mut key = next(start_key)?;
+		// Always return at least one pair, so that the caller makes progress.
+		let complete = loop {
+			let current = match key {
+				Some(current) if entries.is_empty() || size < size_limit => current,
+				Some(_) => break false,
+				None => break true,
+			};
+			let value = read(&current)?.unwrap_or_default();
+			size += current.len() + value.len();
+			key = next(&current)?;
+			entries.push((current, value));
+		};
+
+		let mut keys = entries.iter().map(|(key, _)| &key[..]);
+		let proof = match child_info {
+			Some(child_info) => self.read_child_proof(id, child_info, &mut keys)?,
+			None => self.read_proof(id, &mut keys)?,
+		};
+		Ok((entries, proof, complete))
+	}
+
 	fn execution_proof(
 		&self,
 		id: &BlockId<Block>,
@@ -1262,8 +1309,21 @@ impl<B, E, Block, RA> ProofProvider<Block> for Client<B, E, Block, RA> where
 			cht::size(),
 		)
 	}
+
+	fn trie_node(&self, hash: &Block::Hash) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		self.backend.trie_node(hash)
+	}
 }
 
+impl<B, E, Block, RA> EventIndexProvider<Block> for Client<B, E, Block, RA> where
+	B: backend::Backend<Block>,
+	E: CallExecutor<Block>,
+	Block: BlockT,
+{
+	fn event_index(&self) -> Option<&dyn EventIndex<Block>> {
+		self.backend.event_index()
+	}
+}
 
 impl<B, E, Block, RA> BlockBuilderProvider<B, Block, Self> for Client<B, E, Block, RA>
 	where
@@ -1306,6 +1366,30 @@ impl<B, E, Block, RA> BlockBuilderProvider<B, Block, Self> for Client<B, E, Bloc
 	}
 }
 
+impl<B, E, Block, RA> DryRunProvider<Block> for Client<B, E, Block, RA>
+	where
+		B: backend::Backend<Block> + Send + Sync + 'static,
+		E: CallExecutor<Block> + Send + Sync + 'static,
+		Block: BlockT,
+		Self: ChainHeaderBackend<Block> + ProvideRuntimeApi<Block>,
+		<Self as ProvideRuntimeApi<Block>>::Api: ApiExt<Block, StateBackend = backend::StateBackendFor<B, Block>>
+			+ BlockBuilderApi<Block, Error = Error>,
+{
+	fn dry_run_at(
+		&self,
+		parent: &BlockId<Block>,
+		xt: <Block as BlockT>::Extrinsic,
+	) -> sp_blockchain::Result<DryRun> {
+		sc_block_builder::BlockBuilder::dry_run(
+			self,
+			self.expect_block_hash_from_id(parent)?,
+			self.expect_block_number_from_id(parent)?,
+			xt,
+			&*self.backend,
+		)
+	}
+}
+
 impl<B, E, Block, RA> ExecutorProvider<Block> for Client<B, E, Block, RA> where
 	B: backend::Backend<Block>,
 	E: CallExecutor<Block>,
@@ -1927,6 +2011,13 @@ impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
 	fn have_extrinsic(&self, hash: &Block::Hash) -> sp_blockchain::Result<bool> {
 		self.backend.blockchain().have_extrinsic(hash)
 	}
+
+	fn block_body_by_content_hash(
+		&self,
+		hash: &Block::Hash,
+	) -> sp_blockchain::Result<Option<Vec<<Block as BlockT>::Extrinsic>>> {
+		self.backend.blockchain().body_by_content_hash(hash)
+	}
 }
 
 impl<B, E, Block, RA> backend::AuxStore for Client<B, E, Block, RA>
@@ -25,6 +25,7 @@ pub use sc_client_db::{
 pub use sc_network::Multiaddr;
 pub use sc_network::config::{ExtTransport, MultiaddrWithPeerId, NetworkConfiguration, Role, NodeKeyConfig};
 pub use sc_executor::WasmExecutionMethod;
+pub use sc_rpc_server::{RpcAccessPolicy, RpcLimits};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 
 use std::{io, future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
@@ -67,6 +68,8 @@ pub struct Configuration {
 	pub keep_blocks: KeepBlocks,
 	/// Transaction storage scheme.
 	pub transaction_storage: TransactionStorageMode,
+	/// Index the events of finalized blocks by account and topic, if set.
+	pub event_index: Option<EventIndexConfig>,
 	/// Chain configuration.
 	pub chain_spec: Box<dyn ChainSpec>,
 	/// Wasm execution method.
@@ -89,6 +92,11 @@ pub struct Configuration {
 	pub rpc_cors: Option<Vec<String>>,
 	/// RPC methods to expose (by default only a safe subset or all of them).
 	pub rpc_methods: RpcMethods,
+	/// Per-method access policy of the HTTP & WS servers. `None` if every exposed method may be
+	/// called by anyone.
+	pub rpc_access_policy: Option<RpcAccessPolicy>,
+	/// Rate limits and quotas of the HTTP & WS servers.
+	pub rpc_limits: RpcLimits,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
 	/// Telemetry service URL. `None` if disabled.
@@ -153,11 +161,16 @@ pub enum KeystoreConfig {
 	Path {
 		/// The path of the keystore.
 		path: PathBuf,
-		/// Node keystore's password.
+		/// Node keystore's password, also used to encrypt the key files.
 		password: Option<SecretString>
 	},
 	/// In-memory keystore. Recommended for in-browser nodes.
 	InMemory,
+	/// Keys kept by a remote signer.
+	Remote {
+		/// The address of the signer.
+		signer: sc_keystore::RemoteSigner,
+	},
 }
 
 impl KeystoreConfig {
@@ -165,7 +178,7 @@ impl KeystoreConfig {
 	pub fn path(&self) -> Option<&Path> {
 		match self {
 			Self::Path { path, .. } => Some(path),
-			Self::InMemory => None,
+			Self::InMemory | Self::Remote { .. } => None,
 		}
 	}
 }
@@ -178,6 +191,16 @@ pub struct OffchainWorkerConfig {
 	pub indexing_enabled: bool,
 }
 
+/// Configuration of the event index.
+#[derive(Debug, Clone, Default)]
+pub struct EventIndexConfig {
+	/// Encodings of the runtime types named by the arguments of events, by type name.
+	///
+	/// These extend and override the types of the usual FRAME runtime configuration, e.g.
+	/// `("Balance", "u64")` for a runtime with 64 bit balances.
+	pub types: Vec<(String, String)>,
+}
+
 /// Configuration of the Prometheus endpoint.
 #[derive(Debug, Clone)]
 pub struct PrometheusConfig {
@@ -224,8 +247,17 @@ impl Configuration {
 		};
 		sc_network::config::ProtocolId::from(protocol_id_full)
 	}
+
+	/// Returns the path of the slashing protection store, if it is kept on disk.
+	pub fn slashing_protection_path(&self) -> Option<PathBuf> {
+		self.base_path.as_ref()
+			.map(|base_path| base_path.config_dir(self.chain_spec.id()).join(SLASHING_PROTECTION_FILE))
+	}
 }
 
+/// Name of the slashing protection store file in the chain's config directory.
+pub const SLASHING_PROTECTION_FILE: &str = "slashing_protection.json";
+
 /// Available RPC methods.
 #[derive(Debug, Copy, Clone)]
 pub enum RpcMethods {
@@ -0,0 +1,451 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decoding of `System::Events` with the runtime metadata.
+//!
+//! Metadata only names the types of the arguments of events, so arguments are decoded with a
+//! table of the encodings of the types used by the FRAME pallets, in the usual runtime
+//! configuration, which the node configuration extends and overrides. The events of a block with
+//! an argument of an unknown type can't be decoded at all, since the events following it can't
+//! be found.
+
+use std::collections::HashMap;
+use codec::{Compact, Decode};
+use frame_metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed};
+use sc_client_api::IndexedEvent;
+
+/// Length of an encoded account id.
+const ACCOUNT_ID_LEN: usize = 32;
+
+/// Length of an encoded topic.
+const TOPIC_LEN: usize = 32;
+
+/// Maximum nesting of the types of arguments.
+const MAX_TYPE_DEPTH: usize = 16;
+
+/// Encodings of the types used by the FRAME pallets in the usual runtime configuration.
+const FRAME_TYPES: &[(&str, &str)] = &[
+	("AccountIndex", "u32"),
+	("AssetId", "u32"),
+	("AuthorityId", "[u8;32]"),
+	("AuthorityList", "Vec<(AuthorityId,AuthorityWeight)>"),
+	("AuthorityWeight", "u64"),
+	("Balance", "u128"),
+	("BalanceOf", "Balance"),
+	("BlockNumber", "u32"),
+	("BountyIndex", "u32"),
+	("Bytes", "Vec<u8>"),
+	("CallHash", "[u8;32]"),
+	("CallIndex", "(u8,u8)"),
+	("DispatchInfo", "(Weight,u8,u8)"),
+	("DispatchResult", "Result<(),DispatchError>"),
+	("EraIndex", "u32"),
+	("H256", "[u8;32]"),
+	("Hash", "[u8;32]"),
+	("HashedProof", "[u8;32]"),
+	("Index", "u32"),
+	("Kind", "[u8;16]"),
+	("LockIdentifier", "[u8;8]"),
+	("MemberCount", "u32"),
+	("Moment", "u64"),
+	("OpaqueTimeSlot", "Vec<u8>"),
+	("PeerId", "Vec<u8>"),
+	("PropIndex", "u32"),
+	("ProposalIndex", "u32"),
+	("ReferendumIndex", "u32"),
+	("RegistrarIndex", "u32"),
+	("SessionIndex", "u32"),
+	("TaskAddress", "(BlockNumber,u32)"),
+	("Timepoint", "(BlockNumber,u32)"),
+	("Weight", "u64"),
+];
+
+/// Encoding of the argument of an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TypeDef {
+	/// A fixed number of bytes.
+	Fixed(usize),
+	/// An account id, which the event is indexed under.
+	AccountId,
+	/// A compact encoded integer.
+	Compact,
+	/// A `DispatchError`, whose `Module` variant is followed by two bytes.
+	DispatchError,
+	/// A fixed number of items.
+	Array(Box<TypeDef>, usize),
+	/// A compact encoded number of items.
+	Vec(Box<TypeDef>),
+	/// A tuple or a struct.
+	Tuple(Vec<TypeDef>),
+	/// An optional value.
+	Option(Box<TypeDef>),
+	/// A `Result`.
+	Result(Box<TypeDef>, Box<TypeDef>),
+}
+
+/// Names of runtime types, and the types they stand for.
+pub type Types = HashMap<String, String>;
+
+/// The encodings of the types used by the FRAME pallets, extended and overridden by `types`.
+pub fn types(types: impl IntoIterator<Item=(String, String)>) -> Types {
+	FRAME_TYPES.iter()
+		.map(|(name, def)| (name.to_string(), def.to_string()))
+		.chain(types.into_iter().map(|(name, def)| {
+			let def = def.chars().filter(|c| !c.is_whitespace()).collect();
+			(name, def)
+		}))
+		.collect()
+}
+
+/// Decodes the events of the runtime the metadata was generated by.
+pub struct EventDecoder {
+	/// Arguments of the events by module and event index, or the reason they can't be decoded.
+	events: HashMap<(u8, u8), Result<Vec<TypeDef>, String>>,
+}
+
+impl EventDecoder {
+	/// Create a decoder from the encoded runtime metadata, resolving type names with `types`.
+	pub fn new(metadata: &[u8], types: &Types) -> Result<Self, String> {
+		let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+			.map_err(|e| format!("Invalid metadata: {}", e))?;
+		let modules = match metadata.1 {
+			RuntimeMetadata::V12(metadata) => decoded(metadata.modules)?,
+			_ => return Err("Unsupported metadata version".into()),
+		};
+
+		let mut events = HashMap::new();
+		for module in modules {
+			let module_events = match module.event {
+				Some(module_events) => decoded(module_events)?,
+				None => continue,
+			};
+			for (index, event) in module_events.into_iter().enumerate() {
+				// Events with arguments of unknown types are only a problem once emitted.
+				let arguments = decoded(event.arguments)?
+					.iter()
+					.map(|argument| parse_type(argument, types))
+					.collect::<Result<Vec<_>, _>>();
+				events.insert((module.index, index as u8), arguments);
+			}
+		}
+		Ok(EventDecoder { events })
+	}
+
+	/// Decode the encoded `System::Events`.
+	pub fn decode(&self, events: &[u8]) -> Result<Vec<IndexedEvent>, String> {
+		let input = &mut &events[..];
+		let count = <Compact<u32>>::decode(input).map_err(|e| e.to_string())?.0;
+		let mut decoded = Vec::new();
+		for _ in 0..count {
+			let event = self.decode_record(input)
+				.map_err(|e| format!("Error decoding event {}: {}", decoded.len(), e))?;
+			decoded.push(event);
+		}
+		if !input.is_empty() {
+			return Err(format!("{} bytes left after decoding the events", input.len()));
+		}
+		Ok(decoded)
+	}
+
+	/// Decode an `EventRecord`.
+	fn decode_record(&self, input: &mut &[u8]) -> Result<IndexedEvent, String> {
+		let codec_err = |e: codec::Error| e.to_string();
+		let extrinsic_index = match u8::decode(input).map_err(codec_err)? {
+			0 => Some(u32::decode(input).map_err(codec_err)?),
+			1 | 2 => None,
+			phase => return Err(format!("Invalid phase {}", phase)),
+		};
+		let module = u8::decode(input).map_err(codec_err)?;
+		let index = u8::decode(input).map_err(codec_err)?;
+		let arguments = match self.events.get(&(module, index)) {
+			Some(Ok(arguments)) => arguments,
+			Some(Err(e)) => return Err(e.clone()),
+			None => return Err(format!("Unknown event {} of module {}", index, module)),
+		};
+
+		let mut accounts = Vec::new();
+		for argument in arguments {
+			skip(argument, input, &mut accounts).map_err(codec_err)?;
+		}
+		let topic_count = <Compact<u32>>::decode(input).map_err(codec_err)?.0;
+		let topics = (0..topic_count)
+			.map(|_| take(input, TOPIC_LEN).map(<[u8]>::to_vec))
+			.collect::<Result<_, _>>()
+			.map_err(codec_err)?;
+		Ok(IndexedEvent { extrinsic_index, accounts, topics })
+	}
+}
+
+fn decoded<B, O>(value: DecodeDifferent<B, O>) -> Result<O, String> {
+	match value {
+		DecodeDifferent::Decoded(value) => Ok(value),
+		DecodeDifferent::Encode(_) => Err("Metadata is not decoded".into()),
+	}
+}
+
+fn parse_type(name: &str, types: &Types) -> Result<TypeDef, String> {
+	let name = name.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+	parse(&name, types, 0).ok_or_else(|| format!("Unknown type {}", name))
+}
+
+fn parse(name: &str, types: &Types, depth: usize) -> Option<TypeDef> {
+	if depth > MAX_TYPE_DEPTH {
+		return None;
+	}
+	if let Some(items) = name.strip_prefix('(').and_then(|n| n.strip_suffix(')')) {
+		return split(items, ',').into_iter()
+			.filter(|item| !item.is_empty())
+			.map(|item| parse(item, types, depth + 1))
+			.collect::<Option<_>>()
+			.map(TypeDef::Tuple);
+	}
+	if let Some(array) = name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+		return match split(array, ';')[..] {
+			[item, len] => {
+				Some(TypeDef::Array(Box::new(parse(item, types, depth + 1)?), len.parse().ok()?))
+			},
+			_ => None,
+		};
+	}
+
+	// Qualified paths, such as `<T as frame_system::Config>::AccountId`.
+	let name = match name.strip_prefix('<') {
+		Some(rest) => rest.get(closing(rest)? + 1..)?.trim_start_matches("::"),
+		None => name,
+	};
+	let (path, arguments) = match name.find('<') {
+		Some(start) => (&name[..start], split(name[start + 1..].strip_suffix('>')?, ',')),
+		None => (name, Vec::new()),
+	};
+	let base = path.rsplit("::").next().unwrap_or(path);
+
+	let parse_boxed = |name: &str| parse(name, types, depth + 1).map(Box::new);
+	Some(match (base, &arguments[..]) {
+		("bool", _) | ("u8", _) | ("i8", _) => TypeDef::Fixed(1),
+		("u16", _) | ("i16", _) => TypeDef::Fixed(2),
+		("u32", _) | ("i32", _) => TypeDef::Fixed(4),
+		("u64", _) | ("i64", _) => TypeDef::Fixed(8),
+		("u128", _) | ("i128", _) => TypeDef::Fixed(16),
+		("AccountId", _) => TypeDef::AccountId,
+		("DispatchError", _) => TypeDef::DispatchError,
+		("Compact", [_]) => TypeDef::Compact,
+		("Box", [item]) => return parse(item, types, depth + 1),
+		("Vec", [item]) => TypeDef::Vec(parse_boxed(*item)?),
+		("Option", [item]) => TypeDef::Option(parse_boxed(*item)?),
+		("Result", [ok, err]) => TypeDef::Result(parse_boxed(*ok)?, parse_boxed(*err)?),
+		// Generic arguments of runtime types don't change their encoding.
+		_ => return parse(types.get(base)?, types, depth + 1),
+	})
+}
+
+/// Split `list` at the occurrences of `separator` outside of brackets.
+fn split(list: &str, separator: char) -> Vec<&str> {
+	let mut items = Vec::new();
+	let mut depth = 0usize;
+	let mut start = 0;
+	for (i, c) in list.char_indices() {
+		match c {
+			'<' | '(' | '[' => depth += 1,
+			'>' | ')' | ']' => depth = depth.saturating_sub(1),
+			c if c == separator && depth == 0 => {
+				items.push(&list[start..i]);
+				start = i + c.len_utf8();
+			},
+			_ => (),
+		}
+	}
+	items.push(&list[start..]);
+	items
+}
+
+/// Index of the `>` closing the `<` preceding `rest`.
+fn closing(rest: &str) -> Option<usize> {
+	let mut depth = 0usize;
+	for (i, c) in rest.char_indices() {
+		match c {
+			'<' => depth += 1,
+			'>' if depth == 0 => return Some(i),
+			'>' => depth -= 1,
+			_ => (),
+		}
+	}
+	None
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], codec::Error> {
+	if input.len() < len {
+		return Err("Not enough data to fill buffer".into());
+	}
+	let (taken, rest) = input.split_at(len);
+	*input = rest;
+	Ok(taken)
+}
+
+/// Skip a value of type `def`, collecting the account ids it contains.
+fn skip(def: &TypeDef, input: &mut &[u8], accounts: &mut Vec<Vec<u8>>) -> Result<(), codec::Error> {
+	match def {
+		TypeDef::Fixed(len) => {
+			take(input, *len)?;
+		},
+		TypeDef::AccountId => accounts.push(take(input, ACCOUNT_ID_LEN)?.to_vec()),
+		TypeDef::Compact => {
+			<Compact<u128>>::decode(input)?;
+		},
+		TypeDef::DispatchError => if u8::decode(input)? == 3 {
+			take(input, 2)?;
+		},
+		TypeDef::Array(item, len) => for _ in 0..*len {
+			skip(item, input, accounts)?;
+		},
+		TypeDef::Vec(item) => for _ in 0..<Compact<u32>>::decode(input)?.0 {
+			skip(item, input, accounts)?;
+		},
+		TypeDef::Tuple(items) => for item in items {
+			skip(item, input, accounts)?;
+		},
+		TypeDef::Option(item) => match u8::decode(input)? {
+			0 => (),
+			1 => skip(item, input, accounts)?,
+			_ => return Err("Invalid Option".into()),
+		},
+		TypeDef::Result(ok, err) => match u8::decode(input)? {
+			0 => skip(ok, input, accounts)?,
+			1 => skip(err, input, accounts)?,
+			_ => return Err("Invalid Result".into()),
+		},
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use frame_metadata::{
+		EventMetadata, ExtrinsicMetadata, ModuleMetadata, RuntimeMetadataPrefixed,
+		RuntimeMetadataV12,
+	};
+
+	fn module(name: &str, index: u8, events: &[(&str, &[&str])]) -> ModuleMetadata {
+		let events = events.iter().map(|(name, arguments)| EventMetadata {
+			name: DecodeDifferent::Decoded(name.to_string()),
+			arguments: DecodeDifferent::Decoded(arguments.iter().map(|a| a.to_string()).collect()),
+			documentation: DecodeDifferent::Decoded(Vec::new()),
+		}).collect();
+		ModuleMetadata {
+			name: DecodeDifferent::Decoded(name.to_string()),
+			storage: None,
+			calls: None,
+			event: Some(DecodeDifferent::Decoded(events)),
+			constants: DecodeDifferent::Decoded(Vec::new()),
+			errors: DecodeDifferent::Decoded(Vec::new()),
+			index,
+		}
+	}
+
+	fn decoder() -> EventDecoder {
+		let metadata: RuntimeMetadataPrefixed = RuntimeMetadataV12 {
+			modules: DecodeDifferent::Decoded(vec![
+				module("System", 0, &[
+					("ExtrinsicSuccess", &["DispatchInfo"]),
+					("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+				]),
+				module("Balances", 5, &[
+					("Transfer", &["AccountId", "AccountId", "Balance"]),
+					("Mystery", &["Mystery<T>"]),
+					("Custom", &["AccountId", "Custom<T>"]),
+				]),
+				module("Multisig", 7, &[
+					("Executed", &[
+						"<T as frame_system::Config>::AccountId",
+						"Timepoint<BlockNumber>",
+						"Vec<T::AccountId>",
+						"Option<Compact<u32>>",
+						"DispatchResult",
+					]),
+				]),
+			]),
+			extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: Vec::new() },
+		}.into();
+		let types = types(vec![("Custom".to_string(), "(u8, Balance)".to_string())]);
+		EventDecoder::new(&metadata.encode(), &types).unwrap()
+	}
+
+	#[test]
+	fn parse_types() {
+		let types = types(vec![("Balance".to_string(), "u64".to_string())]);
+		assert_eq!(parse_type("T::Balance", &types), Ok(TypeDef::Fixed(8)));
+		assert_eq!(parse_type("BalanceOf<T, I>", &types), Ok(TypeDef::Fixed(8)));
+		assert_eq!(parse_type("<T as Config>::AccountId", &types), Ok(TypeDef::AccountId));
+		assert_eq!(
+			parse_type("Vec<(AccountId, u32)>", &types),
+			Ok(TypeDef::Vec(Box::new(TypeDef::Tuple(vec![TypeDef::AccountId, TypeDef::Fixed(4)])))),
+		);
+		assert_eq!(
+			parse_type("[u8; 4]", &types),
+			Ok(TypeDef::Array(Box::new(TypeDef::Fixed(1)), 4)),
+		);
+		assert!(parse_type("IdentificationTuple", &types).is_err());
+		assert!(parse_type("Vec<u8", &types).is_err());
+	}
+
+	#[test]
+	fn decode_events() {
+		let alice = [1u8; 32];
+		let bob = [2u8; 32];
+		let topic = [3u8; 32];
+		let dispatch_info = (7u64, 0u8, 0u8);
+
+		let mut events = Compact(4u32).encode();
+		// Balances::Transfer(alice, bob, 10), emitted by extrinsic 1.
+		(0u8, 1u32, 5u8, 0u8, alice, bob, 10u128, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+		// Multisig::Executed(bob, (1, 2), [alice, bob], Some(3), Err(Module)), with a topic.
+		(0u8, 2u32, 7u8, 0u8, bob, (1u32, 2u32), vec![alice, bob]).encode_to(&mut events);
+		(Some(Compact(3u32)), 1u8, 3u8, 1u8, 2u8, vec![topic]).encode_to(&mut events);
+		// System::ExtrinsicFailed(BadOrigin, info), while finalizing.
+		(1u8, 0u8, 1u8, 2u8, dispatch_info, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+		// Balances::Custom(alice, (1, 2)), with a configured type.
+		(2u8, 5u8, 2u8, alice, 1u8, 2u128, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+
+		assert_eq!(decoder().decode(&events).unwrap(), vec![
+			IndexedEvent {
+				extrinsic_index: Some(1),
+				accounts: vec![alice.to_vec(), bob.to_vec()],
+				topics: vec![],
+			},
+			IndexedEvent {
+				extrinsic_index: Some(2),
+				accounts: vec![bob.to_vec(), alice.to_vec(), bob.to_vec()],
+				topics: vec![topic.to_vec()],
+			},
+			IndexedEvent { extrinsic_index: None, accounts: vec![], topics: vec![] },
+			IndexedEvent { extrinsic_index: None, accounts: vec![alice.to_vec()], topics: vec![] },
+		]);
+	}
+
+	#[test]
+	fn refuse_unknown_types() {
+		let alice = [1u8; 32];
+		let mut events = Compact(3u32).encode();
+		(0u8, 0u32, 5u8, 0u8, alice, alice, 10u128, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+		(0u8, 0u32, 5u8, 1u8, 42u64, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+		(0u8, 0u32, 5u8, 0u8, alice, alice, 10u128, Vec::<[u8; 32]>::new()).encode_to(&mut events);
+
+		assert!(decoder().decode(&events).is_err());
+	}
+}
@@ -0,0 +1,197 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Indexing of the events of finalized blocks by the accounts and topics they refer to.
+
+mod decoder;
+
+use std::sync::Arc;
+use futures::StreamExt;
+use log::warn;
+use sc_client_api::{BlockchainEvents, IndexedEvent, StorageProvider, backend::Backend};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::twox_128, storage::{well_known_keys, StorageKey}};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, One, Zero},
+};
+
+use crate::config::EventIndexConfig;
+use self::decoder::{EventDecoder, Types};
+
+/// Storage key of `System::Events`.
+fn events_key() -> StorageKey {
+	StorageKey([twox_128(b"System"), twox_128(b"Events")].concat())
+}
+
+/// Indexes the events of finalized blocks into the event index of the backend.
+struct Indexer<TBl: BlockT, TCl, TBackend> {
+	client: Arc<TCl>,
+	backend: Arc<TBackend>,
+	/// Encodings of the types named by the arguments of events.
+	types: Types,
+	/// Decoder of the last runtime used, by the hash of its code.
+	decoder: Option<(Option<TBl::Hash>, Result<EventDecoder, String>)>,
+	/// The block indexing is stuck at, once reported.
+	stalled: Option<NumberFor<TBl>>,
+}
+
+impl<TBl, TCl, TBackend> Indexer<TBl, TCl, TBackend> where
+	TBl: BlockT,
+	TCl: ProvideRuntimeApi<TBl> + HeaderBackend<TBl> + StorageProvider<TBl, TBackend>,
+	TCl::Api: sp_api::Metadata<TBl>,
+	TBackend: Backend<TBl>,
+{
+	/// Index the finalized blocks that aren't indexed yet.
+	///
+	/// Stops at the first block whose events can't be indexed, to retry on the next call.
+	fn catch_up(&mut self) {
+		let backend = self.backend.clone();
+		let index = match backend.event_index() {
+			Some(index) => index,
+			None => return,
+		};
+		let finalized = self.client.info().finalized_number;
+		let mut number = match index.last_indexed() {
+			Ok(Some(last)) => last + One::one(),
+			Ok(None) => match self.first_with_state(finalized) {
+				Some(first) => first,
+				None => return,
+			},
+			Err(e) => {
+				warn!("Failed to read the event index: {:?}", e);
+				return;
+			},
+		};
+		while number <= finalized {
+			let result = self.block_events(number)
+				.and_then(|(hash, events)| index.index_block(hash, number, &events)
+					.map_err(|e| format!("{:?}", e))
+				);
+			if let Err(e) = result {
+				if self.stalled != Some(number) {
+					warn!("Failed to index the events of block #{}: {}", number, e);
+					self.stalled = Some(number);
+				}
+				return;
+			}
+			number += One::one();
+		}
+	}
+
+	/// The first block up to `finalized` whose state is available.
+	///
+	/// The state is pruned from the oldest blocks on, so blocks with state are found by bisection.
+	fn first_with_state(&self, finalized: NumberFor<TBl>) -> Option<NumberFor<TBl>> {
+		let has_state = |number| match self.client.hash(number) {
+			Ok(Some(hash)) => self.backend.have_state_at(&hash, number),
+			_ => false,
+		};
+		if !has_state(finalized) {
+			return None;
+		}
+		let two: NumberFor<TBl> = 2u32.into();
+		let (mut low, mut high) = (Zero::zero(), finalized);
+		while low < high {
+			let middle = low + (high - low) / two;
+			if has_state(middle) {
+				high = middle;
+			} else {
+				low = middle + One::one();
+			}
+		}
+		Some(low)
+	}
+
+	/// The hash and events of a block.
+	fn block_events(
+		&mut self,
+		number: NumberFor<TBl>,
+	) -> Result<(TBl::Hash, Vec<IndexedEvent>), String> {
+		let header = match self.client.header(BlockId::Number(number)) {
+			Ok(Some(header)) => header,
+			Ok(None) => return Err("Unknown block".into()),
+			Err(e) => return Err(format!("Failed to look up the block: {:?}", e)),
+		};
+		let hash = header.hash();
+		// The genesis block isn't executed by a runtime.
+		if number.is_zero() {
+			return Ok((hash, Vec::new()));
+		}
+		if !self.backend.have_state_at(&hash, number) {
+			return Err("The state of the block was pruned before its events were indexed".into());
+		}
+		let events = match self.client.storage(&BlockId::Hash(hash), &events_key()) {
+			Ok(Some(events)) => events,
+			Ok(None) => return Ok((hash, Vec::new())),
+			Err(e) => return Err(format!("Failed to read the events: {:?}", e)),
+		};
+		let decoder = self.decoder(*header.parent_hash())?;
+		Ok((hash, decoder.decode(&events.0)?))
+	}
+
+	/// The decoder for the runtime that executed the children of `parent`.
+	fn decoder(&mut self, parent: TBl::Hash) -> Result<&EventDecoder, String> {
+		let at = BlockId::Hash(parent);
+		let code_key = StorageKey(well_known_keys::CODE.to_vec());
+		let code_hash = self.client.storage_hash(&at, &code_key).map_err(|e| e.to_string())?;
+		let cached = matches!(self.decoder, Some((ref hash, _)) if *hash == code_hash);
+		if !cached {
+			let decoder = self.client.runtime_api().metadata(&at)
+				.map_err(|e| format!("Failed to get the metadata: {:?}", e))
+				.and_then(|metadata| EventDecoder::new(&metadata, &self.types));
+			self.decoder = Some((code_hash, decoder));
+		}
+		match self.decoder {
+			Some((_, Ok(ref decoder))) => Ok(decoder),
+			Some((_, Err(ref e))) => Err(e.clone()),
+			None => unreachable!("Decoder was set above; qed"),
+		}
+	}
+}
+
+/// Index the events of finalized blocks, as they get finalized.
+///
+/// Indexing starts at the first finalized block whose state is available. A block whose events
+/// can't be decoded, e.g. because the type of an argument is unknown, is not indexed and neither
+/// are the following ones.
+pub async fn run<TBl, TCl, TBackend>(
+	client: Arc<TCl>,
+	backend: Arc<TBackend>,
+	config: EventIndexConfig,
+) where
+	TBl: BlockT,
+	TCl: ProvideRuntimeApi<TBl> + HeaderBackend<TBl> + StorageProvider<TBl, TBackend> +
+		BlockchainEvents<TBl>,
+	TCl::Api: sp_api::Metadata<TBl>,
+	TBackend: Backend<TBl>,
+{
+	let mut finality_notifications = client.finality_notification_stream();
+	let mut indexer = Indexer {
+		client,
+		backend,
+		types: decoder::types(config.types),
+		decoder: None,
+		stalled: None,
+	};
+	indexer.catch_up();
+	while finality_notifications.next().await.is_some() {
+		indexer.catch_up();
+	}
+}
@@ -28,6 +28,7 @@ pub mod error;
 
 mod metrics;
 mod builder;
+mod event_index;
 #[cfg(feature = "test-helpers")]
 pub mod client;
 #[cfg(not(feature = "test-helpers"))]
@@ -41,7 +42,7 @@ use std::time::Duration;
 use std::task::Poll;
 
 use futures::{Future, FutureExt, Stream, StreamExt, stream, compat::*};
-use sc_network::{NetworkStatus, network_state::NetworkState, PeerId};
+use sc_network::{NetworkStatus, config::BanTarget, network_state::NetworkState, PeerId};
 use log::{warn, debug, error};
 use codec::{Encode, Decode};
 use sp_runtime::generic::BlockId;
@@ -59,7 +60,7 @@ pub use self::builder::{
 };
 pub use config::{
 	BasePath, Configuration, DatabaseConfig, PruningMode, Role, RpcMethods, TaskExecutor, TaskType,
-	KeepBlocks, TransactionStorageMode,
+	KeepBlocks, TransactionStorageMode, EventIndexConfig,
 };
 pub use sc_chain_spec::{
 	ChainSpec, GenericChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension,
@@ -302,6 +303,49 @@ async fn build_network_future<
 							))),
 						};
 					}
+					sc_rpc::system::Request::PeerStore(sender) => {
+						let _ = sender.send(network.peer_store().into_iter().map(|entry|
+							sc_rpc::system::KnownPeer {
+								peer_id: entry.peer_id.to_base58(),
+								reputation: entry.reputation,
+								addresses: entry.addresses.iter().map(|a| a.to_string()).collect(),
+							}
+						).collect());
+					}
+					sc_rpc::system::Request::ClearPeerStore(sender) => {
+						network.clear_peer_store();
+						let _ = sender.send(());
+					}
+					sc_rpc::system::Request::NetworkBanPeer(target, sender) => {
+						let _ = match target.parse::<BanTarget>() {
+							Ok(target) => {
+								network.ban(target);
+								sender.send(Ok(()))
+							}
+							Err(e) => sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(
+								e.to_string(),
+							))),
+						};
+					}
+					sc_rpc::system::Request::NetworkUnbanPeer(target, sender) => {
+						let _ = match target.parse::<BanTarget>() {
+							Ok(target) => if network.unban(&target) {
+								sender.send(Ok(()))
+							} else {
+								sender.send(Err(sc_rpc::system::error::Error::NotBanned(
+									target.to_string(),
+								)))
+							},
+							Err(e) => sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(
+								e.to_string(),
+							))),
+						};
+					}
+					sc_rpc::system::Request::NetworkListBanned(sender) => {
+						let _ = sender.send(
+							network.banned().iter().map(|target| target.to_string()).collect()
+						);
+					}
 					sc_rpc::system::Request::NodeRoles(sender) => {
 						use sc_rpc::system::NodeRole;
 
@@ -417,6 +461,18 @@ fn start_rpc_servers<
 		}
 	}
 
+	let access_policy = config.rpc_access_policy.clone().map(Arc::new);
+	// Shared by the servers, so that callers can't use each of them up to the limits.
+	let limiter = Arc::new(sc_rpc_server::RateLimiter::new(config.rpc_limits.clone()));
+	let external_middleware = |transport_label| {
+		let middleware = sc_rpc_server::RpcMiddleware::new(rpc_metrics.clone(), transport_label)
+			.with_limiter(limiter.clone());
+		match access_policy {
+			Some(ref policy) => middleware.with_access_policy(policy.clone()),
+			None => middleware,
+		}
+	};
+
 	Ok(Box::new((
 		config.rpc_ipc.as_ref().map(|path| sc_rpc_server::start_ipc(
 			&*path, gen_handler(
@@ -429,9 +485,10 @@ fn start_rpc_servers<
 			|address| sc_rpc_server::start_http(
 				address,
 				config.rpc_cors.as_ref(),
+				&config.rpc_limits.trusted_proxies,
 				gen_handler(
 					deny_unsafe(&address, &config.rpc_methods),
-					sc_rpc_server::RpcMiddleware::new(rpc_metrics.clone(), "http")
+					external_middleware("http"),
 				),
 			),
 		)?.map(|s| waiting::HttpServer(Some(s))),
@@ -443,7 +500,7 @@ fn start_rpc_servers<
 				config.rpc_cors.as_ref(),
 				gen_handler(
 					deny_unsafe(&address, &config.rpc_methods),
-					sc_rpc_server::RpcMiddleware::new(rpc_metrics.clone(), "ws")
+					external_middleware("ws"),
 				),
 			),
 		)?.map(|s| waiting::WsServer(Some(s))),
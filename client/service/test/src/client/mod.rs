@@ -1852,3 +1852,47 @@ fn reorg_triggers_a_notification_even_for_sources_that_should_not_trigger_notifi
 	let tree_route = notification.tree_route.unwrap();
 	assert_eq!(tree_route.enacted()[0].hash, b1.hash());
 }
+
+#[test]
+fn read_proof_collection_covers_the_whole_state() {
+	use sc_client_api::ProofProvider;
+	use sp_storage::ChildInfo;
+
+	let child_info = ChildInfo::new_default(b"child1");
+	let client = TestClientBuilder::new()
+		.add_extra_child_storage(&child_info, b"key1".to_vec(), b"value1".to_vec())
+		.build();
+	let id = BlockId::Number(0);
+	let root = client.state_at(&id).unwrap().storage_root(std::iter::empty()).0;
+
+	let mut start = Vec::new();
+	let mut top = Vec::new();
+	loop {
+		let (entries, proof, complete) = client.read_proof_collection(&id, None, &start, 100)
+			.unwrap();
+		assert!(!entries.is_empty());
+		let checked = sp_state_machine::read_proof_check::<BlakeTwo256, _>(
+			root,
+			proof,
+			entries.iter().map(|(key, _)| key),
+		).unwrap();
+		for (key, value) in &entries {
+			assert_eq!(checked.get(key), Some(&Some(value.clone())));
+		}
+		start = entries.last().unwrap().0.clone();
+		top.extend(entries);
+		if complete {
+			break
+		}
+	}
+	let expected = client.storage_pairs(&id, &StorageKey(Vec::new())).unwrap()
+		.into_iter()
+		.map(|(key, value)| (key.0, value.0))
+		.collect::<Vec<_>>();
+	assert_eq!(top, expected);
+
+	let (child, _, complete) = client.read_proof_collection(&id, Some(&child_info), &[], 100)
+		.unwrap();
+	assert!(complete);
+	assert_eq!(child, vec![(b"key1".to_vec(), b"value1".to_vec())]);
+}
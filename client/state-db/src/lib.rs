@@ -38,7 +38,7 @@ mod test;
 
 use std::fmt;
 use parking_lot::RwLock;
-use codec::Codec;
+use codec::{Codec, Decode};
 use std::collections::{HashMap, hash_map::Entry};
 use noncanonical::NonCanonicalOverlay;
 use pruning::RefWindow;
@@ -179,6 +179,17 @@ impl PruningMode {
 			PruningMode::Constrained(_) => PRUNING_MODE_CONSTRAINED,
 		}
 	}
+
+	/// Get the pruning mode from its id. Constraints are not part of the id,
+	/// so they are left at their defaults.
+	pub fn from_id(id: &[u8]) -> Option<Self> {
+		match id {
+			PRUNING_MODE_ARCHIVE => Some(PruningMode::ArchiveAll),
+			PRUNING_MODE_ARCHIVE_CANON => Some(PruningMode::ArchiveCanonical),
+			PRUNING_MODE_CONSTRAINED => Some(PruningMode::Constrained(Default::default())),
+			_ => None,
+		}
+	}
 }
 
 impl Default for PruningMode {
@@ -193,6 +204,87 @@ fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	buffer
 }
 
+/// Returns the pruning mode the database was created with, if any block was written to it.
+pub fn fetch_stored_pruning_mode<D: MetaDb>(db: &D) -> Result<Option<PruningMode>, Error<D::Error>> {
+	let db_mode = db.get_meta(&to_meta_key(PRUNING_MODE, &())).map_err(Error::Db)?;
+	match db_mode {
+		Some(v) => PruningMode::from_id(&v)
+			.map(Some)
+			.ok_or_else(|| Error::InvalidPruningMode(String::from_utf8_lossy(&v).into())),
+		None => Ok(None),
+	}
+}
+
+/// Rewrites the state database metadata for a database that stores trie nodes under the
+/// trailing `key_len` bytes of their keys only, e.g. when moving from a database without
+/// reference counting to one with it. `meta` is the full content of the metadata column.
+pub fn strip_meta_key_prefixes<BlockHash: Hash>(
+	meta: Vec<(Vec<u8>, DBValue)>,
+	key_len: usize,
+) -> Result<Vec<(Vec<u8>, DBValue)>, codec::Error> {
+	let mut result = Vec::with_capacity(meta.len());
+	let mut pruning_journal = Vec::new();
+	for (key, value) in meta {
+		if key.ends_with(pruning::PRUNING_JOURNAL) {
+			let block = u64::decode(&mut &key[..])?;
+			pruning_journal.push((block, value));
+		} else if key.ends_with(noncanonical::NON_CANONICAL_JOURNAL) {
+			let value = noncanonical::strip_journal_prefixes::<BlockHash>(&value, key_len)?;
+			result.push((key, value));
+		} else {
+			result.push((key, value));
+		}
+	}
+	for (block, value) in pruning::strip_journal_prefixes::<BlockHash>(pruning_journal, key_len)? {
+		result.push((to_meta_key(pruning::PRUNING_JOURNAL, &block), value));
+	}
+	Ok(result)
+}
+
+/// Creates the metadata that turns an archive database into a `PruningMode::Constrained` one.
+///
+/// `window` lists the canonical blocks to keep, starting at block number `window_start`, each
+/// with the nodes that are deleted once that block is pruned. The last of them becomes the last
+/// canonicalized block. When converting from `PruningMode::ArchiveAll` there is no
+/// non-canonical journal yet, and `overlay` should list the blocks above the last canonical one
+/// as `(hash, number, parent_hash, changes)`, with the nodes each block inserted and deleted
+/// compared to its parent. Nodes of the window must already be in the database.
+pub fn constrained_meta<BlockHash: Hash, Key: Hash>(
+	window_start: u64,
+	window: Vec<(BlockHash, Vec<Key>)>,
+	overlay: Option<Vec<(BlockHash, u64, BlockHash, ChangeSet<Key>)>>,
+) -> ChangeSet<Vec<u8>> {
+	let mut meta = ChangeSet::default();
+	meta.inserted.push((to_meta_key(PRUNING_MODE, &()), PRUNING_MODE_CONSTRAINED.into()));
+	let last_canonicalized = window.last()
+		.map(|(hash, _)| (hash.clone(), window_start + window.len() as u64 - 1));
+	pruning::window_meta(window_start, window, &mut meta);
+	if let (Some(overlay), Some(last_canonicalized)) = (overlay, last_canonicalized) {
+		noncanonical::overlay_meta::<_, Key>(last_canonicalized, overlay, &mut meta);
+	}
+	meta
+}
+
+/// Creates the metadata for a database whose state starts at block `number`, e.g. after
+/// importing a state snapshot. The block becomes the last canonicalized one and its nodes must
+/// already be in the database.
+pub fn snapshot_meta<BlockHash: Hash, Key: Hash>(
+	mode: &PruningMode,
+	hash: BlockHash,
+	number: u64,
+) -> ChangeSet<Vec<u8>> {
+	match mode {
+		PruningMode::Constrained(_) =>
+			constrained_meta::<_, Key>(number, vec![(hash, Vec::new())], Some(Vec::new())),
+		PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => {
+			let mut meta = ChangeSet::default();
+			meta.inserted.push((to_meta_key(PRUNING_MODE, &()), mode.id().into()));
+			noncanonical::overlay_meta::<_, Key>((hash, number), Vec::new(), &mut meta);
+			meta
+		},
+	}
+}
+
 struct StateDbSync<BlockHash: Hash, Key: Hash> {
 	mode: PruningMode,
 	non_canonical: NonCanonicalOverlay<BlockHash, Key>,
@@ -274,6 +366,25 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 		}
 	}
 
+	fn insert_base_block<E: fmt::Debug>(
+		&mut self,
+		hash: &BlockHash,
+		number: u64,
+		mut changeset: ChangeSet<Key>,
+	) -> Result<CommitSet<Key>, Error<E>> {
+		changeset.deleted.clear();
+		let mut commit = CommitSet { data: changeset, meta: ChangeSet::default() };
+		if self.mode == PruningMode::ArchiveAll {
+			return Ok(commit)
+		}
+		self.non_canonical.set_base(hash, number, &mut commit)?;
+		if let Some(ref mut pruning) = self.pruning {
+			pruning.note_canonical(hash, &mut commit);
+		}
+		self.prune(&mut commit);
+		Ok(commit)
+	}
+
 	fn canonicalize_block<E: fmt::Debug>(
 		&mut self,
 		hash: &BlockHash,
@@ -463,6 +574,17 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 		self.db.write().insert_block(hash, number, parent_hash, changeset)
 	}
 
+	/// Add a new canonical block whose parent is unknown, e.g. one whose state was downloaded
+	/// rather than computed. It becomes the base all further blocks must build on.
+	pub fn insert_base_block<E: fmt::Debug>(
+		&self,
+		hash: &BlockHash,
+		number: u64,
+		changeset: ChangeSet<Key>,
+	) -> Result<CommitSet<Key>, Error<E>> {
+		self.db.write().insert_base_block(hash, number, changeset)
+	}
+
 	/// Finalize a previously inserted block.
 	pub fn canonicalize_block<E: fmt::Debug>(
 		&self,
@@ -528,7 +650,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 mod tests {
 	use std::io;
 	use sp_core::H256;
-	use crate::{StateDb, PruningMode, Constraints};
+	use crate::{StateDb, PruningMode, Constraints, CommitSet, constrained_meta, fetch_stored_pruning_mode};
 	use crate::test::{make_db, make_changeset, TestDb};
 
 	fn make_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256>) {
@@ -645,6 +767,88 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn converts_archive_to_constrained() {
+		let (mut db, _) = make_test_db(PruningMode::ArchiveAll);
+		let mut commit = CommitSet::default();
+		commit.meta = constrained_meta(
+			2,
+			vec![
+				(H256::from_low_u64_be(21), vec![]),
+				(H256::from_low_u64_be(3), vec![H256::from_low_u64_be(921)]),
+			],
+			Some(vec![(
+				H256::from_low_u64_be(4),
+				4,
+				H256::from_low_u64_be(3),
+				make_changeset(&[4], &[94]),
+			)]),
+		);
+		db.commit(&commit);
+		assert_eq!(
+			fetch_stored_pruning_mode(&db).unwrap(),
+			Some(PruningMode::Constrained(Default::default())),
+		);
+
+		let sdb: StateDb<H256, H256> = StateDb::new(PruningMode::keep_blocks(1), false, &db).unwrap();
+		assert_eq!(sdb.best_canonical(), Some(3));
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(1), 1));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(21), 2));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(4), 4));
+
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(4)).unwrap());
+		sdb.apply_pending();
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(21), 2));
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(3), 3));
+		assert!(!db.data.contains_key(&H256::from_low_u64_be(921)));
+
+		// Nodes deleted by the journaled overlay block are pruned with it.
+		db.commit(&sdb.insert_block::<io::Error>(
+			&H256::from_low_u64_be(5),
+			5,
+			&H256::from_low_u64_be(4),
+			make_changeset(&[5], &[]),
+		).unwrap());
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(5)).unwrap());
+		sdb.apply_pending();
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(4), 4));
+		assert!(!db.data.contains_key(&H256::from_low_u64_be(94)));
+		assert!(db.data.contains_key(&H256::from_low_u64_be(4)));
+	}
+
+	#[test]
+	fn base_block_continues_the_chain() {
+		let (mut db, sdb) = make_test_db(PruningMode::ArchiveCanonical);
+		// Not possible while there are non-canonical blocks.
+		assert!(sdb.insert_base_block::<io::Error>(
+			&H256::from_low_u64_be(10),
+			10,
+			make_changeset(&[10], &[1]),
+		).is_err());
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(4)).unwrap());
+		sdb.apply_pending();
+
+		db.commit(&sdb.insert_base_block::<io::Error>(
+			&H256::from_low_u64_be(10),
+			10,
+			make_changeset(&[10], &[1]),
+		).unwrap());
+		assert_eq!(sdb.best_canonical(), Some(10));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(10), 10));
+
+		db.commit(&sdb.insert_block::<io::Error>(
+			&H256::from_low_u64_be(11),
+			11,
+			&H256::from_low_u64_be(10),
+			make_changeset(&[11], &[]),
+		).unwrap());
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(11)).unwrap());
+		sdb.apply_pending();
+		assert_eq!(sdb.best_canonical(), Some(11));
+		assert!(db.data_eq(&make_db(&[1, 21, 3, 4, 91, 921, 922, 93, 94, 10, 11])));
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
@@ -28,7 +28,7 @@ use super::{Error, DBValue, ChangeSet, CommitSet, MetaDb, Hash, to_meta_key};
 use codec::{Encode, Decode};
 use log::trace;
 
-const NON_CANONICAL_JOURNAL: &[u8] = b"noncanonical_journal";
+pub(crate) const NON_CANONICAL_JOURNAL: &[u8] = b"noncanonical_journal";
 const LAST_CANONICAL: &[u8] = b"last_canonical";
 
 /// See module documentation.
@@ -144,6 +144,43 @@ fn discard_descendants<BlockHash: Hash, Key: Hash>(
 	pinned_children
 }
 
+/// Creates journal entries for an overlay on top of `last_canonicalized` that contains `blocks`,
+/// given as `(hash, number, parent_hash, changes)` in ascending order of number.
+pub fn overlay_meta<BlockHash: Hash, Key: Hash>(
+	last_canonicalized: (BlockHash, u64),
+	blocks: Vec<(BlockHash, u64, BlockHash, ChangeSet<Key>)>,
+	meta: &mut ChangeSet<Vec<u8>>,
+) {
+	meta.inserted.push((to_meta_key(LAST_CANONICAL, &()), last_canonicalized.encode()));
+	let mut level_sizes: HashMap<u64, u64> = HashMap::new();
+	for (hash, number, parent_hash, changes) in blocks {
+		let index = level_sizes.entry(number).or_default();
+		let journal_record = JournalRecord::<BlockHash, Key> {
+			hash,
+			parent_hash,
+			inserted: changes.inserted,
+			deleted: changes.deleted,
+		};
+		meta.inserted.push((to_journal_key(number, *index), journal_record.encode()));
+		*index += 1;
+	}
+}
+
+/// Converts a journal record from prefixed node keys to the trailing `key_len` bytes of each key.
+pub fn strip_journal_prefixes<BlockHash: Hash>(
+	record: &[u8],
+	key_len: usize,
+) -> Result<DBValue, codec::Error> {
+	let strip = |k: Vec<u8>| k[k.len().saturating_sub(key_len)..].to_vec();
+	let record: JournalRecord<BlockHash, Vec<u8>> = Decode::decode(&mut &record[..])?;
+	Ok(JournalRecord::<BlockHash, Vec<u8>> {
+		hash: record.hash,
+		parent_hash: record.parent_hash,
+		inserted: record.inserted.into_iter().map(|(k, v)| (strip(k), v)).collect(),
+		deleted: record.deleted.into_iter().map(strip).collect(),
+	}.encode())
+}
+
 impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 	/// Creates a new instance. Does not expect any metadata to be present in the DB.
 	pub fn new<D: MetaDb>(db: &D) -> Result<NonCanonicalOverlay<BlockHash, Key>, Error<D::Error>> {
@@ -285,6 +322,24 @@ impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 		}
 	}
 
+	/// Make the given block the last canonicalized one, as the base of a chain imported without
+	/// its history. Fails if there are any non-canonical blocks.
+	pub fn set_base<E: fmt::Debug>(
+		&mut self,
+		hash: &BlockHash,
+		number: u64,
+		commit: &mut CommitSet<Key>,
+	) -> Result<(), Error<E>> {
+		if !self.levels.is_empty() || !self.pending_canonicalizations.is_empty() {
+			return Err(Error::InvalidBlockNumber);
+		}
+		trace!(target: "state-db", "Setting base block {:?} ({})", hash, number);
+		let base = (hash.clone(), number);
+		commit.meta.inserted.push((to_meta_key(LAST_CANONICAL, &()), base.encode()));
+		self.last_canonicalized = Some(base);
+		Ok(())
+	}
+
 	fn front_block_number(&self) -> u64 {
 		self.last_canonicalized.as_ref().map(|&(_, n)| n + 1).unwrap_or(0)
 	}
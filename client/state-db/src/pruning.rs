@@ -26,11 +26,11 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use codec::{Encode, Decode};
-use crate::{CommitSet, Error, MetaDb, to_meta_key, Hash};
+use crate::{ChangeSet, CommitSet, DBValue, Error, MetaDb, to_meta_key, Hash};
 use log::{trace, warn};
 
 const LAST_PRUNED: &[u8] = b"last_pruned";
-const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
+pub(crate) const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
 
 /// See module documentation.
 #[derive(parity_util_mem_derive::MallocSizeOf)]
@@ -71,6 +71,63 @@ fn to_journal_key(block: u64) -> Vec<u8> {
 	to_meta_key(PRUNING_JOURNAL, &block)
 }
 
+/// Creates journal entries for a window that starts at block `first` and contains `blocks`,
+/// each with the keys that are deleted once the block is pruned.
+pub fn window_meta<BlockHash: Hash, Key: Hash>(
+	first: u64,
+	blocks: Vec<(BlockHash, Vec<Key>)>,
+	meta: &mut ChangeSet<Vec<u8>>,
+) {
+	if first > 0 {
+		meta.inserted.push((to_meta_key(LAST_PRUNED, &()), (first - 1).encode()));
+	}
+	for (index, (hash, deleted)) in blocks.into_iter().enumerate() {
+		let journal_record = JournalRecord {
+			hash,
+			inserted: Vec::new(),
+			deleted,
+		};
+		meta.inserted.push((to_journal_key(first + index as u64), journal_record.encode()));
+	}
+}
+
+/// Converts journal entries, given as `(block, journal record)` pairs, from prefixed node keys to
+/// the trailing `key_len` bytes of each key.
+///
+/// Without reference counting a key that is re-inserted by a later block is not deleted when
+/// pruning. That information is lost once keys are shortened, so such keys are dropped from the
+/// death rows here, as `RefWindow::import` would do.
+pub fn strip_journal_prefixes<BlockHash: Hash>(
+	mut journal: Vec<(u64, DBValue)>,
+	key_len: usize,
+) -> Result<Vec<(u64, DBValue)>, codec::Error> {
+	journal.sort_by_key(|(block, _)| *block);
+	let mut records = Vec::with_capacity(journal.len());
+	let mut death_index: HashMap<Vec<u8>, usize> = HashMap::new();
+	for (block, record) in journal {
+		let record: JournalRecord<BlockHash, Vec<u8>> = Decode::decode(&mut record.as_slice())?;
+		for key in record.inserted {
+			if let Some(index) = death_index.remove(&key) {
+				let deleted: &mut HashSet<Vec<u8>> = &mut records[index].2;
+				deleted.remove(&key);
+			}
+		}
+		for key in record.deleted.iter() {
+			death_index.insert(key.clone(), records.len());
+		}
+		records.push((block, record.hash, record.deleted.into_iter().collect()));
+	}
+
+	Ok(records.into_iter().map(|(block, hash, deleted)| {
+		let record = JournalRecord::<BlockHash, Vec<u8>> {
+			hash,
+			inserted: Vec::new(),
+			deleted: deleted.into_iter().map(|k| k[k.len().saturating_sub(key_len)..].to_vec()).collect(),
+		};
+		(block, record.encode())
+	}).collect())
+}
+
 impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 	pub fn new<D: MetaDb>(db: &D, count_insertions: bool) -> Result<RefWindow<BlockHash, Key>, Error<D::Error>> {
 		let last_pruned = db.get_meta(&to_meta_key(LAST_PRUNED, &()))
@@ -218,9 +275,10 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 
 #[cfg(test)]
 mod tests {
-	use super::RefWindow;
+	use super::{RefWindow, JournalRecord, strip_journal_prefixes};
+	use codec::Decode;
 	use sp_core::H256;
-	use crate::CommitSet;
+	use crate::{ChangeSet, CommitSet};
 	use crate::test::{make_db, make_commit, TestDb};
 
 	fn check_journal(pruning: &RefWindow<H256, H256>, db: &TestDb) {
@@ -420,4 +478,33 @@ mod tests {
 		assert!(pruning.death_index.is_empty());
 	}
 
+	#[test]
+	fn strip_prefixes_drops_reinserted() {
+		let db = make_db(&[]);
+		let mut pruning: RefWindow<H256, Vec<u8>> = RefWindow::new(&db, true).unwrap();
+		let rows = vec![
+			(vec![], vec![vec![1, 2]]),
+			(vec![vec![1, 2]], vec![vec![3, 4]]),
+		];
+		let mut journal = Vec::new();
+		for (block, (inserted, deleted)) in rows.into_iter().enumerate() {
+			let mut commit = CommitSet {
+				data: ChangeSet {
+					inserted: inserted.into_iter().map(|k| (k, Vec::new())).collect(),
+					deleted,
+				},
+				meta: Default::default(),
+			};
+			pruning.note_canonical(&H256::random(), &mut commit);
+			journal.push((block as u64, commit.meta.inserted.pop().unwrap().1));
+		}
+
+		let stripped = strip_journal_prefixes::<H256>(journal, 1).unwrap();
+		let deleted: Vec<Vec<Vec<u8>>> = stripped.iter().map(|(_, record)| {
+			let record: JournalRecord<H256, Vec<u8>> = Decode::decode(&mut record.as_slice()).unwrap();
+			assert!(record.inserted.is_empty());
+			record.deleted
+		}).collect();
+		assert_eq!(deleted, vec![vec![], vec![vec![4]]]);
+	}
 }
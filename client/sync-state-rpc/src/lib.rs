@@ -80,7 +80,8 @@ pub struct SyncStateRpcHandler<TBl: BlockT, TCl> {
 impl<TBl, TCl> SyncStateRpcHandler<TBl, TCl>
 	where
 		TBl: BlockT,
-		TCl: HeaderBackend<TBl> + sc_client_api::AuxStore + 'static,
+		TCl: HeaderBackend<TBl> + sc_client_api::BlockBackend<TBl> + sc_client_api::AuxStore
+			+ 'static,
 {
 	/// Create a new handler.
 	pub fn new(
@@ -99,6 +100,7 @@ impl<TBl, TCl> SyncStateRpcHandler<TBl, TCl>
 		let finalized_hash = self.client.info().finalized_hash;
 		let finalized_header = self.client.header(BlockId::Hash(finalized_hash))?
 			.ok_or_else(|| sp_blockchain::Error::MissingHeader(finalized_hash.to_string()))?;
+		let finalized_justification = self.client.justification(&BlockId::Hash(finalized_hash))?;
 
 		let finalized_block_weight = sc_consensus_babe::aux_schema::load_block_weight(
 				&*self.client,
@@ -108,6 +110,7 @@ impl<TBl, TCl> SyncStateRpcHandler<TBl, TCl>
 
 		Ok(sc_chain_spec::LightSyncState {
 			finalized_block_header: finalized_header,
+			finalized_block_justification: finalized_justification,
 			babe_epoch_changes: self.shared_epoch_changes.lock().clone(),
 			babe_finalized_block_weight: finalized_block_weight,
 			grandpa_authority_set: self.shared_authority_set.clone_inner(),
@@ -118,7 +121,8 @@ impl<TBl, TCl> SyncStateRpcHandler<TBl, TCl>
 impl<TBl, TCl> SyncStateRpcApi for SyncStateRpcHandler<TBl, TCl>
 	where
 		TBl: BlockT,
-		TCl: HeaderBackend<TBl> + sc_client_api::AuxStore + 'static,
+		TCl: HeaderBackend<TBl> + sc_client_api::BlockBackend<TBl> + sc_client_api::AuxStore
+			+ 'static,
 {
 	fn system_gen_sync_spec(&self, raw: bool)
 		-> jsonrpc_core::Result<jsonrpc_core::Value>
@@ -41,6 +41,7 @@ use sp_transaction_pool::{error, PoolStatus, InPoolTransaction};
 
 use crate::future::{FutureTransactions, WaitingTransaction};
 use crate::ready::ReadyTransactions;
+use crate::senders::Senders;
 
 /// Successful import result.
 #[derive(Debug, PartialEq, Eq)]
@@ -228,6 +229,8 @@ pub struct BasePool<Hash: hash::Hash + Eq, Ex> {
 	/// transactions to future in case they were just stuck in verification.
 	recently_pruned: [HashSet<Tag>; RECENTLY_PRUNED_TAGS],
 	recently_pruned_index: usize,
+	/// Transactions of both queues grouped by sender.
+	senders: Senders<Hash>,
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> Default for BasePool<Hash, Ex> {
@@ -245,9 +248,26 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			ready: Default::default(),
 			recently_pruned: Default::default(),
 			recently_pruned_index: 0,
+			senders: Default::default(),
 		}
 	}
 
+	/// Sets the minimal priority increase, in percent, required to replace transactions.
+	///
+	/// A transaction replaces the ready ones providing the same tags only if its priority
+	/// is higher than their collective priority increased by this percentage.
+	pub fn set_min_replacement_bump(&mut self, percent: u32) {
+		self.ready.set_min_replacement_bump(percent)
+	}
+
+	/// Sets the number of trailing bytes of the first tag provided by a transaction that are not
+	/// part of its sender, e.g. the nonce of the `(sender, nonce)` tags of FRAME transactions.
+	///
+	/// Only affects transactions imported afterwards.
+	pub fn set_sender_nonce_len(&mut self, nonce_len: usize) {
+		self.senders.set_nonce_len(nonce_len)
+	}
+
 	/// Temporary enables future transactions, runs closure and then restores
 	/// `reject_future_transactions` flag back to previous value.
 	///
@@ -301,10 +321,12 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			}
 
 			let hash = tx.transaction.hash.clone();
+			self.senders.insert(&tx.transaction);
 			self.future.import(tx);
 			return Ok(Imported::Future { hash });
 		}
 
+		self.senders.insert(&tx.transaction);
 		self.import_to_ready(tx)
 	}
 
@@ -338,13 +360,22 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 						promoted.push(current_hash);
 					}
 					// The transactions were removed from the ready pool. We might attempt to re-import them.
+					for tx in &replaced {
+						self.senders.remove(&tx.hash);
+					}
 					removed.append(&mut replaced);
 				},
 				// transaction failed to be imported.
 				Err(e) => if first {
 					debug!(target: "txpool", "[{:?}] Error importing: {:?}", current_hash, e);
+					// the transactions it unlocked have been taken out of the future queue
+					self.senders.remove(&current_hash);
+					for tx in &to_import {
+						self.senders.remove(&tx.transaction.hash);
+					}
 					return Err(e)
 				} else {
+					self.senders.remove(&current_hash);
 					failed.push(current_hash);
 				},
 			}
@@ -359,7 +390,9 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		if removed.iter().any(|tx| tx.hash == hash) {
 			// We still need to remove all transactions that we promoted
 			// since they depend on each other and will never get to the best iterator.
-			self.ready.remove_subtree(&promoted);
+			for tx in self.ready.remove_subtree(&promoted) {
+				self.senders.remove(&tx.hash);
+			}
 
 			debug!(target: "txpool", "[{:?}] Cycle detected, bailing.", hash);
 			return Err(error::Error::CycleDetected)
@@ -407,52 +440,59 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	///
 	/// Removes and returns worst transactions from the queues and all transactions that depend on them.
 	/// Technically the worst transaction should be evaluated by computing the entire pending set.
-	/// We use a simplified approach to remove the transaction that occupies the pool for the longest time.
-	pub fn enforce_limits(&mut self, ready: &Limit, future: &Limit) -> Vec<Arc<Transaction<Hash, Ex>>> {
-		let mut removed = vec![];
-
-		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
-			// find the worst transaction
-			let minimal = self.ready
-				.fold(|minimal, current| {
-					let transaction = &current.transaction;
-					match minimal {
-						None => Some(transaction.clone()),
-						Some(ref tx) if tx.insertion_id > transaction.insertion_id => {
-							Some(transaction.clone())
-						},
-						other => other,
+	/// We use a simplified approach: transactions are grouped by sender (see
+	/// [`set_sender_nonce_len`](Self::set_sender_nonce_len)) and the lowest priority transaction of
+	/// the sender with the most transactions in the pool is removed, so that a single sender
+	/// flooding the pool does not evict everyone else.
+	///
+	/// Senders that got new transactions since the last call and now exceed the `sender` limit
+	/// lose their transactions first, preferring the ones in the future queue.
+	pub fn enforce_limits(
+		&mut self,
+		ready: &Limit,
+		future: &Limit,
+		sender: Option<&Limit>,
+	) -> Evicted<Hash, Ex> {
+		let mut evicted = Evicted::default();
+
+		let grown = self.senders.take_grown();
+		if let Some(limit) = sender {
+			for sender in grown {
+				while self.senders.size(sender)
+					.map_or(false, |(count, bytes)| limit.is_exceeded(count, bytes))
+				{
+					let queue = &self.future;
+					let worst = self.senders.worst(sender, |hash| queue.contains(hash))
+						.or_else(|| self.senders.worst(sender, |_| true));
+
+					if let Some(worst) = worst {
+						evicted.sender.append(&mut self.remove_subtree(&[worst]))
+					} else {
+						break;
 					}
-				});
+				}
+			}
+		}
 
-			if let Some(minimal) = minimal {
-				removed.append(&mut self.remove_subtree(&[minimal.transaction.hash.clone()]))
+		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
+			let queue = &self.ready;
+			if let Some(worst) = self.senders.worst_of_heaviest(|hash| queue.contains(hash)) {
+				evicted.ready.append(&mut self.remove_subtree(&[worst]))
 			} else {
 				break;
 			}
 		}
 
 		while future.is_exceeded(self.future.len(), self.future.bytes()) {
-			// find the worst transaction
-			let minimal = self.future
-				.fold(|minimal, current| {
-					match minimal {
-						None => Some(current.clone()),
-						Some(ref tx) if tx.imported_at > current.imported_at => {
-							Some(current.clone())
-						},
-						other => other,
-					}
-				});
-
-			if let Some(minimal) = minimal {
-				removed.append(&mut self.remove_subtree(&[minimal.transaction.hash.clone()]))
+			let queue = &self.future;
+			if let Some(worst) = self.senders.worst_of_heaviest(|hash| queue.contains(hash)) {
+				evicted.future.append(&mut self.remove_subtree(&[worst]))
 			} else {
 				break;
 			}
 		}
 
-		removed
+		evicted
 	}
 
 	/// Removes all transactions represented by the hashes and all other transactions
@@ -466,12 +506,19 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	pub fn remove_subtree(&mut self, hashes: &[Hash]) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		let mut removed = self.ready.remove_subtree(hashes);
 		removed.extend(self.future.remove(hashes));
+		for tx in &removed {
+			self.senders.remove(&tx.hash);
+		}
 		removed
 	}
 
 	/// Removes and returns all transactions from the future queue.
 	pub fn clear_future(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
-		self.future.clear()
+		let removed = self.future.clear();
+		for tx in &removed {
+			self.senders.remove(&tx.hash);
+		}
+		removed
 	}
 
 	/// Prunes transactions that provide given list of tags.
@@ -496,6 +543,10 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			recently_pruned.insert(tag);
 		}
 
+		for tx in &pruned {
+			self.senders.remove(&tx.hash);
+		}
+
 		let mut promoted = vec![];
 		let mut failed = vec![];
 		for tx in to_import {
@@ -543,6 +594,34 @@ impl Limit {
 	}
 }
 
+/// Transactions removed from the pool to enforce its limits.
+#[derive(Debug)]
+pub struct Evicted<Hash, Ex> {
+	/// Transactions removed because their sender exceeded its limit.
+	pub sender: Vec<Arc<Transaction<Hash, Ex>>>,
+	/// Transactions removed because the ready queue exceeded its limit.
+	pub ready: Vec<Arc<Transaction<Hash, Ex>>>,
+	/// Transactions removed because the future queue exceeded its limit.
+	pub future: Vec<Arc<Transaction<Hash, Ex>>>,
+}
+
+impl<Hash, Ex> Default for Evicted<Hash, Ex> {
+	fn default() -> Self {
+		Evicted {
+			sender: Vec::new(),
+			ready: Vec::new(),
+			future: Vec::new(),
+		}
+	}
+}
+
+impl<Hash, Ex> Evicted<Hash, Ex> {
+	/// Returns an iterator over all evicted transactions.
+	pub fn all(&self) -> impl Iterator<Item=&Arc<Transaction<Hash, Ex>>> {
+		self.sender.iter().chain(&self.ready).chain(&self.future)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1201,4 +1280,96 @@ source: TransactionSource::External, requires: [03,02], provides: [04], data: [4
 		assert_eq!(pool.reject_future_transactions, true);
 		assert_eq!(pool.future.len(), 1);
 	}
+
+	fn sender_tx(sender: u8, nonce: u8, priority: u64) -> Transaction<Hash, Vec<u8>> {
+		Transaction {
+			data: vec![sender, nonce],
+			bytes: 1,
+			hash: sender as u64 * 100 + nonce as u64,
+			priority,
+			valid_till: 64u64,
+			requires: if nonce > 0 { vec![vec![sender, nonce - 1]] } else { vec![] },
+			provides: vec![vec![sender, nonce]],
+			propagate: true,
+			source: Source::External,
+		}
+	}
+
+	fn sender_pool() -> BasePool<Hash, Vec<u8>> {
+		let mut pool = pool();
+		pool.set_sender_nonce_len(1);
+		pool
+	}
+
+	#[test]
+	fn should_evict_from_heaviest_sender() {
+		// given
+		let mut pool = sender_pool();
+		for nonce in 0..5 {
+			pool.import(sender_tx(1, nonce, 5)).unwrap();
+		}
+		pool.import(sender_tx(2, 0, 1)).unwrap();
+		assert_eq!(pool.ready().count(), 6);
+
+		// when
+		let limit = Limit { count: 5, total_bytes: 1000 };
+		let evicted = pool.enforce_limits(&limit, &limit, None);
+
+		// then
+		let evicted = evicted.ready.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+		assert_eq!(evicted, vec![104]);
+		assert_eq!(pool.ready().count(), 5);
+		assert!(pool.ready.contains(&200));
+	}
+
+	#[test]
+	fn should_enforce_sender_limit() {
+		// given
+		let mut pool = sender_pool();
+		for nonce in 0..3 {
+			pool.import(sender_tx(1, nonce, 5)).unwrap();
+		}
+		pool.import(sender_tx(1, 5, 5)).unwrap();
+		pool.import(sender_tx(2, 0, 5)).unwrap();
+		assert_eq!(pool.ready().count(), 4);
+		assert_eq!(pool.futures().count(), 1);
+
+		// when
+		let limit = Limit { count: 100, total_bytes: 1000 };
+		let sender = Limit { count: 2, total_bytes: 1000 };
+		let evicted = pool.enforce_limits(&limit, &limit, Some(&sender));
+
+		// then
+		// the nonce gap does not split the transactions of the sender
+		let evicted = evicted.sender.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+		assert_eq!(evicted, vec![105, 102]);
+		assert_eq!(pool.ready().count(), 3);
+		assert_eq!(pool.futures().count(), 0);
+
+		// and the limit is only enforced again once the sender gets new transactions
+		let evicted = pool.enforce_limits(&limit, &limit, Some(&sender));
+		assert!(evicted.sender.is_empty());
+	}
+
+	#[test]
+	fn should_forget_senders_of_removed_transactions() {
+		// given
+		let mut pool = sender_pool();
+		for nonce in 0..3 {
+			pool.import(sender_tx(1, nonce, 5)).unwrap();
+		}
+		pool.import(sender_tx(1, 5, 5)).unwrap();
+		pool.import(sender_tx(2, 0, 5)).unwrap();
+
+		// when
+		pool.prune_tags(vec![vec![1, 0]]);
+		pool.clear_future();
+		pool.remove_subtree(&[200]);
+
+		// then
+		assert_eq!(pool.ready().count(), 2);
+		assert_eq!(pool.senders.worst_of_heaviest(|_| true), Some(102));
+		pool.remove_subtree(&[101]);
+		assert_eq!(pool.senders.worst_of_heaviest(|_| true), None);
+	}
 }
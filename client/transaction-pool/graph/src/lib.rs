@@ -31,6 +31,7 @@ mod listener;
 mod pool;
 mod ready;
 mod rotator;
+mod senders;
 mod validated_pool;
 mod tracked_map;
 
@@ -39,6 +40,6 @@ pub mod watcher;
 
 pub use self::base_pool::Transaction;
 pub use self::pool::{
-	BlockHash, ChainApi, EventStream, ExtrinsicFor, ExtrinsicHash, IsValidator, NumberFor, Options,
-	Pool, TransactionFor, ValidatedTransaction,
+	BlockHash, ChainApi, EventStream, EvictionStats, ExtrinsicFor, ExtrinsicHash, IsValidator,
+	NumberFor, Options, Pool, TransactionFor, ValidatedTransaction,
 };
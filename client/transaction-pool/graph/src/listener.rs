@@ -96,6 +96,12 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		})
 	}
 
+	/// Transaction was replaced on request by the given transaction.
+	pub fn replaced(&mut self, tx: &H, by: &H) {
+		trace!(target: "txpool", "[{:?}] Replaced with {:?}", tx, by);
+		self.fire(tx, |watcher| watcher.replaced(by.clone()))
+	}
+
 	/// Transaction was removed as invalid.
 	pub fn invalid(&mut self, tx: &H, warn: bool) {
 		if warn {
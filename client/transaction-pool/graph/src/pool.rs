@@ -18,6 +18,7 @@
 
 use std::{
 	collections::HashMap,
+	path::PathBuf,
 	sync::Arc,
 };
 
@@ -36,7 +37,7 @@ use wasm_timer::Instant;
 use futures::channel::mpsc::Receiver;
 
 use crate::validated_pool::ValidatedPool;
-pub use crate::validated_pool::{IsValidator, ValidatedTransaction};
+pub use crate::validated_pool::{EvictionStats, IsValidator, ValidatedTransaction};
 
 /// Modification notification event stream type;
 pub type EventStream<H> = Receiver<H>;
@@ -105,8 +106,23 @@ pub struct Options {
 	pub ready: base::Limit,
 	/// Future queue limits.
 	pub future: base::Limit,
+	/// Limits on the transactions of a single sender, across both queues.
+	pub sender: Option<base::Limit>,
+	/// Number of trailing bytes of the first tag provided by a transaction that are not part of
+	/// its sender.
+	///
+	/// The rest of the tag identifies the sender, e.g. the account of the `(account, nonce)` tags
+	/// provided by FRAME transactions.
+	pub sender_nonce_len: usize,
 	/// Reject future transactions.
 	pub reject_future_transactions: bool,
+	/// Minimal priority increase, in percent, for a transaction to replace the ones
+	/// providing the same tags.
+	pub min_replacement_bump: u32,
+	/// Path of the journal the pool contents are persisted to.
+	///
+	/// Transactions found in the journal are resubmitted to the pool on startup.
+	pub journal: Option<PathBuf>,
 }
 
 impl Default for Options {
@@ -120,7 +136,11 @@ impl Default for Options {
 				count: 512,
 				total_bytes: 1 * 1024 * 1024,
 			},
+			sender: None,
+			sender_nonce_len: crate::senders::DEFAULT_NONCE_LEN,
 			reject_future_transactions: false,
+			min_replacement_bump: 0,
+			journal: None,
 		}
 	}
 }
@@ -193,6 +213,31 @@ impl<B: ChainApi> Pool<B> {
 		res.expect("One extrinsic passed; one result returned; qed")
 	}
 
+	/// Imports one unverified extrinsic to the pool, replacing the transaction `replaced`.
+	///
+	/// See [`ValidatedPool::submit_replacement`] for when a transaction can be replaced.
+	pub async fn replace_one(
+		&self,
+		at: &BlockId<B::Block>,
+		source: TransactionSource,
+		replaced: ExtrinsicHash<B>,
+		xt: ExtrinsicFor<B>,
+	) -> Result<ExtrinsicHash<B>, B::Error> {
+		// Don't bother validating the extrinsic if there is nothing to replace.
+		if self.validated_pool.extrinsics_tags(&[replaced.clone()]).pop().flatten().is_none() {
+			return Err(error::Error::ReplacedNotFound.into());
+		}
+		let block_number = self.resolve_block_number(at)?;
+		let (_, tx) = self.verify_one(
+			at,
+			block_number,
+			source,
+			xt,
+			CheckBannedBeforeVerify::Yes,
+		).await;
+		self.validated_pool.submit_replacement(&replaced, tx)
+	}
+
 	/// Import a single extrinsic and starts to watch their progress in the pool.
 	pub async fn submit_and_watch(
 		&self,
@@ -923,6 +968,48 @@ mod tests {
 			assert_eq!(stream.next(), Some(TransactionStatus::Ready));
 		}
 
+		#[test]
+		fn should_trigger_replaced_for_future_transaction() {
+			// given
+			let pool = pool();
+			let watcher = block_on(pool.submit_and_watch(&BlockId::Number(0), SOURCE, uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 1,
+			}))).unwrap();
+			let unrelated = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 2,
+			});
+			let replacement = uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 6,
+				nonce: 1,
+			});
+
+			// when
+			let not_replacing = block_on(
+				pool.replace_one(&BlockId::Number(0), SOURCE, *watcher.hash(), unrelated.clone())
+			);
+			let hash = block_on(
+				pool.replace_one(&BlockId::Number(0), SOURCE, *watcher.hash(), replacement)
+			).unwrap();
+
+			// then
+			assert!(matches!(not_replacing, Err(error::Error::NotReplacing)));
+			assert_eq!(pool.validated_pool().status().future, 1);
+			// the rejected replacement was not imported nor banned
+			block_on(pool.submit_one(&BlockId::Number(0), SOURCE, unrelated)).unwrap();
+			let mut stream = futures::executor::block_on_stream(watcher.into_stream());
+			assert_eq!(stream.next(), Some(TransactionStatus::Future));
+			assert_eq!(stream.next(), Some(TransactionStatus::Replaced(hash)));
+			assert_eq!(stream.next(), None);
+		}
+
 		#[test]
 		fn should_trigger_invalid_and_ban() {
 			// given
@@ -118,6 +118,8 @@ pub struct ReadyTransactions<Hash: hash::Hash + Eq, Ex> {
 	ready: TrackedMap<Hash, ReadyTx<Hash, Ex>>,
 	/// Best transactions that are ready to be included to the block without any other previous transaction.
 	best: BTreeSet<TransactionRef<Hash, Ex>>,
+	/// Minimal priority increase, in percent, required to replace transactions.
+	min_replacement_bump: u32,
 }
 
 impl<Hash, Ex> tracked_map::Size for ReadyTx<Hash, Ex> {
@@ -133,11 +135,20 @@ impl<Hash: hash::Hash + Eq, Ex> Default for ReadyTransactions<Hash, Ex> {
 			provided_tags: Default::default(),
 			ready: Default::default(),
 			best: Default::default(),
+			min_replacement_bump: 0,
 		}
 	}
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
+	/// Sets the minimal priority increase, in percent, required to replace transactions.
+	///
+	/// A transaction replaces the ones providing the same tags only if its priority is higher
+	/// than their collective priority increased by this percentage.
+	pub fn set_min_replacement_bump(&mut self, percent: u32) {
+		self.min_replacement_bump = percent;
+	}
+
 	/// Borrows a map of tags that are provided by transactions in this queue.
 	pub fn provided_tags(&self) -> &HashMap<Tag, Hash> {
 		&self.provided_tags
@@ -445,6 +456,18 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 				return Err(error::Error::TooLowPriority { old: old_priority, new: tx.priority })
 			}
 
+			// bail - the priority is higher, but not by enough
+			let required = old_priority.saturating_add(
+				old_priority.saturating_mul(self.min_replacement_bump as u64) / 100
+			);
+			if required > tx.priority {
+				return Err(error::Error::TooLowPriorityBump {
+					old: old_priority,
+					new: tx.priority,
+					required,
+				})
+			}
+
 			// construct a list of unlocked transactions
 			let unlocks = {
 				let ready = self.ready.read();
@@ -605,6 +628,33 @@ mod tests {
 		assert_eq!(ready.get().count(), 1);
 	}
 
+	#[test]
+	fn should_require_min_replacement_bump() {
+		// given
+		let mut ready = ReadyTransactions::default();
+		ready.set_min_replacement_bump(10);
+		let mut tx1 = tx(1);
+		tx1.requires.clear();
+		tx1.priority = 100;
+		let mut tx2 = tx(2);
+		tx2.requires.clear();
+		tx2.priority = 109;
+		import(&mut ready, tx1).unwrap();
+
+		// when
+		let err = import(&mut ready, tx2.clone()).unwrap_err();
+
+		// then
+		assert!(matches!(
+			err,
+			error::Error::TooLowPriorityBump { old: 100, new: 109, required: 110 }
+		));
+		tx2.priority = 110;
+		let replaced = import(&mut ready, tx2).unwrap();
+		assert_eq!(replaced.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(ready.get().count(), 1);
+	}
+
 	#[test]
 	fn should_replace_multiple_transactions_correctly() {
 		// given
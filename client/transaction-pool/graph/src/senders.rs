@@ -0,0 +1,253 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Transactions of the pool grouped by sender.
+
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	hash,
+};
+
+use sp_runtime::transaction_validity::{
+	TransactionTag as Tag,
+	TransactionPriority as Priority,
+};
+
+use crate::base_pool::Transaction;
+
+/// Default number of trailing bytes of a provided tag that are not part of the sender.
+///
+/// Signed FRAME transactions provide `(sender, nonce)` as their first tag, with a `u32` nonce.
+pub const DEFAULT_NONCE_LEN: usize = 4;
+
+/// Identifies the sender of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(target_os = "unknown"), derive(parity_util_mem::MallocSizeOf))]
+enum Key<Hash> {
+	/// The first tag provided by the transactions of the sender, without the nonce.
+	Tag(Tag),
+	/// A transaction without such a tag, which is its own sender.
+	Transaction(Hash),
+}
+
+/// A transaction tracked by [`Senders`].
+#[derive(Debug)]
+#[cfg_attr(not(target_os = "unknown"), derive(parity_util_mem::MallocSizeOf))]
+struct Member {
+	sender: u64,
+	/// Position of the transaction in its sender's `transactions`.
+	position: (Priority, u64),
+	bytes: usize,
+}
+
+/// The transactions of a single sender.
+#[derive(Debug)]
+#[cfg_attr(not(target_os = "unknown"), derive(parity_util_mem::MallocSizeOf))]
+struct Sender<Hash> {
+	key: Key<Hash>,
+	bytes: usize,
+	/// Transactions ordered by priority, newest first among equal priorities.
+	transactions: BTreeMap<(Priority, u64), Hash>,
+}
+
+impl<Hash> Sender<Hash> {
+	/// Position of the sender in `Senders::by_size`.
+	fn size(&self, id: u64) -> (usize, usize, u64) {
+		(self.transactions.len(), self.bytes, id)
+	}
+}
+
+/// Transactions of the pool grouped by sender.
+///
+/// The pool does not know who sent a transaction, so the sender is derived from the first tag
+/// the transaction provides, stripped of its trailing nonce bytes. Unlike the dependencies
+/// between transactions this is stable: a gap in the nonces of an account does not split its
+/// transactions. The grouping is updated as transactions enter and leave the pool.
+#[derive(Debug)]
+#[cfg_attr(not(target_os = "unknown"), derive(parity_util_mem::MallocSizeOf))]
+pub struct Senders<Hash: hash::Hash + Eq> {
+	nonce_len: usize,
+	next_sender: u64,
+	/// Decreasing, so that newer transactions sort first.
+	next_age: u64,
+	ids: HashMap<Key<Hash>, u64>,
+	senders: HashMap<u64, Sender<Hash>>,
+	transactions: HashMap<Hash, Member>,
+	/// Senders ordered by the number and total size of their transactions.
+	by_size: BTreeSet<(usize, usize, u64)>,
+	/// Senders with new transactions since the last call to `take_grown`.
+	grown: HashSet<u64>,
+}
+
+impl<Hash: hash::Hash + Eq> Default for Senders<Hash> {
+	fn default() -> Self {
+		Senders {
+			nonce_len: DEFAULT_NONCE_LEN,
+			next_sender: 0,
+			next_age: u64::max_value(),
+			ids: Default::default(),
+			senders: Default::default(),
+			transactions: Default::default(),
+			by_size: Default::default(),
+			grown: Default::default(),
+		}
+	}
+}
+
+impl<Hash: hash::Hash + Eq + Clone> Senders<Hash> {
+	/// Sets the number of trailing bytes of a provided tag that are not part of the sender.
+	///
+	/// Only affects transactions added afterwards.
+	pub fn set_nonce_len(&mut self, nonce_len: usize) {
+		self.nonce_len = nonce_len;
+	}
+
+	/// Starts tracking the given transaction.
+	pub fn insert<Ex>(&mut self, tx: &Transaction<Hash, Ex>) {
+		if self.transactions.contains_key(&tx.hash) {
+			return
+		}
+
+		let key = match tx.provides.first() {
+			Some(tag) if tag.len() > self.nonce_len =>
+				Key::Tag(tag[..tag.len() - self.nonce_len].to_vec()),
+			_ => Key::Transaction(tx.hash.clone()),
+		};
+		let next_sender = &mut self.next_sender;
+		let id = *self.ids.entry(key.clone()).or_insert_with(|| {
+			*next_sender += 1;
+			*next_sender
+		});
+		let sender = self.senders.entry(id).or_insert_with(|| Sender {
+			key,
+			bytes: 0,
+			transactions: BTreeMap::new(),
+		});
+
+		self.by_size.remove(&sender.size(id));
+		let position = (tx.priority, self.next_age);
+		self.next_age -= 1;
+		sender.transactions.insert(position, tx.hash.clone());
+		sender.bytes += tx.bytes;
+		self.by_size.insert(sender.size(id));
+		self.grown.insert(id);
+
+		self.transactions.insert(tx.hash.clone(), Member { sender: id, position, bytes: tx.bytes });
+	}
+
+	/// Stops tracking the given transaction.
+	pub fn remove(&mut self, hash: &Hash) {
+		let member = match self.transactions.remove(hash) {
+			Some(member) => member,
+			None => return,
+		};
+		let id = member.sender;
+		let sender = match self.senders.get_mut(&id) {
+			Some(sender) => sender,
+			None => return,
+		};
+
+		self.by_size.remove(&sender.size(id));
+		sender.transactions.remove(&member.position);
+		sender.bytes -= member.bytes;
+		if sender.transactions.is_empty() {
+			self.ids.remove(&sender.key);
+			self.senders.remove(&id);
+			self.grown.remove(&id);
+		} else {
+			self.by_size.insert(sender.size(id));
+		}
+	}
+
+	/// Returns the senders that got new transactions since the last call.
+	pub fn take_grown(&mut self) -> Vec<u64> {
+		self.grown.drain().collect()
+	}
+
+	/// Returns the number of transactions of the given sender and their total size.
+	pub fn size(&self, sender: u64) -> Option<(usize, usize)> {
+		self.senders.get(&sender).map(|sender| (sender.transactions.len(), sender.bytes))
+	}
+
+	/// Returns the lowest priority transaction of the sender matching the predicate.
+	///
+	/// The newest one is preferred, as it is the least likely to unlock other transactions.
+	pub fn worst(&self, sender: u64, predicate: impl Fn(&Hash) -> bool) -> Option<Hash> {
+		self.senders.get(&sender)?.transactions.values().find(|hash| predicate(*hash)).cloned()
+	}
+
+	/// Returns the worst transaction matching the predicate of the heaviest sender having one.
+	///
+	/// Senders are compared by the number of their transactions, then by their total size.
+	pub fn worst_of_heaviest(&self, predicate: impl Fn(&Hash) -> bool) -> Option<Hash> {
+		self.by_size.iter().rev().find_map(|(_, _, sender)| self.worst(*sender, &predicate))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::transaction_validity::TransactionSource as Source;
+
+	fn tx(hash: u64, provides: Vec<u8>, priority: u64) -> Transaction<u64, ()> {
+		Transaction {
+			data: (),
+			bytes: 1,
+			hash,
+			priority,
+			valid_till: 64,
+			requires: vec![],
+			provides: vec![provides],
+			propagate: true,
+			source: Source::External,
+		}
+	}
+
+	#[test]
+	fn should_group_by_tag_prefix() {
+		let mut senders = Senders::default();
+		senders.set_nonce_len(1);
+		senders.insert(&tx(1, vec![1, 0], 5));
+		senders.insert(&tx(2, vec![1, 7], 5));
+		senders.insert(&tx(3, vec![2, 0], 5));
+		senders.insert(&tx(4, vec![3], 5));
+
+		let heaviest = senders.by_size.iter().rev().next().unwrap().2;
+		assert_eq!(senders.size(heaviest), Some((2, 2)));
+		assert_eq!(senders.senders.len(), 3);
+		assert_eq!(senders.worst_of_heaviest(|_| true), Some(2));
+		assert_eq!(senders.worst_of_heaviest(|hash| *hash != 1 && *hash != 2), Some(4));
+	}
+
+	#[test]
+	fn should_forget_removed_transactions() {
+		let mut senders = Senders::default();
+		senders.set_nonce_len(1);
+		senders.insert(&tx(1, vec![1, 0], 1));
+		senders.insert(&tx(2, vec![1, 1], 5));
+		assert_eq!(senders.take_grown().len(), 1);
+
+		senders.remove(&1);
+		assert_eq!(senders.worst_of_heaviest(|_| true), Some(2));
+		senders.remove(&2);
+		assert!(senders.senders.is_empty());
+		assert!(senders.ids.is_empty());
+		assert!(senders.by_size.is_empty());
+		assert!(senders.take_grown().is_empty());
+	}
+}
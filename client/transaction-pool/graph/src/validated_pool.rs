@@ -105,6 +105,25 @@ impl From<Box<dyn Fn() -> bool + Send + Sync>> for IsValidator {
 	}
 }
 
+/// Number of transactions evicted to enforce the pool limits, by exceeded limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionStats {
+	/// Evicted because their sender exceeded its limit.
+	pub sender: usize,
+	/// Evicted because the ready queue exceeded its limit.
+	pub ready: usize,
+	/// Evicted because the future queue exceeded its limit.
+	pub future: usize,
+}
+
+impl EvictionStats {
+	fn add<Hash, Ex>(&mut self, evicted: &base::Evicted<Hash, Ex>) {
+		self.sender += evicted.sender.len();
+		self.ready += evicted.ready.len();
+		self.future += evicted.future.len();
+	}
+}
+
 /// Pool that deals with validated transactions.
 pub struct ValidatedPool<B: ChainApi> {
 	api: Arc<B>,
@@ -117,6 +136,7 @@ pub struct ValidatedPool<B: ChainApi> {
 	>>,
 	import_notification_sinks: Mutex<Vec<Sender<ExtrinsicHash<B>>>>,
 	rotator: PoolRotator<ExtrinsicHash<B>>,
+	evicted: Mutex<EvictionStats>,
 }
 
 #[cfg(not(target_os = "unknown"))]
@@ -133,7 +153,9 @@ where
 impl<B: ChainApi> ValidatedPool<B> {
 	/// Create a new transaction pool.
 	pub fn new(options: Options, is_validator: IsValidator, api: Arc<B>) -> Self {
-		let base_pool = base::BasePool::new(options.reject_future_transactions);
+		let mut base_pool = base::BasePool::new(options.reject_future_transactions);
+		base_pool.set_min_replacement_bump(options.min_replacement_bump);
+		base_pool.set_sender_nonce_len(options.sender_nonce_len);
 		ValidatedPool {
 			is_validator,
 			options,
@@ -142,6 +164,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 			pool: RwLock::new(base_pool),
 			import_notification_sinks: Default::default(),
 			rotator: Default::default(),
+			evicted: Default::default(),
 		}
 	}
 
@@ -180,7 +203,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 		txs: impl IntoIterator<Item=ValidatedTransactionFor<B>>,
 	) -> Vec<Result<ExtrinsicHash<B>, B::Error>> {
 		let results = txs.into_iter()
-			.map(|validated_tx| self.submit_one(validated_tx))
+			.map(|validated_tx| self.submit_one(validated_tx, None))
 			.collect::<Vec<_>>();
 
 		// only enforce limits if there is at least one imported transaction
@@ -196,8 +219,54 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}).collect()
 	}
 
+	/// Imports a pre-validated transaction replacing the transaction with hash `replaced`.
+	///
+	/// The transaction has to provide all the tags the replaced transaction provides, and may
+	/// only require tags the replaced transaction requires, so that it takes its place in the
+	/// pool. Nothing is imported otherwise.
+	pub fn submit_replacement(
+		&self,
+		replaced: &ExtrinsicHash<B>,
+		tx: ValidatedTransactionFor<B>,
+	) -> Result<ExtrinsicHash<B>, B::Error> {
+		if let ValidatedTransaction::Valid(ref tx) = tx {
+			let old = self.pool.read()
+				.by_hashes(&[replaced.clone()])
+				.pop()
+				.flatten()
+				.ok_or(error::Error::ReplacedNotFound)?;
+			let provides_all = old.provides.iter().all(|tag| tx.provides.contains(tag));
+			let requires_no_more = tx.requires.iter().all(|tag| old.requires.contains(tag));
+			if !provides_all || !requires_no_more {
+				return Err(error::Error::NotReplacing.into());
+			}
+		}
+
+		let hash = self.submit_one(tx, Some(replaced))?;
+		// Ready transactions are replaced on import, future ones are not.
+		let removed = self.pool.write().remove_subtree(&[replaced.clone()]);
+		if !removed.is_empty() {
+			let mut listener = self.listener.write();
+			for tx in removed {
+				listener.replaced(&tx.hash, &hash);
+			}
+		}
+
+		if self.enforce_limits().contains(&hash) {
+			return Err(error::Error::ImmediatelyDropped.into());
+		}
+		Ok(hash)
+	}
+
 	/// Submit single pre-validated transaction to the pool.
-	fn submit_one(&self, tx: ValidatedTransactionFor<B>) -> Result<ExtrinsicHash<B>, B::Error> {
+	///
+	/// If the transaction replaces the transaction `replacing`, its listeners are notified that
+	/// it was replaced rather than usurped.
+	fn submit_one(
+		&self,
+		tx: ValidatedTransactionFor<B>,
+		replacing: Option<&ExtrinsicHash<B>>,
+	) -> Result<ExtrinsicHash<B>, B::Error> {
 		match tx {
 			ValidatedTransaction::Valid(tx) => {
 				if !tx.propagate && !(self.is_validator.0)() {
@@ -224,7 +293,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				}
 
 				let mut listener = self.listener.write();
-				fire_events(&mut *listener, &imported);
+				fire_events(&mut *listener, &imported, replacing);
 				Ok(imported.hash().clone())
 			},
 			ValidatedTransaction::Invalid(hash, err) => {
@@ -242,10 +311,12 @@ impl<B: ChainApi> ValidatedPool<B> {
 		let status = self.pool.read().status();
 		let ready_limit = &self.options.ready;
 		let future_limit = &self.options.future;
+		let sender_limit = self.options.sender.as_ref();
 
 		log::debug!(target: "txpool", "Pool Status: {:?}", status);
 		if ready_limit.is_exceeded(status.ready, status.ready_bytes)
 			|| future_limit.is_exceeded(status.future, status.future_bytes)
+			|| sender_limit.is_some()
 		{
 			log::debug!(
 				target: "txpool",
@@ -257,8 +328,9 @@ impl<B: ChainApi> ValidatedPool<B> {
 			// clean up the pool
 			let removed = {
 				let mut pool = self.pool.write();
-				let removed = pool.enforce_limits(ready_limit, future_limit)
-					.into_iter().map(|x| x.hash.clone()).collect::<HashSet<_>>();
+				let evicted = pool.enforce_limits(ready_limit, future_limit, sender_limit);
+				self.evicted.lock().add(&evicted);
+				let removed = evicted.all().map(|x| x.hash.clone()).collect::<HashSet<_>>();
 				// ban all removed transactions
 				self.rotator.ban(&Instant::now(), removed.iter().map(|x| x.clone()));
 				removed
@@ -449,7 +521,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 		{
 			let mut listener = self.listener.write();
 			for promoted in &status.promoted {
-				fire_events(&mut *listener, promoted);
+				fire_events(&mut *listener, promoted, None);
 			}
 			for f in &status.failed {
 				listener.dropped(f, None);
@@ -612,6 +684,16 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.pool.read().ready()
 	}
 
+	/// Returns the number of transactions evicted to enforce the pool limits since the last call.
+	pub fn take_eviction_stats(&self) -> EvictionStats {
+		std::mem::take(&mut *self.evicted.lock())
+	}
+
+	/// Get a copy of the transactions in the future queue.
+	pub fn futures(&self) -> Vec<TransactionFor<B>> {
+		self.pool.read().futures().map(|tx| Arc::new(tx.duplicate())).collect()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
@@ -633,6 +715,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 fn fire_events<H, B, Ex>(
 	listener: &mut Listener<H, B>,
 	imported: &base::Imported<H, Ex>,
+	replacing: Option<&H>,
 ) where
 	H: hash::Hash + Eq + traits::Member + Serialize,
 	B: ChainApi,
@@ -644,7 +727,11 @@ fn fire_events<H, B, Ex>(
 				listener.invalid(f, true);
 			}
 			for r in removed {
-				listener.dropped(&r.hash, Some(hash));
+				if replacing == Some(&r.hash) {
+					listener.replaced(&r.hash, hash);
+				} else {
+					listener.dropped(&r.hash, Some(hash));
+				}
 			}
 			for p in promoted {
 				listener.ready(p, None);
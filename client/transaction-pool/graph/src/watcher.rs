@@ -88,6 +88,12 @@ impl<H: Clone, BH: Clone> Sender<H, BH> {
 		self.is_finalized = true;
 	}
 
+	/// Extrinsic has been replaced on request by the extrinsic with given hash.
+	pub fn replaced(&mut self, hash: H) {
+		self.send(TransactionStatus::Replaced(hash));
+		self.is_finalized = true;
+	}
+
 	/// Extrinsic has been included in block with given hash.
 	pub fn in_block(&mut self, hash: BH) {
 		self.send(TransactionStatus::InBlock(hash));
@@ -0,0 +1,330 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2018-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Journal of the transaction pool contents.
+//!
+//! The journal holds the ready and future transactions of the pool along with their validity, so
+//! they survive a restart. It is rewritten during maintenance and when the pool is dropped. On
+//! startup the journaled transactions are put back in the pool at the first new best block and
+//! sent to the revalidation queue, which removes the ones that are no longer valid.
+
+use std::{
+	fs, io::{self, Write}, path::{Path, PathBuf},
+	sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}},
+	time::Duration,
+};
+
+use codec::{Encode, Decode};
+use parking_lot::Mutex;
+use sc_transaction_graph::base_pool::Transaction;
+use sp_core::traits::SpawnNamed;
+use sp_runtime::transaction_validity::{
+	TransactionLongevity, TransactionPriority, TransactionSource, TransactionTag,
+};
+use wasm_timer::Instant;
+
+/// Current version of the journal format.
+const VERSION: u32 = 1;
+
+/// Minimum time between two writes of the journal during maintenance.
+const STORE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A journaled transaction, along with the validity it had in the pool.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub struct JournaledTransaction<Ex> {
+	/// Source of the transaction.
+	pub source: TransactionSource,
+	/// The extrinsic.
+	pub data: Ex,
+	/// Priority of the transaction.
+	pub priority: TransactionPriority,
+	/// Block at which the transaction becomes invalid.
+	pub valid_till: TransactionLongevity,
+	/// Tags required by the transaction.
+	pub requires: Vec<TransactionTag>,
+	/// Tags provided by the transaction.
+	pub provides: Vec<TransactionTag>,
+	/// Whether the transaction should be propagated.
+	pub propagate: bool,
+}
+
+/// Journaled transactions.
+pub type Journaled<Ex> = Vec<JournaledTransaction<Ex>>;
+
+/// Journal of the transaction pool contents.
+pub struct Journal<Ex> {
+	file: Arc<JournalFile>,
+	/// Spawns the writes of the journal during maintenance, if any.
+	///
+	/// Without a spawner the journal is written by the caller.
+	spawner: Option<Box<dyn SpawnNamed>>,
+	/// Transactions read on startup that have not been put back in the pool yet.
+	pending: Mutex<Option<Journaled<Ex>>>,
+	/// Whether the transactions of the previous run were put back in the pool.
+	restored: AtomicBool,
+	/// When the journal was last written.
+	last_store: Mutex<Option<Instant>>,
+}
+
+impl<Ex: Encode + Decode> Journal<Ex> {
+	/// Open the journal at `path` and read the transactions of the previous run.
+	pub fn open(path: PathBuf, spawner: Option<Box<dyn SpawnNamed>>) -> Self {
+		let pending = read(&path).unwrap_or_else(|e| {
+			log::warn!(
+				target: "txpool",
+				"Failed to read transaction pool journal {}: {:?}",
+				path.display(),
+				e,
+			);
+			None
+		});
+
+		Journal {
+			file: Arc::new(JournalFile {
+				path,
+				next_generation: AtomicU64::new(1),
+				last_written: Mutex::new(0),
+			}),
+			spawner,
+			restored: AtomicBool::new(pending.is_none()),
+			pending: Mutex::new(pending),
+			last_store: Mutex::new(None),
+		}
+	}
+
+	/// Take the transactions waiting to be put back in the pool.
+	pub fn take_pending(&self) -> Option<Journaled<Ex>> {
+		self.pending.lock().take()
+	}
+
+	/// Record that the transactions of the previous run were put back in the pool.
+	pub fn set_restored(&self) {
+		self.restored.store(true, Ordering::SeqCst);
+	}
+
+	/// Returns true if the transactions of the previous run were put back in the pool.
+	///
+	/// The journal must not be written before, or the transactions it holds would be lost.
+	pub fn is_restored(&self) -> bool {
+		self.restored.load(Ordering::SeqCst)
+	}
+
+	/// Returns true if the journal should be written during maintenance.
+	pub fn is_store_due(&self) -> bool {
+		self.is_restored() &&
+			self.last_store.lock().map_or(true, |at| at.elapsed() >= STORE_INTERVAL)
+	}
+
+	/// Replace the contents of the journal with `transactions`, waiting for the write to complete.
+	pub fn store<'a, Hash: 'a>(
+		&self,
+		transactions: impl IntoIterator<Item=&'a Transaction<Hash, Ex>>,
+	) -> io::Result<usize> where Ex: 'a {
+		let (count, snapshot) = self.encode(transactions);
+		self.file.write(snapshot).map(|_| count)
+	}
+
+	/// Replace the contents of the journal with `transactions`.
+	///
+	/// The transactions are encoded right away, but the journal is written by a blocking task
+	/// if the journal has a spawner.
+	pub fn store_in_background<'a, Hash: 'a>(
+		&self,
+		transactions: impl IntoIterator<Item=&'a Transaction<Hash, Ex>>,
+	) where Ex: 'a {
+		let (count, snapshot) = self.encode(transactions);
+		let file = self.file.clone();
+		let write = move || match file.write(snapshot) {
+			Ok(true) => log::trace!(target: "txpool", "Journaled {} transactions", count),
+			Ok(false) => log::trace!(target: "txpool", "Skipped outdated journal write"),
+			Err(e) => log::warn!(
+				target: "txpool",
+				"Failed to write transaction pool journal: {:?}",
+				e,
+			),
+		};
+
+		match self.spawner.as_ref() {
+			Some(spawner) => spawner.spawn_blocking("txpool-journal", Box::pin(async move {
+				write()
+			})),
+			None => write(),
+		}
+	}
+
+	fn encode<'a, Hash: 'a>(
+		&self,
+		transactions: impl IntoIterator<Item=&'a Transaction<Hash, Ex>>,
+	) -> (usize, Snapshot) where Ex: 'a {
+		let transactions = transactions.into_iter()
+			.map(|tx| JournaledTransaction {
+				source: tx.source,
+				data: &tx.data,
+				priority: tx.priority,
+				valid_till: tx.valid_till,
+				requires: tx.requires.clone(),
+				provides: tx.provides.clone(),
+				propagate: tx.propagate,
+			})
+			.collect::<Vec<_>>();
+		*self.last_store.lock() = Some(Instant::now());
+
+		let snapshot = Snapshot {
+			generation: self.file.next_generation.fetch_add(1, Ordering::SeqCst),
+			encoded: (VERSION, &transactions).encode(),
+		};
+		(transactions.len(), snapshot)
+	}
+}
+
+/// Encoded contents of the journal, taken from the pool at some point.
+struct Snapshot {
+	/// Snapshots taken later have a higher generation.
+	generation: u64,
+	encoded: Vec<u8>,
+}
+
+/// The file the journal is stored in.
+struct JournalFile {
+	path: PathBuf,
+	/// Generation of the next snapshot.
+	next_generation: AtomicU64,
+	/// Generation of the last snapshot written. Also prevents concurrent writes of the journal.
+	last_written: Mutex<u64>,
+}
+
+impl JournalFile {
+	/// Write `snapshot`, unless a later one was written already.
+	///
+	/// Returns whether the snapshot was written.
+	fn write(&self, snapshot: Snapshot) -> io::Result<bool> {
+		let mut last_written = self.last_written.lock();
+		// Writes run in the background may be delayed past later ones.
+		if snapshot.generation <= *last_written {
+			return Ok(false)
+		}
+
+		// Write to a temporary file first, so that a crash does not leave a truncated journal.
+		let tmp_path = self.path.with_extension("tmp");
+		let mut file = fs::File::create(&tmp_path)?;
+		file.write_all(&snapshot.encoded)?;
+		file.sync_all()?;
+		fs::rename(&tmp_path, &self.path)?;
+		*last_written = snapshot.generation;
+		Ok(true)
+	}
+}
+
+/// Read the transactions from the journal at `path`, if any.
+fn read<Ex: Decode>(path: &Path) -> io::Result<Option<Journaled<Ex>>> {
+	let contents = match fs::read(path) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+
+	let invalid = |e: codec::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+	let input = &mut &contents[..];
+	let version = u32::decode(input).map_err(invalid)?;
+	if version != VERSION {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("Unsupported journal version {}", version),
+		));
+	}
+	let transactions = Journaled::<Ex>::decode(input).map_err(invalid)?;
+
+	Ok(Some(transactions).filter(|transactions| !transactions.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn transaction(source: TransactionSource, data: Vec<u8>) -> Transaction<u64, Vec<u8>> {
+		Transaction {
+			bytes: data.len(),
+			hash: data.len() as u64,
+			data,
+			priority: 5,
+			valid_till: 64,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			propagate: true,
+			source,
+		}
+	}
+
+	#[test]
+	fn journal_roundtrip() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("journal");
+
+		let journal = Journal::<Vec<u8>>::open(path.clone(), None);
+		assert!(journal.is_restored());
+		assert!(journal.take_pending().is_none());
+
+		let transactions = vec![
+			transaction(TransactionSource::Local, vec![1]),
+			transaction(TransactionSource::External, vec![2, 3]),
+		];
+		assert_eq!(journal.store(&transactions).unwrap(), 2);
+		assert!(!journal.is_store_due());
+
+		let journal = Journal::<Vec<u8>>::open(path.clone(), None);
+		assert!(!journal.is_restored());
+		let pending = journal.take_pending().unwrap();
+		assert_eq!(pending.len(), 2);
+		assert_eq!(pending[1], JournaledTransaction {
+			source: TransactionSource::External,
+			data: vec![2, 3],
+			priority: 5,
+			valid_till: 64,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			propagate: true,
+		});
+	}
+
+	#[test]
+	fn outdated_snapshot_is_not_written() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("journal");
+		let journal = Journal::<Vec<u8>>::open(path.clone(), None);
+
+		// A snapshot taken during maintenance, whose write is delayed past the one on shutdown.
+		let (_, stale) = journal.encode(&[transaction(TransactionSource::Local, vec![1])]);
+		assert_eq!(journal.store(&[transaction(TransactionSource::Local, vec![2, 3])]).unwrap(), 1);
+		assert!(!journal.file.write(stale).unwrap());
+
+		let pending = Journal::<Vec<u8>>::open(path, None).take_pending().unwrap();
+		assert_eq!(pending.len(), 1);
+		assert_eq!(pending[0].data, vec![2, 3]);
+	}
+
+	#[test]
+	fn invalid_journal_is_ignored() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("journal");
+		fs::write(&path, &(VERSION + 1, Journaled::<Vec<u8>>::new()).encode()).unwrap();
+
+		let journal = Journal::<Vec<u8>>::open(path, None);
+		assert!(journal.is_restored());
+		assert!(journal.take_pending().is_none());
+	}
+}
@@ -23,6 +23,7 @@
 #![warn(unused_extern_crates)]
 
 mod api;
+mod journal;
 mod revalidation;
 mod metrics;
 
@@ -48,11 +49,12 @@ use sp_transaction_pool::{
 	TransactionStatusStreamFor, MaintainedTransactionPool, PoolFuture, ChainEvent,
 	TransactionSource,
 };
-use sc_transaction_graph::{ChainApi, ExtrinsicHash};
+use sc_transaction_graph::{ChainApi, ExtrinsicHash, ExtrinsicFor};
 use wasm_timer::Instant;
 
 use prometheus_endpoint::Registry as PrometheusRegistry;
 use crate::metrics::MetricsLink as PrometheusMetrics;
+use crate::journal::{Journal, Journaled};
 
 type BoxedReadyIterator<Hash, Data> = Box<
 	dyn Iterator<Item=Arc<sc_transaction_graph::base_pool::Transaction<Hash, Data>>> + Send
@@ -81,6 +83,7 @@ pub struct BasicPool<PoolApi, Block>
 	revalidation_queue: Arc<revalidation::RevalidationQueue<PoolApi>>,
 	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<PoolApi>, Block>>>,
 	metrics: PrometheusMetrics,
+	journal: Option<Arc<Journal<ExtrinsicFor<PoolApi>>>>,
 }
 
 struct ReadyPoll<T, Block: BlockT> {
@@ -163,7 +166,17 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 	pub fn new_test(
 		pool_api: Arc<PoolApi>,
 	) -> (Self, Pin<Box<dyn Future<Output=()> + Send>>, intervalier::BackSignalControl) {
-		let pool = Arc::new(sc_transaction_graph::Pool::new(Default::default(), true.into(), pool_api.clone()));
+		Self::new_test_with_options(Default::default(), pool_api)
+	}
+
+	/// Create new basic transaction pool with provided options and api, for tests.
+	#[cfg(test)]
+	pub fn new_test_with_options(
+		options: sc_transaction_graph::Options,
+		pool_api: Arc<PoolApi>,
+	) -> (Self, Pin<Box<dyn Future<Output=()> + Send>>, intervalier::BackSignalControl) {
+		let journal = options.journal.clone().map(|path| Arc::new(Journal::open(path, None)));
+		let pool = Arc::new(sc_transaction_graph::Pool::new(options, true.into(), pool_api.clone()));
 		let (revalidation_queue, background_task, notifier) =
 			revalidation::RevalidationQueue::new_test(pool_api.clone(), pool.clone());
 		(
@@ -174,6 +187,7 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 				revalidation_strategy: Arc::new(Mutex::new(RevalidationStrategy::Always)),
 				ready_poll: Default::default(),
 				metrics: Default::default(),
+				journal,
 			},
 			background_task,
 			notifier,
@@ -190,6 +204,8 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 		revalidation_type: RevalidationType,
 		spawner: impl SpawnNamed,
 	) -> Self {
+		let journal = options.journal.clone()
+			.map(|path| Arc::new(Journal::open(path, Some(Box::new(spawner.clone())))));
 		let pool = Arc::new(sc_transaction_graph::Pool::new(options, is_validator, pool_api.clone()));
 		let (revalidation_queue, background_task) = match revalidation_type {
 			RevalidationType::Light => (revalidation::RevalidationQueue::new(pool_api.clone(), pool.clone()), None),
@@ -215,6 +231,7 @@ impl<PoolApi, Block> BasicPool<PoolApi, Block>
 			)),
 			ready_poll: Default::default(),
 			metrics: PrometheusMetrics::new(prometheus),
+			journal,
 		}
 	}
 
@@ -247,7 +264,12 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
 
-		async move { pool.submit_at(&at, source, xts).await }.boxed()
+		let metrics = self.metrics.clone();
+		async move {
+			let result = pool.submit_at(&at, source, xts).await;
+			report_evicted(&pool, &metrics);
+			result
+		}.boxed()
 	}
 
 	fn submit_one(
@@ -261,7 +283,32 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
-		async move { pool.submit_one(&at, source, xt).await }.boxed()
+		let metrics = self.metrics.clone();
+		async move {
+			let result = pool.submit_one(&at, source, xt).await;
+			report_evicted(&pool, &metrics);
+			result
+		}.boxed()
+	}
+
+	fn replace_one(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		replaced: TxHash<Self>,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		let pool = self.pool.clone();
+		let at = *at;
+
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		let metrics = self.metrics.clone();
+		async move {
+			let result = pool.replace_one(&at, source, replaced, xt).await;
+			report_evicted(&pool, &metrics);
+			result
+		}.boxed()
 	}
 
 	fn submit_and_watch(
@@ -275,10 +322,13 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 
 		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
 
+		let metrics = self.metrics.clone();
 		async move {
-			pool.submit_and_watch(&at, source, xt)
+			let result = pool.submit_and_watch(&at, source, xt)
 				.map(|result| result.map(|watcher| Box::new(watcher.into_stream()) as _))
-				.await
+				.await;
+			report_evicted(&pool, &metrics);
+			result
 		}.boxed()
 	}
 
@@ -431,7 +481,9 @@ where
 			validity,
 		);
 
-		self.pool.validated_pool().submit(vec![validated]).remove(0)
+		let result = self.pool.validated_pool().submit(vec![validated]).remove(0);
+		report_evicted(&self.pool, &self.metrics);
+		result
 	}
 }
 
@@ -544,6 +596,91 @@ async fn prune_known_txs_for_block<Block: BlockT, Api: ChainApi<Block = Block>>(
 	hashes
 }
 
+/// Report the transactions evicted to enforce the pool limits.
+fn report_evicted<Api: ChainApi>(
+	pool: &sc_transaction_graph::Pool<Api>,
+	metrics: &PrometheusMetrics,
+) {
+	let evicted = pool.validated_pool().take_eviction_stats();
+	metrics.report(|metrics| {
+		for (limit, count) in &[
+			("sender", evicted.sender),
+			("ready", evicted.ready),
+			("future", evicted.future),
+		] {
+			metrics.evicted_transactions.with_label_values(&[*limit]).inc_by(*count as u64);
+		}
+	});
+}
+
+/// Put the transactions read from the journal on startup back in the pool.
+///
+/// The transactions are imported with the validity they had in the previous run, and sent to the
+/// revalidation queue which removes those that are no longer valid at `at`.
+async fn restore_journaled<Api: ChainApi>(
+	at: NumberFor<Api>,
+	pool: &sc_transaction_graph::Pool<Api>,
+	revalidation_queue: &revalidation::RevalidationQueue<Api>,
+	transactions: Journaled<ExtrinsicFor<Api>>,
+) {
+	let count = transactions.len();
+	let transactions = transactions.into_iter().map(|tx| {
+		let (hash, bytes) = pool.validated_pool().api().hash_and_length(&tx.data);
+		sc_transaction_graph::ValidatedTransaction::Valid(
+			sc_transaction_graph::base_pool::Transaction {
+				data: tx.data,
+				bytes,
+				hash,
+				priority: tx.priority,
+				valid_till: tx.valid_till,
+				requires: tx.requires,
+				provides: tx.provides,
+				propagate: tx.propagate,
+				source: tx.source,
+			}
+		)
+	});
+	let restored = pool.validated_pool()
+		.submit(transactions)
+		.into_iter()
+		.filter_map(Result::ok)
+		.collect::<Vec<_>>();
+
+	log::info!(target: "txpool", "Restored {} of {} journaled transactions", restored.len(), count);
+	revalidation_queue.revalidate_later(at, restored).await;
+}
+
+/// The ready and future transactions of the pool.
+fn journaled_transactions<Api: ChainApi>(
+	pool: &sc_transaction_graph::Pool<Api>,
+) -> Vec<sc_transaction_graph::TransactionFor<Api>> {
+	let mut transactions = pool.validated_pool().ready().collect::<Vec<_>>();
+	transactions.extend(pool.validated_pool().futures());
+	transactions
+}
+
+impl<PoolApi, Block> Drop for BasicPool<PoolApi, Block>
+	where
+		Block: BlockT,
+		PoolApi: ChainApi<Block=Block>,
+{
+	fn drop(&mut self) {
+		// The node is shutting down, so the journal is written right away rather than in the
+		// background.
+		if let Some(journal) = self.journal.as_ref().filter(|journal| journal.is_restored()) {
+			let transactions = journaled_transactions(&self.pool);
+			match journal.store(transactions.iter().map(|tx| &**tx)) {
+				Ok(count) => log::trace!(target: "txpool", "Journaled {} transactions", count),
+				Err(e) => log::warn!(
+					target: "txpool",
+					"Failed to write transaction pool journal: {:?}",
+					e,
+				),
+			}
+		}
+	}
+}
+
 impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 	where
 		Block: BlockT,
@@ -577,8 +714,22 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 				let revalidation_queue = self.revalidation_queue.clone();
 				let ready_poll = self.ready_poll.clone();
 				let metrics = self.metrics.clone();
+				let journal = self.journal.clone();
 
 				async move {
+					// Transactions of the previous run are restored once we know the best block.
+					if let Some(journal) = journal.as_ref() {
+						if let Some(transactions) = journal.take_pending() {
+							restore_journaled(
+								block_number,
+								&*pool,
+								&*revalidation_queue,
+								transactions,
+							).await;
+							journal.set_restored();
+						}
+					}
+
 					// We keep track of everything we prune so that later we won't add
 					// tranactions with those hashes from the retracted blocks.
 					let mut pruned_log = HashSet::<ExtrinsicHash<PoolApi>>::new();
@@ -692,6 +843,14 @@ impl<PoolApi, Block> MaintainedTransactionPool for BasicPool<PoolApi, Block>
 
 						revalidation_strategy.lock().clear();
 					}
+
+					// Resubmitted and revalidated transactions count towards the limits as well.
+					report_evicted(&pool, &metrics);
+
+					if let Some(journal) = journal.filter(|journal| journal.is_store_due()) {
+						let transactions = journaled_transactions(&pool);
+						journal.store_in_background(transactions.iter().map(|tx| &**tx));
+					}
 				}.boxed()
 			}
 			ChainEvent::Finalized { hash } => {
@@ -1038,3 +1038,40 @@ fn only_revalidate_on_best_block() {
 
 	assert_eq!(pool.status().ready, 1);
 }
+
+#[test]
+fn should_restore_journaled_transactions() {
+	let dir = tempfile::tempdir().expect("Creates temp dir");
+	let options = txpool::Options {
+		journal: Some(dir.path().join("journal")),
+		..Default::default()
+	};
+	let api = Arc::new(TestApi::with_alice_nonce(209));
+
+	let (pool, _background, _notifier) =
+		BasicPool::new_test_with_options(options.clone(), api.clone());
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209))).expect("1. Imported");
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 211))).expect("2. Imported");
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.status().future, 1);
+	drop(pool);
+
+	let (pool, background_task, mut notifier) =
+		BasicPool::new_test_with_options(options, api.clone());
+	let thread_pool = futures::executor::ThreadPool::new().unwrap();
+	thread_pool.spawn_ok(background_task);
+	assert_eq!(pool.status().ready, 0);
+
+	// journaled transactions are restored at the first best block without validating them
+	api.add_invalid(&uxt(Alice, 209));
+	let header = api.push_block(1, vec![], true);
+	block_on(pool.maintain(block_event(header)));
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.status().future, 1);
+	assert_eq!(api.validation_requests().len(), 2);
+
+	// and the revalidation queue removes those that are no longer valid
+	block_on(notifier.next());
+	assert_eq!(pool.status().ready, 0);
+	assert_eq!(pool.status().future, 1);
+}
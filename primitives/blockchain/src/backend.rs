@@ -226,6 +226,16 @@ pub trait Backend<Block: BlockT>: HeaderBackend<Block> + HeaderMetadata<Block, E
 	fn have_extrinsic(&self, hash: &Block::Hash) -> Result<bool> {
 		Ok(self.extrinsic(hash)?.is_some())
 	}
+
+	/// Get the block body whose SCALE encoding hashes to `hash`.
+	///
+	/// Returns `None` if the backend doesn't index bodies by their content.
+	fn body_by_content_hash(
+		&self,
+		_hash: &Block::Hash,
+	) -> Result<Option<Vec<<Block as BlockT>::Extrinsic>>> {
+		Ok(None)
+	}
 }
 
 /// Provides access to the optional cache.
@@ -141,6 +141,14 @@ pub trait Database<H: Clone>: Send + Sync {
 		self.commit(t)
 	}
 
+	/// Call `f` with every key-value pair stored in `col`, in no particular order.
+	///
+	/// Not all databases support iteration, the default implementation returns an error.
+	fn iter(&self, col: ColumnId, f: &mut dyn FnMut(&[u8], &[u8])) -> error::Result<()> {
+		let _ = (col, f);
+		Err(error::DatabaseError("Database does not support iteration".into()))
+	}
+
 	/// Retrieve the first preimage previously `store`d for `hash` or `None` if no preimage is
 	/// currently stored.
 	fn lookup(&self, hash: &H) -> Option<Vec<u8>>;
@@ -48,6 +48,15 @@ impl<H> Database<H> for MemDb<H>
 		s.0.get(&col).and_then(|c| c.get(key).cloned())
 	}
 
+	fn iter(&self, col: ColumnId, f: &mut dyn FnMut(&[u8], &[u8])) -> error::Result<()> {
+		// Take a copy, so that `f` is free to write to the database.
+		let column = self.0.read().0.get(&col).cloned().unwrap_or_default();
+		for (key, value) in column.iter() {
+			f(key, value);
+		}
+		Ok(())
+	}
+
 	fn lookup(&self, hash: &H) -> Option<Vec<u8>> {
 		let s = self.0.read();
 		s.1.get(hash).cloned()
@@ -54,6 +54,16 @@ pub enum Error {
 		/// Transaction entering the pool.
 		new: Priority
 	},
+
+	#[error("Too low priority bump ({} < {})", new, required)]
+	TooLowPriorityBump {
+		/// Transaction already in the pool.
+		old: Priority,
+		/// Transaction entering the pool.
+		new: Priority,
+		/// Minimal priority required to replace the transaction already in the pool.
+		required: Priority,
+	},
 	#[error("Transaction with cyclic dependency")]
 	CycleDetected,
 
@@ -69,6 +79,12 @@ pub enum Error {
 
 	#[error("The pool is not accepting future transactions")]
 	RejectedFutureTransaction,
+
+	#[error("The transaction to replace is not in the pool")]
+	ReplacedNotFound,
+
+	#[error("Transaction does not provide the same tags as the transaction it replaces")]
+	NotReplacing,
 }
 
 /// Transaction pool error conversion.
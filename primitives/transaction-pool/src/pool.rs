@@ -68,6 +68,7 @@ impl PoolStatus {
 ///		- `InBlock`
 ///		- `Invalid`
 ///		- `Usurped`
+///		- `Replaced`
 ///		- `Dropped`
 ///	4. Re-entering the pool:
 ///		- `Retracted`
@@ -120,6 +121,9 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	/// Transaction has been replaced in the pool, by another transaction
 	/// that provides the same tags. (e.g. same (sender, nonce)).
 	Usurped(Hash),
+	/// Transaction has been replaced in the pool on request of its submitter, by another
+	/// transaction that provides the same tags (e.g. same (sender, nonce)).
+	Replaced(Hash),
 	/// Transaction has been dropped from the pool because of the limit.
 	Dropped,
 	/// Transaction is no longer valid in the current state.
@@ -204,6 +208,22 @@ pub trait TransactionPool: Send + Sync {
 		xt: TransactionFor<Self>,
 	) -> PoolFuture<TxHash<Self>, Self::Error>;
 
+	/// Returns a future that imports one unverified transaction to the pool in place of the
+	/// transaction with hash `replaced`.
+	///
+	/// The transaction is validated first and only imported if it provides all the tags of the
+	/// replaced transaction. Listeners of the replaced transaction are notified with
+	/// [`TransactionStatus::Replaced`].
+	fn replace_one(
+		&self,
+		_at: &BlockId<Self::Block>,
+		_source: TransactionSource,
+		_replaced: TxHash<Self>,
+		_xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		Box::pin(futures::future::ready(Err(crate::error::Error::ReplacedNotFound.into())))
+	}
+
 	/// Returns a future that import a single transaction and starts to watch their progress in the pool.
 	fn submit_and_watch(
 		&self,
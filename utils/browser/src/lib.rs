@@ -71,6 +71,7 @@ where
 		wasm_external_transport: Some(transport.clone()),
 		allow_private_ipv4: true,
 		enable_mdns: false,
+		enable_quic: false,
 	};
 	let telemetry_span = telemetry_handle.as_ref().map(|_| TelemetrySpan::new());
 
@@ -90,7 +91,7 @@ where
 			info!("Opening Indexed DB database '{}'...", name);
 			let db = kvdb_web::Database::open(name, 10).await?;
 
-			DatabaseConfig::Custom(sp_database::as_database(db))
+			DatabaseConfig::Custom { db: sp_database::as_database(db), ref_counting: false }
 		},
 		keystore_remote: Default::default(),
 		keystore: KeystoreConfig::InMemory,
@@ -106,11 +107,14 @@ where
 		state_pruning: Default::default(),
 		keep_blocks: KeepBlocks::All,
 		transaction_storage: TransactionStorageMode::BlockBody,
+		event_index: None,
 		rpc_cors: Default::default(),
 		rpc_http: Default::default(),
 		rpc_ipc: Default::default(),
 		rpc_ws: Default::default(),
 		rpc_ws_max_connections: Default::default(),
+		rpc_access_policy: Default::default(),
+		rpc_limits: Default::default(),
 		rpc_methods: Default::default(),
 		state_cache_child_ratio: Default::default(),
 		state_cache_size: Default::default(),